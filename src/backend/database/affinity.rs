@@ -0,0 +1,238 @@
+use super::datetime;
+use super::json;
+use super::Column;
+use super::ColumnType;
+use super::Decimal;
+
+use std::error;
+
+// reals with integral values in this range are kept as integers without losing anything
+const INTEGRAL_REAL_LIMIT: f64 = 9223372036854775808.0;
+
+impl ColumnType {
+    /// Converts a value written to a column the way SQLite's column affinity does. TEXT
+    /// columns turn numbers into text. INTEGER and NUMERIC columns turn text that is a well
+    /// formed number into that number and reals with integral values into integers, REAL
+    /// columns turn integers and numeric text into reals. BLOB columns keep values as they are.
+    /// DECIMAL columns round numbers and numeric text to their scale, values with more digits
+    /// than their precision allows are kept as they are and can't be written. JSON columns
+    /// drop the whitespace of JSON text.
+    pub fn apply(&self, value: Column) -> Column {
+        match (self, value) {
+            (ColumnType::Text, Column::Integer(int)) => Column::Text(int.to_string()),
+            (ColumnType::Text, Column::Real(real)) => Column::Text(format_real(real)),
+            (ColumnType::Text, Column::Decimal(decimal)) => Column::Text(decimal.to_string()),
+            (ColumnType::Integer, value) | (ColumnType::Numeric, value) => match value {
+                Column::Text(text) => match parse_number(&text) {
+                    Some(number) => integral(number),
+                    None => Column::Text(text),
+                },
+                value => integral(value),
+            },
+            (ColumnType::Real, Column::Integer(int)) => Column::Real(int as f64),
+            (ColumnType::Real, Column::Decimal(decimal)) => Column::Real(decimal.to_real()),
+            (ColumnType::Real, Column::Text(text)) => match parse_number(&text) {
+                Some(Column::Integer(int)) => Column::Real(int as f64),
+                Some(number) => number,
+                None => Column::Text(text),
+            },
+            (column_type, value) if column_type.is_temporal() => {
+                datetime::apply(column_type, value)
+            }
+            (ColumnType::Decimal { precision, scale }, value) => {
+                match to_decimal(&value, *precision, *scale) {
+                    Some(decimal) => Column::Decimal(decimal),
+                    None => value,
+                }
+            }
+            (ColumnType::Json, value) => json::apply(value),
+            (_, value) => value,
+        }
+    }
+
+    /// The value as it is stored, reals in REAL columns with integral values take less space
+    /// as integers.
+    pub fn compact(&self, value: Column) -> Column {
+        match self {
+            ColumnType::Real => integral(value),
+            column_type if column_type.is_temporal() => datetime::compact(column_type, value),
+            _ => value,
+        }
+    }
+
+    /// Undoes `compact` for a value read back from a record.
+    pub fn restore(&self, value: Column) -> Column {
+        match (self, value) {
+            (ColumnType::Real, Column::Integer(int)) => Column::Real(int as f64),
+            (column_type, value) if column_type.is_temporal() => {
+                datetime::restore(column_type, value)
+            }
+            (_, value) => value,
+        }
+    }
+
+    /// Whether the column holds dates, times or intervals.
+    pub fn is_temporal(&self) -> bool {
+        matches!(
+            self,
+            ColumnType::Date | ColumnType::Time | ColumnType::Timestamp | ColumnType::Interval
+        )
+    }
+}
+
+/// Gives each value of a row its column's affinity and the form it is stored in. Fails when
+/// a JSON column is given something other than JSON, or a DECIMAL column a number with more
+/// digits than its precision.
+pub fn store_row(
+    columns: &[(String, ColumnType)],
+    row: Vec<Column>,
+) -> Result<Vec<Column>, Box<dyn error::Error>> {
+    let mut types = columns.iter();
+    row.into_iter()
+        .map(|value| match types.next() {
+            Some((name, ColumnType::Json)) => {
+                let value = json::apply(value);
+                json::check(&value).map_err(|message| format!("{} in column {}", message, name))?;
+                Ok(value)
+            }
+            Some((name, column_type)) => {
+                check_decimal(column_type, &value)
+                    .map_err(|message| format!("{} in column {}", message, name))?;
+                Ok(column_type.compact(column_type.apply(value)))
+            }
+            None => Ok(value),
+        })
+        .collect()
+}
+
+/// Fails for a number given to a DECIMAL column that can't be kept exactly, because it has
+/// more digits than the column's precision once rounded to its scale.
+pub fn check_decimal(column_type: &ColumnType, value: &Column) -> Result<(), String> {
+    let (precision, scale) = match column_type {
+        ColumnType::Decimal { precision, scale } => (*precision, *scale),
+        _ => return Ok(()),
+    };
+    let written = match value {
+        Column::Integer(int) => int.to_string(),
+        Column::Real(real) => format_real(*real),
+        Column::Decimal(decimal) => decimal.to_string(),
+        Column::Text(text) if parse_number(text).is_some() => text.trim().to_string(),
+        Column::Text(_) | Column::Null() | Column::Blob(_) => return Ok(()),
+    };
+    if to_decimal(value, precision, scale).is_some() {
+        return Ok(());
+    }
+    Err(format!(
+        "{} does not fit DECIMAL({}, {})",
+        written, precision, scale
+    ))
+}
+
+// a number or numeric text rounded to the scale, None when it isn't one or has more digits
+// than the precision
+fn to_decimal(value: &Column, precision: u8, scale: u8) -> Option<Decimal> {
+    let decimal = match value {
+        Column::Integer(int) => Some(Decimal::from_integer(*int)),
+        Column::Real(real) => Decimal::from_real(*real),
+        Column::Text(text) => Decimal::parse(text),
+        Column::Decimal(decimal) => Some(*decimal),
+        Column::Null() | Column::Blob(_) => None,
+    };
+    decimal
+        .and_then(|decimal| decimal.rescale(scale))
+        .filter(|decimal| decimal.fits(precision))
+}
+
+/// Restores the values of a row read from a table.
+pub fn load_row(columns: &[(String, ColumnType)], row: Vec<Column>) -> Vec<Column> {
+    let mut types = columns.iter().map(|(_name, column_type)| column_type);
+    row.into_iter()
+        .map(|value| match types.next() {
+            Some(column_type) => column_type.restore(value),
+            None => value,
+        })
+        .collect()
+}
+
+// a real or decimal with an integral value as an integer
+fn integral(value: Column) -> Column {
+    match value {
+        Column::Real(real) if real.fract() == 0.0 && real.abs() < INTEGRAL_REAL_LIMIT => {
+            Column::Integer(real as i128)
+        }
+        Column::Decimal(decimal) => match decimal.to_integer() {
+            Some(int) => Column::Integer(int),
+            None => value,
+        },
+        value => value,
+    }
+}
+
+// text that is entirely a number, apart from surrounding spaces
+fn parse_number(text: &str) -> Option<Column> {
+    let (number, rest) = parse_numeric_prefix(text);
+    if rest.len() == text.trim_start().len() || !rest.trim().is_empty() {
+        None
+    } else {
+        Some(number)
+    }
+}
+
+/// Reads the longest number at the start of some text, as SQLite does when text is used as
+/// a number, along with the text after it. Text that doesn't start with a number is 0.
+pub fn parse_numeric_prefix(text: &str) -> (Column, &str) {
+    let text = text.trim_start();
+    let bytes = text.as_bytes();
+    let mut end = 0;
+    let mut is_real = false;
+
+    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+        end += 1;
+    }
+    let digits_start = end;
+    while end < bytes.len() && bytes[end].is_ascii_digit() {
+        end += 1;
+    }
+    if end < bytes.len() && bytes[end] == b'.' {
+        is_real = true;
+        end += 1;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+    }
+    if end == digits_start || (is_real && end == digits_start + 1) {
+        return (Column::Integer(0), text);
+    }
+    if end < bytes.len() && (bytes[end] == b'e' || bytes[end] == b'E') {
+        let mut exponent_end = end + 1;
+        if exponent_end < bytes.len()
+            && (bytes[exponent_end] == b'+' || bytes[exponent_end] == b'-')
+        {
+            exponent_end += 1;
+        }
+        if exponent_end < bytes.len() && bytes[exponent_end].is_ascii_digit() {
+            is_real = true;
+            end = exponent_end;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+        }
+    }
+
+    let (number, rest) = text.split_at(end);
+    if !is_real {
+        if let Ok(int) = number.parse::<i128>() {
+            return (Column::Integer(int), rest);
+        }
+    }
+    (Column::Real(number.parse().unwrap_or(0.0)), rest)
+}
+
+/// The text of a real, which keeps a `.0` when it has an integral value.
+pub fn format_real(real: f64) -> String {
+    if real.fract() == 0.0 && real.abs() < 1e15 {
+        format!("{:.1}", real)
+    } else {
+        format!("{}", real)
+    }
+}
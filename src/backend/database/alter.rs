@@ -0,0 +1,276 @@
+use super::super::record;
+use super::affinity::check_decimal;
+use super::stats::STATS_TABLE;
+use super::trigger::{is_trigger_on, TRIGGER};
+use super::Column;
+use super::ColumnType;
+use super::Database;
+use super::Generated;
+
+use std::error;
+
+impl Database {
+    /// Removes a table along with its indexes, triggers and statistics, the pages go on the
+    /// freelist.
+    pub fn drop_table(&mut self, table_name: String) -> Result<(), Box<dyn error::Error>> {
+        let (rootpage, _columns) = self.describe_table(table_name.clone())?;
+        let indexes = self.list_indexes(table_name.clone())?;
+
+        self.rewrite_master(|row| match (&row[0], &row[1]) {
+            (Column::Integer(1), Column::Text(name)) => Ok(*name != table_name),
+            (Column::Integer(2), Column::Text(name)) => {
+                Ok(!indexes.iter().any(|index| index.name == *name))
+            }
+            _ => Ok(!is_trigger_on(row, &table_name)),
+        })?;
+
+        for index in &indexes {
+            self.free_page(index.rootpage)?;
+        }
+        self.free_page(rootpage)?;
+        self.move_stats(&table_name, None)
+    }
+
+    /// `ALTER TABLE table RENAME TO new_name`, the table's indexes, triggers and statistics
+    /// follow it.
+    pub fn rename_table(
+        &mut self,
+        table_name: String,
+        new_name: String,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.describe_table(table_name.clone())?;
+        self.check_name_free(&new_name)?;
+
+        self.rewrite_master(|row| {
+            match (&row[0], &row[1], row.get(3)) {
+                (Column::Integer(1), Column::Text(name), _) if *name == table_name => {
+                    row[1] = Column::Text(new_name.clone());
+                }
+                (Column::Integer(2), _, Some(Column::Blob(data))) => {
+                    let (indexed_table, columns) =
+                        bincode::deserialize::<(String, Vec<String>)>(data)?;
+                    if indexed_table == table_name {
+                        row[3] = Column::Blob(bincode::serialize(&(&new_name, &columns))?);
+                    }
+                }
+                (Column::Integer(TRIGGER), _, _) if is_trigger_on(row, &table_name) => {
+                    row[4] = Column::Text(new_name.clone());
+                }
+                _ => {}
+            }
+            Ok(true)
+        })?;
+
+        self.move_stats(&table_name, Some(&new_name))
+    }
+
+    /// `ALTER TABLE table RENAME COLUMN column TO new_name`, indexes on the column follow it.
+    pub fn rename_column(
+        &mut self,
+        table_name: String,
+        column: String,
+        new_name: String,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let (_page_number, mut columns) = self.describe_table(table_name.clone())?;
+        let position = column_position(&columns, &column)?;
+        if column_position(&columns, &new_name).is_ok() {
+            return Err(format!("duplicate column name: {}", new_name).into());
+        }
+        let old_name = columns[position].0.clone();
+        columns[position].0 = new_name.clone();
+
+        self.rewrite_master(|row| {
+            match (&row[0], &row[1], row.get(3)) {
+                (Column::Integer(1), Column::Text(name), _) if *name == table_name => {
+                    row[3] = Column::Blob(bincode::serialize(&columns)?);
+                }
+                (Column::Integer(2), _, Some(Column::Blob(data))) => {
+                    let (indexed_table, mut indexed_columns) =
+                        bincode::deserialize::<(String, Vec<String>)>(data)?;
+                    if indexed_table == table_name {
+                        for indexed_column in indexed_columns.iter_mut() {
+                            if *indexed_column == old_name {
+                                *indexed_column = new_name.clone();
+                            }
+                        }
+                        let index = (&indexed_table, &indexed_columns);
+                        row[3] = Column::Blob(bincode::serialize(&index)?);
+                    }
+                }
+                _ => {}
+            }
+            Ok(true)
+        })
+    }
+
+    /// `ALTER TABLE table ADD COLUMN`, records already in the table are left as they are and
+    /// read the default for the new column. A STORED generated column is worked out for every
+    /// record instead.
+    pub fn add_column(
+        &mut self,
+        table_name: String,
+        column: (String, ColumnType),
+        default: Column,
+        collation: Option<String>,
+        generated: Option<Generated>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let (_page_number, mut columns) = self.describe_table(table_name.clone())?;
+        if column_position(&columns, &column.0).is_ok() {
+            return Err(format!("duplicate column name: {}", column.0).into());
+        }
+        if let Some(collation) = &collation {
+            self.collation(collation)?;
+        }
+        if generated.is_some() && default != Column::Null() {
+            return Err("cannot use DEFAULT on a generated column".into());
+        }
+        let stored = matches!(generated, Some(Generated { stored: true, .. }));
+        let mut defaults = self.table_defaults(table_name.clone())?;
+        let mut collations = self.table_collations(table_name.clone())?;
+        let mut generations = self.table_generated(table_name.clone())?;
+        check_decimal(&column.1, &default)
+            .map_err(|message| format!("{} in column {}", message, column.0))?;
+        defaults.push(column.1.apply(default));
+        collations.push(collation);
+        generations.push(generated);
+        columns.push(column);
+
+        self.rewrite_table_schema(&table_name, &columns, &defaults, &collations, &generations)?;
+        if stored {
+            let rows = self.select_all_records(table_name.clone())?;
+            self.replace_records(table_name, rows)?;
+        }
+        Ok(())
+    }
+
+    /// `ALTER TABLE table DROP COLUMN`, every record is rewritten without the column.
+    pub fn drop_column(
+        &mut self,
+        table_name: String,
+        column: String,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let (_page_number, mut columns) = self.describe_table(table_name.clone())?;
+        let position = column_position(&columns, &column)?;
+        if columns.len() == 1 {
+            return Err(format!("cannot drop column {}: no other columns exist", column).into());
+        }
+        for index in self.list_indexes(table_name.clone())? {
+            if index.columns.contains(&columns[position].0) {
+                return Err(format!(
+                    "cannot drop column {}: it is used by index {}",
+                    column, index.name
+                )
+                .into());
+            }
+        }
+
+        let mut defaults = self.table_defaults(table_name.clone())?;
+        let mut collations = self.table_collations(table_name.clone())?;
+        let mut generations = self.table_generated(table_name.clone())?;
+        let rows = self
+            .select_all_records(table_name.clone())?
+            .into_iter()
+            .map(|mut row| {
+                row.remove(position);
+                row
+            })
+            .collect();
+        columns.remove(position);
+        defaults.remove(position);
+        collations.remove(position);
+        generations.remove(position);
+
+        self.rewrite_table_schema(&table_name, &columns, &defaults, &collations, &generations)?;
+        self.replace_records(table_name, rows)
+    }
+
+    // the columns of a table are kept after its rootpage, followed by a record of their
+    // defaults when any aren't NULL, then a record of their collations when any are declared
+    // and then a record of the generated columns when there are any
+    pub(super) fn rewrite_table_schema(
+        &mut self,
+        table_name: &str,
+        columns: &[(String, ColumnType)],
+        defaults: &[Column],
+        collations: &[Option<String>],
+        generations: &[Option<Generated>],
+    ) -> Result<(), Box<dyn error::Error>> {
+        let serialised_columns = bincode::serialize(columns)?;
+        let generated = generations.iter().any(Option::is_some);
+        let collated = generated || collations.iter().any(Option::is_some);
+        self.rewrite_master(|row| {
+            match (&row[0], &row[1]) {
+                (Column::Integer(1), Column::Text(name)) if name == table_name => {
+                    row.truncate(3);
+                    row.push(Column::Blob(serialised_columns.clone()));
+                    if collated || defaults.iter().any(|default| *default != Column::Null()) {
+                        let defaults = record::create_record(defaults.to_vec());
+                        row.push(Column::Blob(defaults));
+                    }
+                    if collated {
+                        let collations = collations
+                            .iter()
+                            .map(|collation| match collation {
+                                Some(name) => Column::Text(name.clone()),
+                                None => Column::Null(),
+                            })
+                            .collect();
+                        row.push(Column::Blob(record::create_record(collations)));
+                    }
+                    if generated {
+                        let generations = generations
+                            .iter()
+                            .map(|generation| match generation {
+                                Some(Generated { expression, stored }) => {
+                                    Column::Blob(record::create_record(vec![
+                                        Column::Text(expression.clone()),
+                                        Column::Integer(*stored as i128),
+                                    ]))
+                                }
+                                None => Column::Null(),
+                            })
+                            .collect();
+                        row.push(Column::Blob(record::create_record(generations)));
+                    }
+                }
+                _ => {}
+            }
+            Ok(true)
+        })
+    }
+
+    // keeps the statistics gathered for a table under its new name, or forgets them
+    fn move_stats(
+        &mut self,
+        table_name: &str,
+        new_name: Option<&str>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        if table_name == STATS_TABLE || self.describe_table(STATS_TABLE.to_string()).is_err() {
+            return Ok(());
+        }
+
+        let rows = self
+            .select_all_records(STATS_TABLE.to_string())?
+            .into_iter()
+            .filter_map(|mut row| match (&row[0], new_name) {
+                (Column::Text(name), Some(new_name)) if name == table_name => {
+                    row[0] = Column::Text(new_name.to_string());
+                    Some(row)
+                }
+                (Column::Text(name), None) if name == table_name => None,
+                _ => Some(row),
+            })
+            .collect();
+        self.replace_records(STATS_TABLE.to_string(), rows)
+    }
+}
+
+fn column_position(
+    columns: &[(String, ColumnType)],
+    column: &str,
+) -> Result<usize, Box<dyn error::Error>> {
+    columns
+        .iter()
+        .position(|(name, _)| name.eq_ignore_ascii_case(column))
+        .ok_or_else(|| format!("no such column: {}", column).into())
+}
@@ -0,0 +1,167 @@
+use super::super::record;
+use super::Column;
+use super::ColumnType;
+use super::Database;
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::rc::Rc;
+
+/// Compares two pieces of text for a custom collation.
+pub type CompareText = Rc<dyn Fn(&str, &str) -> Ordering>;
+
+/// How text is ordered when it is compared, sorted, grouped or indexed. Values that aren't
+/// both text are compared as they always are.
+#[derive(Clone)]
+pub enum Collation {
+    /// byte by byte, the default
+    Binary,
+    /// ignoring the case of ASCII letters
+    NoCase,
+    /// ignoring trailing spaces
+    RTrim,
+    /// a collation registered with `Database::create_collation`
+    Custom(String, CompareText),
+}
+
+impl Collation {
+    /// The built in collation with the name, whatever its case.
+    pub fn builtin(name: &str) -> Option<Collation> {
+        match name.to_ascii_uppercase().as_str() {
+            "BINARY" => Some(Collation::Binary),
+            "NOCASE" => Some(Collation::NoCase),
+            "RTRIM" => Some(Collation::RTrim),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Collation::Binary => "BINARY",
+            Collation::NoCase => "NOCASE",
+            Collation::RTrim => "RTRIM",
+            Collation::Custom(name, _) => name,
+        }
+    }
+
+    pub fn compare(&self, left: &Column, right: &Column) -> Ordering {
+        match (self, left, right) {
+            (Collation::Binary, _, _) => left.compare(right),
+            (_, Column::Text(left), Column::Text(right)) => self.compare_text(left, right),
+            _ => left.compare(right),
+        }
+    }
+
+    pub fn compare_text(&self, left: &str, right: &str) -> Ordering {
+        match self {
+            Collation::Binary => left.as_bytes().cmp(right.as_bytes()),
+            Collation::NoCase => {
+                let left = left.bytes().map(|byte| byte.to_ascii_lowercase());
+                left.cmp(right.bytes().map(|byte| byte.to_ascii_lowercase()))
+            }
+            Collation::RTrim => left
+                .trim_end_matches(' ')
+                .as_bytes()
+                .cmp(right.trim_end_matches(' ').as_bytes()),
+            Collation::Custom(_, compare) => compare(left, right),
+        }
+    }
+}
+
+impl fmt::Debug for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Collation({})", self.name())
+    }
+}
+
+/// Collations are the same when they have the same name.
+impl PartialEq for Collation {
+    fn eq(&self, other: &Collation) -> bool {
+        self.name().eq_ignore_ascii_case(other.name())
+    }
+}
+
+impl Database {
+    /// Registers a collation that SQL can name with `COLLATE` or declare on a column, it
+    /// replaces a collation registered earlier with the same name. Like the built in
+    /// collations the name is matched whatever its case. Registrations aren't saved in the
+    /// file, a database using a custom collation has to register it each time it's loaded.
+    pub fn create_collation<F>(&mut self, name: &str, compare: F)
+    where
+        F: Fn(&str, &str) -> Ordering + 'static,
+    {
+        let collation = Collation::Custom(name.to_string(), Rc::new(compare));
+        self.collations.insert(name.to_ascii_uppercase(), collation);
+    }
+
+    /// The collation with the name, built in or registered.
+    pub fn collation(&self, name: &str) -> Result<Collation, Box<dyn error::Error>> {
+        find_collation(&self.collations, name)
+            .ok_or_else(|| format!("no such collation sequence: {}", name).into())
+    }
+
+    /// `CREATE TABLE` with a collation declared for some of the columns, as by
+    /// `name TEXT COLLATE NOCASE`. Columns given None compare with BINARY.
+    pub fn create_collated_table(
+        &mut self,
+        table_name: String,
+        columns: Vec<(String, ColumnType, Option<String>)>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let mut collations = Vec::with_capacity(columns.len());
+        for (_name, _column_type, collation) in &columns {
+            if let Some(collation) = collation {
+                self.collation(collation)?;
+            }
+            collations.push(collation.clone());
+        }
+        let columns: Vec<(String, ColumnType)> = columns
+            .into_iter()
+            .map(|(name, column_type, _collation)| (name, column_type))
+            .collect();
+        let defaults = vec![Column::Null(); columns.len()];
+        let generations = vec![None; columns.len()];
+
+        self.create_table(table_name.clone(), columns.clone())?;
+        self.rewrite_table_schema(&table_name, &columns, &defaults, &collations, &generations)
+    }
+
+    /// The collation declared for each column of a table, None for the columns that compare
+    /// with BINARY.
+    pub fn table_collations(
+        &mut self,
+        table_name: String,
+    ) -> Result<Vec<Option<String>>, Box<dyn error::Error>> {
+        let (_page_number, columns) = self.describe_table(table_name.clone())?;
+        let record_filter = |row: &Vec<Column>| match (&row[0], &row[1]) {
+            (Column::Integer(1), Column::Text(row_table_name)) => *row_table_name == table_name,
+            _ => false,
+        };
+        let rows = record::select_records(self, 1, record_filter, |row| row)?;
+
+        // the collations are kept as a record after the defaults
+        let collations = match rows.first().and_then(|row| row.get(5)) {
+            Some(Column::Blob(data)) => record::read_record(data),
+            _ => Vec::new(),
+        };
+        let mut collations: Vec<Option<String>> = collations
+            .into_iter()
+            .map(|collation| match collation {
+                Column::Text(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        collations.resize(columns.len(), None);
+        Ok(collations)
+    }
+}
+
+/// Finds a built in collation, or one of the registered `collations` kept under their
+/// uppercase names.
+pub fn find_collation(collations: &HashMap<String, Collation>, name: &str) -> Option<Collation> {
+    collations
+        .get(&name.to_ascii_uppercase())
+        .cloned()
+        .or_else(|| Collation::builtin(name))
+}
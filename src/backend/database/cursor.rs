@@ -0,0 +1,136 @@
+use super::super::record;
+use super::pad_row;
+use super::Column;
+use super::Database;
+use super::GeneratedColumns;
+
+use std::error;
+
+/// A position in the records of a table, in the order they are stored. Writes through a cursor
+/// are committed straight away, the table's indexes are rebuilt when the cursor is closed.
+#[derive(Debug)]
+pub struct TableCursor {
+    pub table_name: String,
+    rootpage: u32,
+    /// the table's columns and how its generated ones are worked out
+    columns: GeneratedColumns,
+    /// what the columns missing from short records read as
+    defaults: Vec<Column>,
+    position: u16,
+    record_count: u16,
+    /// the record at the current position, read when first needed
+    current: Option<Vec<Column>>,
+    /// a deleted record is replaced by the one after it, so the next step stays put
+    deleted: bool,
+    written: bool,
+}
+
+impl Database {
+    pub fn open_cursor(
+        &mut self,
+        table_name: String,
+    ) -> Result<TableCursor, Box<dyn error::Error>> {
+        let (rootpage, _columns) = self.describe_table(table_name.clone())?;
+        let record_count = record::record_count(self, rootpage)?;
+        let defaults = self.table_defaults(table_name.clone())?;
+        let columns = self.generated_columns(table_name.clone())?;
+        Ok(TableCursor {
+            table_name,
+            rootpage,
+            columns,
+            defaults,
+            position: 0,
+            record_count,
+            current: None,
+            deleted: false,
+            written: false,
+        })
+    }
+}
+
+impl TableCursor {
+    /// Moves to the first record, returns false when the table is empty.
+    pub fn rewind(&mut self) -> bool {
+        self.move_to(0);
+        !self.eof()
+    }
+
+    /// Moves to the next record, returns false when there are no more.
+    pub fn advance(&mut self) -> bool {
+        if self.deleted {
+            self.deleted = false;
+            self.current = None;
+        } else {
+            let position = self.position.saturating_add(1).min(self.record_count);
+            self.move_to(position);
+        }
+        !self.eof()
+    }
+
+    pub fn eof(&self) -> bool {
+        self.position >= self.record_count
+    }
+
+    pub fn record(&mut self, database: &mut Database) -> Result<&[Column], Box<dyn error::Error>> {
+        if self.eof() {
+            return Err(format!("cursor on {} is not at a record", self.table_name).into());
+        }
+        if self.current.is_none() {
+            let row = record::select_record(database, self.rootpage, self.position)?;
+            self.current = Some(database.read_row(&self.columns, pad_row(row, &self.defaults))?);
+        }
+        Ok(self.current.as_ref().unwrap())
+    }
+
+    /// Appends a record to the table, the cursor stays where it is.
+    pub fn insert(
+        &mut self,
+        database: &mut Database,
+        row: Vec<Column>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let record = record::create_record(database.write_row(&self.columns, row)?);
+        record::insert_record(database, record, self.rootpage);
+        database.commit()?;
+        self.record_count += 1;
+        self.written = true;
+        Ok(())
+    }
+
+    /// Overwrites the record at the cursor.
+    pub fn replace(
+        &mut self,
+        database: &mut Database,
+        row: Vec<Column>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let row = database.write_row(&self.columns, row)?;
+        record::update_record(database, self.rootpage, self.position, Some(row.clone()))?;
+        database.commit()?;
+        self.current = Some(database.read_row(&self.columns, row)?);
+        self.written = true;
+        Ok(())
+    }
+
+    /// Removes the record at the cursor, the next step moves to the record that followed it.
+    pub fn delete(&mut self, database: &mut Database) -> Result<(), Box<dyn error::Error>> {
+        record::update_record(database, self.rootpage, self.position, None)?;
+        database.commit()?;
+        self.record_count -= 1;
+        self.current = None;
+        self.deleted = true;
+        self.written = true;
+        Ok(())
+    }
+
+    pub fn close(self, database: &mut Database) -> Result<(), Box<dyn error::Error>> {
+        if self.written {
+            database.rebuild_indexes(self.table_name)?;
+        }
+        Ok(())
+    }
+
+    fn move_to(&mut self, position: u16) {
+        self.position = position;
+        self.current = None;
+        self.deleted = false;
+    }
+}
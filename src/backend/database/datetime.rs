@@ -0,0 +1,562 @@
+use super::Column;
+use super::ColumnType;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MS_PER_DAY: i64 = 86_400_000;
+// the Julian day of 1970-01-01, in milliseconds
+const UNIX_EPOCH_MS: i64 = 210_866_760_000_000;
+// 0000-01-01 00:00:00.000 and 9999-12-31 23:59:59.999
+const FIRST_MS: i64 = 148_699_540_800_000;
+const LAST_MS: i64 = 464_269_060_799_999;
+// intervals are stored as their months followed by 15 digits of milliseconds
+const INTERVAL_MONTH: i128 = 1_000_000_000_000_000;
+
+/// A point in time between the years 0000 and 9999, kept like SQLite does as the number of
+/// milliseconds since noon on the first day of the Julian period. Times are always UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DateTime(i64);
+
+impl DateTime {
+    pub fn from_milliseconds(milliseconds: i64) -> Option<DateTime> {
+        if (FIRST_MS..=LAST_MS).contains(&milliseconds) {
+            Some(DateTime(milliseconds))
+        } else {
+            None
+        }
+    }
+
+    /// The date and time with a day that may be past the end of its month, which runs on
+    /// into the next one.
+    pub fn from_civil(year: i64, month: i64, day: i64, millisecond: i64) -> Option<DateTime> {
+        let (mut year, mut month) = (year, month);
+        if month <= 2 {
+            year -= 1;
+            month += 12;
+        }
+        let a = year.div_euclid(100);
+        let b = 2 - a + a.div_euclid(4);
+        let x1 = (36525 * (year + 4716)).div_euclid(100);
+        let x2 = 30601 * (month + 1) / 1000;
+        let day_number = x1 + x2 + day + b - 1524;
+        DateTime::from_milliseconds(day_number * MS_PER_DAY - MS_PER_DAY / 2 + millisecond)
+    }
+
+    pub fn now() -> DateTime {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as i64)
+            .unwrap_or(0);
+        DateTime(UNIX_EPOCH_MS + since_epoch)
+    }
+
+    pub fn from_julian_day(julian_day: f64) -> Option<DateTime> {
+        if !julian_day.is_finite() {
+            return None;
+        }
+        DateTime::from_milliseconds((julian_day * MS_PER_DAY as f64).round() as i64)
+    }
+
+    pub fn from_unix_seconds(seconds: f64) -> Option<DateTime> {
+        if !seconds.is_finite() {
+            return None;
+        }
+        DateTime::from_milliseconds(UNIX_EPOCH_MS + (seconds * 1000.0).round() as i64)
+    }
+
+    /// Reads an ISO-8601 date `YYYY-MM-DD`, time `HH:MM[:SS[.SSS]]` or both separated by a
+    /// space or `T`. A time can end with `Z` or an offset `±HH:MM` from UTC, a time without a
+    /// date is on 2000-01-01.
+    pub fn parse(text: &str) -> Option<DateTime> {
+        let text = text.trim();
+        let mut scanner = Scanner::new(text);
+        let (year, month, day) = match scanner.date() {
+            Some(date) => date,
+            None => {
+                let millisecond = scanner.time_with_zone()?;
+                return if scanner.finished() {
+                    DateTime::from_civil(2000, 1, 1, millisecond)
+                } else {
+                    None
+                };
+            }
+        };
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let millisecond = if scanner.finished() {
+            0
+        } else if scanner.eat(b'T') || scanner.eat(b' ') {
+            scanner.skip_spaces();
+            scanner.time_with_zone()?
+        } else {
+            return None;
+        };
+        if !scanner.finished() {
+            return None;
+        }
+        DateTime::from_civil(year, month, day, millisecond)
+    }
+
+    pub fn milliseconds(&self) -> i64 {
+        self.0
+    }
+
+    pub fn julian_day(&self) -> f64 {
+        self.0 as f64 / MS_PER_DAY as f64
+    }
+
+    pub fn unix_milliseconds(&self) -> i64 {
+        self.0 - UNIX_EPOCH_MS
+    }
+
+    /// The year, month and day.
+    pub fn date(&self) -> (i64, i64, i64) {
+        let z = (self.0 + MS_PER_DAY / 2).div_euclid(MS_PER_DAY);
+        let a = ((z as f64 - 1867216.25) / 36524.25) as i64;
+        let a = z + 1 + a - a.div_euclid(4);
+        let b = a + 1524;
+        let c = ((b as f64 - 122.1) / 365.25) as i64;
+        let d = (36525 * (c & 32767)) / 100;
+        let e = ((b - d) as f64 / 30.6001) as i64;
+        let x1 = (30.6001 * e as f64) as i64;
+        let day = b - d - x1;
+        let month = if e < 14 { e - 1 } else { e - 13 };
+        let year = if month > 2 { c - 4716 } else { c - 4715 };
+        (year, month, day)
+    }
+
+    /// The milliseconds since midnight.
+    pub fn time(&self) -> i64 {
+        (self.0 + MS_PER_DAY / 2).rem_euclid(MS_PER_DAY)
+    }
+
+    /// The day of the week, 0 for Sunday.
+    pub fn weekday(&self) -> i64 {
+        ((self.0 + MS_PER_DAY / 2).div_euclid(MS_PER_DAY) + 1).rem_euclid(7)
+    }
+
+    /// Moves the date by a number of months, keeping the day of the month and time.
+    pub fn add_months(&self, months: i64) -> Option<DateTime> {
+        let (year, month, day) = self.date();
+        let month = year * 12 + month - 1 + months;
+        DateTime::from_civil(
+            month.div_euclid(12),
+            month.rem_euclid(12) + 1,
+            day,
+            self.time(),
+        )
+    }
+
+    pub fn add_milliseconds(&self, milliseconds: i64) -> Option<DateTime> {
+        DateTime::from_milliseconds(self.0.checked_add(milliseconds)?)
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn format_date(&self) -> String {
+        let (year, month, day) = self.date();
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+
+    /// `HH:MM:SS`, followed by the milliseconds when `milliseconds` is set
+    pub fn format_time(&self, milliseconds: bool) -> String {
+        format_time_of_day(self.time(), milliseconds)
+    }
+
+    /// `YYYY-MM-DD HH:MM:SS`, followed by the milliseconds when `milliseconds` is set
+    pub fn format_datetime(&self, milliseconds: bool) -> String {
+        format!("{} {}", self.format_date(), self.format_time(milliseconds))
+    }
+}
+
+fn format_time_of_day(millisecond: i64, milliseconds: bool) -> String {
+    let seconds = millisecond / 1000;
+    let time = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if milliseconds {
+        format!("{}.{:03}", time, millisecond % 1000)
+    } else {
+        time
+    }
+}
+
+/// A length of time in months and milliseconds, which have the same sign. Months are kept
+/// apart as they differ in length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub months: i64,
+    pub milliseconds: i64,
+}
+
+impl Interval {
+    /// Reads `±YYYY-MM-DD HH:MM:SS.SSS` as given by `timediff`, either half of it, or a list
+    /// of amounts of `years`, `months`, `days`, `hours`, `minutes` and `seconds` such as
+    /// `1 day -2 hours`. An interval with months and milliseconds of opposite signs can't be
+    /// represented.
+    pub fn parse(text: &str) -> Option<Interval> {
+        let text = text.trim();
+        let interval = Interval::parse_fields(text).or_else(|| Interval::parse_units(text))?;
+        if interval.months.signum() * interval.milliseconds.signum() < 0 {
+            return None;
+        }
+        Some(interval)
+    }
+
+    fn parse_fields(text: &str) -> Option<Interval> {
+        let mut scanner = Scanner::new(text);
+        let sign = scanner.sign();
+        let date = scanner.date();
+        let (months, days) = match date {
+            Some((years, months, days)) => (years * 12 + months, days),
+            None => (0, 0),
+        };
+        let millisecond = if date.is_none() || scanner.eat(b' ') {
+            scanner.skip_spaces();
+            scanner.time()?
+        } else {
+            0
+        };
+        if !scanner.finished() {
+            return None;
+        }
+        Some(Interval {
+            months: sign * months,
+            milliseconds: sign * (days * MS_PER_DAY + millisecond),
+        })
+    }
+
+    fn parse_units(text: &str) -> Option<Interval> {
+        let mut interval = Interval {
+            months: 0,
+            milliseconds: 0,
+        };
+        let mut words = text.split_whitespace();
+        let mut found = false;
+        while let Some(amount) = words.next() {
+            let amount: f64 = amount.parse().ok()?;
+            let unit = words.next()?.to_ascii_lowercase();
+            let unit = unit.trim_end_matches('s');
+            let months = match unit {
+                "year" => Some(12),
+                "month" => Some(1),
+                _ => None,
+            };
+            match months {
+                Some(_) if amount.fract() != 0.0 => return None,
+                Some(months) => interval.months += amount as i64 * months,
+                None => {
+                    let scale = match unit {
+                        "day" => MS_PER_DAY,
+                        "hour" => 3_600_000,
+                        "minute" => 60_000,
+                        "second" => 1000,
+                        _ => return None,
+                    };
+                    interval.milliseconds += (amount * scale as f64).round() as i64;
+                }
+            }
+            found = true;
+        }
+        if found {
+            Some(interval)
+        } else {
+            None
+        }
+    }
+
+    /// The interval that takes `earlier` to `later`, in whole months then what is left.
+    pub fn between(later: DateTime, earlier: DateTime) -> Interval {
+        let (sign, later, earlier) = if later >= earlier {
+            (1, later, earlier)
+        } else {
+            (-1, earlier, later)
+        };
+        let (later_year, later_month, _) = later.date();
+        let (earlier_year, earlier_month, _) = earlier.date();
+        let mut months = (later_year - earlier_year) * 12 + later_month - earlier_month;
+        let mut start = earlier.add_months(months);
+        while months > 0 && start.is_none_or(|start| start > later) {
+            months -= 1;
+            start = earlier.add_months(months);
+        }
+        let start = start.unwrap_or(earlier);
+        Interval {
+            months: sign * months,
+            milliseconds: sign * (later.milliseconds() - start.milliseconds()),
+        }
+    }
+
+    /// Moves a time by the interval, the months first.
+    pub fn add_to(&self, time: DateTime) -> Option<DateTime> {
+        time.add_months(self.months)?
+            .add_milliseconds(self.milliseconds)
+    }
+
+    /// The interval of the same length the other way.
+    pub fn negate(&self) -> Interval {
+        Interval {
+            months: -self.months,
+            milliseconds: -self.milliseconds,
+        }
+    }
+
+    /// The sum of two intervals, None when its months and milliseconds would have opposite
+    /// signs.
+    pub fn checked_add(&self, other: &Interval) -> Option<Interval> {
+        let sum = Interval {
+            months: self.months.checked_add(other.months)?,
+            milliseconds: self.milliseconds.checked_add(other.milliseconds)?,
+        };
+        if sum.months.signum() * sum.milliseconds.signum() < 0 {
+            return None;
+        }
+        Some(sum)
+    }
+
+    /// `±YYYY-MM-DD HH:MM:SS.SSS`
+    pub fn format(&self) -> String {
+        let sign = if self.months < 0 || self.milliseconds < 0 {
+            '-'
+        } else {
+            '+'
+        };
+        let (months, milliseconds) = (self.months.abs(), self.milliseconds.abs());
+        format!(
+            "{}{:04}-{:02}-{:02} {}",
+            sign,
+            months / 12,
+            months % 12,
+            milliseconds / MS_PER_DAY,
+            format_time_of_day(milliseconds % MS_PER_DAY, true)
+        )
+    }
+
+    fn encode(&self) -> i128 {
+        let sign = if self.months < 0 || self.milliseconds < 0 {
+            -1
+        } else {
+            1
+        };
+        sign * (self.months.abs() as i128 * INTERVAL_MONTH + self.milliseconds.abs() as i128)
+    }
+
+    fn decode(value: i128) -> Interval {
+        let sign = value.signum() as i64;
+        let value = value.abs();
+        Interval {
+            months: sign * (value / INTERVAL_MONTH) as i64,
+            milliseconds: sign * (value % INTERVAL_MONTH) as i64,
+        }
+    }
+}
+
+/// Gives a value written to a DATE, TIME, TIMESTAMP or INTERVAL column its canonical text:
+/// `YYYY-MM-DD`, `HH:MM:SS[.SSS]`, `YYYY-MM-DD HH:MM:SS[.SSS]` or `±YYYY-MM-DD HH:MM:SS.SSS`.
+/// Numbers are read as Julian days. Values that aren't dates or times are kept as text.
+pub fn apply(column_type: &ColumnType, value: Column) -> Column {
+    let time = match &value {
+        Column::Text(text) if *column_type == ColumnType::Interval => {
+            return match Interval::parse(text) {
+                Some(interval) => Column::Text(interval.format()),
+                None => value,
+            }
+        }
+        Column::Text(text) => DateTime::parse(text),
+        Column::Integer(int) if *column_type != ColumnType::Interval => {
+            DateTime::from_julian_day(*int as f64)
+        }
+        Column::Real(real) if *column_type != ColumnType::Interval => {
+            DateTime::from_julian_day(*real)
+        }
+        Column::Decimal(decimal) if *column_type != ColumnType::Interval => {
+            DateTime::from_julian_day(decimal.to_real())
+        }
+        Column::Null() | Column::Blob(_) => return value,
+        Column::Integer(_) | Column::Real(_) | Column::Decimal(_) => None,
+    };
+    match time {
+        Some(time) => Column::Text(format_as(column_type, time)),
+        None => match value {
+            Column::Integer(_) | Column::Real(_) | Column::Decimal(_) => {
+                ColumnType::Text.apply(value)
+            }
+            value => value,
+        },
+    }
+}
+
+// the canonical text of a time for a column
+fn format_as(column_type: &ColumnType, time: DateTime) -> String {
+    let milliseconds = time.time() % 1000 != 0;
+    match column_type {
+        ColumnType::Date => time.format_date(),
+        ColumnType::Time => time.format_time(milliseconds),
+        _ => time.format_datetime(milliseconds),
+    }
+}
+
+/// Stores the canonical text of a date, time or interval as an integer. Only dates and times
+/// are stored as integers in these columns, so any integer read back is one.
+pub fn compact(column_type: &ColumnType, value: Column) -> Column {
+    let text = match &value {
+        Column::Text(text) => text,
+        _ => return value,
+    };
+    let stored = match column_type {
+        ColumnType::Interval => Interval::parse(text).map(|interval| interval.encode()),
+        _ => DateTime::parse(text).map(|time| match column_type {
+            ColumnType::Date => ((time.milliseconds() + MS_PER_DAY / 2) / MS_PER_DAY) as i128,
+            ColumnType::Time => time.time() as i128,
+            _ => time.milliseconds() as i128,
+        }),
+    };
+    match stored {
+        Some(stored) => Column::Integer(stored),
+        None => value,
+    }
+}
+
+/// Turns a date, time or interval stored by `compact` back into its text.
+pub fn restore(column_type: &ColumnType, value: Column) -> Column {
+    let stored = match value {
+        Column::Integer(stored) => stored as i64,
+        value => return value,
+    };
+    let time = match column_type {
+        ColumnType::Interval => return Column::Text(Interval::decode(stored as i128).format()),
+        ColumnType::Date => DateTime::from_milliseconds(stored * MS_PER_DAY - MS_PER_DAY / 2),
+        ColumnType::Time => DateTime::from_civil(2000, 1, 1, stored),
+        _ => DateTime::from_milliseconds(stored),
+    };
+    match time {
+        Some(time) => Column::Text(format_as(column_type, time)),
+        None => Column::Integer(stored as i128),
+    }
+}
+
+// reads the fields of dates and times from the start of some text
+struct Scanner<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Scanner<'a> {
+        Scanner {
+            bytes: text.as_bytes(),
+            position: 0,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.bytes.get(self.position) == Some(&byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.eat(b' ') {}
+    }
+
+    // exactly `count` digits
+    fn number(&mut self, count: usize) -> Option<i64> {
+        let digits = self.bytes.get(self.position..self.position + count)?;
+        if !digits.iter().all(|digit| digit.is_ascii_digit()) {
+            return None;
+        }
+        self.position += count;
+        Some(
+            digits
+                .iter()
+                .fold(0, |number, digit| number * 10 + (digit - b'0') as i64),
+        )
+    }
+
+    fn sign(&mut self) -> i64 {
+        if self.eat(b'-') {
+            -1
+        } else {
+            self.eat(b'+');
+            1
+        }
+    }
+
+    // `YYYY-MM-DD`, the position is left alone when there isn't one
+    fn date(&mut self) -> Option<(i64, i64, i64)> {
+        let start = self.position;
+        let date = self.date_fields();
+        if date.is_none() {
+            self.position = start;
+        }
+        date
+    }
+
+    fn date_fields(&mut self) -> Option<(i64, i64, i64)> {
+        let year = self.number(4)?;
+        if !self.eat(b'-') {
+            return None;
+        }
+        let month = self.number(2)?;
+        if !self.eat(b'-') {
+            return None;
+        }
+        let day = self.number(2)?;
+        Some((year, month, day))
+    }
+
+    // `HH:MM[:SS[.SSS]]` as milliseconds
+    fn time(&mut self) -> Option<i64> {
+        let hour = self.number(2)?;
+        if !self.eat(b':') {
+            return None;
+        }
+        let minute = self.number(2)?;
+        let mut second = 0;
+        let mut millisecond = 0;
+        if self.eat(b':') {
+            second = self.number(2)?;
+            if self.eat(b'.') {
+                let mut scale = 100;
+                while let Some(digit) = self.number(1) {
+                    millisecond += digit * scale;
+                    scale /= 10;
+                }
+            }
+        }
+        if hour > 23 || minute > 59 || second > 59 {
+            return None;
+        }
+        Some(((hour * 60 + minute) * 60 + second) * 1000 + millisecond)
+    }
+
+    // a time followed by an optional time zone, as milliseconds since midnight UTC
+    fn time_with_zone(&mut self) -> Option<i64> {
+        let time = self.time()?;
+        self.skip_spaces();
+        if self.eat(b'Z') || self.eat(b'z') {
+            return Some(time);
+        }
+        let sign = match self.bytes.get(self.position) {
+            Some(b'+') => 1,
+            Some(b'-') => -1,
+            _ => return Some(time),
+        };
+        self.position += 1;
+        let hours = self.number(2)?;
+        if !self.eat(b':') {
+            return None;
+        }
+        let minutes = self.number(2)?;
+        Some(time - sign * (hours * 60 + minutes) * 60_000)
+    }
+}
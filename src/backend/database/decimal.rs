@@ -0,0 +1,224 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The most digits a decimal can have, all of them fit in an `i128`.
+pub const MAX_PRECISION: u8 = 38;
+// digits kept after the point by division beyond those of its operands
+const DIVISION_DIGITS: u8 = 6;
+
+/// An exact number, `value / 10^scale`. The scale is part of the value, so `1.50` keeps both
+/// of its decimal places.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decimal {
+    pub value: i128,
+    pub scale: u8,
+}
+
+impl Decimal {
+    pub fn new(value: i128, scale: u8) -> Decimal {
+        Decimal { value, scale }
+    }
+
+    pub fn from_integer(int: i128) -> Decimal {
+        Decimal::new(int, 0)
+    }
+
+    /// Reads a number like `-12.340` or `1.5e3`, surrounded by any spaces.
+    pub fn parse(text: &str) -> Option<Decimal> {
+        let text = text.trim();
+        let (mantissa, exponent) = match text.find(['e', 'E']) {
+            Some(position) => (&text[..position], text[position + 1..].parse::<i32>().ok()?),
+            None => (text, 0),
+        };
+        let (negative, mantissa) = match mantissa.as_bytes().first() {
+            Some(b'-') => (true, &mantissa[1..]),
+            Some(b'+') => (false, &mantissa[1..]),
+            _ => (false, mantissa),
+        };
+        let (whole, fraction) = match mantissa.find('.') {
+            Some(position) => (&mantissa[..position], &mantissa[position + 1..]),
+            None => (mantissa, ""),
+        };
+        let digits = whole.bytes().chain(fraction.bytes());
+        if whole.len() + fraction.len() == 0 || !digits.clone().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let mut value: i128 = 0;
+        for digit in digits {
+            value = value.checked_mul(10)?.checked_add((digit - b'0') as i128)?;
+        }
+        if negative {
+            value = -value;
+        }
+        let scale = fraction.len() as i32 - exponent;
+        if scale < 0 {
+            return Decimal::new(value, 0).shift((-scale) as u32);
+        }
+        if scale <= MAX_PRECISION as i32 {
+            return Some(Decimal::new(value, scale as u8));
+        }
+        // digits past the most a decimal can keep are rounded away
+        let value = match 10i128.checked_pow((scale - MAX_PRECISION as i32) as u32) {
+            Some(factor) => divide_rounded(value, factor)?,
+            None => 0,
+        };
+        Some(Decimal::new(value, MAX_PRECISION))
+    }
+
+    /// The shortest decimal that reads back as the real, None for infinities, NaN and reals
+    /// too large for a decimal.
+    pub fn from_real(real: f64) -> Option<Decimal> {
+        if !real.is_finite() {
+            return None;
+        }
+        Decimal::parse(&format!("{:e}", real))
+    }
+
+    pub fn to_real(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// The value when it is a whole number.
+    pub fn to_integer(&self) -> Option<i128> {
+        let scale = 10i128.checked_pow(self.scale as u32)?;
+        if self.value % scale == 0 {
+            Some(self.value / scale)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the decimal has at most `precision` digits.
+    pub fn fits(&self, precision: u8) -> bool {
+        match 10u128.checked_pow(precision as u32) {
+            Some(limit) => self.value.unsigned_abs() < limit,
+            None => true,
+        }
+    }
+
+    /// The decimal with another number of digits after the point, rounding halves away from
+    /// zero. None if it doesn't fit.
+    pub fn rescale(&self, scale: u8) -> Option<Decimal> {
+        if scale >= self.scale {
+            let factor = 10i128.checked_pow((scale - self.scale) as u32)?;
+            return Some(Decimal::new(self.value.checked_mul(factor)?, scale));
+        }
+        let factor = 10i128.checked_pow((self.scale - scale) as u32)?;
+        Some(Decimal::new(divide_rounded(self.value, factor)?, scale))
+    }
+
+    // multiplies by 10^digits, keeping the scale
+    fn shift(&self, digits: u32) -> Option<Decimal> {
+        let factor = 10i128.checked_pow(digits)?;
+        Some(Decimal::new(self.value.checked_mul(factor)?, self.scale))
+    }
+
+    // both values with the larger of their scales
+    fn align(&self, other: &Decimal) -> Option<(i128, i128, u8)> {
+        let scale = self.scale.max(other.scale);
+        Some((
+            self.rescale(scale)?.value,
+            other.rescale(scale)?.value,
+            scale,
+        ))
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, right, scale) = self.align(other)?;
+        Some(Decimal::new(left.checked_add(right)?, scale))
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, right, scale) = self.align(other)?;
+        Some(Decimal::new(left.checked_sub(right)?, scale))
+    }
+
+    pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+        let value = self.value.checked_mul(other.value)?;
+        let product = Decimal::new(value, self.scale + other.scale);
+        if product.scale > MAX_PRECISION {
+            product.rescale(MAX_PRECISION)
+        } else {
+            Some(product)
+        }
+    }
+
+    /// The quotient to six more places than either side has, rounded, without the trailing
+    /// zeros past the places of the two sides. None when dividing by zero.
+    pub fn checked_div(&self, other: &Decimal) -> Option<Decimal> {
+        if other.value == 0 {
+            return None;
+        }
+        let least = self.scale.max(other.scale);
+        let scale = (least + DIVISION_DIGITS).min(MAX_PRECISION);
+        // value / 10^s1 / (other / 10^s2) = value * 10^(scale + s2 - s1) / other / 10^scale
+        let numerator =
+            Decimal::new(self.value, 0).shift((scale + other.scale - self.scale) as u32)?;
+        let mut quotient = Decimal::new(divide_rounded(numerator.value, other.value)?, scale);
+        while quotient.scale > least && quotient.value % 10 == 0 {
+            quotient = Decimal::new(quotient.value / 10, quotient.scale - 1);
+        }
+        Some(quotient)
+    }
+
+    /// The remainder after dividing by `other`, which has the sign of this decimal.
+    pub fn checked_rem(&self, other: &Decimal) -> Option<Decimal> {
+        let (left, right, scale) = self.align(other)?;
+        Some(Decimal::new(left.checked_rem(right)?, scale))
+    }
+
+    pub fn negate(&self) -> Option<Decimal> {
+        Some(Decimal::new(self.value.checked_neg()?, self.scale))
+    }
+
+    pub fn compare(&self, other: &Decimal) -> Ordering {
+        match self.align(other) {
+            Some((left, right, _)) => left.cmp(&right),
+            None => self
+                .to_real()
+                .partial_cmp(&other.to_real())
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+
+    /// Compares with a real through the shortest decimal that reads back as the real.
+    pub fn compare_real(&self, real: f64) -> Ordering {
+        match Decimal::from_real(real) {
+            Some(other) => self.compare(&other),
+            None if real.is_nan() => Ordering::Equal,
+            None if real > 0.0 => Ordering::Less,
+            None => Ordering::Greater,
+        }
+    }
+}
+
+// divides, rounding halves away from zero
+fn divide_rounded(numerator: i128, denominator: i128) -> Option<i128> {
+    let quotient = numerator.checked_div(denominator)?;
+    let remainder = (numerator % denominator).unsigned_abs();
+    if remainder >= denominator.unsigned_abs() - remainder {
+        let away = if (numerator < 0) != (denominator < 0) {
+            -1
+        } else {
+            1
+        };
+        quotient.checked_add(away)
+    } else {
+        Some(quotient)
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = self.value.unsigned_abs().to_string();
+        let scale = self.scale as usize;
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        let sign = if self.value < 0 { "-" } else { "" };
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, whole)
+        } else {
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        }
+    }
+}
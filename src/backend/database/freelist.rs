@@ -0,0 +1,92 @@
+use super::Database;
+
+use serialise;
+use std::error;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+
+// where the header keeps the page count, and the first free page followed by the number of
+// free pages
+const PAGE_COUNT_OFFSET: u64 = 2;
+const FREELIST_OFFSET: u64 = 32;
+
+impl Database {
+    /// Writes an empty page of the given type, reusing a free page when there is one, and
+    /// returns its number.
+    pub fn allocate_page(&mut self, page_type: u8) -> Result<u32, Box<dyn error::Error>> {
+        self.commit()?;
+        let (first_free, free_count) = self.read_freelist()?;
+
+        let page_number = if first_free != 0 {
+            // each free page starts with the number of the one after it
+            let next_free = self.read_u32(self.page_offset(first_free))?;
+            self.write_freelist(next_free, free_count - 1)?;
+            first_free
+        } else {
+            self.page_count += 1;
+            let page_count = self.page_count;
+            self.write_u32(PAGE_COUNT_OFFSET, page_count)?;
+            page_count
+        };
+
+        let mut page = vec![0u8; self.page_size as usize];
+        page[0] = page_type;
+        self.file
+            .seek(SeekFrom::Start(self.page_offset(page_number)))?;
+        self.file.write_all(&page)?;
+        self.page_cache.remove(&page_number);
+        Ok(page_number)
+    }
+
+    /// Adds a page nothing refers to any more to the freelist, its contents are cleared.
+    pub fn free_page(&mut self, page_number: u32) -> Result<(), Box<dyn error::Error>> {
+        self.commit()?;
+        let (first_free, free_count) = self.read_freelist()?;
+
+        let mut page = vec![0u8; self.page_size as usize];
+        page[..4].copy_from_slice(&first_free.to_be_bytes());
+        self.file
+            .seek(SeekFrom::Start(self.page_offset(page_number)))?;
+        self.file.write_all(&page)?;
+        self.page_cache.remove(&page_number);
+
+        self.write_freelist(page_number, free_count + 1)
+    }
+
+    /// The number of pages on the freelist.
+    pub fn free_page_count(&mut self) -> Result<u32, Box<dyn error::Error>> {
+        Ok(self.read_freelist()?.1)
+    }
+
+    fn read_freelist(&mut self) -> Result<(u32, u32), Box<dyn error::Error>> {
+        let first_free = self.read_u32(FREELIST_OFFSET)?;
+        let free_count = self.read_u32(FREELIST_OFFSET + 4)?;
+        Ok((first_free, free_count))
+    }
+
+    fn write_freelist(
+        &mut self,
+        first_free: u32,
+        free_count: u32,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.write_u32(FREELIST_OFFSET, first_free)?;
+        self.write_u32(FREELIST_OFFSET + 4, free_count)
+    }
+
+    fn page_offset(&self, page_number: u32) -> u64 {
+        (page_number - 1) as u64 * self.page_size as u64
+    }
+
+    fn read_u32(&mut self, offset: u64) -> Result<u32, Box<dyn error::Error>> {
+        let mut bytes = [0u8; 4];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut bytes)?;
+        serialise::to_integer(&bytes)
+    }
+
+    fn write_u32(&mut self, offset: u64, value: u32) -> Result<(), Box<dyn error::Error>> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(&value.to_be_bytes())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use super::Column;
+use super::Database;
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::rc::Rc;
+
+/// A scalar function registered from Rust, called with the values of its arguments.
+pub type ScalarFunction = Rc<dyn Fn(&[Column]) -> Result<Column, Box<dyn error::Error>>>;
+
+/// Makes the state of a registered aggregate function for a new group of rows.
+pub type AggregateFactory = Rc<dyn Fn() -> Box<dyn AggregateFunction>>;
+
+/// The functions registered on a database, by their lowercase names and the number of
+/// arguments they take, None when they take any number.
+pub type Functions = HashMap<(String, Option<usize>), UserFunction>;
+
+/// The state of an aggregate function registered from Rust, kept for a group of rows or for
+/// the frame of a window function.
+pub trait AggregateFunction {
+    /// Adds the arguments of a row.
+    fn step(&mut self, arguments: &[Column]) -> Result<(), Box<dyn error::Error>>;
+
+    /// Takes away the arguments of a row added earlier, so that a window frame can slide
+    /// along its partition rather than being stepped through again for every row. Gives false
+    /// when the aggregate can't, which is what it does unless it's implemented.
+    fn inverse(&mut self, _arguments: &[Column]) -> Result<bool, Box<dyn error::Error>> {
+        Ok(false)
+    }
+
+    /// The result for the rows added so far.
+    fn finalize(&self) -> Result<Column, Box<dyn error::Error>>;
+}
+
+/// A function registered with `create_scalar_function` or `create_aggregate_function`.
+#[derive(Clone)]
+pub enum UserFunction {
+    Scalar {
+        function: ScalarFunction,
+        /// whether the function always gives the same result for the same arguments
+        deterministic: bool,
+    },
+    Aggregate(AggregateFactory),
+}
+
+impl fmt::Debug for UserFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UserFunction::Scalar { deterministic, .. } => {
+                write!(f, "Scalar {{ deterministic: {} }}", deterministic)
+            }
+            UserFunction::Aggregate(_) => write!(f, "Aggregate"),
+        }
+    }
+}
+
+impl Database {
+    /// Registers a scalar function that SQL can call by name, whatever its case. `n_args` is
+    /// the number of arguments it takes, or -1 for any number, and a function registered
+    /// with the same name and number replaces it. Functions registered on the database are
+    /// found before the built in ones, and like collations they aren't saved in the file.
+    pub fn create_scalar_function<F>(
+        &mut self,
+        name: &str,
+        n_args: i32,
+        deterministic: bool,
+        function: F,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        F: Fn(&[Column]) -> Result<Column, Box<dyn error::Error>> + 'static,
+    {
+        let function = UserFunction::Scalar {
+            function: Rc::new(function),
+            deterministic,
+        };
+        self.register_function(name, n_args, function)
+    }
+
+    /// Registers an aggregate function, `create` makes its state for each group of rows. Like
+    /// the built in aggregates it can be called with DISTINCT and used as a window function.
+    pub fn create_aggregate_function<F, A>(
+        &mut self,
+        name: &str,
+        n_args: i32,
+        create: F,
+    ) -> Result<(), Box<dyn error::Error>>
+    where
+        F: Fn() -> A + 'static,
+        A: AggregateFunction + 'static,
+    {
+        let create = move || Box::new(create()) as Box<dyn AggregateFunction>;
+        self.register_function(name, n_args, UserFunction::Aggregate(Rc::new(create)))
+    }
+
+    fn register_function(
+        &mut self,
+        name: &str,
+        n_args: i32,
+        function: UserFunction,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let arguments = match n_args {
+            -1 => None,
+            n_args if n_args >= 0 => Some(n_args as usize),
+            _ => return Err(format!("invalid number of arguments: {}", n_args).into()),
+        };
+        if name.is_empty() {
+            return Err("a function needs a name".into());
+        }
+        self.functions
+            .insert((name.to_lowercase(), arguments), function);
+        Ok(())
+    }
+}
+
+/// Finds the registered function for a call, one taking exactly as many arguments as it's
+/// called with before one taking any number.
+pub fn find_function<'a>(
+    functions: &'a Functions,
+    name: &str,
+    argument_count: usize,
+) -> Option<&'a UserFunction> {
+    let name = name.to_lowercase();
+    functions
+        .get(&(name.clone(), Some(argument_count)))
+        .or_else(|| functions.get(&(name, None)))
+}
@@ -0,0 +1,189 @@
+use super::super::record;
+use super::affinity::{load_row, store_row};
+use super::Column;
+use super::ColumnType;
+use super::Database;
+
+use std::error;
+
+/// A column worked out from the other columns of its row, declared with
+/// `GENERATED ALWAYS AS (expression)`. A VIRTUAL column is kept as NULL and worked out each
+/// time its row is read, a STORED one is worked out and kept whenever its row is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Generated {
+    /// the expression as SQL, its column references aren't qualified by the table name
+    pub expression: String,
+    pub stored: bool,
+}
+
+/// The columns of a table along with their collations and how the generated ones are worked
+/// out, loaded once for the rows read or written together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratedColumns {
+    pub columns: Vec<(String, ColumnType)>,
+    pub collations: Vec<Option<String>>,
+    pub generated: Vec<Option<Generated>>,
+}
+
+/// Works out the value of a generated column's expression for a row of its table. The
+/// backend can't evaluate SQL itself, so the SQL layer gives it one.
+pub type Generator =
+    fn(&Database, &GeneratedColumns, &str, &[Column]) -> Result<Column, Box<dyn error::Error>>;
+
+impl GeneratedColumns {
+    /// Whether the table has any generated columns.
+    pub fn any(&self) -> bool {
+        self.generated.iter().any(Option::is_some)
+    }
+}
+
+impl Database {
+    /// How each column of a table is generated, None for the columns that aren't.
+    pub fn table_generated(
+        &mut self,
+        table_name: String,
+    ) -> Result<Vec<Option<Generated>>, Box<dyn error::Error>> {
+        let (_page_number, columns) = self.describe_table(table_name.clone())?;
+        let record_filter = |row: &Vec<Column>| match (&row[0], &row[1]) {
+            (Column::Integer(1), Column::Text(row_table_name)) => *row_table_name == table_name,
+            _ => false,
+        };
+        let rows = record::select_records(self, 1, record_filter, |row| row)?;
+
+        // the generated columns are kept as a record after the collations, each one as a
+        // record of its expression and whether it's stored
+        let generated = match rows.first().and_then(|row| row.get(6)) {
+            Some(Column::Blob(data)) => record::read_record(data),
+            _ => Vec::new(),
+        };
+        let mut generated: Vec<Option<Generated>> = generated
+            .into_iter()
+            .map(|generated| match generated {
+                Column::Blob(data) => match record::read_record(&data).as_slice() {
+                    [Column::Text(expression), Column::Integer(stored)] => Some(Generated {
+                        expression: expression.clone(),
+                        stored: *stored != 0,
+                    }),
+                    _ => panic!("Generated column stored incorrectly"),
+                },
+                _ => None,
+            })
+            .collect();
+        generated.resize(columns.len(), None);
+        Ok(generated)
+    }
+
+    /// What's needed to work out the generated columns of a table's rows.
+    pub fn generated_columns(
+        &mut self,
+        table_name: String,
+    ) -> Result<GeneratedColumns, Box<dyn error::Error>> {
+        let (_page_number, columns) = self.describe_table(table_name.clone())?;
+        Ok(GeneratedColumns {
+            columns,
+            collations: self.table_collations(table_name.clone())?,
+            generated: self.table_generated(table_name)?,
+        })
+    }
+
+    /// Works out every generated column of a row being written, in the order they were added
+    /// so that one can use those before it. The other values are given their column's affinity
+    /// first.
+    pub fn generate_columns(
+        &self,
+        table: &GeneratedColumns,
+        row: &mut [Column],
+    ) -> Result<(), Box<dyn error::Error>> {
+        if table.any() {
+            self.generate(table, row, true)?;
+        }
+        Ok(())
+    }
+
+    // works out the virtual columns of a row as it's read, or all the generated ones as
+    // it's written
+    fn generate(
+        &self,
+        table: &GeneratedColumns,
+        row: &mut [Column],
+        writing: bool,
+    ) -> Result<(), Box<dyn error::Error>> {
+        if writing {
+            for (value, (_name, column_type)) in row.iter_mut().zip(&table.columns) {
+                *value = column_type.apply(value.clone());
+            }
+        }
+        for (position, generated) in table.generated.iter().enumerate() {
+            let generated = match generated {
+                Some(generated) if writing || !generated.stored => generated,
+                _ => continue,
+            };
+            let generator = match self.generator {
+                Some(generator) => generator,
+                None => {
+                    return Err(format!(
+                        "generated column {} needs a database loaded by the SQL layer",
+                        table.columns[position].0
+                    )
+                    .into())
+                }
+            };
+            if position < row.len() {
+                let value = generator(self, table, &generated.expression, row)?;
+                row[position] = table.columns[position].1.apply(value);
+            }
+        }
+        Ok(())
+    }
+
+    /// A row read from a table as its values, with its virtual columns worked out.
+    pub(super) fn read_row(
+        &self,
+        table: &GeneratedColumns,
+        row: Vec<Column>,
+    ) -> Result<Vec<Column>, Box<dyn error::Error>> {
+        let mut row = load_row(&table.columns, row);
+        if table.any() {
+            self.generate(table, &mut row, false)?;
+        }
+        Ok(row)
+    }
+
+    /// A row as it's kept in a table apart from its virtual columns, which are worked out.
+    pub(super) fn with_virtual(
+        &self,
+        table: &GeneratedColumns,
+        mut row: Vec<Column>,
+    ) -> Result<Vec<Column>, Box<dyn error::Error>> {
+        if !table.any() {
+            return Ok(row);
+        }
+        let read = self.read_row(table, row.clone())?;
+        for (position, generated) in table.generated.iter().enumerate() {
+            if let Some(Generated { stored: false, .. }) = generated {
+                row[position] = read[position].clone();
+            }
+        }
+        Ok(row)
+    }
+
+    /// A row as it's written to a table, with its stored columns worked out and its virtual
+    /// ones left NULL.
+    pub(super) fn write_row(
+        &self,
+        table: &GeneratedColumns,
+        mut row: Vec<Column>,
+    ) -> Result<Vec<Column>, Box<dyn error::Error>> {
+        if !table.any() {
+            return store_row(&table.columns, row);
+        }
+        self.generate(table, &mut row, true)?;
+        let mut row = store_row(&table.columns, row)?;
+        for (value, generated) in row.iter_mut().zip(&table.generated) {
+            if let Some(Generated { stored: false, .. }) = generated {
+                *value = Column::Null();
+            }
+        }
+        Ok(row)
+    }
+}
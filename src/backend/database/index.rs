@@ -0,0 +1,196 @@
+extern crate serde;
+
+use super::super::record;
+use super::pad_row;
+use super::Collation;
+use super::Column;
+use super::Database;
+
+use std::cmp::Ordering;
+use std::error;
+
+/// An index over some of a table's columns. Index entries are records of the indexed
+/// column values followed by the position of the row in the table, kept in key order. Text
+/// is ordered by the collation declared for its column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Index {
+    pub name: String,
+    pub table_name: String,
+    pub rootpage: u32,
+    pub columns: Vec<String>,
+}
+
+impl Database {
+    pub fn create_index(
+        &mut self,
+        index_name: String,
+        table_name: String,
+        columns: Vec<String>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let (_page_number, table_columns) = self.describe_table(table_name.clone())?;
+        for column in &columns {
+            if !table_columns.iter().any(|(name, _)| name == column) {
+                return Err(format!("table {} has no column named {}", table_name, column).into());
+            }
+        }
+
+        let name_filter = |row: &Vec<Column>| row[1] == Column::Text(index_name.clone());
+        let existing = record::select_records(self, 1, name_filter, |row| row)?;
+        if !existing.is_empty() {
+            return Err(format!("{} already exists", index_name).into());
+        }
+
+        let rootpage = self.allocate_page(10)?;
+        let schema_type = 2;
+        let serialised_index = bincode::serialize(&(&table_name, &columns))?;
+
+        let row = vec![
+            Column::Integer(schema_type),
+            Column::Text(index_name.clone()),
+            Column::Integer(rootpage as i128),
+            Column::Blob(serialised_index),
+        ];
+
+        let record = record::create_record(row);
+        record::insert_record(self, record, 1);
+        self.commit()?;
+
+        let index = Index {
+            name: index_name,
+            table_name,
+            rootpage,
+            columns,
+        };
+        self.rebuild_index(&index)
+    }
+
+    pub fn list_indexes(
+        &mut self,
+        table_name: String,
+    ) -> Result<Vec<Index>, Box<dyn error::Error>> {
+        let record_filter = |row: &Vec<Column>| row[0] == Column::Integer(2);
+        let rows = record::select_records(self, 1, record_filter, |row| row)?;
+
+        let mut indexes = Vec::new();
+        for row in rows {
+            match row.as_slice() {
+                [_, Column::Text(name), Column::Integer(rootpage), Column::Blob(data)] => {
+                    let (indexed_table, columns) =
+                        bincode::deserialize::<(String, Vec<String>)>(data)?;
+                    if indexed_table == table_name {
+                        indexes.push(Index {
+                            name: name.clone(),
+                            table_name: indexed_table,
+                            rootpage: *rootpage as u32,
+                            columns,
+                        });
+                    }
+                }
+                _ => panic!("Index stored incorrectly"),
+            }
+        }
+        Ok(indexes)
+    }
+
+    /// Finds the rows whose leading indexed columns equal `key`.
+    pub fn search_index(
+        &mut self,
+        index: &Index,
+        key: &[Column],
+    ) -> Result<Vec<Vec<Column>>, Box<dyn error::Error>> {
+        // NULL is never equal to anything
+        if key.contains(&Column::Null()) {
+            return Ok(Vec::new());
+        }
+
+        let collations = self.index_collations(index)?;
+        let compare = |entry: &Vec<Column>| compare_entries(entry, key, &collations);
+        let entries = record::search_records(self, index.rootpage, compare)?;
+
+        let (page_number, _columns) = self.describe_table(index.table_name.clone())?;
+        let defaults = self.table_defaults(index.table_name.clone())?;
+        let generated = self.generated_columns(index.table_name.clone())?;
+        let mut rows = Vec::with_capacity(entries.len());
+        for entry in entries {
+            match entry.last() {
+                Some(Column::Integer(position)) => {
+                    let row = record::select_record(self, page_number, *position as u16)?;
+                    rows.push(self.with_virtual(&generated, pad_row(row, &defaults))?);
+                }
+                _ => panic!("Index entry stored incorrectly"),
+            }
+        }
+        Ok(rows)
+    }
+
+    pub fn rebuild_indexes(&mut self, table_name: String) -> Result<(), Box<dyn error::Error>> {
+        for index in self.list_indexes(table_name)? {
+            self.rebuild_index(&index)?;
+        }
+        Ok(())
+    }
+
+    fn rebuild_index(&mut self, index: &Index) -> Result<(), Box<dyn error::Error>> {
+        let (_page_number, table_columns) = self.describe_table(index.table_name.clone())?;
+        let positions: Vec<usize> = index
+            .columns
+            .iter()
+            .filter_map(|column| table_columns.iter().position(|(name, _)| name == column))
+            .collect();
+
+        let rows = self.select_all_records(index.table_name.clone())?;
+        let mut entries: Vec<Vec<Column>> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(position, row)| {
+                let mut entry: Vec<Column> = positions
+                    .iter()
+                    .map(|index| row.get(*index).cloned().unwrap_or(Column::Null()))
+                    .collect();
+                entry.push(Column::Integer(position as i128));
+                entry
+            })
+            .collect();
+
+        let collations = self.index_collations(index)?;
+        entries.sort_by(|left, right| compare_entries(left, right, &collations));
+
+        let records = entries.into_iter().map(record::create_record).collect();
+        record::replace_records(self, records, index.rootpage);
+        self.commit()?;
+        Ok(())
+    }
+
+    // the collation of each indexed column
+    pub(super) fn index_collations(
+        &mut self,
+        index: &Index,
+    ) -> Result<Vec<Collation>, Box<dyn error::Error>> {
+        let (_page_number, table_columns) = self.describe_table(index.table_name.clone())?;
+        let declared = self.table_collations(index.table_name.clone())?;
+        let mut collations = Vec::with_capacity(index.columns.len());
+        for column in &index.columns {
+            let position = table_columns.iter().position(|(name, _)| name == column);
+            match position.and_then(|position| declared[position].as_ref()) {
+                Some(name) => collations.push(self.collation(name)?),
+                None => collations.push(Collation::Binary),
+            }
+        }
+        Ok(collations)
+    }
+}
+
+// compares index entries, or an entry with a key, column by column; the row positions after
+// the indexed columns compare as integers
+fn compare_entries(left: &[Column], right: &[Column], collations: &[Collation]) -> Ordering {
+    for (position, (left, right)) in left.iter().zip(right).enumerate() {
+        let ordering = match collations.get(position) {
+            Some(collation) => collation.compare(left, right),
+            None => left.compare(right),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
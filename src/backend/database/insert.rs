@@ -2,6 +2,7 @@ extern crate serde;
 extern crate sysconf;
 
 use super::super::record;
+use super::pad_row;
 use super::Column;
 use super::ColumnType;
 use super::Database;
@@ -15,18 +16,17 @@ impl Database {
         table_name: String,
         row_hashs: Vec<HashMap<String, String>>,
     ) -> Result<(), Box<dyn error::Error>> {
-        let (page_number, columns) = self.describe_table(table_name)?;
+        let (page_number, columns) = self.describe_table(table_name.clone())?;
+        let generated = self.generated_columns(table_name.clone())?;
 
-        row_hashs
-            .into_iter()
-            .map(|row_hash| {
-                let row = create_row(&columns, row_hash);
-                let record = record::create_record(row);
-                record::insert_record(self, record, page_number);
-            })
-            .count();
+        for row_hash in row_hashs {
+            let row = self.write_row(&generated, create_row(&columns, row_hash))?;
+            let record = record::create_record(row);
+            record::insert_record(self, record, page_number);
+        }
 
         self.commit()?;
+        self.rebuild_indexes(table_name)?;
         Ok(())
     }
 
@@ -35,11 +35,13 @@ impl Database {
         table_name: String,
         row_hash: HashMap<String, String>,
     ) -> Result<(), Box<dyn error::Error>> {
-        let (page_number, columns) = self.describe_table(table_name)?;
-        let row = create_row(&columns, row_hash);
+        let (page_number, columns) = self.describe_table(table_name.clone())?;
+        let generated = self.generated_columns(table_name.clone())?;
+        let row = self.write_row(&generated, create_row(&columns, row_hash))?;
         let record = record::create_record(row);
         record::insert_record(self, record, page_number);
         self.commit()?;
+        self.rebuild_indexes(table_name)?;
         Ok(())
     }
 
@@ -62,63 +64,33 @@ impl Database {
         RecF: Fn(&Vec<Column>) -> bool,
         ColF: FnMut(Vec<Column>) -> Vec<Column>,
     {
-        let (page_number, _columns) = self.describe_table(table_name)?;
-        record::select_records(self, page_number, record_filter, column_filter)
-    }
-}
-
-fn create_row(
-    columns: &Vec<(String, ColumnType)>,
-    row_hash: HashMap<String, String>,
-) -> Vec<Column> {
-    let row = columns
-        .into_iter()
-        .map(|col| match col {
-            (col_name, ColumnType::Integer) => {
-                let column_wrapper = |value| Column::Integer(value);
-                parse_column(&row_hash, col_name, column_wrapper)
-            }
-            (col_name, ColumnType::Real) => {
-                let column_wrapper = |value| Column::Real(value);
-                parse_column(&row_hash, col_name, column_wrapper)
-            }
-            (col_name, ColumnType::Text) => {
-                let column_wrapper = |value| Column::Text(value);
-                parse_column(&row_hash, col_name, column_wrapper)
-            }
-            (col_name, ColumnType::Blob) => {
-                let value = row_hash.get(col_name);
-                match value {
-                    Some(value) => {
-                        // TODO parse binary correctly
-                        let value = value.clone().into_bytes();
-                        Column::Blob(value)
-                    }
-                    None => Column::Null(),
-                }
-            }
-        })
-        .collect();
-    row
-}
-
-fn parse_column<T: std::str::FromStr, ColFn>(
-    row_hash: &HashMap<String, String>,
-    col_name: &String,
-    column_wrapper: ColFn,
-) -> Column
-where
-    ColFn: Fn(T) -> Column,
-{
-    let value = row_hash.get(col_name);
-    match value {
-        Some(value) => {
-            let value = value.parse();
-            match value {
-                Ok(value) => column_wrapper(value),
-                _ => panic!("input value not compatible with data type"),
+        let (page_number, _columns) = self.describe_table(table_name.clone())?;
+        let defaults = self.table_defaults(table_name.clone())?;
+        let generated = self.generated_columns(table_name)?;
+        let rows = record::select_records(self, page_number, |_row| true, |row| row)?;
+        let mut selected = Vec::new();
+        for row in rows {
+            let row = self.read_row(&generated, pad_row(row, &defaults))?;
+            if record_filter(&row) {
+                selected.push(row);
             }
         }
-        None => Column::Null(),
+        Ok(selected.into_iter().map(column_filter).collect())
     }
 }
+
+// the values of a row given as text, each is converted by its column's affinity while a
+// BLOB column takes the bytes of the text
+fn create_row(columns: &[(String, ColumnType)], row_hash: HashMap<String, String>) -> Vec<Column> {
+    columns
+        .iter()
+        .map(
+            |(col_name, column_type)| match (row_hash.get(col_name), column_type) {
+                // TODO parse binary correctly
+                (Some(value), ColumnType::Blob) => Column::Blob(value.clone().into_bytes()),
+                (Some(value), column_type) => column_type.apply(Column::Text(value.clone())),
+                (None, _) => Column::Null(),
+            },
+        )
+        .collect()
+}
@@ -0,0 +1,343 @@
+use super::format_real;
+use super::Column;
+
+use std::fmt;
+
+/// A parsed JSON value. Numbers keep the text they were written with, and the members of an
+/// object keep their order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    True,
+    False,
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+// nesting deeper than this is rejected rather than risking the stack
+const MAX_DEPTH: usize = 1000;
+
+impl Json {
+    /// Reads JSON text as RFC 8259 defines it, with whitespace around the value. None if the
+    /// text is malformed.
+    pub fn parse(text: &str) -> Option<Json> {
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            position: 0,
+        };
+        let value = parser.value(0)?;
+        parser.whitespace();
+        if parser.position == parser.bytes.len() {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// The JSON for a value, text becomes a JSON string.
+    pub fn from_column(value: &Column) -> Json {
+        match value {
+            Column::Null() => Json::Null,
+            Column::Integer(int) => Json::Number(int.to_string()),
+            Column::Real(real) if real.is_finite() => Json::Number(format_real(*real)),
+            Column::Real(_) => Json::Null,
+            Column::Decimal(decimal) => Json::Number(decimal.to_string()),
+            Column::Text(text) => Json::String(text.clone()),
+            Column::Blob(bytes) => Json::String(String::from_utf8_lossy(bytes).into_owned()),
+        }
+    }
+
+    /// The value SQL sees: null is NULL, true and false are 1 and 0, numbers are integers or
+    /// reals, strings are their text and arrays and objects are their JSON text.
+    pub fn to_column(&self) -> Column {
+        match self {
+            Json::Null => Column::Null(),
+            Json::True => Column::Integer(1),
+            Json::False => Column::Integer(0),
+            Json::Number(number) => match number.parse::<i128>() {
+                Ok(int) => Column::Integer(int),
+                Err(_) => Column::Real(number.parse().unwrap_or(0.0)),
+            },
+            Json::String(text) => Column::Text(text.clone()),
+            Json::Array(_) | Json::Object(_) => Column::Text(self.to_string()),
+        }
+    }
+
+    /// The name `json_type` gives the value.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::True => "true",
+            Json::False => "false",
+            Json::Number(number) if number.parse::<i128>().is_ok() => "integer",
+            Json::Number(_) => "real",
+            Json::String(_) => "text",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        }
+    }
+}
+
+/// Writes the value without any whitespace.
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::True => write!(f, "true"),
+            Json::False => write!(f, "false"),
+            Json::Number(number) => write!(f, "{}", number),
+            Json::String(text) => write_string(f, text),
+            Json::Array(elements) => {
+                write!(f, "[")?;
+                for (position, element) in elements.iter().enumerate() {
+                    if position > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (position, (key, value)) in members.iter().enumerate() {
+                    if position > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Converts a value written to a JSON column, well formed JSON text is kept without its
+/// whitespace.
+pub fn apply(value: Column) -> Column {
+    match value {
+        Column::Text(text) => match Json::parse(&text) {
+            Some(json) => Column::Text(json.to_string()),
+            None => Column::Text(text),
+        },
+        value => value,
+    }
+}
+
+/// Whether a value can be stored in a JSON column, which holds NULL, numbers and JSON text.
+pub fn check(value: &Column) -> Result<(), String> {
+    match value {
+        Column::Text(text) if Json::parse(text).is_none() => Err("malformed JSON".to_string()),
+        Column::Blob(_) => Err("JSON cannot hold BLOB values".to_string()),
+        _ => Ok(()),
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn whitespace(&mut self) {
+        while let Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') = self.peek() {
+            self.position += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn consume(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Json) -> Option<Json> {
+        if self.bytes[self.position..].starts_with(word.as_bytes()) {
+            self.position += word.len();
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Option<Json> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        self.whitespace();
+        match self.peek()? {
+            b'n' => self.literal("null", Json::Null),
+            b't' => self.literal("true", Json::True),
+            b'f' => self.literal("false", Json::False),
+            b'"' => self.string().map(Json::String),
+            b'[' => {
+                self.position += 1;
+                let mut elements = Vec::new();
+                self.whitespace();
+                if self.consume(b']') {
+                    return Some(Json::Array(elements));
+                }
+                loop {
+                    elements.push(self.value(depth + 1)?);
+                    self.whitespace();
+                    if self.consume(b']') {
+                        return Some(Json::Array(elements));
+                    }
+                    if !self.consume(b',') {
+                        return None;
+                    }
+                }
+            }
+            b'{' => {
+                self.position += 1;
+                let mut members = Vec::new();
+                self.whitespace();
+                if self.consume(b'}') {
+                    return Some(Json::Object(members));
+                }
+                loop {
+                    self.whitespace();
+                    let key = self.string()?;
+                    self.whitespace();
+                    if !self.consume(b':') {
+                        return None;
+                    }
+                    members.push((key, self.value(depth + 1)?));
+                    self.whitespace();
+                    if self.consume(b'}') {
+                        return Some(Json::Object(members));
+                    }
+                    if !self.consume(b',') {
+                        return None;
+                    }
+                }
+            }
+            _ => self.number(),
+        }
+    }
+
+    // -?(0|[1-9][0-9]*)(\.[0-9]+)?([eE][+-]?[0-9]+)?
+    fn number(&mut self) -> Option<Json> {
+        let start = self.position;
+        self.consume(b'-');
+        if !self.consume(b'0') && self.digits() == 0 {
+            return None;
+        }
+        if self.consume(b'.') && self.digits() == 0 {
+            return None;
+        }
+        if self.consume(b'e') || self.consume(b'E') {
+            if !self.consume(b'+') {
+                self.consume(b'-');
+            }
+            if self.digits() == 0 {
+                return None;
+            }
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.position]).ok()?;
+        Some(Json::Number(text.to_string()))
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.position;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.position += 1;
+        }
+        self.position - start
+    }
+
+    fn string(&mut self) -> Option<String> {
+        if !self.consume(b'"') {
+            return None;
+        }
+        let mut bytes = Vec::new();
+        loop {
+            match self.peek()? {
+                b'"' => {
+                    self.position += 1;
+                    return String::from_utf8(bytes).ok();
+                }
+                b'\\' => {
+                    self.position += 1;
+                    let escaped = match self.peek()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            self.position += 1;
+                            let c = self.unicode_escape()?;
+                            let mut buffer = [0; 4];
+                            bytes.extend(c.encode_utf8(&mut buffer).as_bytes());
+                            continue;
+                        }
+                        _ => return None,
+                    };
+                    self.position += 1;
+                    bytes.push(escaped as u8);
+                }
+                byte if byte < 0x20 => return None,
+                byte => {
+                    self.position += 1;
+                    bytes.push(byte);
+                }
+            }
+        }
+    }
+
+    // the character after `\u`, surrogate pairs are written as two escapes
+    fn unicode_escape(&mut self) -> Option<char> {
+        let high = self.hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high);
+        }
+        if !self.bytes[self.position..].starts_with(b"\\u") {
+            return None;
+        }
+        self.position += 2;
+        let low = self.hex()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return None;
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    }
+
+    fn hex(&mut self) -> Option<u32> {
+        let digits = self.bytes.get(self.position..self.position + 4)?;
+        let digits = std::str::from_utf8(digits).ok()?;
+        if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+            return None;
+        }
+        self.position += 4;
+        u32::from_str_radix(digits, 16).ok()
+    }
+}
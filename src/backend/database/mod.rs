@@ -4,6 +4,7 @@ extern crate sysconf;
 use self::page::Page;
 use super::page;
 use super::record;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::error;
@@ -19,7 +20,37 @@ use std::path::Path;
 use serialise;
 use std::mem;
 
+mod affinity;
+mod alter;
+mod collation;
+mod cursor;
+mod datetime;
+mod decimal;
+mod freelist;
+mod function;
+mod generated;
+mod index;
 mod insert;
+mod json;
+mod savepoint;
+mod stats;
+mod trigger;
+mod update;
+mod view;
+
+pub use self::affinity::{format_real, parse_numeric_prefix};
+pub use self::collation::{find_collation, Collation};
+pub use self::cursor::TableCursor;
+pub use self::datetime::{DateTime, Interval};
+pub use self::decimal::{Decimal, MAX_PRECISION};
+pub use self::function::{
+    find_function, AggregateFunction, Functions, ScalarFunction, UserFunction,
+};
+pub use self::generated::{Generated, GeneratedColumns, Generator};
+pub use self::index::Index;
+pub use self::json::Json;
+pub use self::savepoint::Savepoint;
+pub use self::stats::{TableStats, STATS_TABLE};
 
 #[derive(Debug)]
 pub struct Database {
@@ -27,17 +58,26 @@ pub struct Database {
     pub page_count: u32,
     pub page_size: u16,
     pub file: File,
+    /// the collations registered with `create_collation`, by their uppercase names
+    pub collations: HashMap<String, Collation>,
+    /// the functions registered with `create_scalar_function` and `create_aggregate_function`
+    pub functions: Functions,
+    /// works out generated columns, given by the SQL layer which can evaluate them
+    pub generator: Option<Generator>,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Column {
     Null(),
     Integer(i128),
     Real(f64),
     Blob(Vec<u8>),
     Text(String),
+    Decimal(Decimal),
 }
 
+/// The affinity of a column, which decides how the values written to it are converted. Dates,
+/// times and intervals are kept as text and stored as integers.
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
 #[repr(u8)]
 pub enum ColumnType {
@@ -45,6 +85,79 @@ pub enum ColumnType {
     Real = 2,
     Blob = 3,
     Text = 4,
+    Numeric = 5,
+    Date = 6,
+    Time = 7,
+    Timestamp = 8,
+    Interval = 9,
+    /// Exact numbers with up to `precision` digits, `scale` of them after the point.
+    Decimal {
+        precision: u8,
+        scale: u8,
+    } = 10,
+    /// JSON text, checked when it is written and kept without whitespace.
+    Json = 11,
+}
+
+impl Column {
+    /// The order values are sorted in, NULL first, then numbers, text and finally blobs.
+    /// Integers and reals are compared by their values without rounding the integer, decimals
+    /// are compared exactly with integers and through the shortest decimal of a real.
+    pub fn compare(&self, other: &Column) -> Ordering {
+        match (self, other) {
+            (Column::Null(), Column::Null()) => Ordering::Equal,
+            (Column::Null(), _) => Ordering::Less,
+            (_, Column::Null()) => Ordering::Greater,
+            (Column::Integer(left), Column::Integer(right)) => left.cmp(right),
+            (Column::Integer(left), Column::Real(right)) => compare_integer_real(*left, *right),
+            (Column::Real(left), Column::Integer(right)) => {
+                compare_integer_real(*right, *left).reverse()
+            }
+            (Column::Real(left), Column::Real(right)) => compare_reals(*left, *right),
+            (Column::Decimal(left), Column::Decimal(right)) => left.compare(right),
+            (Column::Decimal(left), Column::Integer(right)) => {
+                left.compare(&Decimal::from_integer(*right))
+            }
+            (Column::Integer(left), Column::Decimal(right)) => {
+                Decimal::from_integer(*left).compare(right)
+            }
+            (Column::Decimal(left), Column::Real(right)) => left.compare_real(*right),
+            (Column::Real(left), Column::Decimal(right)) => right.compare_real(*left).reverse(),
+            (Column::Integer(_), _) | (Column::Real(_), _) | (Column::Decimal(_), _) => {
+                Ordering::Less
+            }
+            (_, Column::Integer(_)) | (_, Column::Real(_)) | (_, Column::Decimal(_)) => {
+                Ordering::Greater
+            }
+            (Column::Text(left), Column::Text(right)) => left.as_bytes().cmp(right.as_bytes()),
+            (Column::Text(_), Column::Blob(_)) => Ordering::Less,
+            (Column::Blob(_), Column::Text(_)) => Ordering::Greater,
+            (Column::Blob(left), Column::Blob(right)) => left.cmp(right),
+        }
+    }
+}
+
+fn compare_reals(left: f64, right: f64) -> Ordering {
+    left.partial_cmp(&right).unwrap_or(Ordering::Equal)
+}
+
+// integers beyond 2^53 can't all be represented as reals, so the real's integer part is
+// compared as an integer and its fraction breaks ties
+fn compare_integer_real(left: i128, right: f64) -> Ordering {
+    if right.is_nan() {
+        return Ordering::Equal;
+    }
+    let whole = right.floor();
+    if whole >= i128::MAX as f64 {
+        return Ordering::Less;
+    }
+    if whole < i128::MIN as f64 {
+        return Ordering::Greater;
+    }
+    match left.cmp(&(whole as i128)) {
+        Ordering::Equal if right > whole => Ordering::Less,
+        ordering => ordering,
+    }
 }
 
 impl std::fmt::Display for Database {
@@ -75,6 +188,9 @@ impl std::fmt::Display for Column {
             Column::Blob(b) => {
                 write!(f, "blob ({})", b.len())
             }
+            Column::Decimal(d) => {
+                write!(f, "decimal: {}", d)
+            }
         }
     }
 }
@@ -108,7 +224,7 @@ impl Database {
         match page_type {
             2 => unimplemented!("index_interior"),
             5 => unimplemented!("table_interior"),
-            10 => unimplemented!("index_leaf"),
+            10 => page::index_leaf::read_page(page, page_header_start),
             13 => page::table_leaf::read_page(page, page_header_start),
             _ => panic!("Unknown page type: {}", page_type),
         }
@@ -118,8 +234,8 @@ impl Database {
         &mut self,
         table_name: String,
     ) -> Result<(u32, Vec<(String, ColumnType)>), Box<dyn error::Error>> {
-        let record_filter = |row: &Vec<Column>| match &row[1] {
-            Column::Text(row_table_name) => *row_table_name == table_name,
+        let record_filter = |row: &Vec<Column>| match (&row[0], &row[1]) {
+            (Column::Integer(1), Column::Text(row_table_name)) => *row_table_name == table_name,
             _ => false,
         };
 
@@ -129,24 +245,58 @@ impl Database {
 
         if let Some(columns) = table.first() {
             match columns.as_slice() {
-                [Column::Integer(page_number), Column::Blob(data)] => {
+                [Column::Integer(page_number), Column::Blob(data), ..] => {
                     let columns = bincode::deserialize::<Vec<(String, ColumnType)>>(data);
                     Ok((*page_number as u32, columns.unwrap()))
                 }
                 _ => panic!("Table columns stored incorrectly"),
             }
         } else {
-            panic!("table {} does not exist", &table_name[..])
+            Err(format!("table {} does not exist", &table_name[..]).into())
         }
     }
 
+    /// The names of every table in the database, in the order they were created.
+    pub fn list_tables(&mut self) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let record_filter = |row: &Vec<Column>| row[0] == Column::Integer(1);
+        let rows = record::select_records(self, 1, record_filter, |row| row)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| match row.into_iter().nth(1) {
+                Some(Column::Text(name)) => Some(name),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// The value each column of a table reads as in records written before the column was
+    /// added, NULL unless `ALTER TABLE ADD COLUMN` gave it a default.
+    pub fn table_defaults(
+        &mut self,
+        table_name: String,
+    ) -> Result<Vec<Column>, Box<dyn error::Error>> {
+        let (_page_number, columns) = self.describe_table(table_name.clone())?;
+        let record_filter = |row: &Vec<Column>| match (&row[0], &row[1]) {
+            (Column::Integer(1), Column::Text(row_table_name)) => *row_table_name == table_name,
+            _ => false,
+        };
+        let rows = record::select_records(self, 1, record_filter, |row| row)?;
+
+        // the defaults are kept as a record after the columns
+        let mut defaults = match rows.first().and_then(|row| row.get(4)) {
+            Some(Column::Blob(data)) => record::read_record(data),
+            _ => Vec::new(),
+        };
+        defaults.resize(columns.len(), Column::Null());
+        Ok(defaults)
+    }
+
     pub fn create_table(
         &mut self,
         table_name: String,
         columns: Vec<(String, ColumnType)>,
     ) -> Result<(), Box<dyn error::Error>> {
-        let rootpage = self.page_count + 1;
-        self.page_count = rootpage;
+        let rootpage = self.allocate_page(13)?;
         let schema_type = 1;
         let serialised_columns = bincode::serialize(&columns)?;
 
@@ -160,10 +310,48 @@ impl Database {
         let record = record::create_record(row);
         record::insert_record(self, record, 1);
         self.commit()?;
-        page::table_leaf::create_page(self)?;
 
         Ok(())
     }
+
+    // tables, indexes and views all share one namespace
+    fn check_name_free(&mut self, name: &str) -> Result<(), Box<dyn error::Error>> {
+        let record_filter = |row: &Vec<Column>| row[1] == Column::Text(name.to_string());
+        let existing = record::select_records(self, 1, record_filter, |row| row)?;
+        if existing.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "there is already another table or index with this name: {}",
+                name
+            )
+            .into())
+        }
+    }
+
+    // rewrites the rows of the master table, those `change` gives false for are removed
+    fn rewrite_master<F>(&mut self, mut change: F) -> Result<(), Box<dyn error::Error>>
+    where
+        F: FnMut(&mut Vec<Column>) -> Result<bool, Box<dyn error::Error>>,
+    {
+        let rows = record::select_records(self, 1, |_row| true, |row| row)?;
+        let mut records = Vec::with_capacity(rows.len());
+        for mut row in rows {
+            if change(&mut row)? {
+                records.push(record::create_record(row));
+            }
+        }
+        record::replace_records(self, records, 1);
+        self.commit()
+    }
+}
+
+/// Fills in the columns a record is missing with their defaults.
+pub fn pad_row(mut row: Vec<Column>, defaults: &[Column]) -> Vec<Column> {
+    if row.len() < defaults.len() {
+        row.extend_from_slice(&defaults[row.len()..]);
+    }
+    row
 }
 
 pub fn create_new_database(
@@ -196,6 +384,9 @@ pub fn create_new_database(
         page_count: page_count,
         page_size: page_size,
         file: file,
+        collations: HashMap::new(),
+        functions: HashMap::new(),
+        generator: None,
     };
 
     Ok(database)
@@ -221,6 +412,9 @@ fn load_existing_database(file_path: &Path) -> Result<Database, Box<dyn error::E
         page_count: page_count,
         page_size: page_size,
         file: file,
+        collations: HashMap::new(),
+        functions: HashMap::new(),
+        generator: None,
     };
 
     Ok(database)
@@ -236,3 +430,13 @@ pub fn load(filename: &String) -> Result<Database, Box<dyn error::Error>> {
         create_new_database(file_path, page_size)
     }
 }
+
+/// Loads a database as `load` does, working out its generated columns with `generator`.
+pub fn load_with(
+    filename: &String,
+    generator: Generator,
+) -> Result<Database, Box<dyn error::Error>> {
+    let mut database = load(filename)?;
+    database.generator = Some(generator);
+    Ok(database)
+}
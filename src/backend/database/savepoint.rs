@@ -0,0 +1,37 @@
+use super::Database;
+
+use std::error;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+
+/// The database file as it was at some point, so that the changes made after it can be
+/// undone. There are no journals, the whole file is kept in memory.
+#[derive(Debug)]
+pub struct Savepoint {
+    data: Vec<u8>,
+    page_count: u32,
+}
+
+impl Database {
+    /// Writes out the pages waiting in the cache and remembers the file as it is.
+    pub fn savepoint(&mut self) -> Result<Savepoint, Box<dyn error::Error>> {
+        self.commit()?;
+        let mut data = Vec::new();
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.read_to_end(&mut data)?;
+        Ok(Savepoint {
+            data,
+            page_count: self.page_count,
+        })
+    }
+
+    /// Undoes every change made since the savepoint, the pages added since are dropped.
+    pub fn rollback(&mut self, savepoint: Savepoint) -> Result<(), Box<dyn error::Error>> {
+        self.page_cache.clear();
+        self.file.set_len(savepoint.data.len() as u64)?;
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&savepoint.data)?;
+        self.page_count = savepoint.page_count;
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use super::super::record;
+use super::Collation;
+use super::Column;
+use super::ColumnType;
+use super::Database;
+
+use std::cmp::Ordering;
+use std::error;
+
+/// The table ANALYZE keeps its statistics in, with a row for each table and each of its
+/// indexes. The `stat` column holds the number of rows, followed for an index by the average
+/// number of rows sharing a value of each leading prefix of its columns.
+pub const STATS_TABLE: &str = "sqlite_stat1";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    pub row_count: usize,
+    /// the analyzed indexes, with the average rows per key for each prefix of their columns
+    pub indexes: Vec<(String, Vec<usize>)>,
+}
+
+impl TableStats {
+    pub fn rows_per_key(&self, index_name: &str) -> Option<&[usize]> {
+        self.indexes
+            .iter()
+            .find(|(name, _)| name == index_name)
+            .map(|(_, rows)| rows.as_slice())
+    }
+}
+
+impl Database {
+    /// Gathers statistics for a table, or for every table when none is given, replacing the
+    /// ones stored before.
+    pub fn analyze(&mut self, table_name: Option<String>) -> Result<(), Box<dyn error::Error>> {
+        let tables = match table_name {
+            Some(table_name) => {
+                self.describe_table(table_name.clone())?;
+                vec![table_name]
+            }
+            None => self
+                .list_tables()?
+                .into_iter()
+                .filter(|name| name != STATS_TABLE)
+                .collect(),
+        };
+
+        if self.describe_table(STATS_TABLE.to_string()).is_err() {
+            let columns = vec![
+                ("tbl".to_string(), ColumnType::Text),
+                ("idx".to_string(), ColumnType::Text),
+                ("stat".to_string(), ColumnType::Text),
+            ];
+            self.create_table(STATS_TABLE.to_string(), columns)?;
+        }
+
+        let mut rows: Vec<Vec<Column>> = self
+            .select_all_records(STATS_TABLE.to_string())?
+            .into_iter()
+            .filter(|row| match row.first() {
+                Some(Column::Text(name)) => !tables.contains(name),
+                _ => true,
+            })
+            .collect();
+
+        for table_name in tables {
+            let row_count = self.select_all_records(table_name.clone())?.len();
+            rows.push(vec![
+                Column::Text(table_name.clone()),
+                Column::Null(),
+                Column::Text(row_count.to_string()),
+            ]);
+
+            for index in self.list_indexes(table_name.clone())? {
+                let entries = record::select_records(self, index.rootpage, |_| true, |row| row)?;
+                let collations = self.index_collations(&index)?;
+                rows.push(vec![
+                    Column::Text(table_name.clone()),
+                    Column::Text(index.name),
+                    Column::Text(index_stat(&entries, &collations)),
+                ]);
+            }
+        }
+
+        self.replace_records(STATS_TABLE.to_string(), rows)
+    }
+
+    /// The statistics ANALYZE stored for a table, None when it has not been analyzed.
+    pub fn table_stats(
+        &mut self,
+        table_name: &str,
+    ) -> Result<Option<TableStats>, Box<dyn error::Error>> {
+        if self.describe_table(STATS_TABLE.to_string()).is_err() {
+            return Ok(None);
+        }
+
+        let record_filter = |row: &Vec<Column>| match row.first() {
+            Some(Column::Text(name)) => name == table_name,
+            _ => false,
+        };
+        let rows = self.select_records(STATS_TABLE.to_string(), record_filter, |row| row)?;
+
+        let mut row_count = None;
+        let mut indexes = Vec::new();
+        for row in rows {
+            match row.as_slice() {
+                [_, Column::Null(), Column::Text(stat)] => {
+                    row_count = parse_stat(stat).first().cloned();
+                }
+                [_, Column::Text(index_name), Column::Text(stat)] => {
+                    let numbers = parse_stat(stat);
+                    indexes.push((index_name.clone(), numbers.into_iter().skip(1).collect()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(row_count.map(|row_count| TableStats { row_count, indexes }))
+    }
+}
+
+fn parse_stat(stat: &str) -> Vec<usize> {
+    stat.split_whitespace()
+        .filter_map(|number| number.parse().ok())
+        .collect()
+}
+
+// index entries are kept in key order, so the distinct values of a prefix are counted by
+// comparing each entry with the one before it, by the collation of each indexed column
+fn index_stat(entries: &[Vec<Column>], collations: &[Collation]) -> String {
+    let mut numbers = vec![entries.len()];
+    if !entries.is_empty() {
+        for length in 1..=collations.len() {
+            let distinct = 1 + entries
+                .windows(2)
+                .filter(|pair| !same_prefix(&pair[0], &pair[1], &collations[..length]))
+                .count();
+            numbers.push(entries.len().div_ceil(distinct));
+        }
+    }
+
+    let numbers: Vec<String> = numbers.iter().map(|number| number.to_string()).collect();
+    numbers.join(" ")
+}
+
+fn same_prefix(left: &[Column], right: &[Column], collations: &[Collation]) -> bool {
+    left.iter()
+        .zip(right.iter())
+        .zip(collations)
+        .all(|((left, right), collation)| collation.compare(left, right) == Ordering::Equal)
+}
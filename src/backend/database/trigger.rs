@@ -0,0 +1,79 @@
+use super::super::record;
+use super::Column;
+use super::Database;
+
+use std::error;
+
+// the schema type of triggers in the master table, after tables, indexes and views
+pub const TRIGGER: i128 = 4;
+
+impl Database {
+    /// Stores the SQL text of a trigger in the master table along with the table or view it
+    /// is on. Like views, triggers have no pages.
+    pub fn create_trigger(
+        &mut self,
+        trigger_name: String,
+        table_name: String,
+        sql: String,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.check_name_free(&trigger_name)?;
+        if self.describe_table(table_name.clone()).is_err()
+            && self.view_definition(&table_name)?.is_none()
+        {
+            return Err(format!("no such table: {}", table_name).into());
+        }
+
+        let row = vec![
+            Column::Integer(TRIGGER),
+            Column::Text(trigger_name),
+            Column::Integer(0),
+            Column::Text(sql),
+            Column::Text(table_name),
+        ];
+        record::insert_record(self, record::create_record(row), 1);
+        self.commit()
+    }
+
+    pub fn drop_trigger(&mut self, trigger_name: String) -> Result<(), Box<dyn error::Error>> {
+        if !self.trigger_exists(&trigger_name)? {
+            return Err(format!("no such trigger: {}", trigger_name).into());
+        }
+        self.rewrite_master(|row| match (&row[0], &row[1]) {
+            (Column::Integer(TRIGGER), Column::Text(name)) => Ok(*name != trigger_name),
+            _ => Ok(true),
+        })
+    }
+
+    pub fn trigger_exists(&mut self, trigger_name: &str) -> Result<bool, Box<dyn error::Error>> {
+        let record_filter = |row: &Vec<Column>| match (&row[0], &row[1]) {
+            (Column::Integer(TRIGGER), Column::Text(name)) => name == trigger_name,
+            _ => false,
+        };
+        let rows = record::select_records(self, 1, record_filter, |row| row)?;
+        Ok(!rows.is_empty())
+    }
+
+    /// The SQL text of the triggers on a table or view, in the order they were created.
+    pub fn table_triggers(
+        &mut self,
+        table_name: &str,
+    ) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let rows =
+            record::select_records(self, 1, |row| is_trigger_on(row, table_name), |row| row)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| match row.into_iter().nth(3) {
+                Some(Column::Text(sql)) => Some(sql),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+/// Whether a row of the master table is a trigger on the table or view.
+pub fn is_trigger_on(row: &[Column], table_name: &str) -> bool {
+    match (&row[0], row.get(4)) {
+        (Column::Integer(TRIGGER), Some(Column::Text(name))) => name == table_name,
+        _ => false,
+    }
+}
@@ -0,0 +1,24 @@
+use super::super::record;
+use super::Column;
+use super::Database;
+
+use std::error;
+
+impl Database {
+    pub fn replace_records(
+        &mut self,
+        table_name: String,
+        rows: Vec<Vec<Column>>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let (page_number, _columns) = self.describe_table(table_name.clone())?;
+        let generated = self.generated_columns(table_name.clone())?;
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            records.push(record::create_record(self.write_row(&generated, row)?));
+        }
+        record::replace_records(self, records, page_number);
+        self.commit()?;
+        self.rebuild_indexes(table_name)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use super::super::record;
+use super::trigger::is_trigger_on;
+use super::Column;
+use super::Database;
+
+use std::error;
+
+// the schema type of views in the master table, after tables and indexes
+const VIEW: i128 = 3;
+
+impl Database {
+    /// Stores the SQL text of a view in the master table, views don't have pages of their
+    /// own so their rootpage is 0.
+    pub fn create_view(
+        &mut self,
+        view_name: String,
+        sql: String,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.check_name_free(&view_name)?;
+
+        let row = vec![
+            Column::Integer(VIEW),
+            Column::Text(view_name),
+            Column::Integer(0),
+            Column::Text(sql),
+        ];
+        record::insert_record(self, record::create_record(row), 1);
+        self.commit()
+    }
+
+    /// Removes a view along with its triggers.
+    pub fn drop_view(&mut self, view_name: String) -> Result<(), Box<dyn error::Error>> {
+        if self.view_definition(&view_name)?.is_none() {
+            return Err(format!("no such view: {}", view_name).into());
+        }
+
+        // the view's triggers go with it
+        self.rewrite_master(|row| Ok(!is_view(row, &view_name) && !is_trigger_on(row, &view_name)))
+    }
+
+    /// The SQL text a view was created with, None when there is no view with the name.
+    pub fn view_definition(
+        &mut self,
+        view_name: &str,
+    ) -> Result<Option<String>, Box<dyn error::Error>> {
+        let rows = record::select_records(self, 1, |row| is_view(row, view_name), |row| row)?;
+        Ok(match rows.first().and_then(|row| row.get(3)) {
+            Some(Column::Text(sql)) => Some(sql.clone()),
+            _ => None,
+        })
+    }
+
+    /// The names of every view in the database, in the order they were created.
+    pub fn list_views(&mut self) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let rows =
+            record::select_records(self, 1, |row| row[0] == Column::Integer(VIEW), |row| row)?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| match row.into_iter().nth(1) {
+                Some(Column::Text(name)) => Some(name),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+fn is_view(row: &[Column], view_name: &str) -> bool {
+    match (&row[0], &row[1]) {
+        (Column::Integer(VIEW), Column::Text(name)) => name == view_name,
+        _ => false,
+    }
+}
@@ -0,0 +1,36 @@
+use super::super::database::Database;
+use super::table_leaf::TableLeaf;
+use super::{Page, PageType};
+use serialise;
+use std::error;
+use std::io::prelude::*;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::mem;
+
+// index leaf pages have the same header as table leaf pages, only the page type differs
+pub type IndexLeaf = TableLeaf;
+
+pub fn create_page(database: &mut Database) -> Result<(), Box<dyn error::Error>> {
+    let mut page = vec![0u8; database.page_size as usize];
+    let page_type: u8 = 10;
+    serialise_integer!(page_type, &mut 0, &mut page);
+    // position at end of file
+    database.file.seek(SeekFrom::End(0))?;
+    database.file.write_all(&page)?;
+    Ok(())
+}
+
+pub fn read_page(page: Vec<u8>, header_start: usize) -> Result<Page, Box<dyn error::Error>> {
+    let index_leaf = IndexLeaf {
+        freeblock_index: serialise::to_integer(&page[header_start + 1..header_start + 3])?,
+        cell_count: serialise::to_integer(&page[header_start + 3..header_start + 5])?,
+        cell_content_start: serialise::to_integer(&page[header_start + 5..header_start + 7])?,
+        fragmented_bytes_count: serialise::to_integer(&page[header_start + 7..header_start + 8])?,
+    };
+
+    Ok(Page {
+        page_type: PageType::IndexLeaf(index_leaf),
+        data: page,
+    })
+}
@@ -1,3 +1,4 @@
+pub mod index_leaf;
 pub mod table_leaf;
 pub mod table_interior;
 
@@ -5,7 +6,7 @@ pub mod table_interior;
 pub enum PageType {
     TableLeaf(table_leaf::TableLeaf),
     TableInterior(table_interior::TableInterior),
-    IndexLeaf,
+    IndexLeaf(index_leaf::IndexLeaf),
     IndexInterior,
 }
 
@@ -18,7 +18,7 @@ pub fn insert_record(database: &mut Database, record: Vec<u8>, rootpage: u32) {
     // find appropriate page for insert
 
     match page.page_type {
-        PageType::TableLeaf(ref mut leaf) => {
+        PageType::TableLeaf(ref mut leaf) | PageType::IndexLeaf(ref mut leaf) => {
             // check if there is enough space
             // TODO if not, do we need to split the leaf or add an overflow page?
 
@@ -80,6 +80,33 @@ pub fn insert_record(database: &mut Database, record: Vec<u8>, rootpage: u32) {
     };
 }
 
+pub fn clear_page(database: &mut Database, page_number: u32) {
+    let mut page = match database.page_cache.remove(&page_number) {
+        None => database.read_page(page_number).unwrap(),
+        Some(page) => page,
+    };
+
+    match page.page_type {
+        PageType::TableLeaf(ref mut leaf) | PageType::IndexLeaf(ref mut leaf) => {
+            let mut page_header_start = 0;
+            if page_number == 1 {
+                page_header_start = 100;
+            }
+
+            // everything after the page type byte is reset, an empty leaf has no content start
+            for byte in page.data[page_header_start + 1..].iter_mut() {
+                *byte = 0;
+            }
+
+            leaf.cell_count = 0;
+            leaf.cell_content_start = 0;
+
+            database.page_cache.insert(page_number, page);
+        }
+        _ => panic!("Not implemented"),
+    };
+}
+
 pub fn create_record(row: Vec<Column>) -> Vec<u8> {
     let data: Vec<(u64, Vec<u8>)> = row
         .into_iter()
@@ -95,6 +122,12 @@ pub fn create_record(row: Vec<Column>) -> Vec<u8> {
                 let text_type = text.len() as u64 * 2 + 13;
                 (text_type, text.into_bytes())
             }
+            // type 11 is the scale in a byte followed by the 16 byte value
+            Column::Decimal(decimal) => {
+                let mut bytes = vec![decimal.scale];
+                bytes.extend_from_slice(&decimal.value.to_be_bytes());
+                (11, bytes)
+            }
         })
         .collect();
 
@@ -140,7 +173,8 @@ fn serialise_record_integer(int: i128) -> (u64, Vec<u8>) {
     if leading_bits == 0 {
         leading_bits = (!int).leading_zeros();
     }
-    let length = 16 - leading_bits / 8;
+    // one bit more than the value needs is kept for the sign
+    let length = 16 - (leading_bits - 1) / 8;
 
     match &length {
         1..=4 => {
@@ -159,7 +193,8 @@ fn serialise_record_integer(int: i128) -> (u64, Vec<u8>) {
             return (6, buffer);
         }
         _ => {
-            panic!("Integer too large to store");
+            // type 10 uses all 16 bytes
+            return (10, buffer);
         }
     }
 }
@@ -1,4 +1,5 @@
 use super::database::{Column, Database};
+use std::cmp::Ordering;
 use std::error;
 
 // serialise integer
@@ -19,14 +20,72 @@ where
     read::select_records(database, page_number, record_filter, column_filter)
 }
 
+pub fn select_record(
+    database: &mut Database,
+    page_number: u32,
+    position: u16,
+) -> Result<Vec<Column>, Box<dyn error::Error>> {
+    read::select_record(database, page_number, position)
+}
+
+pub fn search_records<F>(
+    database: &mut Database,
+    page_number: u32,
+    compare: F,
+) -> Result<Vec<Vec<Column>>, Box<dyn error::Error>>
+where
+    F: Fn(&Vec<Column>) -> Ordering,
+{
+    read::search_records(database, page_number, compare)
+}
+
+pub fn record_count(database: &mut Database, page_number: u32) -> Result<u16, Box<dyn error::Error>> {
+    read::record_count(database, page_number)
+}
+
+/// Overwrites the record at `position`, or removes it when `row` is None. The records after a
+/// removed one move back a position.
+pub fn update_record(
+    database: &mut Database,
+    page_number: u32,
+    position: u16,
+    row: Option<Vec<Column>>,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut rows = read::select_records(database, page_number, |_| true, |row| row)?;
+    if position as usize >= rows.len() {
+        return Err(format!("no record {} on page {}", position, page_number).into());
+    }
+    match row {
+        Some(row) => rows[position as usize] = row,
+        None => {
+            rows.remove(position as usize);
+        }
+    }
+    let records = rows.into_iter().map(create_record).collect();
+    replace_records(database, records, page_number);
+    Ok(())
+}
+
 pub fn create_record(row: Vec<Column>) -> Vec<u8> {
     insert::create_record(row)
 }
 
+/// Reads back a record made by `create_record`.
+pub fn read_record(data: &Vec<u8>) -> Vec<Column> {
+    read::read_record(data)
+}
+
 pub fn insert_record(database: &mut Database, record: Vec<u8>, rootpage: u32) {
     insert::insert_record(database, record, rootpage)
 }
 
+pub fn replace_records(database: &mut Database, records: Vec<Vec<u8>>, rootpage: u32) {
+    insert::clear_page(database, rootpage);
+    for record in records {
+        insert::insert_record(database, record, rootpage);
+    }
+}
+
 // max length of a varint is 9 bytes
 fn varint_length(int: i128) -> u8 {
     let mut leading_bits = int.leading_zeros();
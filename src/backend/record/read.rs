@@ -1,7 +1,8 @@
 use self::page::Page;
 use self::page::PageType;
-use super::super::database::{Column, Database};
+use super::super::database::{Column, Database, Decimal};
 use super::super::page;
+use std::cmp::Ordering;
 use std::error;
 
 use serialise;
@@ -22,6 +23,10 @@ where
         Ok(Page {
             data: page_content,
             page_type: PageType::TableLeaf(leaf),
+        })
+        | Ok(Page {
+            data: page_content,
+            page_type: PageType::IndexLeaf(leaf),
         }) => {
             let mut records: Vec<Vec<Column>> = Vec::new();
             let mut cell_pointer_start = 8;
@@ -59,6 +64,97 @@ where
     }
 }
 
+pub fn select_record(
+    database: &mut Database,
+    page_number: u32,
+    position: u16,
+) -> Result<Vec<Column>, Box<dyn error::Error>> {
+    let page = database.read_page(page_number)?;
+
+    match page.page_type {
+        PageType::TableLeaf(leaf) | PageType::IndexLeaf(leaf) => {
+            if position >= leaf.cell_count {
+                return Err(format!("no record {} on page {}", position, page_number).into());
+            }
+            let cell_pointer = read_cell_pointer(&page.data, page_number, position)?;
+            Ok(fetch_record(&mut (cell_pointer as usize), &page.data))
+        }
+        _ => panic!("Not implemented"),
+    }
+}
+
+pub fn record_count(database: &mut Database, page_number: u32) -> Result<u16, Box<dyn error::Error>> {
+    let page = database.read_page(page_number)?;
+
+    match page.page_type {
+        PageType::TableLeaf(leaf) | PageType::IndexLeaf(leaf) => Ok(leaf.cell_count),
+        _ => panic!("Not implemented"),
+    }
+}
+
+// binary search over records in key order, `compare` orders a record relative to the key
+pub fn search_records<F>(
+    database: &mut Database,
+    page_number: u32,
+    compare: F,
+) -> Result<Vec<Vec<Column>>, Box<dyn error::Error>>
+where
+    F: Fn(&Vec<Column>) -> Ordering,
+{
+    let page = database.read_page(page_number)?;
+
+    match page.page_type {
+        PageType::TableLeaf(ref leaf) | PageType::IndexLeaf(ref leaf) => {
+            let read = |position: u16| -> Result<Vec<Column>, Box<dyn error::Error>> {
+                let cell_pointer = read_cell_pointer(&page.data, page_number, position)?;
+                Ok(fetch_record(&mut (cell_pointer as usize), &page.data))
+            };
+
+            // find the first record that is not less than the key
+            let mut low = 0;
+            let mut high = leaf.cell_count;
+            while low < high {
+                let middle = low + (high - low) / 2;
+                if compare(&read(middle)?) == Ordering::Less {
+                    low = middle + 1;
+                } else {
+                    high = middle;
+                }
+            }
+
+            let mut records = Vec::new();
+            for position in low..leaf.cell_count {
+                let record = read(position)?;
+                if compare(&record) != Ordering::Equal {
+                    break;
+                }
+                records.push(record);
+            }
+            Ok(records)
+        }
+        _ => panic!("Not implemented"),
+    }
+}
+
+fn read_cell_pointer(
+    page_content: &[u8],
+    page_number: u32,
+    position: u16,
+) -> Result<u16, Box<dyn error::Error>> {
+    let mut cell_pointer_start = 8;
+    if page_number == 1 {
+        cell_pointer_start = 108;
+    }
+
+    let cell_pointer_index = (cell_pointer_start + 2 * position) as usize;
+    let slice = &page_content[cell_pointer_index..cell_pointer_index + 2];
+    serialise::to_integer(slice)
+}
+
+pub fn read_record(data: &Vec<u8>) -> Vec<Column> {
+    fetch_record(&mut 0, data)
+}
+
 fn fetch_record(cell_pointer: &mut usize, page_content: &Vec<u8>) -> Vec<Column> {
     let cell_start = cell_pointer.clone();
     let header_size = read_varint(cell_pointer, page_content);
@@ -100,6 +196,15 @@ fn read_column(serial_type: u64, cell_pointer: &mut usize, page_content: &Vec<u8
         }
         8 => Column::Integer(0),
         9 => Column::Integer(1),
+        10 => read_integer_column(cell_pointer, page_content, 16),
+        11 => {
+            let scale = page_content[*cell_pointer];
+            *cell_pointer += 1;
+            match read_integer_column(cell_pointer, page_content, 16) {
+                Column::Integer(value) => Column::Decimal(Decimal::new(value, scale)),
+                _ => unreachable!("integers are read as integers"),
+            }
+        }
         x if x % 2 == 0 => {
             let len = (x - 12) / 2;
             let bytes = read_bytes(cell_pointer, page_content, len as usize);
@@ -135,6 +240,12 @@ fn read_integer_column(cell_pointer: &mut usize, page_content: &Vec<u8>, size: u
         *cell_pointer += 1;
     }
 
+    // integers are stored in two's complement, negative ones shorter than 16 bytes are sign
+    // extended from their top bit
+    if size < 16 && (val >> (size * 8 - 1)) & 1 == 1 {
+        val -= 1 << (size * 8);
+    }
+
     Column::Integer(val)
 }
 
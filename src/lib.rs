@@ -6,7 +6,7 @@ use std::env;
 pub mod serialise;
 pub mod backend;
 mod repl;
-mod sql;
+pub mod sql;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
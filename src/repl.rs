@@ -1,8 +1,11 @@
 use rustyline::error::ReadlineError;
 use rustyline::{DefaultEditor, Editor, Result};
 
+use backend::database::{Column, Database};
 use rustyline::history::FileHistory;
 use sql;
+use sql::expression::to_text;
+use sql::ResultSet;
 
 enum ReplState {
     Continue,
@@ -12,13 +15,14 @@ enum ReplState {
 pub fn run() {
     // `()` can be used when no completer is required
     let mut rl = DefaultEditor::new().unwrap();
+    let mut database = None;
     if rl.load_history("history.txt").is_err() {
         println!("No previous history.");
     }
     loop {
         let readline = rl.readline(">> ");
         match readline {
-            Ok(val) => match user_input(val, &mut rl) {
+            Ok(val) => match user_input(val, &mut rl, &mut database) {
                 ReplState::Exit => {
                     return;
                 }
@@ -42,30 +46,86 @@ pub fn run() {
     rl.save_history("history.txt");
 }
 
-fn user_input(line: String, rl: &mut Editor<(), FileHistory>) -> ReplState {
+fn user_input(
+    line: String,
+    rl: &mut Editor<(), FileHistory>,
+    database: &mut Option<Database>,
+) -> ReplState {
     let first_char = line.chars().next();
     match first_char {
         None => ReplState::Continue,
 
         Some('.') => {
             rl.add_history_entry(&line);
-            do_meta(&line)
+            do_meta(&line, database)
         }
 
         Some(_) => {
             rl.add_history_entry(&line);
-            sql::parse(&line);
+            match database {
+                Some(database) => match database.execute(&line) {
+                    Ok(result) => print_result(&result),
+                    Err(error) => println!("Error: {}", error),
+                },
+                None => println!("No database is open, use .open FILENAME"),
+            }
             ReplState::Continue
         }
     }
 }
 
-fn do_meta(user_input: &String) -> ReplState {
-    match user_input.as_ref() {
-        ".exit" => ReplState::Exit,
+fn do_meta(user_input: &str, database: &mut Option<Database>) -> ReplState {
+    let mut words = user_input.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some(".open"), Some(filename)) => {
+            match sql::load(&filename.to_string()) {
+                Ok(opened) => *database = Some(opened),
+                Err(error) => println!("Error: {}", error),
+            }
+            ReplState::Continue
+        }
+        (Some(".exit"), None) => ReplState::Exit,
         _ => {
             println!("Unknown meta command: {}\n", user_input.clone());
             ReplState::Continue
         }
     }
 }
+
+fn print_result(result: &ResultSet) {
+    // query plans are drawn as a tree, like the sqlite shell does
+    if result.query_plan {
+        println!("QUERY PLAN");
+        print_plan(result, 0, "");
+        return;
+    }
+
+    if !result.columns.is_empty() {
+        println!("{}", result.columns.join(" | "));
+    }
+    for row in &result.rows {
+        let values: Vec<String> = row.iter().map(to_text).collect();
+        println!("{}", values.join(" | "));
+    }
+}
+
+fn print_plan(result: &ResultSet, parent: i128, indent: &str) {
+    let steps: Vec<&Vec<Column>> = result
+        .rows
+        .iter()
+        .filter(|row| row[1] == Column::Integer(parent))
+        .collect();
+    for (index, step) in steps.iter().enumerate() {
+        let last = index == steps.len() - 1;
+        println!(
+            "{}{}{}",
+            indent,
+            if last { "`--" } else { "|--" },
+            to_text(&step[2])
+        );
+        if let Column::Integer(id) = step[0] {
+            let indent = format!("{}{}", indent, if last { "   " } else { "|  " });
+            print_plan(result, id, &indent);
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use super::expression::{ordered, to_numeric, to_text, ColumnName, Expression, Row, Scope};
+use super::SqlError;
+use backend::database::{
+    find_function, AggregateFunction, Collation, Column, ColumnType, Decimal, UserFunction,
+};
+use std::cmp::Ordering;
+
+/// Accumulates a value over the rows of a group.
+pub trait Aggregate {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError>;
+    fn finalize(&self) -> Result<Column, SqlError>;
+
+    /// Takes away a row stepped earlier, false if the aggregate can't.
+    fn inverse(&mut self, _arguments: &[Column]) -> Result<bool, SqlError> {
+        Ok(false)
+    }
+}
+
+/// A group of rows after GROUP BY, `values` is the last row of the group, used for
+/// any columns that are not aggregated.
+pub struct Group {
+    pub values: Vec<Column>,
+    pub aggregates: Vec<(Expression, Column)>,
+}
+
+// min and max with more than one argument are scalar functions
+pub fn is_aggregate(name: &str, argument_count: usize) -> bool {
+    match name {
+        "count" | "sum" | "total" | "avg" | "group_concat" => true,
+        "min" | "max" => argument_count == 1,
+        _ => false,
+    }
+}
+
+/// Makes a built in aggregate, `orders` are the collations and affinities of its arguments.
+pub fn create(
+    name: &str,
+    argument_count: usize,
+    orders: &[(Collation, Option<ColumnType>)],
+) -> Result<Box<dyn Aggregate>, SqlError> {
+    let expected = match name {
+        "count" => 0..=1,
+        "group_concat" => 1..=2,
+        _ => 1..=1,
+    };
+    if !expected.contains(&argument_count) {
+        return Err(SqlError::new(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )));
+    }
+
+    let aggregate: Box<dyn Aggregate> = match name {
+        "count" => Box::new(Count { count: 0 }),
+        "sum" => Box::new(Sum::default()),
+        "total" => Box::new(Total { sum: 0.0 }),
+        "avg" => Box::new(Avg::default()),
+        "min" | "max" => {
+            let (collation, affinity) =
+                orders.first().cloned().unwrap_or((Collation::Binary, None));
+            Box::new(Extreme {
+                value: None,
+                keep: if name == "min" {
+                    Ordering::Less
+                } else {
+                    Ordering::Greater
+                },
+                collation,
+                affinity,
+            })
+        }
+        "group_concat" => Box::new(GroupConcat { text: None }),
+        _ => return Err(SqlError::new(format!("no such function: {}", name))),
+    };
+    Ok(aggregate)
+}
+
+/// Finds the aggregate calls in the expressions, each distinct call is listed once.
+pub fn find_calls<'a, I>(expressions: I) -> Vec<Expression>
+where
+    I: IntoIterator<Item = &'a Expression>,
+{
+    let mut calls = Vec::new();
+    for expression in expressions {
+        collect_calls(expression, &mut calls);
+    }
+    calls
+}
+
+fn collect_calls(expression: &Expression, calls: &mut Vec<Expression>) {
+    match expression {
+        Expression::Function {
+            aggregate: true, ..
+        } => {
+            if !calls.contains(expression) {
+                calls.push(expression.clone());
+            }
+        }
+        _ => {
+            for child in expression.children() {
+                collect_calls(child, calls);
+            }
+        }
+    }
+}
+
+/// Splits the rows into groups by the GROUP BY keys and runs each aggregate call over every
+/// group. Without GROUP BY all the rows form a single group, even when there are none.
+pub fn group_rows(
+    names: &[ColumnName],
+    rows: Vec<Vec<Column>>,
+    group_by: &[Expression],
+    calls: &[Expression],
+    scope: Scope,
+) -> Result<Vec<Group>, SqlError> {
+    // the groups are kept in the order of their keys, which compare by the collation of
+    // each GROUP BY term
+    let mut groups: Vec<(Vec<Column>, GroupState)> = Vec::new();
+    let collations = group_by
+        .iter()
+        .map(|expression| Row::new(names, &[]).with_scope(scope).collation(expression))
+        .collect::<Result<Vec<_>, _>>()?;
+    let argument_orders = calls
+        .iter()
+        .map(|call| argument_orders(call, names, scope))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for values in rows {
+        let row = Row::new(names, &values).with_scope(scope);
+        let mut key = Vec::with_capacity(group_by.len());
+        for expression in group_by {
+            key.push(expression.evaluate(&row)?);
+        }
+
+        let position = groups
+            .binary_search_by(|(group_key, _)| compare_group_keys(group_key, &key, &collations));
+        let position = match position {
+            Ok(position) => position,
+            Err(position) => {
+                let state = GroupState {
+                    last_row: Vec::new(),
+                    aggregates: create_all(calls, &argument_orders, scope)?,
+                };
+                groups.insert(position, (key, state));
+                position
+            }
+        };
+        let state = &mut groups[position].1;
+        for (call, aggregate) in calls.iter().zip(state.aggregates.iter_mut()) {
+            step(call, aggregate.as_mut(), &row)?;
+        }
+        state.last_row = values;
+    }
+
+    if groups.is_empty() && group_by.is_empty() {
+        let state = GroupState {
+            last_row: vec![Column::Null(); names.len()],
+            aggregates: create_all(calls, &argument_orders, scope)?,
+        };
+        groups.push((Vec::new(), state));
+    }
+
+    let mut output = Vec::with_capacity(groups.len());
+    for (_key, state) in groups {
+        let mut results = Vec::with_capacity(calls.len());
+        for (call, aggregate) in calls.iter().zip(state.aggregates.iter()) {
+            results.push((call.clone(), aggregate.finalize()?));
+        }
+        output.push(Group {
+            values: state.last_row,
+            aggregates: results,
+        });
+    }
+    Ok(output)
+}
+
+/// The collation and affinity each argument of an aggregate call is ordered by, from the
+/// columns of the rows it's run over.
+pub fn argument_orders(
+    call: &Expression,
+    names: &[ColumnName],
+    scope: Scope,
+) -> Result<Vec<(Collation, Option<ColumnType>)>, SqlError> {
+    let row = Row::new(names, &[]).with_scope(scope);
+    match call {
+        Expression::Function { arguments, .. } => arguments
+            .iter()
+            .map(|argument| Ok((row.collation(argument)?, row.affinity(argument))))
+            .collect(),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Makes the aggregate an aggregate call runs, one registered on the database is used rather
+/// than a built in one with the same name. `orders` are the collations and affinities of its
+/// arguments.
+pub fn create_call(
+    call: &Expression,
+    orders: &[(Collation, Option<ColumnType>)],
+    scope: Scope,
+) -> Result<Box<dyn Aggregate>, SqlError> {
+    let (name, arguments) = match call {
+        Expression::Function {
+            name, arguments, ..
+        } => (name, arguments),
+        _ => return Err(SqlError::new(format!("{} is not an aggregate call", call))),
+    };
+    let registered = scope
+        .functions
+        .and_then(|functions| find_function(functions, name, arguments.len()));
+    match registered {
+        Some(UserFunction::Aggregate(create)) => Ok(Box::new(Registered(create()))),
+        _ => create(name, arguments.len(), orders),
+    }
+}
+
+fn create_all(
+    calls: &[Expression],
+    orders: &[Vec<(Collation, Option<ColumnType>)>],
+    scope: Scope,
+) -> Result<Vec<Box<dyn Aggregate>>, SqlError> {
+    let mut aggregates = Vec::with_capacity(calls.len());
+    for (call, orders) in calls.iter().zip(orders) {
+        if let Expression::Function { distinct, .. } = call {
+            let aggregate = create_call(call, orders, scope)?;
+            if *distinct {
+                aggregates.push(Box::new(Distinct {
+                    seen: Vec::new(),
+                    collations: orders
+                        .iter()
+                        .map(|(collation, _affinity)| collation.clone())
+                        .collect(),
+                    aggregate,
+                }) as Box<dyn Aggregate>);
+            } else {
+                aggregates.push(aggregate);
+            }
+        }
+    }
+    Ok(aggregates)
+}
+
+/// Evaluates the arguments of an aggregate call against a row and feeds them to the aggregate.
+pub fn step(call: &Expression, aggregate: &mut dyn Aggregate, row: &Row) -> Result<(), SqlError> {
+    if let Expression::Function { arguments, .. } = call {
+        let mut values = Vec::with_capacity(arguments.len());
+        for argument in arguments {
+            values.push(argument.evaluate(row)?);
+        }
+        aggregate.step(&values)?;
+    }
+    Ok(())
+}
+
+struct GroupState {
+    last_row: Vec<Column>,
+    aggregates: Vec<Box<dyn Aggregate>>,
+}
+
+/// Compares GROUP BY keys the same way values compare, so NULLs are grouped together, with
+/// the collation of each term.
+pub fn compare_group_keys(left: &[Column], right: &[Column], collations: &[Collation]) -> Ordering {
+    for ((left, right), collation) in left.iter().zip(right).zip(collations) {
+        let ordering = collation.compare(left, right);
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+// an aggregate registered on the database, its errors become SQL errors
+struct Registered(Box<dyn AggregateFunction>);
+
+impl Aggregate for Registered {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        self.0.step(arguments).map_err(registered_error)
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        self.0.finalize().map_err(registered_error)
+    }
+
+    fn inverse(&mut self, arguments: &[Column]) -> Result<bool, SqlError> {
+        self.0.inverse(arguments).map_err(registered_error)
+    }
+}
+
+fn registered_error(error: Box<dyn std::error::Error>) -> SqlError {
+    SqlError::new(error.to_string())
+}
+
+// `count(*)` steps with no arguments and counts every row
+struct Count {
+    count: i128,
+}
+
+impl Aggregate for Count {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        match arguments.first() {
+            Some(Column::Null()) => {}
+            _ => self.count += 1,
+        }
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        Ok(Column::Integer(self.count))
+    }
+}
+
+// the sum stays an integer until a decimal or real is added, and exact until a real is added
+#[derive(Default)]
+struct Sum {
+    integer: Option<i128>,
+    decimal: Option<Decimal>,
+    real: Option<f64>,
+}
+
+impl Aggregate for Sum {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        match to_numeric(&arguments[0]) {
+            Column::Integer(int) if self.real.is_none() && self.decimal.is_none() => {
+                let sum = self.integer.unwrap_or(0).checked_add(int);
+                match sum {
+                    Some(sum) => self.integer = Some(sum),
+                    None => return Err(SqlError::new("integer overflow")),
+                }
+            }
+            Column::Integer(int) if self.real.is_none() => {
+                self.add_decimal(Decimal::from_integer(int))?
+            }
+            Column::Integer(int) => self.real = Some(self.real.unwrap_or(0.0) + int as f64),
+            Column::Decimal(decimal) if self.real.is_none() => {
+                if let Some(int) = self.integer.take() {
+                    self.add_decimal(Decimal::from_integer(int))?;
+                }
+                self.add_decimal(decimal)?
+            }
+            Column::Decimal(decimal) => {
+                self.real = Some(self.real.unwrap_or(0.0) + decimal.to_real())
+            }
+            Column::Real(real) => {
+                let sum = self.real.unwrap_or(0.0)
+                    + self.integer.take().unwrap_or(0) as f64
+                    + self.decimal.take().map_or(0.0, |decimal| decimal.to_real());
+                self.real = Some(sum + real);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        Ok(match (self.integer, self.decimal, self.real) {
+            (_, _, Some(real)) => Column::Real(real),
+            (_, Some(decimal), None) => Column::Decimal(decimal),
+            (Some(int), None, None) => Column::Integer(int),
+            (None, None, None) => Column::Null(),
+        })
+    }
+}
+
+impl Sum {
+    fn add_decimal(&mut self, decimal: Decimal) -> Result<(), SqlError> {
+        let sum = self
+            .decimal
+            .unwrap_or(Decimal::from_integer(0))
+            .checked_add(&decimal);
+        match sum {
+            Some(sum) => self.decimal = Some(sum),
+            None => return Err(SqlError::new("decimal overflow")),
+        }
+        Ok(())
+    }
+}
+
+struct Total {
+    sum: f64,
+}
+
+impl Aggregate for Total {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        match to_numeric(&arguments[0]) {
+            Column::Integer(int) => self.sum += int as f64,
+            Column::Real(real) => self.sum += real,
+            Column::Decimal(decimal) => self.sum += decimal.to_real(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        Ok(Column::Real(self.sum))
+    }
+}
+
+// the average of decimals is a decimal, divided as decimals are
+#[derive(Default)]
+struct Avg {
+    sum: Sum,
+    count: usize,
+}
+
+impl Aggregate for Avg {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        match to_numeric(&arguments[0]) {
+            Column::Integer(_) | Column::Real(_) | Column::Decimal(_) => self.count += 1,
+            _ => return Ok(()),
+        }
+        self.sum.step(arguments)
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        let count = self.count as f64;
+        Ok(match self.sum.finalize()? {
+            Column::Integer(int) => Column::Real(int as f64 / count),
+            Column::Real(real) => Column::Real(real / count),
+            Column::Decimal(decimal) => {
+                match decimal.checked_div(&Decimal::from_integer(self.count as i128)) {
+                    Some(average) => Column::Decimal(average),
+                    None => Column::Real(decimal.to_real() / count),
+                }
+            }
+            _ => Column::Null(),
+        })
+    }
+}
+
+// min keeps values that compare less, max keeps values that compare greater, in the form
+// their affinity orders them in
+struct Extreme {
+    value: Option<Column>,
+    keep: Ordering,
+    collation: Collation,
+    affinity: Option<ColumnType>,
+}
+
+impl Aggregate for Extreme {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        let argument = &arguments[0];
+        if *argument == Column::Null() {
+            return Ok(());
+        }
+        let replace = match &self.value {
+            Some(value) => {
+                let argument = ordered(argument.clone(), &self.affinity);
+                let value = ordered(value.clone(), &self.affinity);
+                self.collation.compare(&argument, &value) == self.keep
+            }
+            None => true,
+        };
+        if replace {
+            self.value = Some(argument.clone());
+        }
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        Ok(self.value.clone().unwrap_or(Column::Null()))
+    }
+}
+
+struct GroupConcat {
+    text: Option<String>,
+}
+
+impl Aggregate for GroupConcat {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        if arguments[0] == Column::Null() {
+            return Ok(());
+        }
+        let value = to_text(&arguments[0]);
+        let separator = match arguments.get(1) {
+            Some(separator) => to_text(separator),
+            None => ",".to_string(),
+        };
+
+        self.text = Some(match self.text.take() {
+            Some(text) => text + &separator + &value,
+            None => value,
+        });
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        Ok(match &self.text {
+            Some(text) => Column::Text(text.clone()),
+            None => Column::Null(),
+        })
+    }
+}
+
+// `DISTINCT` aggregates only see each value once, values are the same when they compare
+// equal by the collations of the arguments
+struct Distinct {
+    seen: Vec<Vec<Column>>,
+    collations: Vec<Collation>,
+    aggregate: Box<dyn Aggregate>,
+}
+
+impl Aggregate for Distinct {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), SqlError> {
+        let duplicate = self
+            .seen
+            .iter()
+            .any(|seen| compare_group_keys(seen, arguments, &self.collations) == Ordering::Equal);
+        if duplicate {
+            return Ok(());
+        }
+        self.seen.push(arguments.to_vec());
+        self.aggregate.step(arguments)
+    }
+
+    fn finalize(&self) -> Result<Column, SqlError> {
+        self.aggregate.finalize()
+    }
+}
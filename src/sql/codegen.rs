@@ -0,0 +1,741 @@
+use super::executor::{insert_values, table_names};
+use super::expression::{
+    comparison_affinity, resolve, BinaryOperator, ColumnName, Expression, Row, UnaryOperator,
+};
+use super::planner::{self, Access};
+use super::select::{evaluate_count, expand_columns, resolve_sort_keys, SortKey};
+use super::statement::{Delete, Insert, ResultColumn, Select, Statement, TableSource, Update};
+use super::vm::{Instruction, Program};
+use super::SqlError;
+use backend::database::{Column, ColumnType, Database};
+use std::error;
+
+/// Compiles a statement into a program for the virtual machine. Statements using features
+/// the code generator doesn't handle yet give None and are run by the executor instead.
+pub fn compile(
+    database: &mut Database,
+    statement: &Statement,
+) -> Result<Option<Program>, Box<dyn error::Error>> {
+    if collated_or_generated(database, statement) {
+        return Ok(None);
+    }
+    match statement {
+        Statement::Select(select) if compilable_select(select) => {
+            if searches_index(database, select)? {
+                return Ok(None);
+            }
+            compile_select(database, select).map(Some)
+        }
+        Statement::Insert(insert) if insert.values.iter().flatten().all(compilable) => {
+            compile_insert(database, insert).map(Some)
+        }
+        Statement::Update(update)
+            if update.selection.iter().all(compilable)
+                && update
+                    .assignments
+                    .iter()
+                    .all(|(_, value)| compilable(value)) =>
+        {
+            compile_update(database, update).map(Some)
+        }
+        Statement::Delete(delete) if delete.selection.iter().all(compilable) => {
+            compile_delete(database, delete).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+// the machine compares text byte by byte and doesn't check writes to generated columns, so
+// statements on a table with collated, date and time or generated columns, which compare and
+// add up differently, are left to the executor
+fn collated_or_generated(database: &mut Database, statement: &Statement) -> bool {
+    let table = match statement {
+        Statement::Select(select) => match &select.from {
+            Some(from) => match &from.table.source {
+                TableSource::Table(table) => table,
+                _ => return false,
+            },
+            None => return false,
+        },
+        Statement::Insert(insert) => &insert.table,
+        Statement::Update(update) => &update.table,
+        Statement::Delete(delete) => &delete.table,
+        _ => return false,
+    };
+    let generated = match database.table_generated(table.clone()) {
+        Ok(generated) => generated.iter().any(Option::is_some),
+        Err(_) => false,
+    };
+    let temporal = match database.describe_table(table.clone()) {
+        Ok((_rootpage, columns)) => columns
+            .iter()
+            .any(|(_name, column_type)| column_type.is_temporal()),
+        Err(_) => false,
+    };
+    match database.table_collations(table.clone()) {
+        Ok(collations) => generated || temporal || collations.iter().any(Option::is_some),
+        Err(_) => false,
+    }
+}
+
+// a query reading at most one table with no grouping, windows or nested queries
+fn compilable_select(select: &Select) -> bool {
+    let simple_from = match &select.from {
+        Some(from) => from.joins.is_empty() && matches!(from.table.source, TableSource::Table(_)),
+        None => true,
+    };
+    let expressions = select
+        .columns
+        .iter()
+        .filter_map(|column| match column {
+            ResultColumn::Expression { expression, .. } => Some(expression),
+            _ => None,
+        })
+        .chain(select.selection.iter())
+        .chain(select.order_by.iter().map(|term| &term.expression));
+
+    select.with.is_none()
+        && select.compound.is_empty()
+        && !select.distinct
+        && select.group_by.is_empty()
+        && select.having.is_none()
+        && simple_from
+        && expressions
+            .into_iter()
+            .all(|expression| compilable(expression) && !expression.contains_aggregate())
+}
+
+// the machine only scans tables, so a query whose WHERE clause gives the key of an index is
+// left to the executor to search it
+fn searches_index(database: &mut Database, select: &Select) -> Result<bool, Box<dyn error::Error>> {
+    let (from, selection) = match (&select.from, &select.selection) {
+        (Some(from), Some(selection)) => (from, selection),
+        _ => return Ok(false),
+    };
+    match (&from.table.source, from.table.reference_name()) {
+        (TableSource::Table(table), Some(reference_name)) => {
+            let names = table_names(database, table, reference_name)?;
+            let access = planner::choose_access(database, table, &[], &names, Some(selection))?;
+            Ok(access != Access::Scan)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn compilable(expression: &Expression) -> bool {
+    let supported = matches!(
+        expression,
+        Expression::Literal(_)
+            | Expression::Parameter { .. }
+            | Expression::Column { .. }
+            | Expression::Unary { .. }
+            | Expression::IsNull { .. }
+            | Expression::InList { .. }
+            | Expression::Between { .. }
+    ) || matches!(
+        expression,
+        Expression::Binary { operator, .. }
+            if !matches!(operator, BinaryOperator::Extract | BinaryOperator::ExtractValue)
+    );
+    supported && expression.children().into_iter().all(compilable)
+}
+
+fn compile_select(
+    database: &mut Database,
+    select: &Select,
+) -> Result<Program, Box<dyn error::Error>> {
+    let mut generator = Generator::new();
+
+    let (table, names) = match &select.from {
+        Some(from) => match (&from.table.source, from.table.reference_name()) {
+            (TableSource::Table(table), Some(reference_name)) => {
+                let names = table_names(database, table, reference_name)?;
+                let cursor = generator.cursor();
+                generator.emit(Instruction::OpenRead {
+                    cursor,
+                    table: table.clone(),
+                });
+                (Some(cursor), names)
+            }
+            _ => return Err(Box::new(SqlError::new("cannot compile a derived table"))),
+        },
+        None => (None, Vec::new()),
+    };
+    let source = Source {
+        cursor: table,
+        names: &names,
+    };
+
+    let (columns, projection) = expand_columns(&select.columns, &names)?;
+    let sort_keys = resolve_sort_keys(&select.order_by, &columns, projection.len())?;
+    let limits = Limits::compile(&mut generator, select)?;
+
+    // rows go straight to the output, or through a sorter keyed on the ORDER BY terms
+    let sorter = if sort_keys.is_empty() {
+        None
+    } else {
+        let cursor = generator.cursor();
+        generator.emit(Instruction::SorterOpen {
+            cursor,
+            order: select.order_by.clone(),
+        });
+        Some(cursor)
+    };
+
+    let record = generator.allocate(sort_keys.len() + projection.len());
+    let result = record + sort_keys.len();
+
+    let scan = generator.begin_scan(table);
+    let skip = generator.filter(select.selection.as_ref(), &source)?;
+    for (index, expression) in projection.iter().enumerate() {
+        generator.expression(expression, &source, result + index)?;
+    }
+    match sorter {
+        Some(sorter) => {
+            for (index, key) in sort_keys.iter().enumerate() {
+                match key {
+                    SortKey::Output(column) => {
+                        generator.emit(Instruction::Copy {
+                            source: result + column,
+                            target: record + index,
+                        });
+                    }
+                    SortKey::Expression(expression) => {
+                        generator.expression(expression, &source, record + index)?
+                    }
+                }
+            }
+            generator.emit(Instruction::SorterInsert {
+                cursor: sorter,
+                start: record,
+                count: sort_keys.len() + projection.len(),
+            });
+        }
+        None => generator.output(&limits, result, projection.len()),
+    }
+    generator.end_scan(scan, skip);
+
+    if let Some(sorter) = sorter {
+        let sort = generator.emit(Instruction::SorterSort {
+            cursor: sorter,
+            address: 0,
+        });
+        let start = generator.address();
+        for index in 0..projection.len() {
+            generator.emit(Instruction::Column {
+                cursor: sorter,
+                column: sort_keys.len() + index,
+                target: result + index,
+            });
+        }
+        generator.output(&limits, result, projection.len());
+        generator.emit(Instruction::SorterNext {
+            cursor: sorter,
+            address: start,
+        });
+        generator.jump_here(&[sort]);
+    }
+
+    Ok(generator.finish(columns))
+}
+
+fn compile_insert(
+    database: &mut Database,
+    insert: &Insert,
+) -> Result<Program, Box<dyn error::Error>> {
+    let (_page_number, table_columns) = database.describe_table(insert.table.clone())?;
+    let names: Vec<String> = table_columns.into_iter().map(|(name, _)| name).collect();
+    let defaults = database.table_defaults(insert.table.clone())?;
+    let rows = insert_values(&names, &[], insert)?;
+
+    let mut generator = Generator::new();
+    let cursor = generator.cursor();
+    generator.emit(Instruction::OpenWrite {
+        cursor,
+        table: insert.table.clone(),
+    });
+
+    // values can't refer to any columns
+    let source = Source {
+        cursor: None,
+        names: &[],
+    };
+    let record = generator.allocate(names.len());
+    for row in rows {
+        for (index, value) in row.into_iter().enumerate() {
+            match value {
+                Some(expression) => generator.expression(expression, &source, record + index)?,
+                None => {
+                    generator.emit(Instruction::Value {
+                        value: defaults[index].clone(),
+                        target: record + index,
+                    });
+                }
+            }
+        }
+        generator.emit(Instruction::Insert {
+            cursor,
+            start: record,
+            count: names.len(),
+        });
+    }
+
+    generator.emit(Instruction::Close { cursor });
+    Ok(generator.finish(Vec::new()))
+}
+
+fn compile_update(
+    database: &mut Database,
+    update: &Update,
+) -> Result<Program, Box<dyn error::Error>> {
+    let names = table_names(database, &update.table, &update.table)?;
+
+    let mut assignments = Vec::new();
+    for (column, expression) in &update.assignments {
+        match names
+            .iter()
+            .position(|name| name.name.eq_ignore_ascii_case(column))
+        {
+            Some(index) => assignments.push((index, expression)),
+            None => {
+                return Err(Box::new(SqlError::new(format!(
+                    "no such column: {}",
+                    column
+                ))))
+            }
+        }
+    }
+
+    let mut generator = Generator::new();
+    let cursor = generator.cursor();
+    generator.emit(Instruction::OpenWrite {
+        cursor,
+        table: update.table.clone(),
+    });
+    let source = Source {
+        cursor: Some(cursor),
+        names: &names,
+    };
+
+    // every assignment reads the row as it was, the cursor's row only changes on Replace
+    let record = generator.allocate(names.len());
+    let scan = generator.begin_scan(Some(cursor));
+    let skip = generator.filter(update.selection.as_ref(), &source)?;
+    for index in 0..names.len() {
+        let assignment = assignments
+            .iter()
+            .rev()
+            .find(|(column, _)| *column == index);
+        match assignment {
+            Some((_, expression)) => generator.expression(expression, &source, record + index)?,
+            None => {
+                generator.emit(Instruction::Column {
+                    cursor,
+                    column: index,
+                    target: record + index,
+                });
+            }
+        }
+    }
+    generator.emit(Instruction::Replace {
+        cursor,
+        start: record,
+        count: names.len(),
+    });
+    generator.end_scan(scan, skip);
+
+    generator.emit(Instruction::Close { cursor });
+    Ok(generator.finish(Vec::new()))
+}
+
+fn compile_delete(
+    database: &mut Database,
+    delete: &Delete,
+) -> Result<Program, Box<dyn error::Error>> {
+    let names = table_names(database, &delete.table, &delete.table)?;
+
+    let mut generator = Generator::new();
+    let cursor = generator.cursor();
+    generator.emit(Instruction::OpenWrite {
+        cursor,
+        table: delete.table.clone(),
+    });
+    let source = Source {
+        cursor: Some(cursor),
+        names: &names,
+    };
+
+    let scan = generator.begin_scan(Some(cursor));
+    let skip = generator.filter(delete.selection.as_ref(), &source)?;
+    generator.emit(Instruction::Delete { cursor });
+    generator.end_scan(scan, skip);
+
+    generator.emit(Instruction::Close { cursor });
+    Ok(generator.finish(Vec::new()))
+}
+
+// the table expressions read their columns from, through the cursor on its current row
+struct Source<'a> {
+    cursor: Option<usize>,
+    names: &'a [ColumnName],
+}
+
+impl<'a> Source<'a> {
+    fn affinity(&self, expression: &Expression) -> Option<ColumnType> {
+        Row::new(self.names, &[]).affinity(expression)
+    }
+}
+
+// the registers counting down the rows left to skip for OFFSET and to return for LIMIT
+struct Limits {
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+impl Limits {
+    fn compile(generator: &mut Generator, select: &Select) -> Result<Limits, SqlError> {
+        let mut limits = Limits {
+            offset: None,
+            limit: None,
+        };
+        if let Some(Some(limit)) = select.limit.as_ref().map(evaluate_count).transpose()? {
+            let register = generator.allocate(1);
+            generator.emit(Instruction::Value {
+                value: Column::Integer(limit as i128),
+                target: register,
+            });
+            let jump = generator.emit(Instruction::IfNot {
+                register,
+                address: 0,
+            });
+            generator.exits.push(jump);
+            limits.limit = Some(register);
+        }
+        if let Some(Some(offset)) = select.offset.as_ref().map(evaluate_count).transpose()? {
+            if offset > 0 {
+                let register = generator.allocate(1);
+                generator.emit(Instruction::Value {
+                    value: Column::Integer(offset as i128),
+                    target: register,
+                });
+                limits.offset = Some(register);
+            }
+        }
+        Ok(limits)
+    }
+}
+
+struct Generator {
+    instructions: Vec<Instruction>,
+    register_count: usize,
+    cursor_count: usize,
+    /// jumps to the end of the program
+    exits: Vec<usize>,
+}
+
+// the jumps that end a scan, patched once the end of the loop is known
+struct Scan {
+    cursor: Option<usize>,
+    rewind: Option<usize>,
+    start: usize,
+}
+
+impl Generator {
+    fn new() -> Generator {
+        Generator {
+            instructions: Vec::new(),
+            register_count: 0,
+            cursor_count: 0,
+            exits: Vec::new(),
+        }
+    }
+
+    fn finish(mut self, columns: Vec<String>) -> Program {
+        let exits = std::mem::take(&mut self.exits);
+        self.jump_here(&exits);
+        self.emit(Instruction::Halt);
+        Program {
+            instructions: self.instructions,
+            register_count: self.register_count,
+            cursor_count: self.cursor_count,
+            columns,
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.instructions.push(instruction);
+        self.instructions.len() - 1
+    }
+
+    // the address of the next instruction
+    fn address(&self) -> usize {
+        self.instructions.len()
+    }
+
+    fn patch(&mut self, jump: usize, address: usize) {
+        if let Some(target) = self.instructions[jump].address_mut() {
+            *target = address;
+        }
+    }
+
+    // points the jumps at the next instruction
+    fn jump_here(&mut self, jumps: &[usize]) {
+        let address = self.address();
+        for jump in jumps {
+            self.patch(*jump, address);
+        }
+    }
+
+    fn allocate(&mut self, count: usize) -> usize {
+        let start = self.register_count;
+        self.register_count += count;
+        start
+    }
+
+    fn cursor(&mut self) -> usize {
+        self.cursor_count += 1;
+        self.cursor_count - 1
+    }
+
+    // starts a loop over every row of the table, without a table the body runs once
+    fn begin_scan(&mut self, cursor: Option<usize>) -> Scan {
+        let rewind = cursor.map(|cursor| self.emit(Instruction::Rewind { cursor, address: 0 }));
+        Scan {
+            cursor,
+            rewind,
+            start: self.address(),
+        }
+    }
+
+    // `skip` are the jumps to the next row
+    fn end_scan(&mut self, scan: Scan, skip: Option<usize>) {
+        if let Some(skip) = skip {
+            self.jump_here(&[skip]);
+        }
+        if let Some(cursor) = scan.cursor {
+            self.emit(Instruction::Next {
+                cursor,
+                address: scan.start,
+            });
+        }
+        if let Some(rewind) = scan.rewind {
+            self.jump_here(&[rewind]);
+        }
+    }
+
+    // jumps past the rest of the loop body when the condition isn't true
+    fn filter(
+        &mut self,
+        condition: Option<&Expression>,
+        source: &Source,
+    ) -> Result<Option<usize>, SqlError> {
+        match condition {
+            Some(condition) => {
+                let register = self.allocate(1);
+                self.expression(condition, source, register)?;
+                Ok(Some(self.emit(Instruction::IfNot {
+                    register,
+                    address: 0,
+                })))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // returns a row, after skipping the OFFSET and stopping at the LIMIT
+    fn output(&mut self, limits: &Limits, start: usize, count: usize) {
+        let offset = limits.offset.map(|register| {
+            self.emit(Instruction::IfPos {
+                register,
+                address: 0,
+            })
+        });
+        self.emit(Instruction::ResultRow { start, count });
+        if let Some(register) = limits.limit {
+            let jump = self.emit(Instruction::DecrJumpZero {
+                register,
+                address: 0,
+            });
+            self.exits.push(jump);
+        }
+        if let Some(offset) = offset {
+            // a skipped row moves on to the next one, just like a row that was returned
+            self.jump_here(&[offset]);
+        }
+    }
+
+    fn expression(
+        &mut self,
+        expression: &Expression,
+        source: &Source,
+        target: usize,
+    ) -> Result<(), SqlError> {
+        match expression {
+            Expression::Literal(value) | Expression::Parameter { value, .. } => {
+                self.emit(Instruction::Value {
+                    value: value.clone(),
+                    target,
+                });
+            }
+            Expression::Column { table, name } => {
+                let column = resolve(source.names, table, name)?;
+                let cursor = match source.cursor {
+                    Some(cursor) => cursor,
+                    None => return Err(SqlError::new(format!("no such column: {}", name))),
+                };
+                self.emit(Instruction::Column {
+                    cursor,
+                    column,
+                    target,
+                });
+            }
+            Expression::Unary {
+                operator: UnaryOperator::Plus,
+                operand,
+            } => self.expression(operand, source, target)?,
+            Expression::Unary { operator, operand } => {
+                let register = self.allocate(1);
+                self.expression(operand, source, register)?;
+                self.emit(Instruction::Unary {
+                    operator: operator.clone(),
+                    source: register,
+                    target,
+                });
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => self.binary(operator.clone(), left, right, source, target)?,
+            Expression::IsNull { operand, negated } => {
+                let register = self.allocate(1);
+                self.expression(operand, source, register)?;
+                self.emit(Instruction::IsNull {
+                    source: register,
+                    target,
+                    negated: *negated,
+                });
+            }
+            // `x IN (a, b)` is `x = a OR x = b`, which is false for an empty list
+            Expression::InList {
+                operand,
+                list,
+                negated,
+            } => {
+                let value = self.allocate(2);
+                let item = value + 1;
+                self.expression(operand, source, value)?;
+                let (_, affinity) = comparison_affinity(source.affinity(operand), None);
+                self.emit(Instruction::Value {
+                    value: Column::Integer(0),
+                    target,
+                });
+                for expression in list {
+                    self.operand(expression, affinity.clone(), source, item)?;
+                    self.emit(Instruction::Binary {
+                        operator: BinaryOperator::Equal,
+                        left: value,
+                        right: item,
+                        target: item,
+                    });
+                    self.emit(Instruction::Binary {
+                        operator: BinaryOperator::Or,
+                        left: target,
+                        right: item,
+                        target,
+                    });
+                }
+                self.negate_if(*negated, target);
+            }
+            Expression::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => {
+                let above = self.allocate(1);
+                self.binary(BinaryOperator::GreaterEqual, operand, low, source, above)?;
+                self.binary(BinaryOperator::LessEqual, operand, high, source, target)?;
+                self.emit(Instruction::Binary {
+                    operator: BinaryOperator::And,
+                    left: above,
+                    right: target,
+                    target,
+                });
+                self.negate_if(*negated, target);
+            }
+            _ => {
+                return Err(SqlError::new(format!(
+                    "cannot compile expression {}",
+                    expression
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    fn binary(
+        &mut self,
+        operator: BinaryOperator,
+        left: &Expression,
+        right: &Expression,
+        source: &Source,
+        target: usize,
+    ) -> Result<(), SqlError> {
+        let registers = self.allocate(2);
+        let (left_affinity, right_affinity) = if operator.is_comparison() {
+            comparison_affinity(source.affinity(left), source.affinity(right))
+        } else {
+            (None, None)
+        };
+        self.operand(left, left_affinity, source, registers)?;
+        self.operand(right, right_affinity, source, registers + 1)?;
+        self.emit(Instruction::Binary {
+            operator,
+            left: registers,
+            right: registers + 1,
+            target,
+        });
+        Ok(())
+    }
+
+    // evaluates an operand of a comparison, converted by the affinity it is compared with
+    fn operand(
+        &mut self,
+        expression: &Expression,
+        affinity: Option<ColumnType>,
+        source: &Source,
+        target: usize,
+    ) -> Result<(), SqlError> {
+        match (expression, affinity) {
+            // constants are converted as the program is compiled
+            (Expression::Literal(value), Some(affinity))
+            | (Expression::Parameter { value, .. }, Some(affinity)) => {
+                self.emit(Instruction::Value {
+                    value: affinity.apply(value.clone()),
+                    target,
+                });
+            }
+            (expression, affinity) => {
+                self.expression(expression, source, target)?;
+                if let Some(affinity) = affinity {
+                    self.emit(Instruction::Affinity {
+                        register: target,
+                        affinity,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn negate_if(&mut self, negated: bool, register: usize) {
+        if negated {
+            self.emit(Instruction::Unary {
+                operator: UnaryOperator::Not,
+                source: register,
+                target: register,
+            });
+        }
+    }
+}
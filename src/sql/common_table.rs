@@ -0,0 +1,180 @@
+use super::compound::{check_width, first_query};
+use super::executor::{Context, Relation};
+use super::expression::{ColumnName, Row};
+use super::select::{self, evaluate_count, same_row};
+use super::statement::{CommonTable, CompoundOperator, From, Select, TableSource, With};
+use super::SqlError;
+use backend::database::{Collation, Column};
+use std::error;
+
+/// Runs the common tables of a `WITH` clause in order, each one can be referred to by the
+/// tables after it and by the rest of the query.
+pub fn define(
+    context: &Context,
+    with: &With,
+    outer: Option<&Row>,
+) -> Result<(), Box<dyn error::Error>> {
+    for table in &with.tables {
+        let relation = run(context, table, outer)?;
+        context.define_common_table(table.name.clone(), relation);
+    }
+    Ok(())
+}
+
+// a compound whose last query is a UNION reading the table itself is recursive: the queries
+// before it give the initial rows, then it is run against only the rows added by the step
+// before until it adds no more
+fn run(
+    context: &Context,
+    table: &CommonTable,
+    outer: Option<&Row>,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let select = &table.select;
+    let (all, step) = match select.compound.last() {
+        Some((CompoundOperator::Union, step)) if refers_to(step.from.as_ref(), &table.name) => {
+            (false, step)
+        }
+        Some((CompoundOperator::UnionAll, step)) if refers_to(step.from.as_ref(), &table.name) => {
+            (true, step)
+        }
+        _ => {
+            let result = select::execute_select(context, select, outer)?;
+            let names = column_names(table, result.columns)?;
+            return Ok(Relation {
+                names,
+                rows: result.rows,
+            });
+        }
+    };
+    if !select.order_by.is_empty() {
+        return Err(Box::new(SqlError::new(format!(
+            "ORDER BY in the recursive query of {} is not supported",
+            table.name
+        ))));
+    }
+
+    let count = context.common_table_count();
+    let result = match &select.with {
+        Some(with) => define(context, with, outer)
+            .and_then(|_| run_recursive(context, table, all, step, outer)),
+        None => run_recursive(context, table, all, step, outer),
+    };
+    context.forget_common_tables(count);
+    result
+}
+
+fn run_recursive(
+    context: &Context,
+    table: &CommonTable,
+    all: bool,
+    step: &Select,
+    outer: Option<&Row>,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let select = &table.select;
+    let mut initial = first_query(select);
+    initial.compound = select.compound[..select.compound.len() - 1].to_vec();
+    let (result, collations) = select::execute_collated(context, &initial, outer)?;
+    let names = column_names(table, result.columns)?;
+
+    let offset = match &select.offset {
+        Some(offset) => evaluate_count(offset)?.unwrap_or(0),
+        None => 0,
+    };
+    let limit = match &select.limit {
+        Some(limit) => evaluate_count(limit)?,
+        None => None,
+    };
+    let wanted = limit.map(|limit| limit.saturating_add(offset));
+
+    let mut rows = Vec::new();
+    let mut queue = add_rows(&mut rows, result.rows, all, wanted, &collations);
+    let operator = if all {
+        CompoundOperator::UnionAll
+    } else {
+        CompoundOperator::Union
+    };
+
+    while !queue.is_empty() && wanted.is_none_or(|wanted| rows.len() < wanted) {
+        let count = context.common_table_count();
+        let working = Relation {
+            names: names.clone(),
+            rows: queue,
+        };
+        context.define_common_table(table.name.clone(), working);
+        let result = select::execute_select(context, step, outer);
+        context.forget_common_tables(count);
+
+        let result = result?;
+        check_width(&operator, names.len(), result.columns.len())?;
+        queue = add_rows(&mut rows, result.rows, all, wanted, &collations);
+    }
+
+    Ok(Relation {
+        names,
+        rows: rows.into_iter().skip(offset).collect(),
+    })
+}
+
+// adds the new rows to the table, dropping rows already in it by the collations of the
+// initial query's columns for UNION, and returns the rows that were added
+fn add_rows(
+    rows: &mut Vec<Vec<Column>>,
+    new_rows: Vec<Vec<Column>>,
+    all: bool,
+    wanted: Option<usize>,
+    collations: &[Collation],
+) -> Vec<Vec<Column>> {
+    let mut added = Vec::new();
+    for row in new_rows {
+        if wanted.is_some_and(|wanted| rows.len() >= wanted) {
+            break;
+        }
+        if !all && rows.iter().any(|seen| same_row(seen, &row, collations)) {
+            continue;
+        }
+        rows.push(row.clone());
+        added.push(row);
+    }
+    added
+}
+
+fn column_names(table: &CommonTable, columns: Vec<String>) -> Result<Vec<ColumnName>, SqlError> {
+    if table.columns.is_empty() {
+        return Ok(columns
+            .into_iter()
+            .map(|name| ColumnName::new(None, name))
+            .collect());
+    }
+    if table.columns.len() != columns.len() {
+        return Err(SqlError::new(format!(
+            "table {} has {} values for {} columns",
+            table.name,
+            columns.len(),
+            table.columns.len()
+        )));
+    }
+    Ok(table
+        .columns
+        .iter()
+        .map(|name| ColumnName::new(None, name.clone()))
+        .collect())
+}
+
+// whether the FROM clause reads the named table directly
+fn refers_to(from: Option<&From>, name: &str) -> bool {
+    let from = match from {
+        Some(from) => from,
+        None => return false,
+    };
+    let tables = Some(&from.table)
+        .into_iter()
+        .chain(from.joins.iter().map(|join| &join.table));
+    for table in tables {
+        if let TableSource::Table(table_name) = &table.source {
+            if table_name.eq_ignore_ascii_case(name) {
+                return true;
+            }
+        }
+    }
+    false
+}
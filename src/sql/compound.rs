@@ -0,0 +1,141 @@
+use super::executor::{Context, ResultSet};
+use super::expression::Row;
+use super::select::{self, compare_keys, evaluate_count, resolve_sort_keys, same_row, SortKey};
+use super::statement::{CompoundOperator, Select};
+use super::SqlError;
+use backend::database::{Collation, Column};
+use std::error;
+
+/// Runs a compound SELECT. Each query is run on its own and the results are combined from
+/// left to right, then the ORDER BY and LIMIT apply to the combined rows. Rows are compared
+/// by the collations of the leftmost query's columns, which are returned with the result.
+pub fn execute_compound(
+    context: &Context,
+    select: &Select,
+    outer: Option<&Row>,
+) -> Result<(ResultSet, Vec<Collation>), Box<dyn error::Error>> {
+    let first = first_query(select);
+    let (
+        ResultSet {
+            columns, mut rows, ..
+        },
+        collations,
+    ) = select::execute_collated(context, &first, outer)?;
+
+    for (operator, query) in &select.compound {
+        let result = select::execute_select(context, query, outer)?;
+        check_width(operator, columns.len(), result.columns.len())?;
+        rows = combine(operator, rows, result.rows, &collations);
+    }
+
+    // the result has no source row, so ORDER BY can only name result columns
+    let mut keys = Vec::with_capacity(select.order_by.len());
+    let mut key_collations = Vec::with_capacity(select.order_by.len());
+    for key in resolve_sort_keys(&select.order_by, &columns, columns.len())? {
+        match key {
+            SortKey::Output(index) => {
+                keys.push(index);
+                key_collations.push(collations[index].clone());
+            }
+            SortKey::Expression(expression) => {
+                return Err(Box::new(SqlError::new(format!(
+                    "ORDER BY term {} does not match any column in the result set",
+                    expression
+                ))))
+            }
+        }
+    }
+    if !keys.is_empty() {
+        let sort_key = |row: &[Column]| -> Vec<Column> {
+            keys.iter().map(|index| row[*index].clone()).collect()
+        };
+        rows.sort_by(|left, right| {
+            compare_keys(
+                &sort_key(left),
+                &sort_key(right),
+                &select.order_by,
+                &key_collations,
+            )
+        });
+    }
+
+    let offset = match &select.offset {
+        Some(offset) => evaluate_count(offset)?.unwrap_or(0),
+        None => 0,
+    };
+    let limit = match &select.limit {
+        Some(limit) => evaluate_count(limit)?,
+        None => None,
+    };
+    let rows = rows
+        .into_iter()
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok((ResultSet::new(columns, rows), collations))
+}
+
+/// The leftmost query of a compound on its own.
+pub fn first_query(select: &Select) -> Select {
+    Select {
+        with: None,
+        compound: Vec::new(),
+        order_by: Vec::new(),
+        limit: None,
+        offset: None,
+        ..select.clone()
+    }
+}
+
+/// Combines the rows of the queries either side of a compound operator. Every operator but
+/// `UNION ALL` leaves out duplicate rows, rows compare by the collation of each column.
+pub fn combine(
+    operator: &CompoundOperator,
+    left: Vec<Vec<Column>>,
+    right: Vec<Vec<Column>>,
+    collations: &[Collation],
+) -> Vec<Vec<Column>> {
+    match operator {
+        CompoundOperator::UnionAll => {
+            let mut rows = left;
+            rows.extend(right);
+            rows
+        }
+        CompoundOperator::Union => distinct(left.into_iter().chain(right), collations),
+        CompoundOperator::Intersect => distinct(
+            left.into_iter()
+                .filter(|row| right.iter().any(|other| same_row(row, other, collations))),
+            collations,
+        ),
+        CompoundOperator::Except => distinct(
+            left.into_iter()
+                .filter(|row| !right.iter().any(|other| same_row(row, other, collations))),
+            collations,
+        ),
+    }
+}
+
+pub fn check_width(operator: &CompoundOperator, left: usize, right: usize) -> Result<(), SqlError> {
+    if left == right {
+        Ok(())
+    } else {
+        Err(SqlError::new(format!(
+            "SELECTs to the left and right of {} do not have the same number of result columns",
+            operator
+        )))
+    }
+}
+
+fn distinct<I: Iterator<Item = Vec<Column>>>(
+    rows: I,
+    collations: &[Collation],
+) -> Vec<Vec<Column>> {
+    let mut output: Vec<Vec<Column>> = Vec::new();
+    for row in rows {
+        if !output.iter().any(|seen| same_row(seen, &row, collations)) {
+            output.push(row);
+        }
+    }
+    output
+}
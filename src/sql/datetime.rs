@@ -0,0 +1,341 @@
+use super::expression::{to_text, BinaryOperator};
+use super::SqlError;
+use backend::database::{parse_numeric_prefix, Column, ColumnType, DateTime, Interval};
+
+/// `date(time, modifier, ...)`, the date as `YYYY-MM-DD`.
+pub fn date(arguments: &[Column]) -> Result<Column, SqlError> {
+    text_of(time_value(arguments), |time| time.format_date())
+}
+
+/// `time(time, modifier, ...)`, the time as `HH:MM:SS`.
+pub fn time(arguments: &[Column]) -> Result<Column, SqlError> {
+    text_of(time_value(arguments), |time| time.format_time(false))
+}
+
+/// `datetime(time, modifier, ...)`, the date and time as `YYYY-MM-DD HH:MM:SS`.
+pub fn datetime(arguments: &[Column]) -> Result<Column, SqlError> {
+    text_of(time_value(arguments), |time| time.format_datetime(false))
+}
+
+/// `julianday(time, modifier, ...)`, the days since noon on November 24, 4714 BC.
+pub fn julianday(arguments: &[Column]) -> Result<Column, SqlError> {
+    match time_value(arguments) {
+        Some(time) => Ok(Column::Real(time.julian_day())),
+        None => Ok(Column::Null()),
+    }
+}
+
+/// `unixepoch(time, modifier, ...)`, the whole seconds since 1970-01-01.
+pub fn unixepoch(arguments: &[Column]) -> Result<Column, SqlError> {
+    match time_value(arguments) {
+        Some(time) => Ok(Column::Integer(
+            time.unix_milliseconds().div_euclid(1000) as i128
+        )),
+        None => Ok(Column::Null()),
+    }
+}
+
+/// `strftime(format, time, modifier, ...)`, the time written with the substitutions of C's
+/// `strftime` that SQLite supports.
+pub fn strftime(arguments: &[Column]) -> Result<Column, SqlError> {
+    let format = match &arguments[0] {
+        Column::Null() => return Ok(Column::Null()),
+        format => to_text(format),
+    };
+    let time = match time_value(&arguments[1..]) {
+        Some(time) => time,
+        None => return Ok(Column::Null()),
+    };
+    match format_time(&format, time) {
+        Some(text) => Ok(Column::Text(text)),
+        None => Ok(Column::Null()),
+    }
+}
+
+/// `timediff(a, b)`, the interval that takes `b` to `a` as `±YYYY-MM-DD HH:MM:SS.SSS`.
+pub fn timediff(arguments: &[Column]) -> Result<Column, SqlError> {
+    match (time_value(&arguments[..1]), time_value(&arguments[1..])) {
+        (Some(later), Some(earlier)) => {
+            Ok(Column::Text(Interval::between(later, earlier).format()))
+        }
+        _ => Ok(Column::Null()),
+    }
+}
+
+// a date, time or timestamp with its type, or an interval, as an operand of `+` or `-`
+enum Temporal {
+    Moment(DateTime, ColumnType),
+    Interval(Interval),
+}
+
+/// `+` and `-` with dates, times, timestamps and intervals, given the values and affinities
+/// of both sides. An interval added to or taken from a date or time moves it and gives a value
+/// of its type, intervals add up, and a date or time taken from another gives the interval
+/// between them. A side without an affinity is an interval when it's signed or written with
+/// units and otherwise a timestamp. Text that can't be read gives NULL, other arithmetic
+/// fails.
+pub fn arithmetic(
+    operator: &BinaryOperator,
+    left: (&Column, Option<ColumnType>),
+    right: (&Column, Option<ColumnType>),
+) -> Result<Column, SqlError> {
+    if !matches!(operator, BinaryOperator::Add | BinaryOperator::Subtract) {
+        return Err(SqlError::new(format!(
+            "cannot apply {} to dates, times or intervals",
+            operator
+        )));
+    }
+    if *left.0 == Column::Null() || *right.0 == Column::Null() {
+        return Ok(Column::Null());
+    }
+    let (left, right) = match (temporal(left)?, temporal(right)?) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return Ok(Column::Null()),
+    };
+
+    let moved = |interval: Interval, time: DateTime, column_type: ColumnType| {
+        interval
+            .add_to(time)
+            .map(|time| column_type.apply(Column::Text(time.format_datetime(true))))
+    };
+    let result = match (operator, left, right) {
+        (
+            BinaryOperator::Add,
+            Temporal::Moment(time, column_type),
+            Temporal::Interval(interval),
+        )
+        | (
+            BinaryOperator::Add,
+            Temporal::Interval(interval),
+            Temporal::Moment(time, column_type),
+        ) => moved(interval, time, column_type),
+        (
+            BinaryOperator::Subtract,
+            Temporal::Moment(time, column_type),
+            Temporal::Interval(interval),
+        ) => moved(interval.negate(), time, column_type),
+        (BinaryOperator::Subtract, Temporal::Moment(later, _), Temporal::Moment(earlier, _)) => {
+            Some(Column::Text(Interval::between(later, earlier).format()))
+        }
+        (_, Temporal::Interval(left), Temporal::Interval(right)) => {
+            let right = if *operator == BinaryOperator::Subtract {
+                right.negate()
+            } else {
+                right
+            };
+            match left.checked_add(&right) {
+                Some(interval) => Some(Column::Text(interval.format())),
+                None => {
+                    return Err(SqlError::new(
+                        "interval out of range: its months and days have opposite signs",
+                    ))
+                }
+            }
+        }
+        (_, left, right) => {
+            let kind = |operand: &Temporal| match operand {
+                Temporal::Moment(..) => "a date or time",
+                Temporal::Interval(_) => "an interval",
+            };
+            return Err(SqlError::new(format!(
+                "cannot apply {} to {} and {}",
+                operator,
+                kind(&left),
+                kind(&right)
+            )));
+        }
+    };
+    Ok(result.unwrap_or(Column::Null()))
+}
+
+// reads one side of date and time arithmetic, None when its text isn't a date, time or
+// interval
+fn temporal(
+    (value, affinity): (&Column, Option<ColumnType>),
+) -> Result<Option<Temporal>, SqlError> {
+    let is_temporal = affinity.as_ref().is_some_and(ColumnType::is_temporal);
+    let text = match value {
+        Column::Text(text) => text,
+        _ if is_temporal => return Ok(None),
+        _ => {
+            return Err(SqlError::new(format!(
+                "{} is not a date, time or interval",
+                to_text(value)
+            )))
+        }
+    };
+    let operand = match affinity {
+        Some(ColumnType::Interval) => Interval::parse(text).map(Temporal::Interval),
+        Some(column_type) if is_temporal => {
+            DateTime::parse(text).map(|time| Temporal::Moment(time, column_type))
+        }
+        // as with date function modifiers, intervals without units have to be signed so a
+        // time isn't read as one
+        _ => {
+            let text = text.trim();
+            let interval = text.starts_with('+')
+                || text.starts_with('-')
+                || text.contains(|c: char| c.is_ascii_alphabetic());
+            match DateTime::parse(text) {
+                Some(time) => Some(Temporal::Moment(time, ColumnType::Timestamp)),
+                None if interval => Interval::parse(text).map(Temporal::Interval),
+                None => None,
+            }
+        }
+    };
+    Ok(operand)
+}
+
+fn text_of<F>(time: Option<DateTime>, format: F) -> Result<Column, SqlError>
+where
+    F: Fn(&DateTime) -> String,
+{
+    match time {
+        Some(time) => Ok(Column::Text(format(&time))),
+        None => Ok(Column::Null()),
+    }
+}
+
+// The time given by the arguments of a date function: no arguments are now, text is an
+// ISO-8601 time or `now`, and numbers are Julian days unless the first modifier says they are
+// seconds since 1970. The modifiers are then applied in order. NULL, times that can't be read,
+// unknown modifiers and times outside the years 0000 to 9999 give no time.
+fn time_value(arguments: &[Column]) -> Option<DateTime> {
+    let (value, modifiers) = match arguments.split_first() {
+        Some(split) => split,
+        None => return Some(DateTime::now()),
+    };
+    let mut modifiers = modifiers.iter().map(|modifier| match modifier {
+        Column::Null() => None,
+        modifier => Some(to_text(modifier).trim().to_ascii_lowercase()),
+    });
+
+    let number = match value {
+        Column::Integer(int) => *int as f64,
+        Column::Real(real) => *real,
+        Column::Decimal(decimal) => decimal.to_real(),
+        Column::Text(text) if text.trim().eq_ignore_ascii_case("now") => {
+            return apply_modifiers(DateTime::now(), modifiers)
+        }
+        Column::Text(text) => match DateTime::parse(text) {
+            Some(time) => return apply_modifiers(time, modifiers),
+            None => match parse_numeric_prefix(text) {
+                (Column::Integer(int), rest) if rest.trim().is_empty() => int as f64,
+                (Column::Real(real), rest) if rest.trim().is_empty() => real,
+                _ => return None,
+            },
+        },
+        Column::Null() | Column::Blob(_) => return None,
+    };
+
+    let first = modifiers.clone().next();
+    let time = match first
+        .as_ref()
+        .map(|first| first.as_ref().map(String::as_str))
+    {
+        Some(Some("unixepoch")) => DateTime::from_unix_seconds(number),
+        Some(Some("auto")) if !(0.0..5373484.5).contains(&number) => {
+            DateTime::from_unix_seconds(number)
+        }
+        Some(Some("julianday")) | Some(Some("auto")) => DateTime::from_julian_day(number),
+        _ => return apply_modifiers(DateTime::from_julian_day(number)?, modifiers),
+    };
+    modifiers.next();
+    apply_modifiers(time?, modifiers)
+}
+
+fn apply_modifiers<I>(time: DateTime, modifiers: I) -> Option<DateTime>
+where
+    I: Iterator<Item = Option<String>>,
+{
+    let mut time = time;
+    for modifier in modifiers {
+        time = modify(time, &modifier?)?;
+    }
+    Some(time)
+}
+
+// Applies a lowercase modifier: an interval such as `+1 month`, `-2 days` or
+// `+0000-01-15 00:00:00.000`, `start of day`, `start of month` or `start of year`, or
+// `weekday N` which moves forward to the next day that is that day of the week. Times are
+// UTC so `localtime` and `utc` leave the time alone.
+fn modify(time: DateTime, modifier: &str) -> Option<DateTime> {
+    let (year, month, day) = time.date();
+    match modifier {
+        "start of day" => return DateTime::from_civil(year, month, day, 0),
+        "start of month" => return DateTime::from_civil(year, month, 1, 0),
+        "start of year" => return DateTime::from_civil(year, 1, 1, 0),
+        "localtime" | "utc" => return Some(time),
+        _ => {}
+    }
+    if let Some(weekday) = modifier.strip_prefix("weekday ") {
+        let weekday: i64 = weekday.trim().parse().ok()?;
+        if !(0..7).contains(&weekday) {
+            return None;
+        }
+        let days = (weekday - time.weekday()).rem_euclid(7);
+        return time.add_milliseconds(days * 86_400_000);
+    }
+    // intervals given as fields have to be signed, so a time isn't read as one
+    let signed = modifier.starts_with('+') || modifier.starts_with('-');
+    if !signed && !modifier.contains(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Interval::parse(modifier)?.add_to(time)
+}
+
+// the format with each `%` substitution replaced, or None for an unknown one
+fn format_time(format: &str, time: DateTime) -> Option<String> {
+    let (year, month, day) = time.date();
+    let millisecond = time.time();
+    let (hour, minute, second) = (
+        millisecond / 3_600_000,
+        millisecond / 60_000 % 60,
+        millisecond / 1000 % 60,
+    );
+    let hour_12 = match hour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    let day_of_year = (time.milliseconds()
+        - DateTime::from_civil(year, 1, 1, millisecond)?.milliseconds())
+        / 86_400_000;
+    let monday_weekday = (time.weekday() + 6) % 7;
+
+    let mut text = String::new();
+    let mut characters = format.chars();
+    while let Some(c) = characters.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let substitution = match characters.next()? {
+            'd' => format!("{:02}", day),
+            'e' => format!("{:2}", day),
+            'f' => format!("{:02}.{:03}", second, millisecond % 1000),
+            'F' => format!("{:04}-{:02}-{:02}", year, month, day),
+            'H' => format!("{:02}", hour),
+            'I' => format!("{:02}", hour_12),
+            'j' => format!("{:03}", day_of_year + 1),
+            'J' => format!("{}", time.julian_day()),
+            'k' => format!("{:2}", hour),
+            'l' => format!("{:2}", hour_12),
+            'm' => format!("{:02}", month),
+            'M' => format!("{:02}", minute),
+            'p' => (if hour < 12 { "AM" } else { "PM" }).to_string(),
+            'P' => (if hour < 12 { "am" } else { "pm" }).to_string(),
+            'R' => format!("{:02}:{:02}", hour, minute),
+            's' => format!("{}", time.unix_milliseconds().div_euclid(1000)),
+            'S' => format!("{:02}", second),
+            'T' => format!("{:02}:{:02}:{:02}", hour, minute, second),
+            'u' => format!("{}", monday_weekday + 1),
+            'w' => format!("{}", time.weekday()),
+            'W' => format!("{:02}", (day_of_year + 7 - monday_weekday) / 7),
+            'Y' => format!("{:04}", year),
+            '%' => "%".to_string(),
+            _ => return None,
+        };
+        text.push_str(&substitution);
+    }
+    Some(text)
+}
@@ -0,0 +1,642 @@
+use super::codegen;
+use super::explain;
+use super::expression::{ColumnName, Expression, Row, Scope, Subqueries};
+use super::function;
+use super::generated;
+use super::parser;
+use super::select;
+use super::statement::{
+    AlterAction, AlterTable, CreateTrigger, Delete, Insert, Select, Statement, TriggerEvent,
+    TriggerTiming, Update,
+};
+use super::trigger::{self, Triggers};
+use super::view;
+use super::vm;
+use super::SqlError;
+use backend::database::{Collation, Column, Database, Functions, Generated, GeneratedColumns};
+use std::cell::{RefCell, RefMut};
+use std::collections::HashMap;
+use std::error;
+
+/// The rows returned by a statement along with the names of their columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Column>>,
+    /// whether the rows are the steps of an `EXPLAIN QUERY PLAN`, each one a row of its id,
+    /// the id of the step it's part of and what it does
+    pub query_plan: bool,
+}
+
+impl ResultSet {
+    pub fn new(columns: Vec<String>, rows: Vec<Vec<Column>>) -> ResultSet {
+        ResultSet {
+            columns,
+            rows,
+            query_plan: false,
+        }
+    }
+
+    pub fn empty() -> ResultSet {
+        ResultSet::new(Vec::new(), Vec::new())
+    }
+}
+
+impl Database {
+    pub fn execute(&mut self, sql: &str) -> Result<ResultSet, Box<dyn error::Error>> {
+        let statement = parser::parse_statement(sql)?;
+        execute(self, statement)
+    }
+}
+
+/// The database a statement runs against. Subqueries run while the enclosing query is part
+/// way through, so the database is only borrowed for each read or write.
+pub struct Context<'d> {
+    database: RefCell<&'d mut Database>,
+    /// the common tables in scope, innermost last
+    common_tables: RefCell<Vec<(String, Relation)>>,
+    /// the triggers whose statements are running, innermost last
+    triggers: RefCell<Vec<String>>,
+    /// the collations registered on the database
+    collations: HashMap<String, Collation>,
+    /// the functions registered on the database
+    functions: Functions,
+}
+
+impl<'d> Context<'d> {
+    pub fn new(database: &'d mut Database) -> Context<'d> {
+        Context {
+            collations: database.collations.clone(),
+            functions: database.functions.clone(),
+            database: RefCell::new(database),
+            common_tables: RefCell::new(Vec::new()),
+            triggers: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn database(&self) -> RefMut<'_, &'d mut Database> {
+        self.database.borrow_mut()
+    }
+
+    pub fn common_table(&self, name: &str) -> Option<Relation> {
+        let common_tables = self.common_tables.borrow();
+        common_tables
+            .iter()
+            .rev()
+            .find(|(table_name, _)| table_name.eq_ignore_ascii_case(name))
+            .map(|(_, relation)| relation.clone())
+    }
+
+    pub fn define_common_table(&self, name: String, relation: Relation) {
+        self.common_tables.borrow_mut().push((name, relation));
+    }
+
+    pub fn common_table_count(&self) -> usize {
+        self.common_tables.borrow().len()
+    }
+
+    /// Drops the common tables defined after the first `count`.
+    pub fn forget_common_tables(&self, count: usize) {
+        self.common_tables.borrow_mut().truncate(count);
+    }
+
+    pub fn trigger_running(&self, name: &str) -> bool {
+        self.triggers.borrow().iter().any(|trigger| trigger == name)
+    }
+
+    pub fn start_trigger(&self, name: String) {
+        self.triggers.borrow_mut().push(name);
+    }
+
+    pub fn finish_trigger(&self) {
+        self.triggers.borrow_mut().pop();
+    }
+
+    /// The scope expressions of a query are evaluated in, `outer` is the row of the
+    /// enclosing query when running a subquery.
+    pub fn scope<'a>(&'a self, outer: Option<&'a Row<'a>>) -> Scope<'a> {
+        Scope {
+            subqueries: Some(self),
+            collations: Some(&self.collations),
+            functions: Some(&self.functions),
+            outer,
+        }
+    }
+}
+
+impl<'d> Subqueries for Context<'d> {
+    fn query(&self, select: &Select, outer: &Row) -> Result<Vec<Vec<Column>>, SqlError> {
+        match select::execute_select(self, select, Some(outer)) {
+            Ok(result) => Ok(result.rows),
+            Err(error) => Err(SqlError::new(error.to_string())),
+        }
+    }
+}
+
+pub fn execute(
+    database: &mut Database,
+    mut statement: Statement,
+) -> Result<ResultSet, Box<dyn error::Error>> {
+    database.generator.get_or_insert(generated::generate);
+    view::expand(database, &mut statement)?;
+    function::resolve(&mut statement, &database.functions);
+    let fires = trigger::fires(database, &statement)?;
+    let program = if fires {
+        None
+    } else {
+        codegen::compile(database, &statement)?
+    };
+
+    // a write and the triggers it fires are undone together when any of them fails, so a
+    // statement never leaves only some of its rows written
+    let writes = matches!(
+        statement,
+        Statement::Insert(_) | Statement::Update(_) | Statement::Delete(_)
+    );
+    let savepoint = if writes {
+        Some(database.savepoint()?)
+    } else {
+        None
+    };
+    let result = match program {
+        Some(program) => vm::run(database, &program),
+        None => execute_statement(&Context::new(database), statement),
+    };
+    match (result, savepoint) {
+        (Err(error), Some(savepoint)) => {
+            database.rollback(savepoint)?;
+            Err(error)
+        }
+        (result, _) => result,
+    }
+}
+
+/// Runs a statement whose views have been expanded without compiling it, which is how the
+/// statements of triggers are run.
+pub fn execute_statement(
+    context: &Context,
+    statement: Statement,
+) -> Result<ResultSet, Box<dyn error::Error>> {
+    match statement {
+        Statement::Select(select) => select::execute_select(context, &select, None),
+        Statement::Insert(insert) => {
+            execute_insert(context, insert)?;
+            Ok(ResultSet::empty())
+        }
+        Statement::Update(update) => {
+            execute_update(context, update)?;
+            Ok(ResultSet::empty())
+        }
+        Statement::Delete(delete) => {
+            execute_delete(context, delete)?;
+            Ok(ResultSet::empty())
+        }
+        Statement::Explain {
+            statement,
+            query_plan,
+        } => explain::explain(&mut context.database(), &statement, query_plan),
+        Statement::CreateIndex(create_index) => {
+            let (name, table, columns) =
+                (create_index.name, create_index.table, create_index.columns);
+            context.database().create_index(name, table, columns)?;
+            Ok(ResultSet::empty())
+        }
+        Statement::CreateView(create_view) => {
+            let mut database = context.database();
+            let exists = database.view_definition(&create_view.name)?.is_some();
+            if !create_view.if_not_exists || !exists {
+                database.create_view(create_view.name, create_view.sql)?;
+            }
+            Ok(ResultSet::empty())
+        }
+        Statement::DropView { view, if_exists } => {
+            let mut database = context.database();
+            if !if_exists || database.view_definition(&view)?.is_some() {
+                database.drop_view(view)?;
+            }
+            Ok(ResultSet::empty())
+        }
+        Statement::CreateTrigger(create_trigger) => {
+            execute_create_trigger(context, create_trigger)?;
+            Ok(ResultSet::empty())
+        }
+        Statement::DropTrigger { trigger, if_exists } => {
+            let mut database = context.database();
+            if !if_exists || database.trigger_exists(&trigger)? {
+                database.drop_trigger(trigger)?;
+            }
+            Ok(ResultSet::empty())
+        }
+        Statement::DropTable { table, if_exists } => {
+            let mut database = context.database();
+            if !if_exists || database.describe_table(table.clone()).is_ok() {
+                database.drop_table(table)?;
+            }
+            Ok(ResultSet::empty())
+        }
+        Statement::AlterTable(alter_table) => {
+            execute_alter_table(context, alter_table)?;
+            Ok(ResultSet::empty())
+        }
+        Statement::Analyze(table) => {
+            context.database().analyze(table)?;
+            Ok(ResultSet::empty())
+        }
+    }
+}
+
+// views only take INSTEAD OF triggers, which tables can't have
+fn execute_create_trigger(
+    context: &Context,
+    create_trigger: CreateTrigger,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut database = context.database();
+    if create_trigger.if_not_exists && database.trigger_exists(&create_trigger.name)? {
+        return Ok(());
+    }
+    let view = database.view_definition(&create_trigger.table)?.is_some();
+    match (create_trigger.timing, view) {
+        (TriggerTiming::InsteadOf, false) => Err(Box::new(SqlError::new(format!(
+            "cannot create INSTEAD OF trigger on table: {}",
+            create_trigger.table
+        )))),
+        (TriggerTiming::Before, true) | (TriggerTiming::After, true) => {
+            Err(Box::new(SqlError::new(format!(
+                "cannot create BEFORE or AFTER trigger on view: {}",
+                create_trigger.table
+            ))))
+        }
+        _ => database.create_trigger(
+            create_trigger.name,
+            create_trigger.table,
+            create_trigger.sql,
+        ),
+    }
+}
+
+fn execute_alter_table(
+    context: &Context,
+    alter_table: AlterTable,
+) -> Result<(), Box<dyn error::Error>> {
+    let table = alter_table.table;
+    match alter_table.action {
+        AlterAction::RenameTable(new_name) => context.database().rename_table(table, new_name),
+        AlterAction::RenameColumn { column, new_name } => {
+            let mut database = context.database();
+            generated::check_unused(&mut database, &table, &column, "rename")?;
+            database.rename_column(table, column, new_name)
+        }
+        AlterAction::AddColumn {
+            name,
+            column_type,
+            default,
+            collation,
+            generated,
+        } => {
+            // the default is worked out once, it can't refer to any columns
+            let default = match default {
+                Some(expression) => expression.evaluate(&Row::empty())?,
+                None => Column::Null(),
+            };
+            let mut database = context.database();
+            let generated = match generated {
+                Some((expression, stored)) => Some(Generated {
+                    expression: generated::check(&mut database, &table, expression)?,
+                    stored,
+                }),
+                None => None,
+            };
+            database.add_column(table, (name, column_type), default, collation, generated)
+        }
+        AlterAction::DropColumn(column) => {
+            let mut database = context.database();
+            generated::check_unused(&mut database, &table, &column, "drop")?;
+            database.drop_column(table, column)
+        }
+    }
+}
+
+fn execute_insert(context: &Context, insert: Insert) -> Result<usize, Box<dyn error::Error>> {
+    let view = is_view(context, &insert.table)?;
+    let (names, defaults, generated) = if view {
+        let names = view::scan(context, &insert.table)?.names;
+        let defaults = vec![Column::Null(); names.len()];
+        (names, defaults, None)
+    } else {
+        let mut database = context.database();
+        let names = table_names(&mut database, &insert.table, &insert.table)?;
+        let defaults = database.table_defaults(insert.table.clone())?;
+        let generated = database.generated_columns(insert.table.clone())?;
+        (names, defaults, Some(generated))
+    };
+    let names: Vec<String> = names.into_iter().map(|name| name.name).collect();
+    let scope = context.scope(None);
+
+    let computed = generated_flags(&generated);
+    let mut records = Vec::with_capacity(insert.values.len());
+    for row in insert_values(&names, &computed, &insert)? {
+        let mut record = Vec::with_capacity(row.len());
+        for (value, default) in row.into_iter().zip(defaults.iter()) {
+            match value {
+                Some(expression) => {
+                    record.push(expression.evaluate(&Row::empty().with_scope(scope))?)
+                }
+                None => record.push(default.clone()),
+            }
+        }
+        if let Some(generated) = &generated {
+            context
+                .database()
+                .generate_columns(generated, &mut record)?;
+        }
+        records.push(record);
+    }
+
+    let event = TriggerEvent::Insert;
+    let triggers = Triggers::load(&mut context.database(), &insert.table, &event, names)?;
+    if view {
+        check_instead_of(&triggers, &insert.table)?;
+        for record in &records {
+            triggers.fire(context, TriggerTiming::InsteadOf, None, Some(record))?;
+        }
+        return Ok(records.len());
+    }
+
+    for record in &records {
+        triggers.fire(context, TriggerTiming::Before, None, Some(record))?;
+    }
+    {
+        let mut database = context.database();
+        let mut cursor = database.open_cursor(insert.table.clone())?;
+        for record in &records {
+            cursor.insert(&mut database, record.clone())?;
+        }
+        cursor.close(&mut database)?;
+    }
+    for record in &records {
+        triggers.fire(context, TriggerTiming::After, None, Some(record))?;
+    }
+    Ok(records.len())
+}
+
+/// Lines the values of each inserted row up with the table's columns, columns that aren't
+/// given a value are None and take their default. Those `generated` marks can't be given
+/// values, without a list of columns the values are for the others.
+pub fn insert_values<'a>(
+    columns: &[String],
+    generated: &[bool],
+    insert: &'a Insert,
+) -> Result<Vec<Vec<Option<&'a Expression>>>, SqlError> {
+    let is_generated = |position: usize| generated.get(position) == Some(&true);
+    let mut positions = Vec::with_capacity(insert.columns.len());
+    for column in &insert.columns {
+        match columns
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(column))
+        {
+            Some(position) if is_generated(position) => {
+                return Err(SqlError::new(format!(
+                    "cannot INSERT into generated column {}",
+                    column
+                )))
+            }
+            Some(position) => positions.push(position),
+            None => {
+                return Err(SqlError::new(format!(
+                    "table {} has no column named {}",
+                    insert.table, column
+                )))
+            }
+        }
+    }
+    if insert.columns.is_empty() {
+        positions = (0..columns.len())
+            .filter(|position| !is_generated(*position))
+            .collect();
+    }
+
+    let mut rows = Vec::with_capacity(insert.values.len());
+    for values in &insert.values {
+        if values.len() != positions.len() {
+            return Err(SqlError::new(if insert.columns.is_empty() {
+                format!(
+                    "table {} has {} columns but {} values were supplied",
+                    insert.table,
+                    positions.len(),
+                    values.len()
+                )
+            } else {
+                format!("{} values for {} columns", values.len(), positions.len())
+            }));
+        }
+        let mut row = vec![None; columns.len()];
+        for (position, value) in positions.iter().zip(values) {
+            row[*position] = Some(value);
+        }
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+fn execute_update(context: &Context, update: Update) -> Result<usize, Box<dyn error::Error>> {
+    let view = is_view(context, &update.table)?;
+    let Relation { names, rows } = scan_target(context, &update.table, view)?;
+    let generated = if view {
+        None
+    } else {
+        Some(context.database().generated_columns(update.table.clone())?)
+    };
+    let computed = generated_flags(&generated);
+    let scope = context.scope(None);
+
+    let mut assignments = Vec::new();
+    for (column, expression) in &update.assignments {
+        match names
+            .iter()
+            .position(|name| name.name.eq_ignore_ascii_case(column))
+        {
+            Some(index) if computed.get(index) == Some(&true) => {
+                return Err(Box::new(SqlError::new(format!(
+                    "cannot UPDATE generated column {}",
+                    column
+                ))))
+            }
+            Some(index) => assignments.push((index, expression)),
+            None => {
+                return Err(Box::new(SqlError::new(format!(
+                    "no such column: {}",
+                    column
+                ))))
+            }
+        }
+    }
+
+    let mut records = Vec::with_capacity(rows.len());
+    let mut changes = Vec::new();
+    for row in rows {
+        if !matches(&update.selection, &names, &row, scope)? {
+            records.push(row);
+            continue;
+        }
+
+        // every assignment sees the row as it was before the update
+        let mut record = row.clone();
+        for (index, expression) in &assignments {
+            record[*index] = expression.evaluate(&Row::new(&names, &row).with_scope(scope))?;
+        }
+        if let Some(generated) = &generated {
+            context
+                .database()
+                .generate_columns(generated, &mut record)?;
+        }
+        records.push(record.clone());
+        changes.push((row, record));
+    }
+
+    let assigned = update
+        .assignments
+        .iter()
+        .map(|(column, _)| column.clone())
+        .collect();
+    let event = TriggerEvent::Update(assigned);
+    let names = names.into_iter().map(|name| name.name).collect();
+    let triggers = Triggers::load(&mut context.database(), &update.table, &event, names)?;
+    if view {
+        check_instead_of(&triggers, &update.table)?;
+        for (old, new) in &changes {
+            triggers.fire(context, TriggerTiming::InsteadOf, Some(old), Some(new))?;
+        }
+        return Ok(changes.len());
+    }
+
+    for (old, new) in &changes {
+        triggers.fire(context, TriggerTiming::Before, Some(old), Some(new))?;
+    }
+    context.database().replace_records(update.table, records)?;
+    for (old, new) in &changes {
+        triggers.fire(context, TriggerTiming::After, Some(old), Some(new))?;
+    }
+    Ok(changes.len())
+}
+
+fn execute_delete(context: &Context, delete: Delete) -> Result<usize, Box<dyn error::Error>> {
+    let view = is_view(context, &delete.table)?;
+    let Relation { names, rows } = scan_target(context, &delete.table, view)?;
+    let scope = context.scope(None);
+
+    let mut records = Vec::with_capacity(rows.len());
+    let mut deleted = Vec::new();
+    for row in rows {
+        if matches(&delete.selection, &names, &row, scope)? {
+            deleted.push(row);
+        } else {
+            records.push(row);
+        }
+    }
+
+    let event = TriggerEvent::Delete;
+    let names = names.into_iter().map(|name| name.name).collect();
+    let triggers = Triggers::load(&mut context.database(), &delete.table, &event, names)?;
+    if view {
+        check_instead_of(&triggers, &delete.table)?;
+        for row in &deleted {
+            triggers.fire(context, TriggerTiming::InsteadOf, Some(row), None)?;
+        }
+        return Ok(deleted.len());
+    }
+
+    for row in &deleted {
+        triggers.fire(context, TriggerTiming::Before, Some(row), None)?;
+    }
+    context.database().replace_records(delete.table, records)?;
+    for row in &deleted {
+        triggers.fire(context, TriggerTiming::After, Some(row), None)?;
+    }
+    Ok(deleted.len())
+}
+
+// which of a table's columns are generated, none of a view's are
+fn generated_flags(generated: &Option<GeneratedColumns>) -> Vec<bool> {
+    match generated {
+        Some(generated) => generated.generated.iter().map(Option::is_some).collect(),
+        None => Vec::new(),
+    }
+}
+
+fn is_view(context: &Context, name: &str) -> Result<bool, Box<dyn error::Error>> {
+    Ok(context.database().view_definition(name)?.is_some())
+}
+
+// the rows of the table a statement changes, or of the view whose triggers make the change
+fn scan_target(
+    context: &Context,
+    name: &str,
+    view: bool,
+) -> Result<Relation, Box<dyn error::Error>> {
+    if view {
+        view::scan(context, name)
+    } else {
+        scan_table(&mut context.database(), name, name)
+    }
+}
+
+// a view can only be changed by INSTEAD OF triggers for the change
+fn check_instead_of(triggers: &Triggers, view: &str) -> Result<(), SqlError> {
+    if triggers.any(TriggerTiming::InsteadOf) {
+        Ok(())
+    } else {
+        Err(SqlError::new(format!(
+            "cannot modify {} because it is a view",
+            view
+        )))
+    }
+}
+
+/// Rows along with the names of their columns.
+#[derive(Debug, Clone)]
+pub struct Relation {
+    pub names: Vec<ColumnName>,
+    pub rows: Vec<Vec<Column>>,
+}
+
+// the names of a table's columns, qualified by the name the table is referred to by
+pub fn table_names(
+    database: &mut Database,
+    table_name: &str,
+    reference_name: &str,
+) -> Result<Vec<ColumnName>, Box<dyn error::Error>> {
+    let (_page_number, columns) = database.describe_table(table_name.to_string())?;
+    let names = columns
+        .into_iter()
+        .zip(database.table_collations(table_name.to_string())?)
+        .map(|((name, column_type), collation)| ColumnName {
+            affinity: Some(column_type),
+            collation,
+            ..ColumnName::new(Some(reference_name.to_string()), name)
+        })
+        .collect();
+    Ok(names)
+}
+
+// reads every row of a table
+pub fn scan_table(
+    database: &mut Database,
+    table_name: &str,
+    reference_name: &str,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let names = table_names(database, table_name, reference_name)?;
+    let rows = database.select_all_records(table_name.to_string())?;
+    Ok(Relation { names, rows })
+}
+
+pub fn matches(
+    selection: &Option<Expression>,
+    names: &[ColumnName],
+    row: &[Column],
+    scope: Scope,
+) -> Result<bool, SqlError> {
+    match selection {
+        Some(expression) => expression.is_true(&Row::new(names, row).with_scope(scope)),
+        None => Ok(true),
+    }
+}
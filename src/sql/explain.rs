@@ -0,0 +1,553 @@
+use super::codegen;
+use super::compound::first_query;
+use super::executor::{table_names, ResultSet};
+use super::expression::{quote, BinaryOperator, ColumnName, Expression, UnaryOperator};
+use super::function;
+use super::join::join_condition;
+use super::planner::{self, Access, JoinInput};
+use super::select::expand_columns;
+use super::statement::{
+    From, JoinOperator, ResultColumn, Select, Statement, TableReference, TableSource,
+};
+use super::vm::{Instruction, Program};
+use super::window;
+use super::SqlError;
+use backend::database::{Column, Database};
+use std::error;
+
+/// `EXPLAIN` lists the program a statement compiles to, or the query plan of statements
+/// the executor runs. `EXPLAIN QUERY PLAN` always gives the query plan.
+pub fn explain(
+    database: &mut Database,
+    statement: &Statement,
+    query_plan: bool,
+) -> Result<ResultSet, Box<dyn error::Error>> {
+    if let Statement::Explain { .. } = statement {
+        return Err(Box::new(SqlError::new("cannot EXPLAIN an EXPLAIN")));
+    }
+    if !query_plan {
+        if let Some(program) = codegen::compile(database, statement)? {
+            return Ok(list_program(&program));
+        }
+    }
+
+    let mut planner = Planner {
+        database,
+        common_tables: Vec::new(),
+    };
+    let steps = planner.statement(statement)?;
+
+    let mut rows = Vec::new();
+    flatten(&steps, 0, &mut rows);
+    Ok(ResultSet {
+        columns: vec!["id".to_string(), "parent".to_string(), "detail".to_string()],
+        rows,
+        query_plan: true,
+    })
+}
+
+fn list_program(program: &Program) -> ResultSet {
+    let rows = program
+        .instructions
+        .iter()
+        .enumerate()
+        .map(|(address, instruction)| {
+            let (opcode, p1, p2, p3, p4) = describe(instruction);
+            let operand = |value: usize| Column::Integer(value as i128);
+            vec![
+                operand(address),
+                Column::Text(opcode.to_string()),
+                operand(p1),
+                operand(p2),
+                operand(p3),
+                p4.map(Column::Text).unwrap_or(Column::Null()),
+            ]
+        })
+        .collect();
+
+    let columns = ["addr", "opcode", "p1", "p2", "p3", "p4"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+    ResultSet::new(columns, rows)
+}
+
+// the name and operands of an instruction the way sqlite lays them out, cursors and inputs
+// first, then jump addresses or outputs
+fn describe(instruction: &Instruction) -> (&'static str, usize, usize, usize, Option<String>) {
+    match instruction {
+        Instruction::Goto { address } => ("Goto", 0, *address, 0, None),
+        Instruction::Halt => ("Halt", 0, 0, 0, None),
+        Instruction::OpenRead { cursor, table } => ("OpenRead", *cursor, 0, 0, Some(table.clone())),
+        Instruction::OpenWrite { cursor, table } => {
+            ("OpenWrite", *cursor, 0, 0, Some(table.clone()))
+        }
+        Instruction::SorterOpen { cursor, order } => {
+            let order = order
+                .iter()
+                .map(|term| if term.descending { "DESC" } else { "ASC" })
+                .collect::<Vec<_>>()
+                .join(", ");
+            ("SorterOpen", *cursor, 0, 0, Some(order))
+        }
+        Instruction::Close { cursor } => ("Close", *cursor, 0, 0, None),
+        Instruction::Rewind { cursor, address } => ("Rewind", *cursor, *address, 0, None),
+        Instruction::Next { cursor, address } => ("Next", *cursor, *address, 0, None),
+        Instruction::SorterSort { cursor, address } => ("SorterSort", *cursor, *address, 0, None),
+        Instruction::SorterNext { cursor, address } => ("SorterNext", *cursor, *address, 0, None),
+        Instruction::SorterInsert {
+            cursor,
+            start,
+            count,
+        } => ("SorterInsert", *cursor, *start, *count, None),
+        Instruction::Column {
+            cursor,
+            column,
+            target,
+        } => ("Column", *cursor, *column, *target, None),
+        Instruction::Value { value, target } => {
+            let opcode = match value {
+                Column::Null() => "Null",
+                Column::Integer(_) => "Integer",
+                Column::Real(_) => "Real",
+                Column::Text(_) => "String",
+                Column::Blob(_) => "Blob",
+                Column::Decimal(_) => "Decimal",
+            };
+            (opcode, 0, *target, 0, Some(quote(value)))
+        }
+        Instruction::Copy { source, target } => ("Copy", *source, *target, 0, None),
+        Instruction::Unary {
+            operator,
+            source,
+            target,
+        } => {
+            let opcode = match operator {
+                UnaryOperator::Not => "Not",
+                UnaryOperator::Negate => "Negate",
+                UnaryOperator::Plus => "Copy",
+            };
+            (opcode, *source, *target, 0, None)
+        }
+        Instruction::Binary {
+            operator,
+            left,
+            right,
+            target,
+        } => {
+            let opcode = match operator {
+                BinaryOperator::And => "And",
+                BinaryOperator::Or => "Or",
+                BinaryOperator::Equal => "Eq",
+                BinaryOperator::NotEqual => "Ne",
+                BinaryOperator::Less => "Lt",
+                BinaryOperator::LessEqual => "Le",
+                BinaryOperator::Greater => "Gt",
+                BinaryOperator::GreaterEqual => "Ge",
+                BinaryOperator::Is => "Is",
+                BinaryOperator::IsNot => "IsNot",
+                BinaryOperator::Add => "Add",
+                BinaryOperator::Subtract => "Subtract",
+                BinaryOperator::Multiply => "Multiply",
+                BinaryOperator::Divide => "Divide",
+                BinaryOperator::Modulo => "Remainder",
+                BinaryOperator::Concat => "Concat",
+                BinaryOperator::Extract => "Extract",
+                BinaryOperator::ExtractValue => "ExtractValue",
+            };
+            (opcode, *left, *right, *target, None)
+        }
+        Instruction::IsNull {
+            source,
+            target,
+            negated,
+        } => {
+            let opcode = if *negated { "NotNull" } else { "IsNull" };
+            (opcode, *source, *target, 0, None)
+        }
+        Instruction::Affinity { register, affinity } => {
+            let affinity = format!("{:?}", affinity).to_uppercase();
+            ("Affinity", *register, 1, 0, Some(affinity))
+        }
+        Instruction::If { register, address } => ("If", *register, *address, 0, None),
+        Instruction::IfNot { register, address } => ("IfNot", *register, *address, 0, None),
+        Instruction::IfPos { register, address } => ("IfPos", *register, *address, 0, None),
+        Instruction::DecrJumpZero { register, address } => {
+            ("DecrJumpZero", *register, *address, 0, None)
+        }
+        Instruction::ResultRow { start, count } => ("ResultRow", *start, *count, 0, None),
+        Instruction::Insert {
+            cursor,
+            start,
+            count,
+        } => ("Insert", *cursor, *start, *count, None),
+        Instruction::Replace {
+            cursor,
+            start,
+            count,
+        } => ("Replace", *cursor, *start, *count, None),
+        Instruction::Delete { cursor } => ("Delete", *cursor, 0, 0, None),
+    }
+}
+
+/// A step of a query plan and the steps it is made of.
+struct Step {
+    detail: String,
+    children: Vec<Step>,
+}
+
+impl Step {
+    fn new<S: Into<String>>(detail: S) -> Step {
+        Step {
+            detail: detail.into(),
+            children: Vec::new(),
+        }
+    }
+
+    fn with_children(mut self, children: Vec<Step>) -> Step {
+        self.children = children;
+        self
+    }
+}
+
+// numbers the steps in order, each row names the step it is part of
+fn flatten(steps: &[Step], parent: usize, rows: &mut Vec<Vec<Column>>) {
+    for step in steps {
+        let id = rows.len() + 1;
+        rows.push(vec![
+            Column::Integer(id as i128),
+            Column::Integer(parent as i128),
+            Column::Text(step.detail.clone()),
+        ]);
+        flatten(&step.children, id, rows);
+    }
+}
+
+// works out how the executor will run a statement without running it, common tables are
+// only known by the names of their columns
+struct Planner<'d> {
+    database: &'d mut Database,
+    common_tables: Vec<(String, Vec<String>)>,
+}
+
+impl<'d> Planner<'d> {
+    fn statement(&mut self, statement: &Statement) -> Result<Vec<Step>, Box<dyn error::Error>> {
+        match statement {
+            Statement::Select(select) => self.select(select),
+            Statement::Insert(insert) => {
+                let mut steps = Vec::new();
+                for value in insert.values.iter().flatten() {
+                    self.subqueries(value, &mut steps)?;
+                }
+                Ok(steps)
+            }
+            Statement::Update(update) => {
+                let mut steps = vec![Step::new(format!("SCAN {}", update.table))];
+                for expression in update
+                    .assignments
+                    .iter()
+                    .map(|(_, value)| value)
+                    .chain(update.selection.iter())
+                {
+                    self.subqueries(expression, &mut steps)?;
+                }
+                Ok(steps)
+            }
+            Statement::Delete(delete) => {
+                let mut steps = vec![Step::new(format!("SCAN {}", delete.table))];
+                if let Some(selection) = &delete.selection {
+                    self.subqueries(selection, &mut steps)?;
+                }
+                Ok(steps)
+            }
+            Statement::CreateIndex(_)
+            | Statement::CreateView(_)
+            | Statement::DropView { .. }
+            | Statement::CreateTrigger(_)
+            | Statement::DropTrigger { .. }
+            | Statement::DropTable { .. }
+            | Statement::AlterTable(_)
+            | Statement::Analyze(_)
+            | Statement::Explain { .. } => Ok(Vec::new()),
+        }
+    }
+
+    fn select(&mut self, select: &Select) -> Result<Vec<Step>, Box<dyn error::Error>> {
+        let count = self.common_tables.len();
+        let result = self.query(select);
+        self.common_tables.truncate(count);
+        result
+    }
+
+    fn query(&mut self, select: &Select) -> Result<Vec<Step>, Box<dyn error::Error>> {
+        let mut steps = Vec::new();
+        if let Some(with) = &select.with {
+            for table in &with.tables {
+                // a recursive table can refer to itself before its columns are known
+                self.common_tables
+                    .push((table.name.clone(), table.columns.clone()));
+                let columns = if table.columns.is_empty() {
+                    self.result_columns(&table.select)?
+                } else {
+                    table.columns.clone()
+                };
+                let children = self.select(&table.select)?;
+                self.common_tables.pop();
+                self.common_tables.push((table.name.clone(), columns));
+                steps
+                    .push(Step::new(format!("MATERIALIZE {}", table.name)).with_children(children));
+            }
+        }
+
+        if select.compound.is_empty() {
+            steps.extend(self.core(select)?);
+            return Ok(steps);
+        }
+
+        let mut children =
+            vec![Step::new("LEFT-MOST SUBQUERY").with_children(self.core(&first_query(select))?)];
+        for (operator, term) in &select.compound {
+            children.push(Step::new(operator.to_string()).with_children(self.core(term)?));
+        }
+        steps.push(Step::new("COMPOUND QUERY").with_children(children));
+        if !select.order_by.is_empty() {
+            steps.push(Step::new("USE TEMP B-TREE FOR ORDER BY"));
+        }
+        Ok(steps)
+    }
+
+    // a query without compound terms
+    fn core(&mut self, select: &Select) -> Result<Vec<Step>, Box<dyn error::Error>> {
+        let mut steps = match &select.from {
+            Some(from) => self.from(from, select.selection.as_ref())?.0,
+            None => vec![Step::new("SCAN CONSTANT ROW")],
+        };
+
+        let expressions = select
+            .columns
+            .iter()
+            .filter_map(|column| match column {
+                ResultColumn::Expression { expression, .. } => Some(expression),
+                _ => None,
+            })
+            .chain(select.selection.iter())
+            .chain(select.group_by.iter())
+            .chain(select.having.iter())
+            .chain(select.order_by.iter().map(|term| &term.expression));
+        for expression in expressions.clone() {
+            self.subqueries(expression, &mut steps)?;
+        }
+
+        if !select.group_by.is_empty() {
+            steps.push(Step::new("USE TEMP B-TREE FOR GROUP BY"));
+        }
+        if !window::find_calls(expressions).is_empty() {
+            steps.push(Step::new("USE TEMP B-TREE FOR WINDOW"));
+        }
+        if select.distinct {
+            steps.push(Step::new("USE TEMP B-TREE FOR DISTINCT"));
+        }
+        if !select.order_by.is_empty() {
+            steps.push(Step::new("USE TEMP B-TREE FOR ORDER BY"));
+        }
+        Ok(steps)
+    }
+
+    // the steps reading each table and the names of the columns they give
+    fn from(
+        &mut self,
+        from: &From,
+        selection: Option<&Expression>,
+    ) -> Result<(Vec<Step>, Vec<ColumnName>), Box<dyn error::Error>> {
+        if planner::reorderable(from) {
+            return self.planned_joins(from, selection);
+        }
+
+        let mut steps = Vec::new();
+        let mut names = self.reference_names(&from.table)?;
+        let access = match (&from.table.source, selection) {
+            (TableSource::Table(name), Some(selection)) if self.common_table(name).is_none() => {
+                planner::choose_access(self.database, name, &[], &names, Some(selection))?
+            }
+            _ => Access::Scan,
+        };
+        steps.push(self.scan(&from.table, access, "")?);
+
+        for join in &from.joins {
+            let mut inner_names = self.reference_names(&join.table)?;
+            let condition = join_condition(&names, &mut inner_names, &join.constraint)?;
+
+            let access = match &join.table.source {
+                TableSource::Table(name) if self.common_table(name).is_none() => {
+                    planner::choose_access(
+                        self.database,
+                        name,
+                        &names,
+                        &inner_names,
+                        condition.as_ref(),
+                    )?
+                }
+                _ => Access::Scan,
+            };
+            let suffix = if join.operator == JoinOperator::Left {
+                " LEFT-JOIN"
+            } else {
+                ""
+            };
+            steps.push(self.scan(&join.table, access, suffix)?);
+            names.extend(inner_names);
+        }
+        Ok((steps, names))
+    }
+
+    // inner joins in the order the planner picks
+    fn planned_joins(
+        &mut self,
+        from: &From,
+        selection: Option<&Expression>,
+    ) -> Result<(Vec<Step>, Vec<ColumnName>), Box<dyn error::Error>> {
+        let tables: Vec<&TableReference> = std::iter::once(&from.table)
+            .chain(from.joins.iter().map(|join| &join.table))
+            .collect();
+        let mut inputs = Vec::with_capacity(tables.len());
+        for table in &tables {
+            let stored = match &table.source {
+                TableSource::Table(name) if self.common_table(name).is_none() => Some(name.clone()),
+                _ => None,
+            };
+            inputs.push(JoinInput {
+                table: stored,
+                names: self.reference_names(table)?,
+            });
+        }
+
+        let mut steps = Vec::with_capacity(tables.len());
+        for step in planner::plan_joins(self.database, &inputs, from, selection)? {
+            steps.push(self.scan(tables[step.input], step.access, "")?);
+        }
+        let names = inputs.into_iter().flat_map(|input| input.names).collect();
+        Ok((steps, names))
+    }
+
+    fn scan(
+        &mut self,
+        table: &TableReference,
+        access: Access,
+        suffix: &str,
+    ) -> Result<Step, Box<dyn error::Error>> {
+        let alias = match &table.alias {
+            Some(alias) => format!(" AS {}", alias),
+            None => String::new(),
+        };
+        let step = match (&table.source, access) {
+            (TableSource::Subquery(subquery), _) => {
+                let children = self.select(subquery)?;
+                Step::new(format!("SCAN SUBQUERY{}{}", alias, suffix)).with_children(children)
+            }
+            (TableSource::Function { name, .. }, _) => {
+                Step::new(format!("SCAN {}{} VIRTUAL TABLE{}", name, alias, suffix))
+            }
+            (TableSource::Table(name), Access::Scan) => {
+                Step::new(format!("SCAN {}{}{}", name, alias, suffix))
+            }
+            (TableSource::Table(name), Access::IndexSearch { index, key, .. }) => {
+                let columns: Vec<String> = index.columns[..key.len()]
+                    .iter()
+                    .map(|column| format!("{}=?", column))
+                    .collect();
+                Step::new(format!(
+                    "SEARCH {}{} USING INDEX {} ({}){}",
+                    name,
+                    alias,
+                    index.name,
+                    columns.join(" AND "),
+                    suffix
+                ))
+            }
+        };
+        Ok(step)
+    }
+
+    // the queries nested in an expression
+    fn subqueries(
+        &mut self,
+        expression: &Expression,
+        steps: &mut Vec<Step>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let (detail, select) = match expression {
+            Expression::Subquery(select) => ("SCALAR SUBQUERY", select),
+            Expression::InSubquery { select, .. } => ("LIST SUBQUERY", select),
+            Expression::Exists(select) => ("EXISTS SUBQUERY", select),
+            _ => {
+                for child in expression.children() {
+                    self.subqueries(child, steps)?;
+                }
+                return Ok(());
+            }
+        };
+        for child in expression.children() {
+            self.subqueries(child, steps)?;
+        }
+        let children = self.select(select)?;
+        steps.push(Step::new(detail).with_children(children));
+        Ok(())
+    }
+
+    fn common_table(&self, name: &str) -> Option<&Vec<String>> {
+        self.common_tables
+            .iter()
+            .rev()
+            .find(|(table_name, _)| table_name.eq_ignore_ascii_case(name))
+            .map(|(_, columns)| columns)
+    }
+
+    fn reference_names(
+        &mut self,
+        table: &TableReference,
+    ) -> Result<Vec<ColumnName>, Box<dyn error::Error>> {
+        let reference_name = table.reference_name().map(|name| name.to_string());
+        let columns = match &table.source {
+            TableSource::Table(name) => match self.common_table(name) {
+                Some(columns) => columns.clone(),
+                None => {
+                    let reference_name = reference_name.as_deref().unwrap_or(name);
+                    return table_names(self.database, name, reference_name);
+                }
+            },
+            TableSource::Subquery(subquery) => self.result_columns(subquery)?,
+            TableSource::Function { name, .. } => function::table_columns(name)?,
+        };
+        Ok(columns
+            .into_iter()
+            .map(|column| ColumnName::new(reference_name.clone(), column))
+            .collect())
+    }
+
+    // the names of a query's result columns, taken from its first query
+    fn result_columns(&mut self, select: &Select) -> Result<Vec<String>, Box<dyn error::Error>> {
+        let count = self.common_tables.len();
+        let result = self.first_columns(select);
+        self.common_tables.truncate(count);
+        result
+    }
+
+    fn first_columns(&mut self, select: &Select) -> Result<Vec<String>, Box<dyn error::Error>> {
+        if let Some(with) = &select.with {
+            for table in &with.tables {
+                self.common_tables
+                    .push((table.name.clone(), table.columns.clone()));
+                if table.columns.is_empty() {
+                    let columns = self.result_columns(&table.select)?;
+                    self.common_tables.pop();
+                    self.common_tables.push((table.name.clone(), columns));
+                }
+            }
+        }
+        let names = match &select.from {
+            Some(from) => self.from(from, None)?.1,
+            None => Vec::new(),
+        };
+        let (columns, _projection) = expand_columns(&select.columns, &names)?;
+        Ok(columns)
+    }
+}
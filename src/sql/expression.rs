@@ -0,0 +1,1434 @@
+use super::datetime;
+use super::function;
+use super::json;
+use super::statement::{FrameBound, Select, Window};
+use super::window;
+use super::SqlError;
+use backend::database::{
+    find_collation, format_real, parse_numeric_prefix, Collation, Column, ColumnType, Decimal,
+    Functions,
+};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    Negate,
+    Plus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BinaryOperator {
+    And,
+    Or,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Is,
+    IsNot,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+    /// `->`, the JSON of the value at a path
+    Extract,
+    /// `->>`, the SQL value at a path
+    ExtractValue,
+}
+
+impl BinaryOperator {
+    /// Whether the operator compares its operands, which are converted by their affinities.
+    pub fn is_comparison(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperator::Equal
+                | BinaryOperator::NotEqual
+                | BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual
+                | BinaryOperator::Is
+                | BinaryOperator::IsNot
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    Literal(Column),
+    /// a placeholder of a prepared statement, numbered from 1, holding the value bound to it
+    Parameter {
+        number: usize,
+        /// as written, `?`, `?NNN`, `:name`, `@name` or `$name`
+        name: String,
+        value: Column,
+    },
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    IsNull {
+        operand: Box<Expression>,
+        negated: bool,
+    },
+    InList {
+        operand: Box<Expression>,
+        list: Vec<Expression>,
+        negated: bool,
+    },
+    Between {
+        operand: Box<Expression>,
+        low: Box<Expression>,
+        high: Box<Expression>,
+        negated: bool,
+    },
+    Like {
+        operand: Box<Expression>,
+        pattern: Box<Expression>,
+        escape: Option<Box<Expression>>,
+        negated: bool,
+    },
+    Glob {
+        operand: Box<Expression>,
+        pattern: Box<Expression>,
+        negated: bool,
+    },
+    Function {
+        name: String,
+        arguments: Vec<Expression>,
+        distinct: bool,
+        /// `count(*)`
+        wildcard: bool,
+        /// whether the function is an aggregate, built in or registered on the database
+        aggregate: bool,
+    },
+    /// `function(...) OVER (...)`, `call` is the function call
+    WindowFunction {
+        call: Box<Expression>,
+        window: Box<Window>,
+    },
+    /// a scalar subquery, the first column of its first row
+    Subquery(Box<Select>),
+    InSubquery {
+        operand: Box<Expression>,
+        select: Box<Select>,
+        negated: bool,
+    },
+    Exists(Box<Select>),
+    /// `operand COLLATE collation`, the value of the operand compared by the collation
+    Collate {
+        operand: Box<Expression>,
+        collation: String,
+    },
+    /// `CASE operand WHEN value THEN result ... ELSE otherwise END`, the result of the first
+    /// value equal to the operand. Without an operand each WHEN is a condition instead.
+    Case {
+        operand: Option<Box<Expression>>,
+        branches: Vec<(Expression, Expression)>,
+        otherwise: Option<Box<Expression>>,
+    },
+    /// `CAST(operand AS type)`, `type_name` is the type as it was written
+    Cast {
+        operand: Box<Expression>,
+        column_type: ColumnType,
+        type_name: String,
+    },
+}
+
+/// The name a value in a row is known by, `table` is the table name or alias it came from.
+/// Hidden columns, such as the right hand copy of a `USING` column, can only be referred
+/// to by their qualified name and are left out of `*`. Only the columns of tables have an
+/// affinity and a collation, which comparisons with them use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnName {
+    pub table: Option<String>,
+    pub name: String,
+    pub hidden: bool,
+    pub affinity: Option<ColumnType>,
+    /// the collation declared for the column, None when it compares with BINARY
+    pub collation: Option<String>,
+}
+
+impl ColumnName {
+    pub fn new(table: Option<String>, name: String) -> ColumnName {
+        ColumnName {
+            table,
+            name,
+            hidden: false,
+            affinity: None,
+            collation: None,
+        }
+    }
+}
+
+/// Finds the position of a column reference within a row's column names.
+pub fn resolve(
+    names: &[ColumnName],
+    table: &Option<String>,
+    name: &str,
+) -> Result<usize, SqlError> {
+    let mut found = None;
+
+    for (index, column) in names.iter().enumerate() {
+        if !column.name.eq_ignore_ascii_case(name) {
+            continue;
+        }
+        match table {
+            Some(table) => match &column.table {
+                Some(column_table) if column_table.eq_ignore_ascii_case(table) => {}
+                _ => continue,
+            },
+            None if column.hidden => continue,
+            None => {}
+        }
+        if found.is_some() {
+            return Err(SqlError::new(format!("ambiguous column name: {}", name)));
+        }
+        found = Some(index);
+    }
+
+    match (found, table) {
+        (Some(index), _) => Ok(index),
+        (None, Some(table)) => Err(SqlError::new(format!("no such column: {}.{}", table, name))),
+        (None, None) => Err(SqlError::new(format!("no such column: {}", name))),
+    }
+}
+
+/// Runs the queries nested in an expression.
+pub trait Subqueries {
+    /// Runs `select` for a row of the enclosing query, which correlated subqueries refer to.
+    fn query(&self, select: &Select, outer: &Row) -> Result<Vec<Vec<Column>>, SqlError>;
+}
+
+/// What an expression is evaluated within besides its own row: the runner for nested
+/// queries, the collations and functions registered on the database and, inside a subquery,
+/// the row of the enclosing query.
+#[derive(Clone, Copy, Default)]
+pub struct Scope<'a> {
+    pub subqueries: Option<&'a dyn Subqueries>,
+    pub collations: Option<&'a HashMap<String, Collation>>,
+    pub functions: Option<&'a Functions>,
+    pub outer: Option<&'a Row<'a>>,
+}
+
+/// A row being evaluated against, along with the names of its columns.
+/// When evaluating over a group of rows `aggregates` holds the result of each aggregate call.
+pub struct Row<'a> {
+    pub names: &'a [ColumnName],
+    pub values: &'a [Column],
+    pub aggregates: &'a [(Expression, Column)],
+    pub scope: Scope<'a>,
+}
+
+impl<'a> Row<'a> {
+    pub fn new(names: &'a [ColumnName], values: &'a [Column]) -> Row<'a> {
+        Row {
+            names,
+            values,
+            aggregates: &[],
+            scope: Scope::default(),
+        }
+    }
+
+    pub fn with_scope(self, scope: Scope<'a>) -> Row<'a> {
+        Row { scope, ..self }
+    }
+
+    pub fn with_aggregates(
+        names: &'a [ColumnName],
+        values: &'a [Column],
+        aggregates: &'a [(Expression, Column)],
+    ) -> Row<'a> {
+        Row {
+            names,
+            values,
+            aggregates,
+            scope: Scope::default(),
+        }
+    }
+
+    pub fn empty() -> Row<'static> {
+        Row {
+            names: &[],
+            values: &[],
+            aggregates: &[],
+            scope: Scope::default(),
+        }
+    }
+
+    // the value of an aggregate or window function call computed over the row's group
+    // or window, which is only known where such calls are allowed
+    fn computed(&self, call: &Expression, kind: &str, name: &str) -> Result<Column, SqlError> {
+        match self
+            .aggregates
+            .iter()
+            .find(|(expression, _)| expression == call)
+        {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(SqlError::new(format!(
+                "misuse of {} function {}()",
+                kind, name
+            ))),
+        }
+    }
+
+    // columns that aren't in this row are looked for in the rows of enclosing queries
+    fn lookup(&self, table: &Option<String>, name: &str) -> Result<&Column, SqlError> {
+        match resolve(self.names, table, name) {
+            Ok(index) => Ok(&self.values[index]),
+            Err(error) => match self.scope.outer {
+                Some(outer) => outer.lookup(table, name).map_err(|_| error),
+                None => Err(error),
+            },
+        }
+    }
+
+    /// The affinity of the values an expression gives, only a column of a table or a CAST
+    /// has one.
+    pub fn affinity(&self, expression: &Expression) -> Option<ColumnType> {
+        match expression {
+            Expression::Collate { operand, .. } => self.affinity(operand),
+            Expression::Cast { column_type, .. } => Some(column_type.clone()),
+            Expression::Column { table, name } => match resolve(self.names, table, name) {
+                Ok(index) => self.names[index].affinity.clone(),
+                Err(_) => self
+                    .scope
+                    .outer
+                    .and_then(|outer| outer.affinity(expression)),
+            },
+            _ => None,
+        }
+    }
+
+    /// The collation named by a COLLATE or declared on a column, and whether it was named.
+    pub fn collation_name(&self, expression: &Expression) -> Option<(String, bool)> {
+        match expression {
+            Expression::Collate { collation, .. } => Some((collation.clone(), true)),
+            Expression::Column { table, name } => match resolve(self.names, table, name) {
+                Ok(index) => self.names[index]
+                    .collation
+                    .clone()
+                    .map(|collation| (collation, false)),
+                Err(_) => self
+                    .scope
+                    .outer
+                    .and_then(|outer| outer.collation_name(expression)),
+            },
+            _ => None,
+        }
+    }
+
+    /// The collation the values an expression gives are sorted and grouped by, BINARY unless
+    /// the expression is a collated column or names one with COLLATE.
+    pub fn collation(&self, expression: &Expression) -> Result<Collation, SqlError> {
+        match self.collation_name(expression) {
+            Some((name, _)) => self.resolve_collation(&name),
+            None => Ok(Collation::Binary),
+        }
+    }
+
+    /// The collation two expressions are compared by. As in SQLite a COLLATE on the left
+    /// wins over one on the right, which wins over the collation of a column on the left and
+    /// then of one on the right.
+    pub fn comparison_collation(
+        &self,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<Collation, SqlError> {
+        let (left, right) = (self.collation_name(left), self.collation_name(right));
+        let named = match (&left, &right) {
+            (Some((name, true)), _) | (_, Some((name, true))) => Some(name),
+            (Some((name, false)), _) | (_, Some((name, false))) => Some(name),
+            _ => None,
+        };
+        match named {
+            Some(name) => self.resolve_collation(name),
+            None => Ok(Collation::Binary),
+        }
+    }
+
+    /// A built in collation or one registered on the database, by name.
+    pub fn resolve_collation(&self, name: &str) -> Result<Collation, SqlError> {
+        let registered = HashMap::new();
+        find_collation(self.scope.collations.unwrap_or(&registered), name)
+            .ok_or_else(|| SqlError::new(format!("no such collation sequence: {}", name)))
+    }
+
+    // evaluates both sides of a comparison, converted by the affinities of the expressions
+    // and in the form they're ordered in, along with the collation they're compared by
+    fn comparison(
+        &self,
+        left: &Expression,
+        right: &Expression,
+    ) -> Result<(Column, Column, Collation), SqlError> {
+        let (left_type, right_type) = (self.affinity(left), self.affinity(right));
+        let order = match (&left_type, &right_type) {
+            (Some(ColumnType::Interval), _) | (_, Some(ColumnType::Interval)) => {
+                Some(ColumnType::Interval)
+            }
+            _ => None,
+        };
+        let (left_affinity, right_affinity) = comparison_affinity(left_type, right_type);
+        Ok((
+            ordered(convert(left.evaluate(self)?, &left_affinity), &order),
+            ordered(convert(right.evaluate(self)?, &right_affinity), &order),
+            self.comparison_collation(left, right)?,
+        ))
+    }
+
+    // evaluates the operand of IN, along with the affinity its list is converted by and the
+    // collation it's compared by
+    fn in_operand(
+        &self,
+        operand: &Expression,
+    ) -> Result<(Column, Option<ColumnType>, Collation), SqlError> {
+        let (_, list_affinity) = comparison_affinity(self.affinity(operand), None);
+        Ok((
+            operand.evaluate(self)?,
+            list_affinity,
+            self.collation(operand)?,
+        ))
+    }
+
+    fn query(&self, select: &Select) -> Result<Vec<Vec<Column>>, SqlError> {
+        match self.scope.subqueries {
+            Some(subqueries) => subqueries.query(select, self),
+            None => Err(SqlError::new("subqueries are not supported here")),
+        }
+    }
+}
+
+impl Expression {
+    pub fn evaluate(&self, row: &Row) -> Result<Column, SqlError> {
+        match self {
+            Expression::Literal(value) => Ok(value.clone()),
+            Expression::Parameter { value, .. } => Ok(value.clone()),
+            Expression::Column { table, name } => row.lookup(table, name).cloned(),
+            Expression::Unary { operator, operand } => {
+                let value = operand.evaluate(row)?;
+                Ok(evaluate_unary(operator, value))
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => evaluate_binary(operator, left, right, row),
+            Expression::IsNull { operand, negated } => {
+                let is_null = operand.evaluate(row)? == Column::Null();
+                Ok(from_bool(is_null != *negated))
+            }
+            Expression::InList {
+                operand,
+                list,
+                negated,
+            } => {
+                let (value, affinity, collation) = row.in_operand(operand)?;
+                let mut values = Vec::with_capacity(list.len());
+                for item in list {
+                    values.push(convert(item.evaluate(row)?, &affinity));
+                }
+                Ok(negate_if(in_values(&value, &values, &collation), *negated))
+            }
+            Expression::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => {
+                let (value, low, collation) = row.comparison(operand, low)?;
+                let low = compare_values(&value, &low, &collation, |o| o != Ordering::Less);
+                let (value, high, collation) = row.comparison(operand, high)?;
+                let high = compare_values(&value, &high, &collation, |o| o != Ordering::Greater);
+                Ok(negate_if(and(low, high), *negated))
+            }
+            Expression::Like {
+                operand,
+                pattern,
+                escape,
+                negated,
+            } => {
+                let value = operand.evaluate(row)?;
+                let pattern = pattern.evaluate(row)?;
+                let escape = match escape {
+                    Some(escape) => match escape.evaluate(row)? {
+                        Column::Null() => return Ok(Column::Null()),
+                        escape => {
+                            let escape = to_text(&escape);
+                            let mut chars = escape.chars();
+                            match (chars.next(), chars.next()) {
+                                (Some(c), None) => Some(c),
+                                _ => {
+                                    return Err(SqlError::new(
+                                        "ESCAPE expression must be a single character",
+                                    ))
+                                }
+                            }
+                        }
+                    },
+                    None => None,
+                };
+                if value == Column::Null() || pattern == Column::Null() {
+                    return Ok(Column::Null());
+                }
+                let matched = like(&to_text(&pattern), &to_text(&value), escape);
+                Ok(from_bool(matched != *negated))
+            }
+            Expression::Glob {
+                operand,
+                pattern,
+                negated,
+            } => {
+                let value = operand.evaluate(row)?;
+                let pattern = pattern.evaluate(row)?;
+                if value == Column::Null() || pattern == Column::Null() {
+                    return Ok(Column::Null());
+                }
+                let matched = glob(&to_text(&pattern), &to_text(&value));
+                Ok(from_bool(matched != *negated))
+            }
+            Expression::Function {
+                name,
+                aggregate: true,
+                ..
+            } => row.computed(self, "aggregate", name),
+            Expression::Function { name, .. } if window::is_window_function(name) => Err(
+                SqlError::new(format!("misuse of window function {}()", name)),
+            ),
+            Expression::Function {
+                name, arguments, ..
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(row))
+                    .collect::<Result<Vec<_>, _>>()?;
+                function::call(name, &arguments, row.scope.functions)
+            }
+            Expression::WindowFunction { call, .. } => match call.as_ref() {
+                Expression::Function { name, .. } => row.computed(self, "window", name),
+                _ => Err(SqlError::new("window calls must be function calls")),
+            },
+            Expression::Subquery(select) => {
+                let rows = single_column(row.query(select)?)?;
+                Ok(rows.into_iter().next().unwrap_or(Column::Null()))
+            }
+            Expression::InSubquery {
+                operand,
+                select,
+                negated,
+            } => {
+                let (value, affinity, collation) = row.in_operand(operand)?;
+                let values = single_column(row.query(select)?)?
+                    .into_iter()
+                    .map(|item| convert(item, &affinity))
+                    .collect::<Vec<_>>();
+                Ok(negate_if(in_values(&value, &values, &collation), *negated))
+            }
+            Expression::Exists(select) => Ok(from_bool(!row.query(select)?.is_empty())),
+            Expression::Collate { operand, collation } => {
+                row.resolve_collation(collation)?;
+                operand.evaluate(row)
+            }
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                for (when, then) in branches {
+                    let matched = match operand {
+                        Some(operand) => {
+                            let (value, when, collation) = row.comparison(operand, when)?;
+                            compare_values(&value, &when, &collation, |o| o == Ordering::Equal)
+                                == Some(true)
+                        }
+                        None => when.is_true(row)?,
+                    };
+                    if matched {
+                        return then.evaluate(row);
+                    }
+                }
+                match otherwise {
+                    Some(otherwise) => otherwise.evaluate(row),
+                    None => Ok(Column::Null()),
+                }
+            }
+            Expression::Cast {
+                operand,
+                column_type,
+                ..
+            } => Ok(cast(operand.evaluate(row)?, column_type)),
+        }
+    }
+
+    /// The expressions directly nested in this one.
+    pub fn children(&self) -> Vec<&Expression> {
+        match self {
+            Expression::Literal(_)
+            | Expression::Parameter { .. }
+            | Expression::Column { .. }
+            | Expression::Subquery(_)
+            | Expression::Exists(_) => Vec::new(),
+            Expression::Unary { operand, .. }
+            | Expression::IsNull { operand, .. }
+            | Expression::InSubquery { operand, .. }
+            | Expression::Collate { operand, .. }
+            | Expression::Cast { operand, .. } => vec![operand],
+            Expression::Binary { left, right, .. } => vec![left, right],
+            Expression::InList { operand, list, .. } => {
+                let mut children = vec![operand.as_ref()];
+                children.extend(list.iter());
+                children
+            }
+            Expression::Between {
+                operand, low, high, ..
+            } => vec![operand, low, high],
+            Expression::Like {
+                operand,
+                pattern,
+                escape,
+                ..
+            } => {
+                let mut children = vec![operand.as_ref(), pattern.as_ref()];
+                children.extend(escape.iter().map(|escape| escape.as_ref()));
+                children
+            }
+            Expression::Glob {
+                operand, pattern, ..
+            } => vec![operand, pattern],
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let mut children: Vec<&Expression> = operand.iter().map(|o| o.as_ref()).collect();
+                for (when, then) in branches {
+                    children.push(when);
+                    children.push(then);
+                }
+                children.extend(otherwise.iter().map(|otherwise| otherwise.as_ref()));
+                children
+            }
+            Expression::Function { arguments, .. } => arguments.iter().collect(),
+            // the call itself is left out so that an aggregate used as a window function
+            // isn't mistaken for one that groups rows
+            Expression::WindowFunction { call, window } => {
+                let mut children = call.children();
+                children.extend(window.partition_by.iter());
+                children.extend(window.order_by.iter().map(|term| &term.expression));
+                children
+            }
+        }
+    }
+
+    /// The expressions nested in this one that can be changed in place, which unlike
+    /// `children` includes the call and frame bounds of a window function.
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Expression::Literal(_)
+            | Expression::Parameter { .. }
+            | Expression::Column { .. }
+            | Expression::Subquery(_)
+            | Expression::Exists(_) => Vec::new(),
+            Expression::Unary { operand, .. }
+            | Expression::IsNull { operand, .. }
+            | Expression::InSubquery { operand, .. }
+            | Expression::Collate { operand, .. }
+            | Expression::Cast { operand, .. } => vec![operand.as_mut()],
+            Expression::Binary { left, right, .. } => vec![left.as_mut(), right.as_mut()],
+            Expression::InList { operand, list, .. } => {
+                let mut children = vec![operand.as_mut()];
+                children.extend(list.iter_mut());
+                children
+            }
+            Expression::Between {
+                operand, low, high, ..
+            } => vec![operand.as_mut(), low.as_mut(), high.as_mut()],
+            Expression::Like {
+                operand,
+                pattern,
+                escape,
+                ..
+            } => {
+                let mut children = vec![operand.as_mut(), pattern.as_mut()];
+                children.extend(escape.iter_mut().map(|escape| escape.as_mut()));
+                children
+            }
+            Expression::Glob {
+                operand, pattern, ..
+            } => vec![operand.as_mut(), pattern.as_mut()],
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                let mut children: Vec<&mut Expression> =
+                    operand.iter_mut().map(|o| o.as_mut()).collect();
+                for (when, then) in branches {
+                    children.push(when);
+                    children.push(then);
+                }
+                children.extend(otherwise.iter_mut().map(|otherwise| otherwise.as_mut()));
+                children
+            }
+            Expression::Function { arguments, .. } => arguments.iter_mut().collect(),
+            Expression::WindowFunction { call, window } => {
+                let mut children = vec![call.as_mut()];
+                children.extend(window.partition_by.iter_mut());
+                children.extend(window.order_by.iter_mut().map(|term| &mut term.expression));
+                if let Some(frame) = &mut window.frame {
+                    for bound in [&mut frame.start, &mut frame.end] {
+                        if let FrameBound::Preceding(offset) | FrameBound::Following(offset) = bound
+                        {
+                            children.push(offset);
+                        }
+                    }
+                }
+                children
+            }
+        }
+    }
+
+    /// Calls `visit` on this expression and then on every expression nested in it, those of
+    /// subqueries included.
+    pub fn visit_mut<F>(&mut self, visit: &mut F)
+    where
+        F: FnMut(&mut Expression),
+    {
+        visit(self);
+        match self {
+            Expression::Subquery(select)
+            | Expression::Exists(select)
+            | Expression::InSubquery { select, .. } => select.visit_expressions_mut(visit),
+            _ => {}
+        }
+        for child in self.children_mut() {
+            child.visit_mut(visit);
+        }
+    }
+
+    /// Whether this expression calls an aggregate function outside of a nested query.
+    pub fn contains_aggregate(&self) -> bool {
+        match self {
+            Expression::Function {
+                aggregate: true, ..
+            } => true,
+            _ => self
+                .children()
+                .iter()
+                .any(|child| child.contains_aggregate()),
+        }
+    }
+
+    /// Evaluates the expression as a condition, NULL is treated as false.
+    pub fn is_true(&self, row: &Row) -> Result<bool, SqlError> {
+        Ok(to_bool(&self.evaluate(row)?) == Some(true))
+    }
+}
+
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self {
+            BinaryOperator::And => "AND",
+            BinaryOperator::Or => "OR",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Is => "IS",
+            BinaryOperator::IsNot => "IS NOT",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Concat => "||",
+            BinaryOperator::Extract => "->",
+            BinaryOperator::ExtractValue => "->>",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+/// Writes the expression back out as SQL, used to name result columns that have no alias.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Literal(value) => write!(f, "{}", quote(value)),
+            Expression::Parameter { name, .. } => write!(f, "{}", name),
+            Expression::Column {
+                table: Some(table),
+                name,
+            } => write!(f, "{}.{}", table, name),
+            Expression::Column { table: None, name } => write!(f, "{}", name),
+            Expression::Unary { operator, operand } => match operator {
+                UnaryOperator::Not => write!(f, "NOT {}", Nested(operand)),
+                UnaryOperator::Negate => write!(f, "-{}", Nested(operand)),
+                UnaryOperator::Plus => write!(f, "+{}", Nested(operand)),
+            },
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => write!(f, "{} {} {}", Nested(left), operator, Nested(right)),
+            Expression::IsNull { operand, negated } => {
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{} IS{} NULL", Nested(operand), not)
+            }
+            Expression::InList {
+                operand,
+                list,
+                negated,
+            } => {
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{}{} IN (", Nested(operand), not)?;
+                write_list(f, list)?;
+                write!(f, ")")
+            }
+            Expression::Between {
+                operand,
+                low,
+                high,
+                negated,
+            } => {
+                let not = if *negated { " NOT" } else { "" };
+                let (operand, low, high) = (Nested(operand), Nested(low), Nested(high));
+                write!(f, "{}{} BETWEEN {} AND {}", operand, not, low, high)
+            }
+            Expression::Like {
+                operand,
+                pattern,
+                escape,
+                negated,
+            } => {
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{}{} LIKE {}", Nested(operand), not, Nested(pattern))?;
+                match escape {
+                    Some(escape) => write!(f, " ESCAPE {}", Nested(escape)),
+                    None => Ok(()),
+                }
+            }
+            Expression::Glob {
+                operand,
+                pattern,
+                negated,
+            } => {
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{}{} GLOB {}", Nested(operand), not, Nested(pattern))
+            }
+            Expression::Function {
+                name,
+                arguments,
+                distinct,
+                wildcard,
+                ..
+            } => {
+                write!(f, "{}(", name)?;
+                if *distinct {
+                    write!(f, "DISTINCT ")?;
+                }
+                if *wildcard {
+                    write!(f, "*")?;
+                }
+                write_list(f, arguments)?;
+                write!(f, ")")
+            }
+            Expression::WindowFunction { call, window } => {
+                write!(f, "{} OVER ({})", call, window)
+            }
+            Expression::Subquery(select) => write!(f, "({})", select),
+            Expression::InSubquery {
+                operand,
+                select,
+                negated,
+            } => {
+                let not = if *negated { " NOT" } else { "" };
+                write!(f, "{}{} IN ({})", Nested(operand), not, select)
+            }
+            Expression::Exists(select) => write!(f, "EXISTS ({})", select),
+            Expression::Collate { operand, collation } => {
+                write!(f, "{} COLLATE {}", Nested(operand), collation)
+            }
+            Expression::Case {
+                operand,
+                branches,
+                otherwise,
+            } => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", Nested(operand))?;
+                }
+                for (when, then) in branches {
+                    write!(f, " WHEN {} THEN {}", when, then)?;
+                }
+                if let Some(otherwise) = otherwise {
+                    write!(f, " ELSE {}", otherwise)?;
+                }
+                write!(f, " END")
+            }
+            Expression::Cast {
+                operand, type_name, ..
+            } => write!(f, "CAST({} AS {})", operand, type_name),
+        }
+    }
+}
+
+// wraps compound sub-expressions in parentheses
+struct Nested<'a>(&'a Expression);
+
+impl<'a> fmt::Display for Nested<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Expression::Literal(_)
+            | Expression::Parameter { .. }
+            | Expression::Column { .. }
+            | Expression::Function { .. }
+            | Expression::WindowFunction { .. }
+            | Expression::Subquery(_)
+            | Expression::Exists(_)
+            | Expression::Case { .. }
+            | Expression::Cast { .. } => write!(f, "{}", self.0),
+            _ => write!(f, "({})", self.0),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, list: &[Expression]) -> fmt::Result {
+    for (index, item) in list.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+// the values of a subquery used as an expression, which must have exactly one column
+fn single_column(rows: Vec<Vec<Column>>) -> Result<Vec<Column>, SqlError> {
+    let mut values = Vec::with_capacity(rows.len());
+    for row in rows {
+        if row.len() != 1 {
+            return Err(SqlError::new(format!(
+                "sub-select returns {} columns - expected 1",
+                row.len()
+            )));
+        }
+        values.extend(row);
+    }
+    Ok(values)
+}
+
+/// A value as an SQL literal, which reads back as the same value.
+pub fn quote(value: &Column) -> String {
+    match value {
+        Column::Null() => "NULL".to_string(),
+        Column::Integer(int) => int.to_string(),
+        Column::Real(real) => quote_real(*real),
+        Column::Decimal(decimal) => decimal.to_string(),
+        Column::Text(text) => format!("'{}'", text.replace('\'', "''")),
+        Column::Blob(blob) => {
+            let hex: String = blob.iter().map(|byte| format!("{:02X}", byte)).collect();
+            format!("X'{}'", hex)
+        }
+    }
+}
+
+// reals that would be written like integers are written with an exponent, and infinities as
+// a real too large to read
+fn quote_real(real: f64) -> String {
+    let text = format_real(real);
+    if real.is_nan() {
+        "NULL".to_string()
+    } else if real.is_infinite() {
+        format!("{}9e999", if real < 0.0 { "-" } else { "" })
+    } else if text.contains(['.', 'e']) {
+        text
+    } else {
+        format!("{:e}", real)
+    }
+}
+
+pub fn evaluate_unary(operator: &UnaryOperator, value: Column) -> Column {
+    match operator {
+        UnaryOperator::Not => match to_bool(&value) {
+            Some(value) => from_bool(!value),
+            None => Column::Null(),
+        },
+        UnaryOperator::Negate => match to_numeric(&value) {
+            Column::Integer(int) => match int.checked_neg() {
+                Some(int) => Column::Integer(int),
+                None => Column::Real(-(int as f64)),
+            },
+            Column::Real(real) => Column::Real(-real),
+            Column::Decimal(decimal) => match decimal.negate() {
+                Some(decimal) => Column::Decimal(decimal),
+                None => Column::Real(-decimal.to_real()),
+            },
+            _ => Column::Null(),
+        },
+        UnaryOperator::Plus => value,
+    }
+}
+
+fn evaluate_binary(
+    operator: &BinaryOperator,
+    left: &Expression,
+    right: &Expression,
+    row: &Row,
+) -> Result<Column, SqlError> {
+    // AND and OR only evaluate the right hand side when it can change the result
+    match operator {
+        BinaryOperator::And => {
+            let left = to_bool(&left.evaluate(row)?);
+            if left == Some(false) {
+                return Ok(from_bool(false));
+            }
+            let right = to_bool(&right.evaluate(row)?);
+            return Ok(from_option(and(left, right)));
+        }
+        BinaryOperator::Or => {
+            let left = to_bool(&left.evaluate(row)?);
+            if left == Some(true) {
+                return Ok(from_bool(true));
+            }
+            let right = to_bool(&right.evaluate(row)?);
+            return Ok(from_option(or(left, right)));
+        }
+        _ if operator.is_comparison() => {
+            let (left, right, collation) = row.comparison(left, right)?;
+            return Ok(apply_comparison(operator, &left, &right, &collation));
+        }
+        // reading malformed JSON is an error
+        BinaryOperator::Extract | BinaryOperator::ExtractValue => {
+            let as_value = *operator == BinaryOperator::ExtractValue;
+            return json::arrow(&left.evaluate(row)?, &right.evaluate(row)?, as_value);
+        }
+        _ => {}
+    }
+
+    let temporal =
+        |affinity: &Option<ColumnType>| affinity.as_ref().is_some_and(ColumnType::is_temporal);
+    let (left_affinity, right_affinity) = (row.affinity(left), row.affinity(right));
+    let left = left.evaluate(row)?;
+    let right = right.evaluate(row)?;
+    if *operator != BinaryOperator::Concat
+        && (temporal(&left_affinity) || temporal(&right_affinity))
+    {
+        return datetime::arithmetic(operator, (&left, left_affinity), (&right, right_affinity));
+    }
+    Ok(apply_binary(operator, &left, &right))
+}
+
+/// The affinities the two sides of a comparison are converted by, following SQLite. When
+/// one side has an INTEGER, REAL or NUMERIC affinity and the other doesn't, the other side
+/// becomes a number if it looks like one. When one side has a TEXT affinity and the other
+/// has none, numbers on the other side become text. A date, time or interval column compared
+/// with a value without an affinity gives the value its canonical form. Otherwise neither side
+/// is converted.
+pub fn comparison_affinity(
+    left: Option<ColumnType>,
+    right: Option<ColumnType>,
+) -> (Option<ColumnType>, Option<ColumnType>) {
+    let numeric = |affinity: &Option<ColumnType>| {
+        matches!(
+            affinity,
+            Some(ColumnType::Integer)
+                | Some(ColumnType::Real)
+                | Some(ColumnType::Numeric)
+                | Some(ColumnType::Decimal { .. })
+        )
+    };
+    if numeric(&left) && !numeric(&right) {
+        (None, Some(ColumnType::Numeric))
+    } else if numeric(&right) && !numeric(&left) {
+        (Some(ColumnType::Numeric), None)
+    } else if left == Some(ColumnType::Text) && right.is_none() {
+        (None, Some(ColumnType::Text))
+    } else if right == Some(ColumnType::Text) && left.is_none() {
+        (Some(ColumnType::Text), None)
+    } else if left.as_ref().is_some_and(ColumnType::is_temporal) && right.is_none() {
+        (None, left)
+    } else if right.as_ref().is_some_and(ColumnType::is_temporal) && left.is_none() {
+        (right, None)
+    } else {
+        (None, None)
+    }
+}
+
+/// Converts a value for `CAST`. Unlike the affinity of a column, which keeps values it can't
+/// convert as they are, casting to INTEGER, REAL or NUMERIC always gives a number, read from
+/// the longest numeric prefix of text and blobs, and a real cast to INTEGER loses its
+/// fraction. Casting to TEXT gives text and to BLOB the bytes of the value's text. The other
+/// types convert as their columns do. NULL stays NULL.
+pub fn cast(value: Column, column_type: &ColumnType) -> Column {
+    if value == Column::Null() {
+        return value;
+    }
+    match column_type {
+        ColumnType::Integer => match to_numeric(&value) {
+            // `as` saturates, and gives 0 for NaN
+            Column::Real(real) => Column::Integer(real as i128),
+            Column::Decimal(decimal) => {
+                Column::Integer(decimal.value / 10i128.pow(decimal.scale as u32))
+            }
+            number => number,
+        },
+        ColumnType::Real => match to_numeric(&value) {
+            Column::Integer(int) => Column::Real(int as f64),
+            Column::Decimal(decimal) => Column::Real(decimal.to_real()),
+            number => number,
+        },
+        ColumnType::Numeric => ColumnType::Numeric.apply(to_numeric(&value)),
+        ColumnType::Text => Column::Text(to_text(&value)),
+        ColumnType::Blob => match value {
+            Column::Blob(blob) => Column::Blob(blob),
+            value => Column::Blob(to_text(&value).into_bytes()),
+        },
+        column_type => column_type.apply(value),
+    }
+}
+
+/// A value converted by the affinity a comparison gives it, if any.
+pub fn convert(value: Column, affinity: &Option<ColumnType>) -> Column {
+    match affinity {
+        Some(affinity) => affinity.apply(value),
+        None => value,
+    }
+}
+
+/// A value in the form it's ordered in by its affinity. Intervals order by their length
+/// rather than their text, so they're ordered as the integer they're stored as.
+pub fn ordered(value: Column, affinity: &Option<ColumnType>) -> Column {
+    match affinity {
+        Some(ColumnType::Interval) => ColumnType::Interval.compact(value),
+        _ => value,
+    }
+}
+
+/// Applies a binary operator to two values, both sides of AND and OR have already been
+/// evaluated.
+pub fn apply_binary(operator: &BinaryOperator, left: &Column, right: &Column) -> Column {
+    match operator {
+        BinaryOperator::And => from_option(and(to_bool(left), to_bool(right))),
+        BinaryOperator::Or => from_option(or(to_bool(left), to_bool(right))),
+        _ if operator.is_comparison() => {
+            apply_comparison(operator, left, right, &Collation::Binary)
+        }
+        BinaryOperator::Concat => match (left, right) {
+            (Column::Null(), _) | (_, Column::Null()) => Column::Null(),
+            _ => Column::Text(to_text(left) + &to_text(right)),
+        },
+        BinaryOperator::Extract | BinaryOperator::ExtractValue => {
+            unreachable!("JSON operators can fail, evaluate_binary applies them")
+        }
+        _ => arithmetic(operator, left, right),
+    }
+}
+
+/// Applies a comparison operator, text on both sides is compared by `collation`.
+pub fn apply_comparison(
+    operator: &BinaryOperator,
+    left: &Column,
+    right: &Column,
+    collation: &Collation,
+) -> Column {
+    let test =
+        |test: fn(Ordering) -> bool| from_option(compare_values(left, right, collation, test));
+    match operator {
+        BinaryOperator::Equal => test(|o| o == Ordering::Equal),
+        BinaryOperator::NotEqual => test(|o| o != Ordering::Equal),
+        BinaryOperator::Less => test(|o| o == Ordering::Less),
+        BinaryOperator::LessEqual => test(|o| o != Ordering::Greater),
+        BinaryOperator::Greater => test(|o| o == Ordering::Greater),
+        BinaryOperator::GreaterEqual => test(|o| o != Ordering::Less),
+        BinaryOperator::Is => from_bool(collation.compare(left, right) == Ordering::Equal),
+        BinaryOperator::IsNot => from_bool(collation.compare(left, right) != Ordering::Equal),
+        _ => unreachable!("not a comparison operator"),
+    }
+}
+
+fn arithmetic(operator: &BinaryOperator, left: &Column, right: &Column) -> Column {
+    match (to_numeric(left), to_numeric(right)) {
+        (Column::Integer(left), Column::Integer(right)) => {
+            let result = match operator {
+                BinaryOperator::Add => left.checked_add(right),
+                BinaryOperator::Subtract => left.checked_sub(right),
+                BinaryOperator::Multiply => left.checked_mul(right),
+                BinaryOperator::Divide if right == 0 => return Column::Null(),
+                BinaryOperator::Divide => left.checked_div(right),
+                BinaryOperator::Modulo if right == 0 => return Column::Null(),
+                // only the smallest integer % -1 overflows, and its remainder is 0
+                BinaryOperator::Modulo => Some(left.wrapping_rem(right)),
+                _ => unreachable!("not an arithmetic operator"),
+            };
+            match result {
+                Some(int) => Column::Integer(int),
+                // overflowing integer arithmetic falls back to floating point
+                None => real_arithmetic(operator, left as f64, right as f64),
+            }
+        }
+        (Column::Integer(left), Column::Real(right)) => {
+            real_arithmetic(operator, left as f64, right)
+        }
+        (Column::Real(left), Column::Integer(right)) => {
+            real_arithmetic(operator, left, right as f64)
+        }
+        (Column::Real(left), Column::Real(right)) => real_arithmetic(operator, left, right),
+        (Column::Null(), _) | (_, Column::Null()) => Column::Null(),
+        (left, right) => decimal_arithmetic(operator, &left, &right),
+    }
+}
+
+// Decimals mixed with integers are exact, a real is read as the shortest decimal that gives it
+// back. Arithmetic that overflows falls back to floating point.
+fn decimal_arithmetic(operator: &BinaryOperator, left: &Column, right: &Column) -> Column {
+    let (left, right) = match (decimal_operand(left), decimal_operand(right)) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return real_arithmetic(operator, real_operand(left), real_operand(right)),
+    };
+    let result = match operator {
+        BinaryOperator::Add => left.checked_add(&right),
+        BinaryOperator::Subtract => left.checked_sub(&right),
+        BinaryOperator::Multiply => left.checked_mul(&right),
+        _ if right.value == 0 => return Column::Null(),
+        BinaryOperator::Divide => left.checked_div(&right),
+        BinaryOperator::Modulo => left.checked_rem(&right),
+        _ => unreachable!("not an arithmetic operator"),
+    };
+    match result {
+        Some(decimal) => Column::Decimal(decimal),
+        None => real_arithmetic(operator, left.to_real(), right.to_real()),
+    }
+}
+
+fn real_operand(value: &Column) -> f64 {
+    match value {
+        Column::Integer(int) => *int as f64,
+        Column::Real(real) => *real,
+        Column::Decimal(decimal) => decimal.to_real(),
+        _ => 0.0,
+    }
+}
+
+fn decimal_operand(value: &Column) -> Option<Decimal> {
+    match value {
+        Column::Integer(int) => Some(Decimal::from_integer(*int)),
+        Column::Real(real) => Decimal::from_real(*real),
+        Column::Decimal(decimal) => Some(*decimal),
+        _ => None,
+    }
+}
+
+fn real_arithmetic(operator: &BinaryOperator, left: f64, right: f64) -> Column {
+    match operator {
+        BinaryOperator::Add => Column::Real(left + right),
+        BinaryOperator::Subtract => Column::Real(left - right),
+        BinaryOperator::Multiply => Column::Real(left * right),
+        BinaryOperator::Divide if right == 0.0 => Column::Null(),
+        BinaryOperator::Divide => Column::Real(left / right),
+        // like sqlite, the remainder of reals is taken on their integer parts
+        BinaryOperator::Modulo => {
+            let (left, right) = (left as i128, right as i128);
+            if right == 0 {
+                Column::Null()
+            } else {
+                Column::Real(left.wrapping_rem(right) as f64)
+            }
+        }
+        _ => unreachable!("not an arithmetic operator"),
+    }
+}
+
+fn in_values(value: &Column, values: &[Column], collation: &Collation) -> Option<bool> {
+    if *value == Column::Null() {
+        return if values.is_empty() { Some(false) } else { None };
+    }
+
+    let mut result = Some(false);
+    for item in values {
+        match compare_values(value, item, collation, |o| o == Ordering::Equal) {
+            Some(true) => return Some(true),
+            None => result = None,
+            Some(false) => {}
+        }
+    }
+    result
+}
+
+/// Compares two values, NULL compares equal to NULL and less than everything else.
+/// Numbers sort before text, which sorts before blobs.
+pub fn compare(left: &Column, right: &Column) -> Ordering {
+    left.compare(right)
+}
+
+// SQL comparison, the result is unknown if either side is NULL
+fn compare_values<F>(left: &Column, right: &Column, collation: &Collation, test: F) -> Option<bool>
+where
+    F: Fn(Ordering) -> bool,
+{
+    match (left, right) {
+        (Column::Null(), _) | (_, Column::Null()) => None,
+        _ => Some(test(collation.compare(left, right))),
+    }
+}
+
+fn and(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(false), _) | (_, Some(false)) => Some(false),
+        (Some(true), Some(true)) => Some(true),
+        _ => None,
+    }
+}
+
+fn or(left: Option<bool>, right: Option<bool>) -> Option<bool> {
+    match (left, right) {
+        (Some(true), _) | (_, Some(true)) => Some(true),
+        (Some(false), Some(false)) => Some(false),
+        _ => None,
+    }
+}
+
+fn negate_if(value: Option<bool>, negated: bool) -> Column {
+    from_option(value.map(|value| value != negated))
+}
+
+pub fn from_bool(value: bool) -> Column {
+    Column::Integer(value as i128)
+}
+
+fn from_option(value: Option<bool>) -> Column {
+    match value {
+        Some(value) => from_bool(value),
+        None => Column::Null(),
+    }
+}
+
+/// The truth value of a column, NULL is unknown.
+pub fn to_bool(value: &Column) -> Option<bool> {
+    match to_numeric(value) {
+        Column::Integer(int) => Some(int != 0),
+        Column::Real(real) => Some(real != 0.0),
+        Column::Decimal(decimal) => Some(decimal.value != 0),
+        _ => None,
+    }
+}
+
+/// Converts text and blobs to numbers the way sqlite does, using the longest numeric prefix.
+pub fn to_numeric(value: &Column) -> Column {
+    match value {
+        Column::Null() => Column::Null(),
+        Column::Integer(_) | Column::Real(_) | Column::Decimal(_) => value.clone(),
+        Column::Text(text) => parse_numeric_prefix(text).0,
+        Column::Blob(blob) => parse_numeric_prefix(&String::from_utf8_lossy(blob)).0,
+    }
+}
+
+/// The text representation of a value, as used by concatenation and LIKE.
+pub fn to_text(value: &Column) -> String {
+    match value {
+        Column::Null() => String::new(),
+        Column::Integer(int) => int.to_string(),
+        Column::Real(real) => format_real(*real),
+        Column::Decimal(decimal) => decimal.to_string(),
+        Column::Text(text) => text.clone(),
+        Column::Blob(blob) => String::from_utf8_lossy(blob).into_owned(),
+    }
+}
+
+// case insensitive (for ascii) matching of `%` and `_` wildcards
+fn like(pattern: &str, text: &str, escape: Option<char>) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    like_from(&pattern, &text, escape)
+}
+
+fn like_from(pattern: &[char], text: &[char], escape: Option<char>) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some(&c) if Some(c) == escape => match (pattern.get(1), text.first()) {
+            (Some(p), Some(t)) if p.eq_ignore_ascii_case(t) => {
+                like_from(&pattern[2..], &text[1..], escape)
+            }
+            _ => false,
+        },
+        Some('%') => (0..=text.len()).any(|skip| like_from(&pattern[1..], &text[skip..], escape)),
+        Some('_') => !text.is_empty() && like_from(&pattern[1..], &text[1..], escape),
+        Some(p) => match text.first() {
+            Some(t) if p.eq_ignore_ascii_case(t) => like_from(&pattern[1..], &text[1..], escape),
+            _ => false,
+        },
+    }
+}
+
+// case sensitive matching of `*`, `?` and `[...]` character classes
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_from(&pattern, &text)
+}
+
+fn glob_from(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|skip| glob_from(&pattern[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && glob_from(&pattern[1..], &text[1..]),
+        Some('[') => {
+            let c = match text.first() {
+                Some(c) => *c,
+                None => return false,
+            };
+            match match_class(&pattern[1..], c) {
+                Some((true, rest)) => glob_from(rest, &text[1..]),
+                _ => false,
+            }
+        }
+        Some(p) => text.first() == Some(p) && glob_from(&pattern[1..], &text[1..]),
+    }
+}
+
+// matches `c` against a character class, returning the pattern after the closing `]`
+fn match_class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let mut index = 0;
+    let invert = pattern.first() == Some(&'^');
+    if invert {
+        index += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while index < pattern.len() {
+        let start = pattern[index];
+        if start == ']' && !first {
+            return Some((matched != invert, &pattern[index + 1..]));
+        }
+        first = false;
+
+        if pattern.get(index + 1) == Some(&'-') && pattern.get(index + 2).is_some_and(|&e| e != ']')
+        {
+            let end = pattern[index + 2];
+            matched = matched || (start <= c && c <= end);
+            index += 3;
+        } else {
+            matched = matched || start == c;
+            index += 1;
+        }
+    }
+    // unterminated character class
+    None
+}
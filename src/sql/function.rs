@@ -0,0 +1,184 @@
+use super::datetime;
+use super::expression::Expression;
+use super::json;
+use super::math;
+use super::scalar;
+use super::statement::Statement;
+use super::SqlError;
+use backend::database::{find_function, Column, Functions, UserFunction};
+
+type Function = fn(&[Column]) -> Result<Column, SqlError>;
+
+// the functions with the least and most arguments they take
+fn find(name: &str) -> Option<(Function, usize, usize)> {
+    let function: (Function, usize, usize) = match name {
+        "date" => (datetime::date, 0, usize::MAX),
+        "time" => (datetime::time, 0, usize::MAX),
+        "datetime" => (datetime::datetime, 0, usize::MAX),
+        "julianday" => (datetime::julianday, 0, usize::MAX),
+        "unixepoch" => (datetime::unixepoch, 0, usize::MAX),
+        "strftime" => (datetime::strftime, 1, usize::MAX),
+        "timediff" => (datetime::timediff, 2, 2),
+        "abs" => (scalar::abs, 1, 1),
+        "round" => (scalar::round, 1, 2),
+        "length" => (scalar::length, 1, 1),
+        "lower" => (scalar::lower, 1, 1),
+        "upper" => (scalar::upper, 1, 1),
+        "substr" | "substring" => (scalar::substr, 2, 3),
+        "trim" => (scalar::trim, 1, 2),
+        "ltrim" => (scalar::ltrim, 1, 2),
+        "rtrim" => (scalar::rtrim, 1, 2),
+        "replace" => (scalar::replace, 3, 3),
+        "instr" => (scalar::instr, 2, 2),
+        "coalesce" => (scalar::coalesce, 2, usize::MAX),
+        "ifnull" => (scalar::coalesce, 2, 2),
+        "nullif" => (scalar::nullif, 2, 2),
+        "typeof" => (scalar::type_of, 1, 1),
+        "hex" => (scalar::hex, 1, 1),
+        "quote" => (scalar::quote, 1, 1),
+        "random" => (scalar::random, 0, 0),
+        "printf" | "format" => (scalar::printf, 1, usize::MAX),
+        // with one argument these are the aggregates
+        "min" => (scalar::min, 2, usize::MAX),
+        "max" => (scalar::max, 2, usize::MAX),
+        "sqrt" => (|arguments| math::unary(arguments, f64::sqrt), 1, 1),
+        "exp" => (|arguments| math::unary(arguments, f64::exp), 1, 1),
+        "pow" | "power" => (|arguments| math::binary(arguments, f64::powf), 2, 2),
+        "ln" => (|arguments| math::logarithm(arguments, f64::ln), 1, 1),
+        "log10" => (|arguments| math::logarithm(arguments, f64::log10), 1, 1),
+        "log2" => (|arguments| math::logarithm(arguments, f64::log2), 1, 1),
+        "log" => (math::log, 1, 2),
+        "sin" => (|arguments| math::unary(arguments, f64::sin), 1, 1),
+        "cos" => (|arguments| math::unary(arguments, f64::cos), 1, 1),
+        "tan" => (|arguments| math::unary(arguments, f64::tan), 1, 1),
+        "asin" => (|arguments| math::unary(arguments, f64::asin), 1, 1),
+        "acos" => (|arguments| math::unary(arguments, f64::acos), 1, 1),
+        "atan" => (|arguments| math::unary(arguments, f64::atan), 1, 1),
+        "atan2" => (|arguments| math::binary(arguments, f64::atan2), 2, 2),
+        "sinh" => (|arguments| math::unary(arguments, f64::sinh), 1, 1),
+        "cosh" => (|arguments| math::unary(arguments, f64::cosh), 1, 1),
+        "tanh" => (|arguments| math::unary(arguments, f64::tanh), 1, 1),
+        "asinh" => (|arguments| math::unary(arguments, f64::asinh), 1, 1),
+        "acosh" => (|arguments| math::unary(arguments, f64::acosh), 1, 1),
+        "atanh" => (|arguments| math::unary(arguments, f64::atanh), 1, 1),
+        "degrees" => (|arguments| math::unary(arguments, f64::to_degrees), 1, 1),
+        "radians" => (|arguments| math::unary(arguments, f64::to_radians), 1, 1),
+        "pi" => (math::pi, 0, 0),
+        "ceil" | "ceiling" => (math::ceil, 1, 1),
+        "floor" => (math::floor, 1, 1),
+        "trunc" => (math::trunc, 1, 1),
+        "sign" => (math::sign, 1, 1),
+        "mod" => (math::remainder, 2, 2),
+        "json" => (json::json, 1, 1),
+        "json_valid" => (json::json_valid, 1, 1),
+        "json_type" => (json::json_type, 1, 2),
+        "json_array_length" => (json::json_array_length, 1, 2),
+        "json_extract" => (json::json_extract, 2, usize::MAX),
+        "json_set" => (json::json_set, 1, usize::MAX),
+        "json_insert" => (json::json_insert, 1, usize::MAX),
+        "json_replace" => (json::json_replace, 1, usize::MAX),
+        "json_remove" => (json::json_remove, 1, usize::MAX),
+        _ => return None,
+    };
+    Some(function)
+}
+
+/// Calls a scalar function with the values of its arguments, a function registered on the
+/// database is called rather than a built in one with the same name.
+pub fn call(
+    name: &str,
+    arguments: &[Column],
+    registered: Option<&Functions>,
+) -> Result<Column, SqlError> {
+    let registered =
+        registered.and_then(|functions| find_function(functions, name, arguments.len()));
+    if let Some(UserFunction::Scalar { function, .. }) = registered {
+        return function(arguments).map_err(|error| SqlError::new(error.to_string()));
+    }
+
+    let (function, least, most) = match find(name) {
+        Some(function) => function,
+        None => return Err(SqlError::new(format!("no such function: {}", name))),
+    };
+    if arguments.len() < least || arguments.len() > most {
+        return Err(SqlError::new(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )));
+    }
+    function(arguments)
+}
+
+/// Whether a scalar function always gives the same result for the same arguments. Date and
+/// time functions of the current moment don't, and neither does `random`.
+pub fn is_deterministic(
+    name: &str,
+    arguments: &[Expression],
+    registered: &Functions,
+) -> Result<bool, SqlError> {
+    if let Some(UserFunction::Scalar { deterministic, .. }) =
+        find_function(registered, name, arguments.len())
+    {
+        return Ok(*deterministic);
+    }
+    let now = |argument: &Expression| match argument {
+        Expression::Literal(Column::Text(text)) => text.eq_ignore_ascii_case("now"),
+        _ => false,
+    };
+    match name {
+        "random" => Ok(false),
+        // the moment is the first argument, after the format for strftime
+        "date" | "time" | "datetime" | "julianday" | "unixepoch" | "strftime" | "timediff" => {
+            let moment = if name == "strftime" { 1 } else { 0 };
+            Ok(arguments.len() > moment && !arguments.iter().any(now))
+        }
+        _ if find(name).is_some() => Ok(true),
+        _ => Err(SqlError::new(format!("no such function: {}", name))),
+    }
+}
+
+/// Marks the calls of a statement to aggregates registered on the database as aggregate
+/// calls, and those to scalar functions registered with the name of a built in aggregate as
+/// scalar calls.
+pub fn resolve(statement: &mut Statement, functions: &Functions) {
+    if functions.is_empty() {
+        return;
+    }
+    statement.visit_expressions_mut(&mut |expression| {
+        if let Expression::Function {
+            name,
+            arguments,
+            aggregate,
+            ..
+        } = expression
+        {
+            match find_function(functions, name, arguments.len()) {
+                Some(UserFunction::Aggregate(_)) => *aggregate = true,
+                Some(UserFunction::Scalar { .. }) => *aggregate = false,
+                None => {}
+            }
+        }
+    });
+}
+
+/// The names of the columns of a table-valued function, a function read in `FROM` like a
+/// table.
+pub fn table_columns(name: &str) -> Result<Vec<String>, SqlError> {
+    let columns: &[&str] = match name {
+        "json_each" | "json_tree" => &json::TABLE_COLUMNS,
+        _ => return Err(no_such_table_function(name)),
+    };
+    Ok(columns.iter().map(|column| column.to_string()).collect())
+}
+
+/// Calls a table-valued function with the values of its arguments.
+pub fn call_table(name: &str, arguments: &[Column]) -> Result<Vec<Vec<Column>>, SqlError> {
+    match name {
+        "json_each" | "json_tree" => json::table_rows(name, arguments),
+        _ => Err(no_such_table_function(name)),
+    }
+}
+
+fn no_such_table_function(name: &str) -> SqlError {
+    SqlError::new(format!("no such table-valued function: {}", name))
+}
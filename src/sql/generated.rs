@@ -0,0 +1,152 @@
+use super::executor::table_names;
+use super::expression::{resolve, ColumnName, Expression, Row, Scope};
+use super::function;
+use super::parser;
+use super::window;
+use super::SqlError;
+use backend::database::{Column, Database, GeneratedColumns};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error;
+
+// how many parsed expressions are kept before they're all dropped, more than the generated
+// columns any one statement reads
+const PARSED_LIMIT: usize = 256;
+
+thread_local! {
+    // the expressions of generated columns already parsed, by their SQL
+    static PARSED: RefCell<HashMap<String, Expression>> = RefCell::new(HashMap::new());
+}
+
+/// Works out the value of a generated column for a row of its table, this is the generator
+/// given to databases loaded or run through this layer.
+pub fn generate(
+    database: &Database,
+    table: &GeneratedColumns,
+    expression: &str,
+    row: &[Column],
+) -> Result<Column, Box<dyn error::Error>> {
+    let expression = parsed(expression)?;
+    let names: Vec<ColumnName> = table
+        .columns
+        .iter()
+        .zip(&table.collations)
+        .map(|((name, column_type), collation)| ColumnName {
+            affinity: Some(column_type.clone()),
+            collation: collation.clone(),
+            ..ColumnName::new(None, name.clone())
+        })
+        .collect();
+    let scope = Scope {
+        collations: Some(&database.collations),
+        functions: Some(&database.functions),
+        ..Scope::default()
+    };
+    Ok(expression.evaluate(&Row::new(&names, row).with_scope(scope))?)
+}
+
+fn parsed(expression: &str) -> Result<Expression, SqlError> {
+    PARSED.with(|parsed| {
+        let mut parsed = parsed.borrow_mut();
+        if let Some(parsed) = parsed.get(expression) {
+            return Ok(parsed.clone());
+        }
+        let parsed_expression = parser::parse_expression(expression)?;
+        if parsed.len() >= PARSED_LIMIT {
+            parsed.clear();
+        }
+        parsed.insert(expression.to_string(), parsed_expression.clone());
+        Ok(parsed_expression)
+    })
+}
+
+/// Checks the expression of a generated column being added to a table and gives the SQL it's
+/// kept as. It can use the columns the table already has and deterministic functions, but not
+/// subqueries, parameters or aggregates. Its column references are kept without the table
+/// name so that they follow the table when it's renamed.
+pub fn check(
+    database: &mut Database,
+    table: &str,
+    mut expression: Expression,
+) -> Result<String, Box<dyn error::Error>> {
+    let names = table_names(database, table, table)?;
+    check_expression(&expression, &names, database)?;
+    expression.visit_mut(&mut |expression| {
+        if let Expression::Column { table, .. } = expression {
+            *table = None;
+        }
+    });
+    Ok(expression.to_string())
+}
+
+fn check_expression(
+    expression: &Expression,
+    names: &[ColumnName],
+    database: &Database,
+) -> Result<(), SqlError> {
+    let prohibited = |what: &str| {
+        Err(SqlError::new(format!(
+            "{} prohibited in generated columns",
+            what
+        )))
+    };
+    match expression {
+        Expression::Subquery(_) | Expression::Exists(_) | Expression::InSubquery { .. } => {
+            return prohibited("subqueries")
+        }
+        Expression::Parameter { .. } => return prohibited("parameters"),
+        Expression::WindowFunction { .. }
+        | Expression::Function {
+            aggregate: true, ..
+        } => return prohibited("aggregate functions"),
+        Expression::Function { name, .. } if window::is_window_function(name) => {
+            return prohibited("window functions")
+        }
+        Expression::Function {
+            name, arguments, ..
+        } if !function::is_deterministic(name, arguments, &database.functions)? => {
+            return prohibited("non-deterministic functions")
+        }
+        Expression::Column { table, name } => {
+            resolve(names, table, name)?;
+        }
+        _ => {}
+    }
+    for child in expression.children() {
+        check_expression(child, names, database)?;
+    }
+    Ok(())
+}
+
+/// Fails when a generated column of the table uses the column, which then can't be renamed
+/// or dropped.
+pub fn check_unused(
+    database: &mut Database,
+    table: &str,
+    column: &str,
+    action: &str,
+) -> Result<(), Box<dyn error::Error>> {
+    let generated = database.generated_columns(table.to_string())?;
+    for ((name, _), generated) in generated.columns.iter().zip(&generated.generated) {
+        if let Some(generated) = generated {
+            if uses(&parsed(&generated.expression)?, column) {
+                return Err(format!(
+                    "cannot {} column {}: it is used by generated column {}",
+                    action, column, name
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn uses(expression: &Expression, column: &str) -> bool {
+    match expression {
+        Expression::Column { name, .. } => name.eq_ignore_ascii_case(column),
+        _ => expression
+            .children()
+            .iter()
+            .any(|child| uses(child, column)),
+    }
+}
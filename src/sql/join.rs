@@ -0,0 +1,448 @@
+use super::executor::{scan_table, table_names, Context, Relation};
+use super::expression::{convert, BinaryOperator, ColumnName, Expression, Row, Scope};
+use super::function;
+use super::planner::{self, Access, JoinInput};
+use super::select;
+use super::statement::{From, Join, JoinConstraint, JoinOperator, TableReference, TableSource};
+use super::SqlError;
+use backend::database::Column;
+use std::error;
+
+/// Reads the rows of the `FROM` clause, joining each table onto the rows before it with a
+/// nested loop. When the join condition matches an index on the inner table the index is
+/// searched for each outer row instead of scanning the whole table, and the first table is
+/// searched by the terms of `selection` the same way. Inner joins are read in
+/// the order the planner picks, with the terms of `selection` checked as the tables they
+/// read are joined. A table-valued function joined onto other tables is called again for
+/// each outer row, so its arguments can read them.
+pub fn scan_from(
+    context: &Context,
+    from: &From,
+    selection: Option<&Expression>,
+    scope: Scope,
+) -> Result<Relation, Box<dyn error::Error>> {
+    if planner::reorderable(from) {
+        return scan_planned(context, from, selection, scope);
+    }
+
+    let mut relation = scan_first(context, &from.table, selection, scope)?;
+
+    for join in &from.joins {
+        relation = join_table(context, relation, join, scope)?;
+    }
+    Ok(relation)
+}
+
+// joins the tables in the planned order, then puts their columns back in the order the
+// tables are written in
+fn scan_planned(
+    context: &Context,
+    from: &From,
+    selection: Option<&Expression>,
+    scope: Scope,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let mut inners = Vec::new();
+    for table in std::iter::once(&from.table).chain(from.joins.iter().map(|join| &join.table)) {
+        inners.push(open_reference(context, table, scope)?);
+    }
+    let inputs: Vec<JoinInput> = inners.iter().map(|inner| inner.input.clone()).collect();
+    let steps = planner::plan_joins(&mut context.database(), &inputs, from, selection)?;
+
+    // the first table is joined onto a single empty row
+    let mut relation = Relation {
+        names: Vec::new(),
+        rows: vec![Vec::new()],
+    };
+    let mut offsets = vec![0; inputs.len()];
+    for step in steps {
+        offsets[step.input] = relation.names.len();
+        let inner = Inner {
+            input: inputs[step.input].clone(),
+            rows: inners[step.input].rows.take(),
+            function: None,
+        };
+        relation = join_rows(
+            context,
+            relation,
+            inner,
+            &step.access,
+            step.condition.as_ref(),
+            false,
+            scope,
+        )?;
+    }
+
+    let order: Vec<usize> = inputs
+        .iter()
+        .zip(offsets)
+        .flat_map(|(input, offset)| offset..offset + input.names.len())
+        .collect();
+    let names = order
+        .iter()
+        .map(|&column| relation.names[column].clone())
+        .collect();
+    let rows = relation
+        .rows
+        .into_iter()
+        .map(|row| order.iter().map(|&column| row[column].clone()).collect())
+        .collect();
+    Ok(Relation { names, rows })
+}
+
+// the rows of the first table, an index is searched when the terms of `selection` give the
+// key of one
+fn scan_first(
+    context: &Context,
+    table: &TableReference,
+    selection: Option<&Expression>,
+    scope: Scope,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let selection = match (&table.source, selection) {
+        (TableSource::Table(_), Some(selection)) => selection,
+        _ => return scan_reference(context, table, scope),
+    };
+    let inner = open_reference(context, table, scope)?;
+    let access = match (&inner.rows, &inner.input.table) {
+        (None, Some(name)) => planner::choose_access(
+            &mut context.database(),
+            name,
+            &[],
+            &inner.input.names,
+            Some(selection),
+        )?,
+        _ => Access::Scan,
+    };
+    let empty = Relation {
+        names: Vec::new(),
+        rows: vec![Vec::new()],
+    };
+    join_rows(context, empty, inner, &access, None, false, scope)
+}
+
+// every row of a table, derived table, common table or table-valued function
+fn scan_reference(
+    context: &Context,
+    table: &TableReference,
+    scope: Scope,
+) -> Result<Relation, Box<dyn error::Error>> {
+    match (materialize(context, table, scope)?, &table.source) {
+        (Some(relation), _) => Ok(relation),
+        (None, TableSource::Table(name)) => {
+            let reference_name = table.reference_name().unwrap_or(name);
+            scan_table(&mut context.database(), name, reference_name)
+        }
+        (None, _) => unreachable!(),
+    }
+}
+
+// a table about to be joined on, stored tables are read once the access path is known
+fn open_reference<'a>(
+    context: &Context,
+    table: &'a TableReference,
+    scope: Scope,
+) -> Result<Inner<'a>, Box<dyn error::Error>> {
+    if let TableSource::Function { name, arguments } = &table.source {
+        let input = JoinInput {
+            table: None,
+            names: function_names(table, name)?,
+        };
+        return Ok(Inner {
+            input,
+            rows: None,
+            function: Some((name, arguments)),
+        });
+    }
+    match materialize(context, table, scope)? {
+        Some(relation) => {
+            let input = JoinInput {
+                table: None,
+                names: relation.names,
+            };
+            Ok(Inner {
+                input,
+                rows: Some(relation.rows),
+                function: None,
+            })
+        }
+        None => {
+            let name = match &table.source {
+                TableSource::Table(name) => name,
+                _ => unreachable!(),
+            };
+            let reference_name = table.reference_name().unwrap_or(name);
+            let input = JoinInput {
+                table: Some(name.clone()),
+                names: table_names(&mut context.database(), name, reference_name)?,
+            };
+            Ok(Inner {
+                input,
+                rows: None,
+                function: None,
+            })
+        }
+    }
+}
+
+// derived tables, common tables and table-valued functions aren't stored in pages, their
+// rows are produced up front
+fn materialize(
+    context: &Context,
+    table: &TableReference,
+    scope: Scope,
+) -> Result<Option<Relation>, Box<dyn error::Error>> {
+    let relation = match &table.source {
+        TableSource::Table(name) => match context.common_table(name) {
+            Some(relation) => relation,
+            None => return Ok(None),
+        },
+        TableSource::Subquery(subquery) => {
+            let result = select::execute_select(context, subquery, scope.outer)?;
+            let names = result
+                .columns
+                .into_iter()
+                .map(|column| ColumnName::new(None, column))
+                .collect();
+            Relation {
+                names,
+                rows: result.rows,
+            }
+        }
+        TableSource::Function { name, arguments } => {
+            let mut values = Vec::with_capacity(arguments.len());
+            for argument in arguments {
+                values.push(argument.evaluate(&Row::empty().with_scope(scope))?);
+            }
+            Relation {
+                names: function_names(table, name)?,
+                rows: function::call_table(name, &values)?,
+            }
+        }
+    };
+
+    let reference_name = table.reference_name().map(|name| name.to_string());
+    let names = relation
+        .names
+        .into_iter()
+        .map(|name| ColumnName::new(reference_name.clone(), name.name))
+        .collect();
+    Ok(Some(Relation {
+        names,
+        rows: relation.rows,
+    }))
+}
+
+fn join_table(
+    context: &Context,
+    outer: Relation,
+    join: &Join,
+    scope: Scope,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let mut inner = open_reference(context, &join.table, scope)?;
+    let condition = join_condition(&outer.names, &mut inner.input.names, &join.constraint)?;
+
+    let access = match (&inner.rows, &inner.input.table) {
+        (None, Some(name)) => planner::choose_access(
+            &mut context.database(),
+            name,
+            &outer.names,
+            &inner.input.names,
+            condition.as_ref(),
+        )?,
+        _ => Access::Scan,
+    };
+    let left = join.operator == JoinOperator::Left;
+    join_rows(
+        context,
+        outer,
+        inner,
+        &access,
+        condition.as_ref(),
+        left,
+        scope,
+    )
+}
+
+// a table being joined on, with its rows when they were produced up front or the name and
+// arguments of the table-valued function giving them
+struct Inner<'a> {
+    input: JoinInput,
+    rows: Option<Vec<Vec<Column>>>,
+    function: Option<(&'a str, &'a [Expression])>,
+}
+
+// the columns of a table-valued function, qualified by its alias or name
+fn function_names(table: &TableReference, name: &str) -> Result<Vec<ColumnName>, SqlError> {
+    let reference_name = table.reference_name().map(|name| name.to_string());
+    Ok(function::table_columns(name)?
+        .into_iter()
+        .map(|column| ColumnName::new(reference_name.clone(), column))
+        .collect())
+}
+
+// pairs each outer row with the inner rows meeting the condition, a left join keeps outer
+// rows without any, padding them with NULLs
+fn join_rows(
+    context: &Context,
+    outer: Relation,
+    inner: Inner,
+    access: &Access,
+    condition: Option<&Expression>,
+    left: bool,
+    scope: Scope,
+) -> Result<Relation, Box<dyn error::Error>> {
+    let inner_names = &inner.input.names;
+    let scanned = match (inner.rows, access, &inner.input.table) {
+        (Some(rows), _, _) => rows,
+        (None, Access::Scan, Some(name)) => context.database().select_all_records(name.clone())?,
+        _ => Vec::new(),
+    };
+
+    let mut names = outer.names.clone();
+    names.extend(inner_names.iter().cloned());
+
+    let mut rows = Vec::new();
+    for outer_row in outer.rows {
+        let searched;
+        let candidates = match (inner.function, access) {
+            (Some((name, arguments)), _) => {
+                let outer_values = Row::new(&outer.names, &outer_row).with_scope(scope);
+                let mut values = Vec::with_capacity(arguments.len());
+                for argument in arguments {
+                    values.push(argument.evaluate(&outer_values)?);
+                }
+                searched = function::call_table(name, &values)?;
+                &searched
+            }
+            (None, Access::Scan) => &scanned,
+            (
+                None,
+                Access::IndexSearch {
+                    index,
+                    key,
+                    affinities,
+                },
+            ) => {
+                let outer_values = Row::new(&outer.names, &outer_row).with_scope(scope);
+                let mut values = Vec::with_capacity(key.len());
+                for (expression, affinity) in key.iter().zip(affinities) {
+                    values.push(convert(expression.evaluate(&outer_values)?, affinity));
+                }
+                searched = context.database().search_index(index, &values)?;
+                &searched
+            }
+        };
+
+        let mut matched = false;
+        for inner_row in candidates {
+            let mut row = outer_row.clone();
+            row.extend(pad(inner_row, inner_names.len()));
+
+            let keep = match condition {
+                Some(condition) => condition.is_true(&Row::new(&names, &row).with_scope(scope))?,
+                None => true,
+            };
+            if keep {
+                rows.push(row);
+                matched = true;
+            }
+        }
+
+        if !matched && left {
+            let mut row = outer_row;
+            row.extend(vec![Column::Null(); inner_names.len()]);
+            rows.push(row);
+        }
+    }
+
+    Ok(Relation { names, rows })
+}
+
+/// The condition a join matches rows on. The inner copies of the columns matched by `USING`
+/// and `NATURAL` are hidden.
+pub fn join_condition(
+    outer_names: &[ColumnName],
+    inner_names: &mut [ColumnName],
+    constraint: &JoinConstraint,
+) -> Result<Option<Expression>, SqlError> {
+    match constraint {
+        JoinConstraint::On(condition) => Ok(Some(condition.clone())),
+        JoinConstraint::Using(columns) => {
+            using_condition(outer_names, inner_names, columns).map(Some)
+        }
+        JoinConstraint::Natural => {
+            let columns: Vec<String> = inner_names
+                .iter()
+                .filter(|inner| {
+                    outer_names
+                        .iter()
+                        .any(|outer| !outer.hidden && outer.name.eq_ignore_ascii_case(&inner.name))
+                })
+                .map(|inner| inner.name.clone())
+                .collect();
+            if columns.is_empty() {
+                Ok(None)
+            } else {
+                using_condition(outer_names, inner_names, &columns).map(Some)
+            }
+        }
+        JoinConstraint::None => Ok(None),
+    }
+}
+
+// `USING (a, b)` is the same as `ON outer.a = inner.a AND outer.b = inner.b`,
+// the inner copies of the columns are hidden
+fn using_condition(
+    outer_names: &[ColumnName],
+    inner_names: &mut [ColumnName],
+    columns: &[String],
+) -> Result<Expression, SqlError> {
+    let mut condition: Option<Expression> = None;
+
+    for column in columns {
+        let outer = outer_names
+            .iter()
+            .find(|name| !name.hidden && name.name.eq_ignore_ascii_case(column));
+        let inner = inner_names
+            .iter_mut()
+            .find(|name| name.name.eq_ignore_ascii_case(column));
+        let (outer, inner) = match (outer, inner) {
+            (Some(outer), Some(inner)) => (outer, inner),
+            _ => {
+                return Err(SqlError::new(format!(
+                    "cannot join using column {} - column not present in both tables",
+                    column
+                )))
+            }
+        };
+        inner.hidden = true;
+
+        let equality = Expression::Binary {
+            operator: BinaryOperator::Equal,
+            left: Box::new(Expression::Column {
+                table: outer.table.clone(),
+                name: outer.name.clone(),
+            }),
+            right: Box::new(Expression::Column {
+                table: inner.table.clone(),
+                name: inner.name.clone(),
+            }),
+        };
+        condition = Some(match condition {
+            Some(condition) => Expression::Binary {
+                operator: BinaryOperator::And,
+                left: Box::new(condition),
+                right: Box::new(equality),
+            },
+            None => equality,
+        });
+    }
+
+    condition.ok_or_else(|| SqlError::new("USING requires at least one column"))
+}
+
+// rows written before a column was added are shorter than the table
+fn pad(row: &[Column], length: usize) -> Vec<Column> {
+    let mut row = row.to_vec();
+    row.resize(length, Column::Null());
+    row
+}
@@ -0,0 +1,514 @@
+use super::SqlError;
+use backend::database::{Column, Json};
+
+/// The columns of the rows `json_each` and `json_tree` give.
+pub const TABLE_COLUMNS: [&str; 8] = [
+    "key", "value", "type", "atom", "id", "parent", "fullkey", "path",
+];
+
+/// `json(x)`, the JSON without its whitespace.
+pub fn json(arguments: &[Column]) -> Result<Column, SqlError> {
+    match parse(&arguments[0])? {
+        Some(json) => Ok(Column::Text(json.to_string())),
+        None => Ok(Column::Null()),
+    }
+}
+
+/// `json_valid(x)`, whether the value is well formed JSON.
+pub fn json_valid(arguments: &[Column]) -> Result<Column, SqlError> {
+    let valid = match &arguments[0] {
+        Column::Null() => return Ok(Column::Null()),
+        Column::Text(text) => Json::parse(text).is_some(),
+        Column::Blob(_) => false,
+        _ => true,
+    };
+    Ok(Column::Integer(valid as i128))
+}
+
+/// `json_type(json, path)`, the type of the value at the path: null, true, false, integer,
+/// real, text, array or object.
+pub fn json_type(arguments: &[Column]) -> Result<Column, SqlError> {
+    let value = match find(arguments)? {
+        Some(value) => value,
+        None => return Ok(Column::Null()),
+    };
+    Ok(Column::Text(value.type_name().to_string()))
+}
+
+/// `json_array_length(json, path)`, the number of elements of the array at the path, 0 when
+/// the value there isn't an array.
+pub fn json_array_length(arguments: &[Column]) -> Result<Column, SqlError> {
+    let length = match find(arguments)? {
+        Some(Json::Array(elements)) => elements.len(),
+        Some(_) => 0,
+        None => return Ok(Column::Null()),
+    };
+    Ok(Column::Integer(length as i128))
+}
+
+/// `json_extract(json, path, ...)`, the value at the path as SQL sees it. With more than one
+/// path the values are returned as a JSON array, with null for the paths that aren't found.
+pub fn json_extract(arguments: &[Column]) -> Result<Column, SqlError> {
+    let json = match parse(&arguments[0])? {
+        Some(json) => json,
+        None => return Ok(Column::Null()),
+    };
+    let mut values = Vec::new();
+    for path in &arguments[1..] {
+        let path = match path {
+            Column::Null() => return Ok(Column::Null()),
+            path => Path::parse(&text(path))?,
+        };
+        values.push(path.lookup(&json));
+    }
+    if values.len() == 1 {
+        return Ok(values[0].map_or(Column::Null(), Json::to_column));
+    }
+    let array = values
+        .into_iter()
+        .map(|value| value.cloned().unwrap_or(Json::Null))
+        .collect();
+    Ok(Column::Text(Json::Array(array).to_string()))
+}
+
+/// `json_set(json, path, value, ...)`, the JSON with the value at each path replaced or
+/// added. Missing objects on the way to the path are created.
+pub fn json_set(arguments: &[Column]) -> Result<Column, SqlError> {
+    edit_values(arguments, "json_set", |_exists| true)
+}
+
+/// `json_insert(json, path, value, ...)`, like `json_set` but only adds values.
+pub fn json_insert(arguments: &[Column]) -> Result<Column, SqlError> {
+    edit_values(arguments, "json_insert", |exists| !exists)
+}
+
+/// `json_replace(json, path, value, ...)`, like `json_set` but only replaces values.
+pub fn json_replace(arguments: &[Column]) -> Result<Column, SqlError> {
+    edit_values(arguments, "json_replace", |exists| exists)
+}
+
+/// `json_remove(json, path, ...)`, the JSON without the values at the paths. Removing the
+/// whole value gives NULL.
+pub fn json_remove(arguments: &[Column]) -> Result<Column, SqlError> {
+    let mut json = match parse(&arguments[0])? {
+        Some(json) => json,
+        None => return Ok(Column::Null()),
+    };
+    for path in &arguments[1..] {
+        let path = match path {
+            Column::Null() => return Ok(Column::Null()),
+            path => Path::parse(&text(path))?,
+        };
+        if path.steps.is_empty() {
+            return Ok(Column::Null());
+        }
+        edit(&mut json, &path.steps, &Edit::Remove);
+    }
+    Ok(Column::Text(json.to_string()))
+}
+
+/// `json -> path` gives the JSON of the value at the path and `json ->> path` its SQL value.
+/// The path is a full path when it starts with `$`, otherwise text is the key of an object
+/// member and an integer the index of an array element, counting from the end when negative.
+pub fn arrow(json: &Column, path: &Column, as_value: bool) -> Result<Column, SqlError> {
+    let document = match parse(json)? {
+        Some(document) => document,
+        None => return Ok(Column::Null()),
+    };
+    let path = match path {
+        Column::Null() => return Ok(Column::Null()),
+        Column::Text(text) if text.starts_with('$') => Path::parse(text)?,
+        Column::Text(text) => Path::from_step(Step::Key(text.clone())),
+        Column::Integer(index) if *index < 0 => {
+            Path::from_step(Step::Index(Index::FromEnd(index.unsigned_abs() as usize)))
+        }
+        Column::Integer(index) => Path::from_step(Step::Index(Index::FromStart(*index as usize))),
+        path => return Err(bad_path(&text(path))),
+    };
+    Ok(match path.lookup(&document) {
+        Some(value) if as_value => value.to_column(),
+        Some(value) => Column::Text(value.to_string()),
+        None => Column::Null(),
+    })
+}
+
+/// The rows of `json_each(json, path)`, one for each element or member of the value at the
+/// path, or `json_tree(json, path)`, one for the value and everything nested in it.
+pub fn table_rows(name: &str, arguments: &[Column]) -> Result<Vec<Vec<Column>>, SqlError> {
+    if arguments.is_empty() || arguments.len() > 2 {
+        return Err(SqlError::new(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )));
+    }
+    let json = match parse(&arguments[0])? {
+        Some(json) => json,
+        None => return Ok(Vec::new()),
+    };
+    let path = match arguments.get(1) {
+        Some(Column::Null()) => return Ok(Vec::new()),
+        Some(path) => Path::parse(&text(path))?,
+        None => Path::parse("$")?,
+    };
+    let (root, key, fullkey, parent_path) = match path.locate(&json) {
+        Some(location) => location,
+        None => return Ok(Vec::new()),
+    };
+    let mut nodes = Vec::new();
+    walk(root, key, fullkey, parent_path, None, 0, &mut nodes);
+
+    let each = name == "json_each";
+    let scalar = !matches!(root, Json::Array(_) | Json::Object(_));
+    Ok(nodes
+        .into_iter()
+        .filter(|node| !each || node.depth == 1 || scalar)
+        .map(|node| {
+            let atom = match node.value {
+                Json::Array(_) | Json::Object(_) => Column::Null(),
+                value => value.to_column(),
+            };
+            let parent = match node.parent {
+                Some(parent) if !each => Column::Integer(parent),
+                _ => Column::Null(),
+            };
+            vec![
+                node.key,
+                node.value.to_column(),
+                Column::Text(node.value.type_name().to_string()),
+                atom,
+                Column::Integer(node.id),
+                parent,
+                Column::Text(node.fullkey),
+                Column::Text(node.path),
+            ]
+        })
+        .collect())
+}
+
+// the JSON given as an argument, None for NULL
+fn parse(value: &Column) -> Result<Option<Json>, SqlError> {
+    match value {
+        Column::Null() => Ok(None),
+        Column::Text(text) => match Json::parse(text) {
+            Some(json) => Ok(Some(json)),
+            None => Err(SqlError::new("malformed JSON")),
+        },
+        Column::Blob(_) => Err(SqlError::new("malformed JSON")),
+        value => Ok(Some(Json::from_column(value))),
+    }
+}
+
+// the value at the path given by the second argument, `$` when there isn't one
+fn find(arguments: &[Column]) -> Result<Option<Json>, SqlError> {
+    let json = match parse(&arguments[0])? {
+        Some(json) => json,
+        None => return Ok(None),
+    };
+    let path = match arguments.get(1) {
+        Some(Column::Null()) => return Ok(None),
+        Some(path) => Path::parse(&text(path))?,
+        None => return Ok(Some(json)),
+    };
+    Ok(path.lookup(&json).cloned())
+}
+
+fn text(value: &Column) -> String {
+    super::expression::to_text(value)
+}
+
+fn bad_path(path: &str) -> SqlError {
+    SqlError::new(format!("bad JSON path: '{}'", path))
+}
+
+// the edits of json_set, json_insert and json_replace, which take path and value pairs and
+// write a value when `write` is true of whether the path already exists. Text values are
+// written as JSON strings.
+fn edit_values<F>(arguments: &[Column], name: &str, write: F) -> Result<Column, SqlError>
+where
+    F: Fn(bool) -> bool,
+{
+    if arguments.len().is_multiple_of(2) {
+        return Err(SqlError::new(format!(
+            "{}() needs an odd number of arguments",
+            name
+        )));
+    }
+    let mut json = match parse(&arguments[0])? {
+        Some(json) => json,
+        None => return Ok(Column::Null()),
+    };
+    for pair in arguments[1..].chunks(2) {
+        let path = match &pair[0] {
+            Column::Null() => return Ok(Column::Null()),
+            path => Path::parse(&text(path))?,
+        };
+        let value = Json::from_column(&pair[1]);
+        let exists = path.lookup(&json).is_some();
+        if !write(exists) {
+            continue;
+        }
+        if path.steps.is_empty() {
+            json = value;
+        } else {
+            edit(&mut json, &path.steps, &Edit::Write(value));
+        }
+    }
+    Ok(Column::Text(json.to_string()))
+}
+
+enum Edit {
+    Write(Json),
+    Remove,
+}
+
+// applies an edit to the value at a path that isn't empty
+fn edit(target: &mut Json, steps: &[Step], change: &Edit) {
+    let (step, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    match (target, step) {
+        (Json::Object(members), Step::Key(key)) => {
+            match members.iter().position(|(name, _)| name == key) {
+                Some(position) if rest.is_empty() => match change {
+                    Edit::Write(value) => members[position].1 = value.clone(),
+                    Edit::Remove => {
+                        members.remove(position);
+                    }
+                },
+                Some(position) => edit(&mut members[position].1, rest, change),
+                None => {
+                    if let Some(value) = created(rest, change) {
+                        members.push((key.clone(), value));
+                    }
+                }
+            }
+        }
+        (Json::Array(elements), Step::Index(index)) => match index.position(elements.len()) {
+            Some(position) if position < elements.len() && rest.is_empty() => match change {
+                Edit::Write(value) => elements[position] = value.clone(),
+                Edit::Remove => {
+                    elements.remove(position);
+                }
+            },
+            Some(position) if position < elements.len() => {
+                edit(&mut elements[position], rest, change)
+            }
+            Some(position) if position == elements.len() => {
+                if let Some(value) = created(rest, change) {
+                    elements.push(value);
+                }
+            }
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+// the value written where a path doesn't exist yet, with the objects and arrays that lead
+// to it
+fn created(steps: &[Step], change: &Edit) -> Option<Json> {
+    let value = match change {
+        Edit::Write(value) => value,
+        Edit::Remove => return None,
+    };
+    match steps.split_first() {
+        None => Some(value.clone()),
+        Some((Step::Key(key), rest)) => {
+            Some(Json::Object(vec![(key.clone(), created(rest, change)?)]))
+        }
+        Some((Step::Index(index), rest)) if index.position(0) == Some(0) => {
+            Some(Json::Array(vec![created(rest, change)?]))
+        }
+        Some(_) => None,
+    }
+}
+
+// a path into JSON such as `$.items[0]."first name"` or `$.list[#-1]`
+struct Path {
+    steps: Vec<Step>,
+}
+
+enum Step {
+    Key(String),
+    Index(Index),
+}
+
+// `[N]` counts from the start, `[#-N]` from the end where `#` is one past the last element
+enum Index {
+    FromStart(usize),
+    FromEnd(usize),
+}
+
+impl Index {
+    fn position(&self, length: usize) -> Option<usize> {
+        match self {
+            Index::FromStart(position) => Some(*position),
+            Index::FromEnd(back) => length.checked_sub(*back),
+        }
+    }
+}
+
+impl Path {
+    fn parse(text: &str) -> Result<Path, SqlError> {
+        let error = || bad_path(text);
+        let mut rest = text.strip_prefix('$').ok_or_else(error)?;
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                // keys with dots or brackets in them are quoted
+                let (key, after) = if let Some(quoted) = after.strip_prefix('"') {
+                    let end = quoted.find('"').ok_or_else(error)?;
+                    (&quoted[..end], &quoted[end + 1..])
+                } else {
+                    let end = after.find(['.', '[']).unwrap_or(after.len());
+                    if end == 0 {
+                        return Err(error());
+                    }
+                    (&after[..end], &after[end..])
+                };
+                steps.push(Step::Key(key.to_string()));
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(error)?;
+                let index = after[..end].trim();
+                let index = match index.strip_prefix('#') {
+                    Some("") => Index::FromEnd(0),
+                    Some(back) => {
+                        let back = back.trim_start().strip_prefix('-').ok_or_else(error)?;
+                        Index::FromEnd(back.trim().parse().map_err(|_| error())?)
+                    }
+                    None => Index::FromStart(index.parse().map_err(|_| error())?),
+                };
+                steps.push(Step::Index(index));
+                rest = &after[end + 1..];
+            } else {
+                return Err(error());
+            }
+        }
+        Ok(Path { steps })
+    }
+
+    fn from_step(step: Step) -> Path {
+        Path { steps: vec![step] }
+    }
+
+    fn lookup<'a>(&self, json: &'a Json) -> Option<&'a Json> {
+        self.locate(json).map(|location| location.0)
+    }
+
+    // the value the path leads to, with its key, its full path with indexes counted from the
+    // start and the path of the value holding it
+    fn locate<'a>(&self, json: &'a Json) -> Option<(&'a Json, Column, String, String)> {
+        let mut value = json;
+        let mut key = Column::Null();
+        let mut fullkey = "$".to_string();
+        let mut path = "$".to_string();
+        for step in &self.steps {
+            path = fullkey.clone();
+            value = match (value, step) {
+                (Json::Object(members), Step::Key(name)) => {
+                    key = Column::Text(name.clone());
+                    push_step(&mut fullkey, step);
+                    members
+                        .iter()
+                        .rev()
+                        .find(|(member, _)| member == name)
+                        .map(|(_, value)| value)?
+                }
+                (Json::Array(elements), Step::Index(index)) => {
+                    let position = index.position(elements.len())?;
+                    key = Column::Integer(position as i128);
+                    push_step(&mut fullkey, &Step::Index(Index::FromStart(position)));
+                    elements.get(position)?
+                }
+                _ => return None,
+            };
+        }
+        Some((value, key, fullkey, path))
+    }
+}
+
+fn push_step(text: &mut String, step: &Step) {
+    match step {
+        Step::Key(key)
+            if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+        {
+            text.push('.');
+            text.push_str(key);
+        }
+        Step::Key(key) => text.push_str(&format!(".\"{}\"", key)),
+        Step::Index(Index::FromStart(position)) => text.push_str(&format!("[{}]", position)),
+        Step::Index(Index::FromEnd(0)) => text.push_str("[#]"),
+        Step::Index(Index::FromEnd(back)) => text.push_str(&format!("[#-{}]", back)),
+    }
+}
+
+// a value of a json_tree walk
+struct Node<'a> {
+    value: &'a Json,
+    key: Column,
+    id: i128,
+    parent: Option<i128>,
+    fullkey: String,
+    path: String,
+    depth: usize,
+}
+
+// adds the value and everything nested in it in depth first order, numbering them as they
+// are reached
+fn walk<'a>(
+    value: &'a Json,
+    key: Column,
+    fullkey: String,
+    path: String,
+    parent: Option<i128>,
+    depth: usize,
+    nodes: &mut Vec<Node<'a>>,
+) {
+    let id = nodes.len() as i128;
+    nodes.push(Node {
+        value,
+        key,
+        id,
+        parent,
+        fullkey: fullkey.clone(),
+        path,
+        depth,
+    });
+    match value {
+        Json::Array(elements) => {
+            for (position, element) in elements.iter().enumerate() {
+                let mut child = fullkey.clone();
+                push_step(&mut child, &Step::Index(Index::FromStart(position)));
+                let key = Column::Integer(position as i128);
+                walk(
+                    element,
+                    key,
+                    child,
+                    fullkey.clone(),
+                    Some(id),
+                    depth + 1,
+                    nodes,
+                );
+            }
+        }
+        Json::Object(members) => {
+            for (name, member) in members {
+                let mut child = fullkey.clone();
+                push_step(&mut child, &Step::Key(name.clone()));
+                let key = Column::Text(name.clone());
+                walk(
+                    member,
+                    key,
+                    child,
+                    fullkey.clone(),
+                    Some(id),
+                    depth + 1,
+                    nodes,
+                );
+            }
+        }
+        _ => {}
+    }
+}
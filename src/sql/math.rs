@@ -0,0 +1,133 @@
+use super::SqlError;
+use backend::database::{Column, ColumnType, Decimal};
+
+use std::f64::consts::PI;
+
+/// Applies a math function of one number, like `sqrt` or `sin`. As in SQLite the result is
+/// a real, and NULL for NULL, text or blobs that aren't numbers and for results that aren't
+/// numbers.
+pub fn unary(arguments: &[Column], function: fn(f64) -> f64) -> Result<Column, SqlError> {
+    match operand(&arguments[0]) {
+        Some(x) => Ok(real(function(x))),
+        None => Ok(Column::Null()),
+    }
+}
+
+/// Applies a math function of two numbers, like `pow` or `atan2`.
+pub fn binary(arguments: &[Column], function: fn(f64, f64) -> f64) -> Result<Column, SqlError> {
+    match (operand(&arguments[0]), operand(&arguments[1])) {
+        (Some(x), Some(y)) => Ok(real(function(x, y))),
+        _ => Ok(Column::Null()),
+    }
+}
+
+/// `ln(x)`, `log10(x)` and `log2(x)`, NULL for numbers that aren't positive.
+pub fn logarithm(arguments: &[Column], function: fn(f64) -> f64) -> Result<Column, SqlError> {
+    match operand(&arguments[0]) {
+        Some(x) if x > 0.0 => Ok(real(function(x))),
+        _ => Ok(Column::Null()),
+    }
+}
+
+/// `log(x)` is the logarithm in base 10 and `log(b, x)` the logarithm in base `b`.
+pub fn log(arguments: &[Column]) -> Result<Column, SqlError> {
+    if arguments.len() == 1 {
+        return logarithm(arguments, f64::log10);
+    }
+    match (operand(&arguments[0]), operand(&arguments[1])) {
+        (Some(base), Some(x)) if base > 0.0 && base != 1.0 && x > 0.0 => {
+            Ok(real(x.ln() / base.ln()))
+        }
+        _ => Ok(Column::Null()),
+    }
+}
+
+/// `pi()`
+pub fn pi(_arguments: &[Column]) -> Result<Column, SqlError> {
+    Ok(Column::Real(PI))
+}
+
+/// `ceil(x)`, also called `ceiling`, the smallest whole number not less than `x`.
+/// Integers are given back as they are and decimals stay decimals.
+pub fn ceil(arguments: &[Column]) -> Result<Column, SqlError> {
+    whole(&arguments[0], f64::ceil, |value, factor| {
+        -(-value).div_euclid(factor)
+    })
+}
+
+/// `floor(x)`, the largest whole number not greater than `x`.
+pub fn floor(arguments: &[Column]) -> Result<Column, SqlError> {
+    whole(&arguments[0], f64::floor, i128::div_euclid)
+}
+
+/// `trunc(x)`, `x` without its fraction.
+pub fn trunc(arguments: &[Column]) -> Result<Column, SqlError> {
+    whole(&arguments[0], f64::trunc, |value, factor| value / factor)
+}
+
+fn whole(
+    value: &Column,
+    function: fn(f64) -> f64,
+    divide: fn(i128, i128) -> i128,
+) -> Result<Column, SqlError> {
+    match numeric(value) {
+        Column::Integer(int) => Ok(Column::Integer(int)),
+        Column::Real(x) => Ok(Column::Real(function(x))),
+        Column::Decimal(decimal) => {
+            let factor = 10i128.pow(decimal.scale as u32);
+            Ok(Column::Decimal(Decimal::new(
+                divide(decimal.value, factor),
+                0,
+            )))
+        }
+        _ => Ok(Column::Null()),
+    }
+}
+
+/// `sign(x)`, -1, 0 or 1 as `x` is negative, zero or positive.
+pub fn sign(arguments: &[Column]) -> Result<Column, SqlError> {
+    let sign = match numeric(&arguments[0]) {
+        Column::Integer(int) => int.signum(),
+        Column::Decimal(decimal) => decimal.value.signum(),
+        Column::Real(x) if x.is_nan() => return Ok(Column::Null()),
+        Column::Real(0.0) => 0,
+        Column::Real(x) => x.signum() as i128,
+        _ => return Ok(Column::Null()),
+    };
+    Ok(Column::Integer(sign))
+}
+
+/// `mod(x, y)`, the remainder of dividing `x` by `y`, NULL when `y` is 0.
+pub fn remainder(arguments: &[Column]) -> Result<Column, SqlError> {
+    match (operand(&arguments[0]), operand(&arguments[1])) {
+        (Some(_), Some(0.0)) => Ok(Column::Null()),
+        (Some(x), Some(y)) => Ok(real(x % y)),
+        _ => Ok(Column::Null()),
+    }
+}
+
+// a number, or text that is one, as a real
+fn operand(value: &Column) -> Option<f64> {
+    match numeric(value) {
+        Column::Integer(int) => Some(int as f64),
+        Column::Real(real) => Some(real),
+        Column::Decimal(decimal) => Some(decimal.to_real()),
+        _ => None,
+    }
+}
+
+// text that is a number as that number, other values as they are
+fn numeric(value: &Column) -> Column {
+    match value {
+        Column::Text(_) => ColumnType::Numeric.apply(value.clone()),
+        value => value.clone(),
+    }
+}
+
+fn real(value: f64) -> Column {
+    if value.is_nan() {
+        Column::Null()
+    } else {
+        Column::Real(value)
+    }
+}
@@ -0,0 +1,70 @@
+use backend::database::{self, Database};
+use std::error;
+use std::fmt;
+use std::num;
+
+mod aggregate;
+mod codegen;
+mod common_table;
+mod compound;
+mod datetime;
+mod executor;
+mod explain;
+mod function;
+mod generated;
+pub mod expression;
+mod join;
+mod json;
+mod math;
+pub mod parser;
+mod planner;
+mod prepared;
+mod scalar;
+mod select;
+pub mod statement;
+mod tokenizer;
+mod trigger;
+mod view;
+mod vm;
+mod window;
+
+pub use self::executor::ResultSet;
+pub use self::prepared::Statement;
+
+#[derive(Debug, PartialEq)]
+pub struct SqlError {
+    message: String,
+}
+
+impl SqlError {
+    pub fn new<S: Into<String>>(message: S) -> SqlError {
+        SqlError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for SqlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl error::Error for SqlError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+impl From<num::ParseIntError> for SqlError {
+    fn from(err: num::ParseIntError) -> SqlError {
+        SqlError::new(err.to_string())
+    }
+}
+
+/// Loads a database as `database::load` does, with its generated columns worked out by
+/// this layer.
+pub fn load(filename: &String) -> Result<Database, Box<dyn error::Error>> {
+    database::load_with(filename, generated::generate)
+}
@@ -0,0 +1,1532 @@
+use super::aggregate;
+use super::expression::{BinaryOperator, Expression, UnaryOperator};
+use super::statement::{
+    AlterAction, AlterTable, CommonTable, CompoundOperator, CreateIndex, CreateTrigger, CreateView,
+    Delete, Frame, FrameBound, FrameUnits, From, Insert, Join, JoinConstraint, JoinOperator,
+    OrderingTerm, ResultColumn, Select, Statement, TableReference, TableSource, TriggerEvent,
+    TriggerTiming, Update, Window, With,
+};
+use super::tokenizer::{tokenize_with_offsets, Token};
+use super::SqlError;
+use backend::database::{Column, ColumnType, MAX_PRECISION};
+
+// keywords that can't be used as bare column names
+const RESERVED: &[&str] = &[
+    "ADD",
+    "ALL",
+    "ALTER",
+    "ANALYZE",
+    "AND",
+    "AS",
+    "ASC",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CAST",
+    "COLLATE",
+    "CREATE",
+    "CROSS",
+    "DEFAULT",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCEPT",
+    "EXISTS",
+    "EXPLAIN",
+    "FROM",
+    "GLOB",
+    "GROUP",
+    "HAVING",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "JOIN",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "NATURAL",
+    "NOT",
+    "NULL",
+    "OFFSET",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "SELECT",
+    "SET",
+    "TABLE",
+    "THEN",
+    "UNION",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "WHEN",
+    "WHERE",
+    "WITH",
+];
+
+// the largest number a parameter can have
+const MAX_PARAMETER: usize = 32766;
+
+pub fn parse_statement(sql: &str) -> Result<Statement, SqlError> {
+    parse_prepared(sql).map(|(statement, _parameters)| statement)
+}
+
+/// Parses a statement along with the names of its parameters by number, None for those
+/// written `?` and numbers no parameter was given.
+pub fn parse_prepared(sql: &str) -> Result<(Statement, Vec<Option<String>>), SqlError> {
+    let mut parser = Parser::new(sql)?;
+    let statement = parser.statement()?;
+
+    while parser.consume(&Token::Semicolon) {}
+    match parser.peek() {
+        None => Ok((statement, parser.parameters)),
+        Some(token) => Err(SqlError::new(format!(
+            "unexpected {:?} after statement",
+            token
+        ))),
+    }
+}
+
+pub fn parse_expression(sql: &str) -> Result<Expression, SqlError> {
+    let mut parser = Parser::new(sql)?;
+    let expression = parser.expression()?;
+
+    match parser.peek() {
+        None => Ok(expression),
+        Some(token) => Err(SqlError::new(format!(
+            "unexpected {:?} after expression",
+            token
+        ))),
+    }
+}
+
+struct Parser {
+    sql: String,
+    tokens: Vec<Token>,
+    /// where each token starts in the SQL
+    offsets: Vec<usize>,
+    position: usize,
+    /// the name of each parameter seen so far, by number
+    parameters: Vec<Option<String>>,
+}
+
+impl Parser {
+    fn new(sql: &str) -> Result<Parser, SqlError> {
+        let (tokens, offsets) = tokenize_with_offsets(sql)?.into_iter().unzip();
+        Ok(Parser {
+            sql: sql.to_string(),
+            tokens,
+            offsets,
+            position: 0,
+            parameters: Vec::new(),
+        })
+    }
+
+    // the SQL text of the tokens from `start` up to the current one
+    fn source(&self, start: usize) -> String {
+        let end = self
+            .offsets
+            .get(self.position)
+            .copied()
+            .unwrap_or(self.sql.len());
+        self.sql[self.offsets[start]..end].trim_end().to_string()
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.position + offset)
+    }
+
+    fn consume(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), SqlError> {
+        if self.consume(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("{:?}", token)))
+        }
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|token| token.is_keyword(keyword))
+    }
+
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_keyword(keyword) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), SqlError> {
+        if self.consume_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(keyword))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> SqlError {
+        match self.peek() {
+            Some(token) => SqlError::new(format!("expected {} but found {:?}", expected, token)),
+            None => SqlError::new(format!("expected {} but reached end of input", expected)),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, SqlError> {
+        match self.peek().cloned() {
+            Some(Token::Identifier(ref word)) if !is_reserved(word) => {
+                self.position += 1;
+                Ok(word.clone())
+            }
+            Some(Token::QuotedIdentifier(name)) => {
+                self.position += 1;
+                Ok(name)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, SqlError> {
+        if self.peek_keyword("SELECT") || self.peek_keyword("WITH") {
+            self.query()
+                .map(|select| Statement::Select(Box::new(select)))
+        } else if self.consume_keyword("EXPLAIN") {
+            self.explain()
+        } else if self.consume_keyword("INSERT") {
+            self.insert().map(Statement::Insert)
+        } else if self.consume_keyword("UPDATE") {
+            self.update().map(Statement::Update)
+        } else if self.consume_keyword("DELETE") {
+            self.delete().map(Statement::Delete)
+        } else if self.consume_keyword("ANALYZE") {
+            let table = match self.peek() {
+                Some(Token::Identifier(_)) | Some(Token::QuotedIdentifier(_)) => {
+                    Some(self.identifier()?)
+                }
+                _ => None,
+            };
+            Ok(Statement::Analyze(table))
+        } else if self.peek_keyword("CREATE") {
+            let start = self.position;
+            self.position += 1;
+            if self.consume_keyword("VIEW") {
+                self.create_view(start).map(Statement::CreateView)
+            } else if self.consume_keyword("TRIGGER") {
+                self.create_trigger(start).map(Statement::CreateTrigger)
+            } else {
+                self.expect_keyword("INDEX")?;
+                self.create_index().map(Statement::CreateIndex)
+            }
+        } else if self.consume_keyword("DROP") {
+            if self.consume_keyword("VIEW") {
+                let if_exists = self.if_exists();
+                let view = self.identifier()?;
+                return Ok(Statement::DropView { view, if_exists });
+            }
+            if self.consume_keyword("TRIGGER") {
+                let if_exists = self.if_exists();
+                let trigger = self.identifier()?;
+                return Ok(Statement::DropTrigger { trigger, if_exists });
+            }
+            self.expect_keyword("TABLE")?;
+            let if_exists = self.if_exists();
+            let table = self.identifier()?;
+            Ok(Statement::DropTable { table, if_exists })
+        } else if self.consume_keyword("ALTER") {
+            self.expect_keyword("TABLE")?;
+            self.alter_table().map(Statement::AlterTable)
+        } else {
+            Err(self.unexpected("statement"))
+        }
+    }
+
+    fn explain(&mut self) -> Result<Statement, SqlError> {
+        // QUERY and PLAN aren't reserved, `EXPLAIN QUERY PLAN` is only special here
+        let query_plan = self.peek_keyword("QUERY")
+            && self
+                .peek_at(1)
+                .is_some_and(|token| token.is_keyword("PLAN"));
+        if query_plan {
+            self.position += 2;
+        }
+        if self.peek_keyword("EXPLAIN") {
+            return Err(self.unexpected("statement"));
+        }
+        let statement = self.statement()?;
+        Ok(Statement::Explain {
+            statement: Box::new(statement),
+            query_plan,
+        })
+    }
+
+    // a SELECT, optionally preceded by a WITH clause
+    fn query(&mut self) -> Result<Select, SqlError> {
+        let with = if self.consume_keyword("WITH") {
+            Some(self.with()?)
+        } else {
+            None
+        };
+        self.expect_keyword("SELECT")?;
+        let mut select = self.select()?;
+        select.with = with;
+        Ok(select)
+    }
+
+    fn with(&mut self) -> Result<With, SqlError> {
+        let recursive = self.consume_keyword("RECURSIVE");
+
+        let mut tables = Vec::new();
+        loop {
+            let name = self.identifier()?;
+            let columns = if self.peek() == Some(&Token::LeftParen) {
+                self.identifier_list()?
+            } else {
+                Vec::new()
+            };
+            self.expect_keyword("AS")?;
+            self.expect(&Token::LeftParen)?;
+            let select = self.subquery()?;
+
+            tables.push(CommonTable {
+                name,
+                columns,
+                select: Box::new(select),
+            });
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+
+        Ok(With { recursive, tables })
+    }
+
+    // the SELECT keyword has already been consumed, any compound operators that follow are
+    // parsed into the first SELECT, which holds the ORDER BY and LIMIT for the whole compound
+    fn select(&mut self) -> Result<Select, SqlError> {
+        let mut select = self.select_core()?;
+
+        loop {
+            let operator = if self.consume_keyword("UNION") {
+                if self.consume_keyword("ALL") {
+                    CompoundOperator::UnionAll
+                } else {
+                    CompoundOperator::Union
+                }
+            } else if self.consume_keyword("INTERSECT") {
+                CompoundOperator::Intersect
+            } else if self.consume_keyword("EXCEPT") {
+                CompoundOperator::Except
+            } else {
+                break;
+            };
+            self.expect_keyword("SELECT")?;
+            let core = self.select_core()?;
+            select.compound.push((operator, core));
+        }
+
+        let mut order_by = Vec::new();
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                order_by.push(self.ordering_term()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let mut limit = None;
+        let mut offset = None;
+        if self.consume_keyword("LIMIT") {
+            limit = Some(self.expression()?);
+            if self.consume_keyword("OFFSET") {
+                offset = Some(self.expression()?);
+            } else if self.consume(&Token::Comma) {
+                // `LIMIT offset, count`
+                offset = limit;
+                limit = Some(self.expression()?);
+            }
+        }
+
+        select.order_by = order_by;
+        select.limit = limit;
+        select.offset = offset;
+        Ok(select)
+    }
+
+    // a SELECT without ORDER BY or LIMIT
+    fn select_core(&mut self) -> Result<Select, SqlError> {
+        let distinct = self.consume_keyword("DISTINCT");
+        if !distinct {
+            self.consume_keyword("ALL");
+        }
+
+        let mut columns = Vec::new();
+        loop {
+            columns.push(self.result_column()?);
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+
+        let from = if self.consume_keyword("FROM") {
+            Some(self.from()?)
+        } else {
+            None
+        };
+        let selection = self.selection()?;
+
+        let mut group_by = Vec::new();
+        if self.consume_keyword("GROUP") {
+            self.expect_keyword("BY")?;
+            loop {
+                group_by.push(self.expression()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+        let having = if self.consume_keyword("HAVING") {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        Ok(Select {
+            with: None,
+            distinct,
+            columns,
+            from,
+            selection,
+            group_by,
+            having,
+            compound: Vec::new(),
+            order_by: Vec::new(),
+            limit: None,
+            offset: None,
+        })
+    }
+
+    fn from(&mut self) -> Result<From, SqlError> {
+        let table = self.table_reference()?;
+
+        let mut joins = Vec::new();
+        loop {
+            if self.consume(&Token::Comma) {
+                joins.push(Join {
+                    operator: JoinOperator::Inner,
+                    table: self.table_reference()?,
+                    constraint: JoinConstraint::None,
+                });
+                continue;
+            }
+
+            let natural = self.consume_keyword("NATURAL");
+            let operator = if self.consume_keyword("LEFT") {
+                self.consume_keyword("OUTER");
+                JoinOperator::Left
+            } else if self.consume_keyword("INNER") {
+                JoinOperator::Inner
+            } else if self.consume_keyword("CROSS") {
+                JoinOperator::Cross
+            } else if self.peek_keyword("JOIN") {
+                JoinOperator::Inner
+            } else if natural {
+                return Err(self.unexpected("JOIN"));
+            } else {
+                break;
+            };
+            self.expect_keyword("JOIN")?;
+
+            let table = self.table_reference()?;
+            let constraint = if natural {
+                JoinConstraint::Natural
+            } else if self.consume_keyword("ON") {
+                JoinConstraint::On(self.expression()?)
+            } else if self.consume_keyword("USING") {
+                JoinConstraint::Using(self.identifier_list()?)
+            } else {
+                JoinConstraint::None
+            };
+
+            joins.push(Join {
+                operator,
+                table,
+                constraint,
+            });
+        }
+
+        Ok(From { table, joins })
+    }
+
+    fn table_reference(&mut self) -> Result<TableReference, SqlError> {
+        let source = if self.consume(&Token::LeftParen) {
+            let select = self.subquery()?;
+            TableSource::Subquery(Box::new(select))
+        } else if self.peek_at(1) == Some(&Token::LeftParen) {
+            let name = self.identifier()?.to_lowercase();
+            self.expect(&Token::LeftParen)?;
+            let mut arguments = Vec::new();
+            if !self.consume(&Token::RightParen) {
+                loop {
+                    arguments.push(self.expression()?);
+                    if !self.consume(&Token::Comma) {
+                        break;
+                    }
+                }
+                self.expect(&Token::RightParen)?;
+            }
+            TableSource::Function { name, arguments }
+        } else {
+            TableSource::Table(self.identifier()?)
+        };
+        let alias = self.alias()?;
+        Ok(TableReference { source, alias })
+    }
+
+    // the rest of `(SELECT ...)` once the opening parenthesis has been consumed
+    fn subquery(&mut self) -> Result<Select, SqlError> {
+        let select = self.query()?;
+        self.expect(&Token::RightParen)?;
+        Ok(select)
+    }
+
+    // `AS name`, or just `name`
+    fn alias(&mut self) -> Result<Option<String>, SqlError> {
+        if self.consume_keyword("AS") {
+            return self.identifier().map(Some);
+        }
+        match self.peek() {
+            Some(Token::Identifier(word)) if !is_reserved(word) => self.identifier().map(Some),
+            Some(Token::QuotedIdentifier(_)) => self.identifier().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // a parenthesised, comma separated list of names
+    fn identifier_list(&mut self) -> Result<Vec<String>, SqlError> {
+        self.expect(&Token::LeftParen)?;
+        let mut names = Vec::new();
+        loop {
+            names.push(self.identifier()?);
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        self.expect(&Token::RightParen)?;
+        Ok(names)
+    }
+
+    fn result_column(&mut self) -> Result<ResultColumn, SqlError> {
+        if self.consume(&Token::Star) {
+            return Ok(ResultColumn::All);
+        }
+
+        let is_table_all =
+            self.peek_at(1) == Some(&Token::Dot) && self.peek_at(2) == Some(&Token::Star);
+        if is_table_all {
+            let table = self.identifier()?;
+            self.position += 2;
+            return Ok(ResultColumn::TableAll(table));
+        }
+
+        let expression = self.expression()?;
+        let alias = self.alias()?;
+
+        Ok(ResultColumn::Expression { expression, alias })
+    }
+
+    fn ordering_term(&mut self) -> Result<OrderingTerm, SqlError> {
+        let expression = self.expression()?;
+        let descending = if self.consume_keyword("DESC") {
+            true
+        } else {
+            self.consume_keyword("ASC");
+            false
+        };
+
+        let nulls_first = if self.consume_keyword("NULLS") {
+            if self.consume_keyword("FIRST") {
+                Some(true)
+            } else {
+                self.expect_keyword("LAST")?;
+                Some(false)
+            }
+        } else {
+            None
+        };
+
+        Ok(OrderingTerm {
+            expression,
+            descending,
+            nulls_first,
+        })
+    }
+
+    fn insert(&mut self) -> Result<Insert, SqlError> {
+        self.expect_keyword("INTO")?;
+        let table = self.identifier()?;
+        let columns = if self.peek() == Some(&Token::LeftParen) {
+            self.identifier_list()?
+        } else {
+            Vec::new()
+        };
+        self.expect_keyword("VALUES")?;
+
+        let mut values = Vec::new();
+        loop {
+            self.expect(&Token::LeftParen)?;
+            let mut row = vec![self.expression()?];
+            while self.consume(&Token::Comma) {
+                row.push(self.expression()?);
+            }
+            self.expect(&Token::RightParen)?;
+            values.push(row);
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+
+        Ok(Insert {
+            table,
+            columns,
+            values,
+        })
+    }
+
+    fn update(&mut self) -> Result<Update, SqlError> {
+        let table = self.identifier()?;
+        self.expect_keyword("SET")?;
+
+        let mut assignments = Vec::new();
+        loop {
+            let column = self.identifier()?;
+            self.expect(&Token::Equal)?;
+            assignments.push((column, self.expression()?));
+            if !self.consume(&Token::Comma) {
+                break;
+            }
+        }
+        let selection = self.selection()?;
+
+        Ok(Update {
+            table,
+            assignments,
+            selection,
+        })
+    }
+
+    fn delete(&mut self) -> Result<Delete, SqlError> {
+        self.expect_keyword("FROM")?;
+        let table = self.identifier()?;
+        let selection = self.selection()?;
+
+        Ok(Delete { table, selection })
+    }
+
+    fn create_index(&mut self) -> Result<CreateIndex, SqlError> {
+        let name = self.identifier()?;
+        self.expect_keyword("ON")?;
+        let table = self.identifier()?;
+        let columns = self.identifier_list()?;
+
+        Ok(CreateIndex {
+            name,
+            table,
+            columns,
+        })
+    }
+
+    // `CREATE VIEW`, `start` is the position of CREATE
+    fn create_view(&mut self, start: usize) -> Result<CreateView, SqlError> {
+        let if_not_exists = self.if_not_exists();
+        let name = self.identifier()?;
+        let columns = if self.peek() == Some(&Token::LeftParen) {
+            self.identifier_list()?
+        } else {
+            Vec::new()
+        };
+        self.expect_keyword("AS")?;
+        let select = self.query()?;
+        if !self.parameters.is_empty() {
+            return Err(SqlError::new("parameters are not allowed in views"));
+        }
+
+        Ok(CreateView {
+            name,
+            if_not_exists,
+            columns,
+            select: Box::new(select),
+            sql: self.source(start),
+        })
+    }
+
+    // `CREATE TRIGGER`, `start` is the position of CREATE
+    fn create_trigger(&mut self, start: usize) -> Result<CreateTrigger, SqlError> {
+        let if_not_exists = self.if_not_exists();
+        let name = self.identifier()?;
+
+        let timing = if self.consume_keyword("BEFORE") {
+            TriggerTiming::Before
+        } else if self.consume_keyword("AFTER") {
+            TriggerTiming::After
+        } else if self.consume_keyword("INSTEAD") {
+            self.expect_keyword("OF")?;
+            TriggerTiming::InsteadOf
+        } else {
+            TriggerTiming::Before
+        };
+        let event = if self.consume_keyword("INSERT") {
+            TriggerEvent::Insert
+        } else if self.consume_keyword("DELETE") {
+            TriggerEvent::Delete
+        } else if self.consume_keyword("UPDATE") {
+            let mut columns = Vec::new();
+            if self.consume_keyword("OF") {
+                loop {
+                    columns.push(self.identifier()?);
+                    if !self.consume(&Token::Comma) {
+                        break;
+                    }
+                }
+            }
+            TriggerEvent::Update(columns)
+        } else {
+            return Err(self.unexpected("INSERT, UPDATE or DELETE"));
+        };
+        self.expect_keyword("ON")?;
+        let table = self.identifier()?;
+
+        if self.consume_keyword("FOR") {
+            self.expect_keyword("EACH")?;
+            self.expect_keyword("ROW")?;
+        }
+        let when = if self.consume_keyword("WHEN") {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.expect_keyword("BEGIN")?;
+        let mut body = Vec::new();
+        while !self.consume_keyword("END") {
+            let allowed = ["INSERT", "UPDATE", "DELETE", "SELECT", "WITH"]
+                .iter()
+                .any(|keyword| self.peek_keyword(keyword));
+            if !allowed {
+                return Err(self.unexpected("INSERT, UPDATE, DELETE, SELECT or END"));
+            }
+            body.push(self.statement()?);
+            self.expect(&Token::Semicolon)?;
+        }
+        if body.is_empty() {
+            return Err(SqlError::new("a trigger needs at least one statement"));
+        }
+        if !self.parameters.is_empty() {
+            return Err(SqlError::new("parameters are not allowed in triggers"));
+        }
+
+        Ok(CreateTrigger {
+            name,
+            if_not_exists,
+            timing,
+            event,
+            table,
+            when,
+            body,
+            sql: self.source(start),
+        })
+    }
+
+    fn if_not_exists(&mut self) -> bool {
+        let if_not_exists = self.peek_keyword("IF")
+            && self.peek_at(1).is_some_and(|token| token.is_keyword("NOT"))
+            && self
+                .peek_at(2)
+                .is_some_and(|token| token.is_keyword("EXISTS"));
+        if if_not_exists {
+            self.position += 3;
+        }
+        if_not_exists
+    }
+
+    // IF isn't reserved, `IF EXISTS` is only special before a name
+    fn if_exists(&mut self) -> bool {
+        let if_exists = self.peek_keyword("IF")
+            && self
+                .peek_at(1)
+                .is_some_and(|token| token.is_keyword("EXISTS"));
+        if if_exists {
+            self.position += 2;
+        }
+        if_exists
+    }
+
+    fn alter_table(&mut self) -> Result<AlterTable, SqlError> {
+        let table = self.identifier()?;
+        let action = if self.consume_keyword("RENAME") {
+            if self.consume_keyword("TO") {
+                AlterAction::RenameTable(self.identifier()?)
+            } else {
+                self.consume_keyword("COLUMN");
+                let column = self.identifier()?;
+                self.expect_keyword("TO")?;
+                let new_name = self.identifier()?;
+                AlterAction::RenameColumn { column, new_name }
+            }
+        } else if self.consume_keyword("ADD") {
+            self.consume_keyword("COLUMN");
+            self.column_definition()?
+        } else if self.consume_keyword("DROP") {
+            self.consume_keyword("COLUMN");
+            AlterAction::DropColumn(self.identifier()?)
+        } else {
+            return Err(self.unexpected("RENAME, ADD or DROP"));
+        };
+
+        Ok(AlterTable { table, action })
+    }
+
+    // `name [type] [DEFAULT value]`
+    // the type is any number of words with an optional size, such as `VARCHAR(20)`, given
+    // as it was written along with the affinity it declares
+    fn type_name(&mut self) -> Result<(String, ColumnType), SqlError> {
+        let mut words = Vec::new();
+        while let Some(Token::Identifier(word)) = self.peek().cloned() {
+            // `GENERATED ALWAYS AS` ends the type of a generated column
+            let generated = word.eq_ignore_ascii_case("GENERATED")
+                && self
+                    .peek_at(1)
+                    .is_some_and(|token| token.is_keyword("ALWAYS"));
+            if is_reserved(&word) || generated {
+                break;
+            }
+            words.push(word);
+            self.position += 1;
+        }
+        let mut type_name = words.join(" ");
+        let mut size = Vec::new();
+        if !words.is_empty() && self.consume(&Token::LeftParen) {
+            let mut written = Vec::new();
+            loop {
+                let negative = self.consume(&Token::Minus);
+                let sign = if negative { "-" } else { "" };
+                match self.peek() {
+                    Some(Token::Integer(int)) => {
+                        let int = if negative { int.wrapping_neg() } else { *int };
+                        size.push(int);
+                        written.push(int.to_string());
+                        self.position += 1;
+                    }
+                    Some(Token::Real(real)) => {
+                        written.push(format!("{}{}", sign, real));
+                        self.position += 1;
+                    }
+                    _ => return Err(self.unexpected("type size")),
+                }
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(&Token::RightParen)?;
+            type_name = format!("{}({})", type_name, written.join(", "));
+        }
+        let column_type = column_type(&words.join(" "), &size)?;
+        Ok((type_name, column_type))
+    }
+
+    fn column_definition(&mut self) -> Result<AlterAction, SqlError> {
+        let name = self.identifier()?;
+        let (_type_name, column_type) = self.type_name()?;
+
+        // the DEFAULT, COLLATE and generated clauses can come in any order
+        let mut default = None;
+        let mut collation = None;
+        let mut generated = None;
+        loop {
+            if default.is_none() && self.consume_keyword("DEFAULT") {
+                default = Some(if self.consume(&Token::LeftParen) {
+                    let default = self.expression()?;
+                    self.expect(&Token::RightParen)?;
+                    default
+                } else {
+                    // a COLLATE after an unparenthesised default is the column's
+                    match self.unary()? {
+                        Expression::Collate {
+                            operand,
+                            collation: name,
+                        } => {
+                            collation = Some(name);
+                            *operand
+                        }
+                        default => default,
+                    }
+                });
+            } else if collation.is_none() && self.consume_keyword("COLLATE") {
+                collation = Some(self.identifier()?);
+            } else if generated.is_none()
+                && (self.peek_keyword("GENERATED") || self.peek_keyword("AS"))
+            {
+                if self.consume_keyword("GENERATED") {
+                    self.expect_keyword("ALWAYS")?;
+                }
+                self.expect_keyword("AS")?;
+                self.expect(&Token::LeftParen)?;
+                let expression = self.expression()?;
+                self.expect(&Token::RightParen)?;
+                let stored = if self.consume_keyword("STORED") {
+                    true
+                } else {
+                    self.consume_keyword("VIRTUAL");
+                    false
+                };
+                generated = Some((expression, stored));
+            } else {
+                break;
+            }
+        }
+
+        Ok(AlterAction::AddColumn {
+            name,
+            column_type,
+            default,
+            collation,
+            generated,
+        })
+    }
+
+    fn selection(&mut self) -> Result<Option<Expression>, SqlError> {
+        if self.consume_keyword("WHERE") {
+            self.expression().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expression, SqlError> {
+        self.or()
+    }
+
+    fn or(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.and()?;
+        while self.consume_keyword("OR") {
+            let right = self.and()?;
+            left = binary(BinaryOperator::Or, left, right);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.not()?;
+        while self.consume_keyword("AND") {
+            let right = self.not()?;
+            left = binary(BinaryOperator::And, left, right);
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expression, SqlError> {
+        if self.consume_keyword("NOT") {
+            let operand = self.not()?;
+            Ok(Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand: Box::new(operand),
+            })
+        } else {
+            self.equality()
+        }
+    }
+
+    fn equality(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.comparison()?;
+
+        loop {
+            if self.consume(&Token::Equal) {
+                let right = self.comparison()?;
+                left = binary(BinaryOperator::Equal, left, right);
+            } else if self.consume(&Token::NotEqual) {
+                let right = self.comparison()?;
+                left = binary(BinaryOperator::NotEqual, left, right);
+            } else if self.consume_keyword("IS") {
+                let negated = self.consume_keyword("NOT");
+                let right = self.comparison()?;
+                let operator = if negated {
+                    BinaryOperator::IsNot
+                } else {
+                    BinaryOperator::Is
+                };
+                left = binary(operator, left, right);
+            } else if self.consume_keyword("ISNULL") {
+                left = is_null(left, false);
+            } else if self.consume_keyword("NOTNULL") {
+                left = is_null(left, true);
+            } else if self.peek_keyword("NOT")
+                && self.peek_at(1).is_some_and(|t| t.is_keyword("NULL"))
+            {
+                self.position += 2;
+                left = is_null(left, true);
+            } else {
+                let negated = self.peek_keyword("NOT");
+                let offset = negated as usize;
+                let keyword = match self.peek_at(offset) {
+                    Some(Token::Identifier(word)) => word.to_uppercase(),
+                    _ => break,
+                };
+                match keyword.as_str() {
+                    "IN" | "BETWEEN" | "LIKE" | "GLOB" => self.position += offset + 1,
+                    _ => break,
+                }
+                left = match keyword.as_str() {
+                    "IN" => self.in_list(left, negated)?,
+                    "BETWEEN" => self.between(left, negated)?,
+                    "LIKE" => self.like(left, negated)?,
+                    _ => Expression::Glob {
+                        operand: Box::new(left),
+                        pattern: Box::new(self.comparison()?),
+                        negated,
+                    },
+                };
+            }
+        }
+
+        Ok(left)
+    }
+
+    fn in_list(&mut self, operand: Expression, negated: bool) -> Result<Expression, SqlError> {
+        self.expect(&Token::LeftParen)?;
+        if self.peek_keyword("SELECT") || self.peek_keyword("WITH") {
+            let select = self.subquery()?;
+            return Ok(Expression::InSubquery {
+                operand: Box::new(operand),
+                select: Box::new(select),
+                negated,
+            });
+        }
+
+        let mut list = Vec::new();
+        if !self.consume(&Token::RightParen) {
+            loop {
+                list.push(self.expression()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+            self.expect(&Token::RightParen)?;
+        }
+
+        Ok(Expression::InList {
+            operand: Box::new(operand),
+            list,
+            negated,
+        })
+    }
+
+    fn between(&mut self, operand: Expression, negated: bool) -> Result<Expression, SqlError> {
+        let low = self.comparison()?;
+        self.expect_keyword("AND")?;
+        let high = self.comparison()?;
+
+        Ok(Expression::Between {
+            operand: Box::new(operand),
+            low: Box::new(low),
+            high: Box::new(high),
+            negated,
+        })
+    }
+
+    fn like(&mut self, operand: Expression, negated: bool) -> Result<Expression, SqlError> {
+        let pattern = self.comparison()?;
+        let escape = if self.consume_keyword("ESCAPE") {
+            Some(Box::new(self.comparison()?))
+        } else {
+            None
+        };
+
+        Ok(Expression::Like {
+            operand: Box::new(operand),
+            pattern: Box::new(pattern),
+            escape,
+            negated,
+        })
+    }
+
+    fn comparison(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.additive()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Less) => BinaryOperator::Less,
+                Some(Token::LessEqual) => BinaryOperator::LessEqual,
+                Some(Token::Greater) => BinaryOperator::Greater,
+                Some(Token::GreaterEqual) => BinaryOperator::GreaterEqual,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.additive()?;
+            left = binary(operator, left, right);
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.multiplicative()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Plus) => BinaryOperator::Add,
+                Some(Token::Minus) => BinaryOperator::Subtract,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.multiplicative()?;
+            left = binary(operator, left, right);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.concat()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Star) => BinaryOperator::Multiply,
+                Some(Token::Slash) => BinaryOperator::Divide,
+                Some(Token::Percent) => BinaryOperator::Modulo,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.concat()?;
+            left = binary(operator, left, right);
+        }
+    }
+
+    // `||`, `->` and `->>` bind tighter than the other binary operators
+    fn concat(&mut self) -> Result<Expression, SqlError> {
+        let mut left = self.unary()?;
+
+        loop {
+            let operator = match self.peek() {
+                Some(Token::Concat) => BinaryOperator::Concat,
+                Some(Token::Arrow) => BinaryOperator::Extract,
+                Some(Token::DoubleArrow) => BinaryOperator::ExtractValue,
+                _ => return Ok(left),
+            };
+            self.position += 1;
+            let right = self.unary()?;
+            left = binary(operator, left, right);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expression, SqlError> {
+        if self.peek() == Some(&Token::Minus) && self.peek_at(1) == Some(&Token::Integer(i128::MIN))
+        {
+            self.position += 2;
+            return Ok(Expression::Literal(Column::Integer(i128::MIN)));
+        }
+        let operator = match self.peek() {
+            Some(Token::Minus) => UnaryOperator::Negate,
+            Some(Token::Plus) => UnaryOperator::Plus,
+            _ => return self.collate(),
+        };
+        self.position += 1;
+        let operand = self.unary()?;
+
+        Ok(Expression::Unary {
+            operator,
+            operand: Box::new(operand),
+        })
+    }
+
+    // `COLLATE` binds tighter than any operator
+    fn collate(&mut self) -> Result<Expression, SqlError> {
+        let mut operand = self.primary()?;
+        while self.consume_keyword("COLLATE") {
+            operand = Expression::Collate {
+                operand: Box::new(operand),
+                collation: self.identifier()?,
+            };
+        }
+        Ok(operand)
+    }
+
+    fn primary(&mut self) -> Result<Expression, SqlError> {
+        match self.peek().cloned() {
+            Some(Token::Integer(i128::MIN)) => {
+                self.position += 1;
+                Ok(Expression::Literal(Column::Real(-(i128::MIN as f64))))
+            }
+            Some(Token::Integer(int)) => {
+                self.position += 1;
+                Ok(Expression::Literal(Column::Integer(int)))
+            }
+            Some(Token::Real(real)) => {
+                self.position += 1;
+                Ok(Expression::Literal(Column::Real(real)))
+            }
+            Some(Token::Text(text)) => {
+                self.position += 1;
+                Ok(Expression::Literal(Column::Text(text)))
+            }
+            Some(Token::Blob(blob)) => {
+                self.position += 1;
+                Ok(Expression::Literal(Column::Blob(blob)))
+            }
+            Some(Token::Parameter(name)) => {
+                self.position += 1;
+                let number = self.parameter_number(&name)?;
+                Ok(Expression::Parameter {
+                    number,
+                    name,
+                    value: Column::Null(),
+                })
+            }
+            Some(Token::LeftParen)
+                if self
+                    .peek_at(1)
+                    .is_some_and(|t| t.is_keyword("SELECT") || t.is_keyword("WITH")) =>
+            {
+                self.position += 1;
+                let select = self.subquery()?;
+                Ok(Expression::Subquery(Box::new(select)))
+            }
+            Some(Token::LeftParen) => {
+                self.position += 1;
+                let expression = self.expression()?;
+                self.expect(&Token::RightParen)?;
+                Ok(expression)
+            }
+            Some(ref token) if token.is_keyword("EXISTS") => {
+                self.position += 1;
+                self.expect(&Token::LeftParen)?;
+                let select = self.subquery()?;
+                Ok(Expression::Exists(Box::new(select)))
+            }
+            Some(ref token) if token.is_keyword("NULL") => {
+                self.position += 1;
+                Ok(Expression::Literal(Column::Null()))
+            }
+            Some(ref token) if token.is_keyword("CASE") => {
+                self.position += 1;
+                self.case()
+            }
+            Some(ref token) if token.is_keyword("CAST") => {
+                self.position += 1;
+                self.cast()
+            }
+            Some(Token::Identifier(_)) if self.peek_at(1) == Some(&Token::LeftParen) => {
+                self.function()
+            }
+            Some(Token::Identifier(_)) | Some(Token::QuotedIdentifier(_)) => self.column(),
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    // the rest of a CASE expression once `CASE` has been consumed
+    fn case(&mut self) -> Result<Expression, SqlError> {
+        let operand = if self.peek_keyword("WHEN") {
+            None
+        } else {
+            Some(Box::new(self.expression()?))
+        };
+        let mut branches = Vec::new();
+        while self.consume_keyword("WHEN") {
+            let when = self.expression()?;
+            self.expect_keyword("THEN")?;
+            branches.push((when, self.expression()?));
+        }
+        if branches.is_empty() {
+            return Err(self.unexpected("WHEN"));
+        }
+        let otherwise = if self.consume_keyword("ELSE") {
+            Some(Box::new(self.expression()?))
+        } else {
+            None
+        };
+        self.expect_keyword("END")?;
+        Ok(Expression::Case {
+            operand,
+            branches,
+            otherwise,
+        })
+    }
+
+    // the rest of `CAST(operand AS type)` once `CAST` has been consumed
+    fn cast(&mut self) -> Result<Expression, SqlError> {
+        self.expect(&Token::LeftParen)?;
+        let operand = self.expression()?;
+        self.expect_keyword("AS")?;
+        let (type_name, column_type) = self.type_name()?;
+        if type_name.is_empty() {
+            return Err(self.unexpected("type name"));
+        }
+        self.expect(&Token::RightParen)?;
+        Ok(Expression::Cast {
+            operand: Box::new(operand),
+            column_type,
+            type_name,
+        })
+    }
+
+    // `?` is numbered one more than the largest number so far, `?NNN` is numbered NNN and a
+    // named parameter keeps the number it was first given
+    fn parameter_number(&mut self, name: &str) -> Result<usize, SqlError> {
+        if name == "?" {
+            self.parameters.push(None);
+            return Ok(self.parameters.len());
+        }
+        if let Some(digits) = name.strip_prefix('?') {
+            let number = match digits.parse::<usize>() {
+                Ok(number) if (1..=MAX_PARAMETER).contains(&number) => number,
+                _ => {
+                    return Err(SqlError::new(format!(
+                        "variable number must be between ?1 and ?{}",
+                        MAX_PARAMETER
+                    )))
+                }
+            };
+            if self.parameters.len() < number {
+                self.parameters.resize(number, None);
+            }
+            self.parameters[number - 1].get_or_insert_with(|| name.to_string());
+            return Ok(number);
+        }
+
+        let existing = self
+            .parameters
+            .iter()
+            .position(|parameter| parameter.as_deref() == Some(name));
+        match existing {
+            Some(index) => Ok(index + 1),
+            None => {
+                self.parameters.push(Some(name.to_string()));
+                Ok(self.parameters.len())
+            }
+        }
+    }
+
+    fn function(&mut self) -> Result<Expression, SqlError> {
+        let name = self.identifier()?.to_lowercase();
+        self.expect(&Token::LeftParen)?;
+
+        let mut arguments = Vec::new();
+        let mut distinct = false;
+        let mut wildcard = false;
+        if self.consume(&Token::Star) {
+            wildcard = true;
+        } else if self.peek() != Some(&Token::RightParen) {
+            distinct = self.consume_keyword("DISTINCT");
+            loop {
+                arguments.push(self.expression()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+        self.expect(&Token::RightParen)?;
+
+        // aggregates registered on the database are marked when the statement is run
+        let call = Expression::Function {
+            aggregate: aggregate::is_aggregate(&name, arguments.len()),
+            name,
+            arguments,
+            distinct,
+            wildcard,
+        };
+        if self.consume_keyword("OVER") {
+            let window = self.window()?;
+            Ok(Expression::WindowFunction {
+                call: Box::new(call),
+                window: Box::new(window),
+            })
+        } else {
+            Ok(call)
+        }
+    }
+
+    fn window(&mut self) -> Result<Window, SqlError> {
+        self.expect(&Token::LeftParen)?;
+
+        let mut partition_by = Vec::new();
+        if self.consume_keyword("PARTITION") {
+            self.expect_keyword("BY")?;
+            loop {
+                partition_by.push(self.expression()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = Vec::new();
+        if self.consume_keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            loop {
+                order_by.push(self.ordering_term()?);
+                if !self.consume(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+
+        let units = if self.consume_keyword("ROWS") {
+            Some(FrameUnits::Rows)
+        } else if self.consume_keyword("RANGE") {
+            Some(FrameUnits::Range)
+        } else {
+            None
+        };
+        let frame = match units {
+            Some(units) => {
+                // a lone starting bound ends at the current row
+                let (start, end) = if self.consume_keyword("BETWEEN") {
+                    let start = self.frame_bound()?;
+                    self.expect_keyword("AND")?;
+                    (start, self.frame_bound()?)
+                } else {
+                    (self.frame_bound()?, FrameBound::CurrentRow)
+                };
+                Some(Frame { units, start, end })
+            }
+            None => None,
+        };
+
+        self.expect(&Token::RightParen)?;
+        Ok(Window {
+            partition_by,
+            order_by,
+            frame,
+        })
+    }
+
+    fn frame_bound(&mut self) -> Result<FrameBound, SqlError> {
+        if self.consume_keyword("UNBOUNDED") {
+            if self.consume_keyword("PRECEDING") {
+                return Ok(FrameBound::UnboundedPreceding);
+            }
+            self.expect_keyword("FOLLOWING")?;
+            return Ok(FrameBound::UnboundedFollowing);
+        }
+        if self.consume_keyword("CURRENT") {
+            self.expect_keyword("ROW")?;
+            return Ok(FrameBound::CurrentRow);
+        }
+
+        let offset = self.additive()?;
+        if self.consume_keyword("PRECEDING") {
+            Ok(FrameBound::Preceding(offset))
+        } else {
+            self.expect_keyword("FOLLOWING")?;
+            Ok(FrameBound::Following(offset))
+        }
+    }
+
+    fn column(&mut self) -> Result<Expression, SqlError> {
+        let name = self.identifier()?;
+
+        if self.consume(&Token::Dot) {
+            let column = self.identifier()?;
+            Ok(Expression::Column {
+                table: Some(name),
+                name: column,
+            })
+        } else {
+            Ok(Expression::Column { table: None, name })
+        }
+    }
+}
+
+fn is_reserved(word: &str) -> bool {
+    RESERVED
+        .iter()
+        .any(|keyword| keyword.eq_ignore_ascii_case(word))
+}
+
+// the affinity of a column, from the name it was declared with by the same rules as SQLite
+fn column_type(type_name: &str, size: &[i128]) -> Result<ColumnType, SqlError> {
+    let upper = type_name.to_ascii_uppercase();
+    // checked first, INTERVAL would otherwise be an integer
+    match upper.as_str() {
+        "DATE" => return Ok(ColumnType::Date),
+        "TIME" => return Ok(ColumnType::Time),
+        "TIMESTAMP" | "DATETIME" => return Ok(ColumnType::Timestamp),
+        "INTERVAL" => return Ok(ColumnType::Interval),
+        "JSON" => return Ok(ColumnType::Json),
+        // NUMERIC without a precision keeps sqlite's NUMERIC affinity
+        "DECIMAL" | "DEC" | "NUMERIC" if upper != "NUMERIC" || !size.is_empty() => {
+            return decimal_type(size)
+        }
+        _ => {}
+    }
+    Ok(if upper.contains("INT") {
+        ColumnType::Integer
+    } else if ["CHAR", "CLOB", "TEXT"]
+        .iter()
+        .any(|name| upper.contains(name))
+    {
+        ColumnType::Text
+    } else if upper.is_empty() || upper.contains("BLOB") {
+        ColumnType::Blob
+    } else if ["REAL", "FLOA", "DOUB"]
+        .iter()
+        .any(|name| upper.contains(name))
+    {
+        ColumnType::Real
+    } else {
+        ColumnType::Numeric
+    })
+}
+
+// `DECIMAL(precision, scale)`, by default with the most digits a decimal can have and none
+// after the point
+fn decimal_type(size: &[i128]) -> Result<ColumnType, SqlError> {
+    let precision = size.first().cloned().unwrap_or(MAX_PRECISION as i128);
+    let scale = size.get(1).cloned().unwrap_or(0);
+    if size.len() > 2
+        || !(1..=MAX_PRECISION as i128).contains(&precision)
+        || !(0..=precision).contains(&scale)
+    {
+        return Err(SqlError::new(format!(
+            "DECIMAL precision must be from 1 to {} and its scale from 0 to the precision",
+            MAX_PRECISION
+        )));
+    }
+    Ok(ColumnType::Decimal {
+        precision: precision as u8,
+        scale: scale as u8,
+    })
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+    Expression::Binary {
+        operator,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+fn is_null(operand: Expression, negated: bool) -> Expression {
+    Expression::IsNull {
+        operand: Box::new(operand),
+        negated,
+    }
+}
@@ -0,0 +1,407 @@
+use super::expression::{
+    comparison_affinity, resolve, BinaryOperator, ColumnName, Expression, Row,
+};
+use super::statement::{From, JoinConstraint, JoinOperator, TableReference, TableSource};
+use backend::database::{ColumnType, Database, Index, TableStats};
+use std::error;
+
+/// How the rows of a table are found.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    /// every row on the table's rootpage is read
+    Scan,
+    /// rows are found by searching an index, `key` is evaluated for each outer row and
+    /// converted by `affinities` as the comparisons it stands for would convert it
+    IndexSearch {
+        index: Index,
+        key: Vec<Expression>,
+        affinities: Vec<Option<ColumnType>>,
+    },
+}
+
+/// A table of a FROM clause made up of inner joins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinInput {
+    /// the stored table the rows are read from, None for derived and common tables
+    pub table: Option<String>,
+    pub names: Vec<ColumnName>,
+}
+
+/// A table in the order the tables of a FROM clause are joined, with how its rows are found
+/// and the condition checked as they are joined on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JoinStep {
+    pub input: usize,
+    pub access: Access,
+    pub condition: Option<Expression>,
+}
+
+/// An access path with its expected cost and the rows it finds, for each outer row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessPlan {
+    pub access: Access,
+    pub cost: f64,
+    pub rows: f64,
+}
+
+// without statistics a table is taken to be large and its index keys to be selective, so
+// an index is always searched when it can be
+const DEFAULT_ROWS: f64 = 1_000_000.0;
+const DEFAULT_ROWS_PER_KEY: f64 = 10.0;
+// the share of a scanned table's rows expected to meet a join condition
+const CONDITION_SELECTIVITY: f64 = 0.25;
+
+/// Picks how to find the rows of the inner table of a join, using the statistics ANALYZE
+/// gathered for the table when there are any. The first table of a FROM clause has no outer
+/// columns, its index keys can only be values known before it's read.
+pub fn choose_access(
+    database: &mut Database,
+    table_name: &str,
+    outer_names: &[ColumnName],
+    inner_names: &[ColumnName],
+    condition: Option<&Expression>,
+) -> Result<Access, Box<dyn error::Error>> {
+    let stats = database.table_stats(table_name)?;
+    let plan = plan_access(
+        database,
+        table_name,
+        stats.as_ref(),
+        outer_names,
+        inner_names,
+        condition,
+    )?;
+    Ok(plan.access)
+}
+
+/// Costs the ways of finding the rows of a join's inner table and picks the cheapest. A scan
+/// reads every row, an index search costs a descent of the index plus the rows sharing its
+/// key. An index can be searched when the join condition has equality terms for its leading
+/// columns that only depend on the outer row.
+pub fn plan_access(
+    database: &mut Database,
+    table_name: &str,
+    stats: Option<&TableStats>,
+    outer_names: &[ColumnName],
+    inner_names: &[ColumnName],
+    condition: Option<&Expression>,
+) -> Result<AccessPlan, Box<dyn error::Error>> {
+    let row_count = stats.map_or(DEFAULT_ROWS, |stats| stats.row_count as f64);
+    let mut best = AccessPlan {
+        access: Access::Scan,
+        cost: row_count,
+        rows: match condition {
+            Some(_) => row_count * CONDITION_SELECTIVITY,
+            None => row_count,
+        },
+    };
+
+    let mut equalities = Vec::new();
+    if let Some(condition) = condition {
+        for term in conjuncts(condition) {
+            if let Some(equality) = inner_equality(term, outer_names, inner_names) {
+                equalities.push(equality);
+            }
+        }
+    }
+    if equalities.is_empty() {
+        return Ok(best);
+    }
+
+    for index in database.list_indexes(table_name.to_string())? {
+        let mut key = Vec::new();
+        let mut affinities = Vec::new();
+        for column in &index.columns {
+            let equality = equalities
+                .iter()
+                .find(|(name, _, _)| name.eq_ignore_ascii_case(column));
+            match equality {
+                Some((_, expression, affinity)) => {
+                    key.push((*expression).clone());
+                    affinities.push(affinity.clone());
+                }
+                None => break,
+            }
+        }
+        if key.is_empty() {
+            continue;
+        }
+
+        let rows = rows_per_key(stats, &index.name, key.len(), row_count);
+        let cost = row_count.max(2.0).log2() + rows;
+        if cost < best.cost {
+            best = AccessPlan {
+                access: Access::IndexSearch {
+                    index,
+                    key,
+                    affinities,
+                },
+                cost,
+                rows,
+            };
+        }
+    }
+    Ok(best)
+}
+
+// the rows expected to share a value of the index's first `length` columns, each further
+// column is guessed to divide them by ten when the index hasn't been analyzed
+fn rows_per_key(
+    stats: Option<&TableStats>,
+    index_name: &str,
+    length: usize,
+    row_count: f64,
+) -> f64 {
+    let analyzed = stats
+        .and_then(|stats| stats.rows_per_key(index_name))
+        .and_then(|rows| rows.get(length - 1));
+    match analyzed {
+        Some(rows) => *rows as f64,
+        None => (DEFAULT_ROWS_PER_KEY / 10f64.powi(length as i32 - 1)).min(row_count),
+    }
+}
+
+/// Whether the tables of a FROM clause can be joined in any order, which they can when they
+/// are all inner joins whose conditions are given by ON. Table-valued functions read the
+/// tables before them, so they keep their place.
+pub fn reorderable(from: &From) -> bool {
+    let function = |table: &TableReference| matches!(table.source, TableSource::Function { .. });
+    !from.joins.is_empty()
+        && !function(&from.table)
+        && !from.joins.iter().any(|join| function(&join.table))
+        && from.joins.iter().all(|join| {
+            join.operator != JoinOperator::Left
+                && matches!(
+                    join.constraint,
+                    JoinConstraint::On(_) | JoinConstraint::None
+                )
+        })
+}
+
+/// Orders the tables of a reorderable FROM clause and picks how each is read. The tables are
+/// kept in the order they are written unless ANALYZE has gathered statistics for all of
+/// them, then the smallest is read first and the table that is cheapest to join onto the
+/// rows so far is added next. Each term of the ON conditions, and of the WHERE clause when it
+/// only reads the joined tables, is checked as soon as the tables it reads are joined.
+pub fn plan_joins(
+    database: &mut Database,
+    inputs: &[JoinInput],
+    from: &From,
+    selection: Option<&Expression>,
+) -> Result<Vec<JoinStep>, Box<dyn error::Error>> {
+    let names: Vec<ColumnName> = inputs
+        .iter()
+        .flat_map(|input| input.names.iter().cloned())
+        .collect();
+    let owners: Vec<usize> = inputs
+        .iter()
+        .enumerate()
+        .flat_map(|(number, input)| std::iter::repeat_n(number, input.names.len()))
+        .collect();
+    let every_input: Vec<usize> = (0..inputs.len()).collect();
+
+    // each term with the tables it reads, ON terms that can't be placed wait for every table
+    let mut terms = Vec::new();
+    for join in &from.joins {
+        if let JoinConstraint::On(condition) = &join.constraint {
+            for term in conjuncts(condition) {
+                let reads =
+                    read_inputs(term, &names, &owners).unwrap_or_else(|| every_input.clone());
+                terms.push((term, reads));
+            }
+        }
+    }
+    if let Some(selection) = selection {
+        for term in conjuncts(selection) {
+            if let Some(reads) = read_inputs(term, &names, &owners) {
+                terms.push((term, reads));
+            }
+        }
+    }
+
+    let mut stats = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        stats.push(match &input.table {
+            Some(table_name) => database.table_stats(table_name)?,
+            None => None,
+        });
+    }
+    let analyzed = stats.iter().all(Option::is_some);
+
+    let mut remaining = every_input;
+    let mut joined: Vec<usize> = Vec::new();
+    let mut joined_names: Vec<ColumnName> = Vec::new();
+    let mut applied = vec![false; terms.len()];
+    let mut steps = Vec::with_capacity(inputs.len());
+
+    while !remaining.is_empty() {
+        let candidates = if analyzed { remaining.len() } else { 1 };
+        let mut best: Option<(usize, AccessPlan, Vec<usize>)> = None;
+
+        for (position, &input) in remaining.iter().enumerate().take(candidates) {
+            let ready: Vec<usize> = (0..terms.len())
+                .filter(|&term| {
+                    !applied[term]
+                        && terms[term]
+                            .1
+                            .iter()
+                            .all(|read| *read == input || joined.contains(read))
+                })
+                .collect();
+
+            let plan = match &inputs[input].table {
+                Some(table_name) => plan_access(
+                    database,
+                    table_name,
+                    stats[input].as_ref(),
+                    &joined_names,
+                    &inputs[input].names,
+                    and_all(ready.iter().map(|&term| terms[term].0)).as_ref(),
+                )?,
+                _ => {
+                    let rows = stats[input]
+                        .as_ref()
+                        .map_or(DEFAULT_ROWS, |stats| stats.row_count as f64);
+                    AccessPlan {
+                        access: Access::Scan,
+                        cost: rows,
+                        rows,
+                    }
+                }
+            };
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_plan, _)| plan.cost < best_plan.cost)
+            {
+                best = Some((position, plan, ready));
+            }
+        }
+
+        let (position, plan, ready) = best.unwrap();
+        let input = remaining.remove(position);
+        for &term in &ready {
+            applied[term] = true;
+        }
+        joined.push(input);
+        joined_names.extend(inputs[input].names.iter().cloned());
+        steps.push(JoinStep {
+            input,
+            access: plan.access,
+            condition: and_all(ready.iter().map(|&term| terms[term].0)),
+        });
+    }
+    Ok(steps)
+}
+
+// the inputs a term reads, None when that can't be told: its columns don't resolve, or it
+// has subqueries or function calls that could read anything
+fn read_inputs(
+    expression: &Expression,
+    names: &[ColumnName],
+    owners: &[usize],
+) -> Option<Vec<usize>> {
+    match expression {
+        Expression::Column { table, name } => resolve(names, table, name)
+            .ok()
+            .map(|index| vec![owners[index]]),
+        Expression::Subquery(_)
+        | Expression::InSubquery { .. }
+        | Expression::Exists(_)
+        | Expression::Function { .. }
+        | Expression::WindowFunction { .. } => None,
+        _ => {
+            let mut reads = Vec::new();
+            for child in expression.children() {
+                for input in read_inputs(child, names, owners)? {
+                    if !reads.contains(&input) {
+                        reads.push(input);
+                    }
+                }
+            }
+            Some(reads)
+        }
+    }
+}
+
+fn and_all<'a, I: Iterator<Item = &'a Expression>>(terms: I) -> Option<Expression> {
+    terms.cloned().reduce(|left, right| Expression::Binary {
+        operator: BinaryOperator::And,
+        left: Box::new(left),
+        right: Box::new(right),
+    })
+}
+
+/// Splits a condition into the terms that are joined by AND.
+pub fn conjuncts(expression: &Expression) -> Vec<&Expression> {
+    match expression {
+        Expression::Binary {
+            operator: BinaryOperator::And,
+            left,
+            right,
+        } => {
+            let mut terms = conjuncts(left);
+            terms.extend(conjuncts(right));
+            terms
+        }
+        _ => vec![expression],
+    }
+}
+
+// a term of the form `inner_column = outer_expression`, in either order, that compares by the
+// inner column's collation as its index does, with the affinity the outer value is converted
+// by. Comparisons that would convert the inner column's values can't search its index.
+fn inner_equality<'a>(
+    term: &'a Expression,
+    outer_names: &[ColumnName],
+    inner_names: &[ColumnName],
+) -> Option<(String, &'a Expression, Option<ColumnType>)> {
+    if let Expression::Binary {
+        operator: BinaryOperator::Equal,
+        left,
+        right,
+    } = term
+    {
+        for (column, other) in [(left, right), (right, left)] {
+            if let Expression::Column { table, name } = column.as_ref() {
+                let is_inner = resolve(inner_names, table, name).is_ok()
+                    && (table.is_some() || resolve(outer_names, table, name).is_err());
+                if !is_inner || !depends_only_on(other, outer_names, inner_names) {
+                    continue;
+                }
+                let inner = &inner_names[resolve(inner_names, table, name).ok()?];
+                let (inner_affinity, other_affinity) = comparison_affinity(
+                    inner.affinity.clone(),
+                    Row::new(outer_names, &[]).affinity(other),
+                );
+                if inner_affinity.is_none() && same_collation(inner, other, outer_names) {
+                    return Some((name.clone(), other.as_ref(), other_affinity));
+                }
+            }
+        }
+    }
+    None
+}
+
+// whether the collation an outer expression brings to a comparison, if any, is the one
+// declared on the inner column
+fn same_collation(inner: &ColumnName, other: &Expression, outer_names: &[ColumnName]) -> bool {
+    let inner_collation = inner.collation.as_deref().unwrap_or("BINARY");
+    match Row::new(outer_names, &[]).collation_name(other) {
+        Some((name, _)) => name.eq_ignore_ascii_case(inner_collation),
+        None => true,
+    }
+}
+
+// whether every column the expression refers to comes from `names` and not `others`,
+// subqueries could refer to anything
+fn depends_only_on(expression: &Expression, names: &[ColumnName], others: &[ColumnName]) -> bool {
+    match expression {
+        Expression::Column { table, name } => {
+            resolve(names, table, name).is_ok() && resolve(others, table, name).is_err()
+        }
+        Expression::Subquery(_) | Expression::InSubquery { .. } | Expression::Exists(_) => false,
+        _ => expression
+            .children()
+            .iter()
+            .all(|child| depends_only_on(child, names, others)),
+    }
+}
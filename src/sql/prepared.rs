@@ -0,0 +1,142 @@
+use super::executor::{self, ResultSet};
+use super::expression::Expression;
+use super::parser;
+use super::statement;
+use super::SqlError;
+use backend::database::{Column, Database};
+use std::error;
+use std::vec;
+
+/// A statement parsed once and run any number of times with different values bound to its
+/// parameters. Parameters are numbered from 1 and are NULL until a value is bound.
+#[derive(Debug, Clone)]
+pub struct Statement {
+    statement: statement::Statement,
+    /// the name of each parameter by number, None for those written `?`
+    parameters: Vec<Option<String>>,
+    values: Vec<Column>,
+    /// the names of the result columns of the latest run
+    columns: Vec<String>,
+    /// the rows of the current run still to be stepped through
+    rows: Option<vec::IntoIter<Vec<Column>>>,
+}
+
+impl Database {
+    /// Parses a statement to be run with `Statement::step` or `Statement::query`.
+    pub fn prepare(&self, sql: &str) -> Result<Statement, Box<dyn error::Error>> {
+        let (statement, parameters) = parser::parse_prepared(sql)?;
+        Ok(Statement {
+            statement,
+            values: vec![Column::Null(); parameters.len()],
+            parameters,
+            columns: Vec::new(),
+            rows: None,
+        })
+    }
+}
+
+impl Statement {
+    /// The largest parameter number used by the statement.
+    pub fn parameter_count(&self) -> usize {
+        self.parameters.len()
+    }
+
+    /// The name of a parameter as written, such as `:id` or `?2`, None for `?`.
+    pub fn parameter_name(&self, number: usize) -> Option<&str> {
+        match number.checked_sub(1) {
+            Some(index) => self.parameters.get(index)?.as_deref(),
+            None => None,
+        }
+    }
+
+    /// The number of the parameter with the given name, which includes its prefix.
+    pub fn parameter_number(&self, name: &str) -> Option<usize> {
+        self.parameters
+            .iter()
+            .position(|parameter| parameter.as_deref() == Some(name))
+            .map(|index| index + 1)
+    }
+
+    pub fn bind(&mut self, number: usize, value: Column) -> Result<(), SqlError> {
+        if self.rows.is_some() {
+            return Err(SqlError::new(
+                "cannot bind a running statement, reset it first",
+            ));
+        }
+        if number == 0 || number > self.values.len() {
+            return Err(SqlError::new(format!(
+                "parameter {} is out of range, the statement has {}",
+                number,
+                self.values.len()
+            )));
+        }
+        self.values[number - 1] = value;
+        Ok(())
+    }
+
+    pub fn bind_named(&mut self, name: &str, value: Column) -> Result<(), SqlError> {
+        match self.parameter_number(name) {
+            Some(number) => self.bind(number, value),
+            None => Err(SqlError::new(format!("no parameter named {}", name))),
+        }
+    }
+
+    /// Sets every parameter back to NULL.
+    pub fn clear_bindings(&mut self) {
+        for value in self.values.iter_mut() {
+            *value = Column::Null();
+        }
+    }
+
+    /// The next row of the result. The first step runs the statement, once every row has
+    /// been returned the statement is reset and the next step runs it again.
+    pub fn step(
+        &mut self,
+        database: &mut Database,
+    ) -> Result<Option<Vec<Column>>, Box<dyn error::Error>> {
+        if self.rows.is_none() {
+            let result = executor::execute(database, self.bound())?;
+            self.columns = result.columns;
+            self.rows = Some(result.rows.into_iter());
+        }
+
+        let row = self.rows.as_mut().and_then(|rows| rows.next());
+        if row.is_none() {
+            self.reset();
+        }
+        Ok(row)
+    }
+
+    /// Runs the statement from the start and returns all of its rows.
+    pub fn query(&mut self, database: &mut Database) -> Result<ResultSet, Box<dyn error::Error>> {
+        self.reset();
+        let result = executor::execute(database, self.bound())?;
+        self.columns = result.columns.clone();
+        Ok(result)
+    }
+
+    /// Abandons the current run, the values bound to the parameters are kept.
+    pub fn reset(&mut self) {
+        self.rows = None;
+    }
+
+    /// The names of the result columns, known once the statement has run.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    // the parsed statement with the bound values filled in
+    fn bound(&self) -> statement::Statement {
+        let mut statement = self.statement.clone();
+        bind_statement(&mut statement, &self.values);
+        statement
+    }
+}
+
+fn bind_statement(statement: &mut statement::Statement, values: &[Column]) {
+    statement.visit_expressions_mut(&mut |expression| {
+        if let Expression::Parameter { number, value, .. } = expression {
+            *value = values[*number - 1].clone();
+        }
+    });
+}
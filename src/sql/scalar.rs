@@ -0,0 +1,616 @@
+use super::expression::{compare, quote as quote_value, to_numeric, to_text};
+use super::SqlError;
+use backend::database::Column;
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// the largest printf() width and precision, Rust's formatting takes precisions up to
+// u16::MAX and %g adds up to 4 digits to the one it's given
+const PRINTF_LIMIT: usize = 65_000;
+
+thread_local! {
+    // the state of the generator behind random(), seeded from the clock
+    static RANDOM_STATE: Cell<u64> = Cell::new(random_seed());
+}
+
+/// `abs(x)`, the absolute value of a number. Text and blobs are read as reals.
+pub fn abs(arguments: &[Column]) -> Result<Column, SqlError> {
+    let value = match &arguments[0] {
+        Column::Null() => Column::Null(),
+        Column::Integer(int) => match int.checked_abs() {
+            Some(int) => Column::Integer(int),
+            None => return Err(SqlError::new("integer overflow")),
+        },
+        Column::Real(real) => Column::Real(real.abs()),
+        Column::Decimal(decimal) if decimal.value < 0 => match decimal.negate() {
+            Some(decimal) => Column::Decimal(decimal),
+            None => Column::Real(-decimal.to_real()),
+        },
+        Column::Decimal(decimal) => Column::Decimal(*decimal),
+        value => Column::Real(real_of(value).abs()),
+    };
+    Ok(value)
+}
+
+/// `round(x, digits)`, the number rounded to `digits` places after the point, halves away
+/// from zero. The result is a real, or a decimal when the number is one.
+pub fn round(arguments: &[Column]) -> Result<Column, SqlError> {
+    let digits = match arguments.get(1) {
+        Some(Column::Null()) => return Ok(Column::Null()),
+        Some(digits) => integer_of(digits).clamp(0, 30) as i32,
+        None => 0,
+    };
+    let value = match &arguments[0] {
+        Column::Null() => Column::Null(),
+        Column::Decimal(decimal) if (digits as u8) < decimal.scale => {
+            match decimal.rescale(digits as u8) {
+                Some(decimal) => Column::Decimal(decimal),
+                None => Column::Decimal(*decimal),
+            }
+        }
+        Column::Decimal(decimal) => Column::Decimal(*decimal),
+        value => {
+            let real = real_of(value);
+            // reals this large have no fraction left to round, and reals are only exact to
+            // about 15 digits, past which scaling them adds error rather than removing it
+            if real.abs() >= 4503599627370496.0 || digits >= 15 {
+                Column::Real(real)
+            } else {
+                let factor = 10f64.powi(digits);
+                Column::Real((real * factor).round() / factor)
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// `length(x)`, the number of characters of text, or of bytes of a blob. Numbers are
+/// measured as text.
+pub fn length(arguments: &[Column]) -> Result<Column, SqlError> {
+    let length = match &arguments[0] {
+        Column::Null() => return Ok(Column::Null()),
+        Column::Blob(blob) => blob.len(),
+        value => to_text(value).chars().count(),
+    };
+    Ok(Column::Integer(length as i128))
+}
+
+/// `lower(x)`, the text with its ASCII letters in lower case, as the NOCASE collation sees
+/// them.
+pub fn lower(arguments: &[Column]) -> Result<Column, SqlError> {
+    text_function(&arguments[0], |text| text.to_ascii_lowercase())
+}
+
+/// `upper(x)`, the text with its ASCII letters in upper case.
+pub fn upper(arguments: &[Column]) -> Result<Column, SqlError> {
+    text_function(&arguments[0], |text| text.to_ascii_uppercase())
+}
+
+/// `substr(x, start, length)`, also called `substring`, the part of the text from the
+/// `start`th character, counting from 1, or from the end when it's negative. Without a
+/// length it runs to the end, a negative length takes the characters before `start`. Blobs
+/// are cut by bytes.
+pub fn substr(arguments: &[Column]) -> Result<Column, SqlError> {
+    if any_null(arguments) {
+        return Ok(Column::Null());
+    }
+    let size = match &arguments[0] {
+        Column::Blob(blob) => blob.len(),
+        value => to_text(value).chars().count(),
+    } as i128;
+    let (start, count) = substr_range(
+        size,
+        integer_of(&arguments[1]),
+        arguments.get(2).map(integer_of),
+    );
+
+    let value = match &arguments[0] {
+        Column::Blob(blob) => Column::Blob(blob.iter().skip(start).take(count).cloned().collect()),
+        value => Column::Text(to_text(value).chars().skip(start).take(count).collect()),
+    };
+    Ok(value)
+}
+
+// the first character and number of characters substr() takes, following SQLite
+fn substr_range(size: i128, start: i128, length: Option<i128>) -> (usize, usize) {
+    let mut start = start;
+    let (mut count, before) = match length {
+        Some(length) if length < 0 => (length.saturating_neg(), true),
+        Some(length) => (length, false),
+        None => (size, false),
+    };
+    if start < 0 {
+        start += size;
+        if start < 0 {
+            count = (count + start).max(0);
+            start = 0;
+        }
+    } else if start > 0 {
+        start -= 1;
+    } else if count > 0 {
+        // there is no character 0, so it takes one fewer
+        count -= 1;
+    }
+    if before {
+        start -= count;
+        if start < 0 {
+            count += start;
+            start = 0;
+        }
+    }
+    let start = start.min(size);
+    let count = count.max(0).min(size - start);
+    (start as usize, count as usize)
+}
+
+/// `trim(x, characters)`, the text without the characters at either end, spaces when none
+/// are given.
+pub fn trim(arguments: &[Column]) -> Result<Column, SqlError> {
+    trim_function(arguments, true, true)
+}
+
+/// `ltrim(x, characters)`, like `trim` but only at the start of the text.
+pub fn ltrim(arguments: &[Column]) -> Result<Column, SqlError> {
+    trim_function(arguments, true, false)
+}
+
+/// `rtrim(x, characters)`, like `trim` but only at the end of the text.
+pub fn rtrim(arguments: &[Column]) -> Result<Column, SqlError> {
+    trim_function(arguments, false, true)
+}
+
+fn trim_function(arguments: &[Column], start: bool, end: bool) -> Result<Column, SqlError> {
+    if any_null(arguments) {
+        return Ok(Column::Null());
+    }
+    let characters: Vec<char> = match arguments.get(1) {
+        Some(characters) => to_text(characters).chars().collect(),
+        None => vec![' '],
+    };
+    let text = to_text(&arguments[0]);
+    let mut trimmed = text.as_str();
+    if start {
+        trimmed = trimmed.trim_start_matches(&characters[..]);
+    }
+    if end {
+        trimmed = trimmed.trim_end_matches(&characters[..]);
+    }
+    Ok(Column::Text(trimmed.to_string()))
+}
+
+/// `replace(x, pattern, replacement)`, the text with every occurrence of the pattern
+/// replaced. An empty pattern leaves the text as it is.
+pub fn replace(arguments: &[Column]) -> Result<Column, SqlError> {
+    if any_null(arguments) {
+        return Ok(Column::Null());
+    }
+    let text = to_text(&arguments[0]);
+    let pattern = to_text(&arguments[1]);
+    if pattern.is_empty() {
+        return Ok(Column::Text(text));
+    }
+    Ok(Column::Text(
+        text.replace(&pattern, &to_text(&arguments[2])),
+    ))
+}
+
+/// `instr(x, y)`, the position of the first `y` in `x` counting from 1, or 0 when there is
+/// none. Positions in blobs are counted in bytes, otherwise in characters.
+pub fn instr(arguments: &[Column]) -> Result<Column, SqlError> {
+    let position = match (&arguments[0], &arguments[1]) {
+        (Column::Null(), _) | (_, Column::Null()) => return Ok(Column::Null()),
+        (Column::Blob(haystack), Column::Blob(needle)) => match needle.len() {
+            0 => 1,
+            size => haystack
+                .windows(size)
+                .position(|window| window == &needle[..])
+                .map_or(0, |index| index + 1),
+        },
+        (haystack, needle) => {
+            let haystack = to_text(haystack);
+            match haystack.find(&to_text(needle)) {
+                Some(index) => haystack[..index].chars().count() + 1,
+                None => 0,
+            }
+        }
+    };
+    Ok(Column::Integer(position as i128))
+}
+
+/// `coalesce(x, y, ...)`, the first of the arguments that isn't NULL. `ifnull(x, y)` is the
+/// same with two arguments.
+pub fn coalesce(arguments: &[Column]) -> Result<Column, SqlError> {
+    let value = arguments
+        .iter()
+        .find(|value| **value != Column::Null())
+        .cloned()
+        .unwrap_or(Column::Null());
+    Ok(value)
+}
+
+/// `nullif(x, y)`, NULL when the arguments are the same, otherwise `x`.
+pub fn nullif(arguments: &[Column]) -> Result<Column, SqlError> {
+    match compare(&arguments[0], &arguments[1]) {
+        Ordering::Equal => Ok(Column::Null()),
+        _ => Ok(arguments[0].clone()),
+    }
+}
+
+/// `typeof(x)`, the type of the value: null, integer, real, decimal, text or blob.
+pub fn type_of(arguments: &[Column]) -> Result<Column, SqlError> {
+    let name = match &arguments[0] {
+        Column::Null() => "null",
+        Column::Integer(_) => "integer",
+        Column::Real(_) => "real",
+        Column::Decimal(_) => "decimal",
+        Column::Text(_) => "text",
+        Column::Blob(_) => "blob",
+    };
+    Ok(Column::Text(name.to_string()))
+}
+
+/// `hex(x)`, the bytes of a blob, or of the text of any other value, in upper case
+/// hexadecimal. Like SQLite it gives empty text for NULL.
+pub fn hex(arguments: &[Column]) -> Result<Column, SqlError> {
+    let bytes = match &arguments[0] {
+        Column::Blob(blob) => blob.clone(),
+        value => to_text(value).into_bytes(),
+    };
+    let hex = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    Ok(Column::Text(hex))
+}
+
+/// `quote(x)`, the value written as an SQL literal.
+pub fn quote(arguments: &[Column]) -> Result<Column, SqlError> {
+    Ok(Column::Text(quote_value(&arguments[0])))
+}
+
+/// `random()`, a pseudo-random 64 bit integer.
+pub fn random(_arguments: &[Column]) -> Result<Column, SqlError> {
+    // xorshift64*
+    let value = RANDOM_STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545F4914F6CDD1D)
+    });
+    Ok(Column::Integer(value as i64 as i128))
+}
+
+fn random_seed() -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    // the state must never be zero
+    nanos | 1
+}
+
+/// `min(x, y, ...)` with more than one argument, the smallest of them, or NULL when any of
+/// them is NULL. With one argument `min` is the aggregate.
+pub fn min(arguments: &[Column]) -> Result<Column, SqlError> {
+    extreme(arguments, Ordering::Less)
+}
+
+/// `max(x, y, ...)` with more than one argument, the largest of them, or NULL when any of
+/// them is NULL. With one argument `max` is the aggregate.
+pub fn max(arguments: &[Column]) -> Result<Column, SqlError> {
+    extreme(arguments, Ordering::Greater)
+}
+
+fn extreme(arguments: &[Column], wanted: Ordering) -> Result<Column, SqlError> {
+    if any_null(arguments) {
+        return Ok(Column::Null());
+    }
+    let mut result = &arguments[0];
+    for value in &arguments[1..] {
+        if compare(value, result) == wanted {
+            result = value;
+        }
+    }
+    Ok(result.clone())
+}
+
+/// `printf(format, ...)`, also called `format`, the arguments written into the format with
+/// the conversions of C's `printf`: `%d`, `%i`, `%u`, `%f`, `%e`, `%g`, `%x`, `%X`, `%o`,
+/// `%c` and `%s`, with the flags `-`, `+`, space, `0`, `#` and `,`, widths and precisions.
+/// As in SQLite `%q` doubles single quotes, `%Q` also surrounds the text with them or gives
+/// NULL, and `%w` doubles double quotes. Missing arguments are NULL, which is 0 or empty
+/// text.
+pub fn printf(arguments: &[Column]) -> Result<Column, SqlError> {
+    let format = match &arguments[0] {
+        Column::Null() => return Ok(Column::Null()),
+        format => to_text(format),
+    };
+    let mut values = arguments[1..].iter();
+    let mut next_value = || values.next().cloned().unwrap_or(Column::Null());
+    let mut output = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        let mut spec = Spec::default();
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '0' => spec.zero = true,
+                '#' => spec.alternate = true,
+                ',' => spec.thousands = true,
+                _ => break,
+            }
+            chars.next();
+        }
+        if chars.peek() == Some(&'*') {
+            chars.next();
+            let width = integer_of(&next_value());
+            spec.left |= width < 0;
+            spec.width = width.unsigned_abs().min(PRINTF_LIMIT as u128) as usize;
+        } else {
+            spec.width = read_number(&mut chars);
+        }
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            spec.precision = if chars.peek() == Some(&'*') {
+                chars.next();
+                Some(integer_of(&next_value()).clamp(0, PRINTF_LIMIT as i128) as usize)
+            } else {
+                Some(read_number(&mut chars))
+            };
+        }
+        // C's length modifiers change nothing here
+        while let Some('l') | Some('h') = chars.peek() {
+            chars.next();
+        }
+
+        let conversion = match chars.next() {
+            Some(conversion) => conversion,
+            None => break,
+        };
+        let (prefix, body) = match conversion {
+            '%' => {
+                output.push('%');
+                continue;
+            }
+            'd' | 'i' => {
+                let int = integer_of(&next_value());
+                let digits = group_thousands(&int.unsigned_abs().to_string(), &spec);
+                (sign(int < 0, &spec), pad_digits(digits, spec.precision))
+            }
+            'u' => {
+                let int = integer_of(&next_value()) as i64 as u64;
+                let digits = group_thousands(&int.to_string(), &spec);
+                (String::new(), pad_digits(digits, spec.precision))
+            }
+            'x' | 'X' | 'o' => {
+                let int = integer_of(&next_value()) as i64 as u64;
+                let (digits, prefix) = match conversion {
+                    'x' => (format!("{:x}", int), "0x"),
+                    'X' => (format!("{:X}", int), "0X"),
+                    _ => (format!("{:o}", int), "0"),
+                };
+                let prefix = if spec.alternate && int != 0 {
+                    prefix
+                } else {
+                    ""
+                };
+                (prefix.to_string(), pad_digits(digits, spec.precision))
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let real = real_of(&next_value());
+                let body = format_float(real.abs(), conversion, &spec);
+                (sign(real.is_sign_negative() && real != 0.0, &spec), body)
+            }
+            's' | 'z' => {
+                let text = to_text(&next_value());
+                (String::new(), truncate(text, spec.precision))
+            }
+            'c' => {
+                let text = to_text(&next_value());
+                let c = text.chars().next().map_or(String::new(), String::from);
+                (String::new(), c)
+            }
+            'q' | 'w' => {
+                let value = next_value();
+                let quote = if conversion == 'q' { "'" } else { "\"" };
+                let text = truncate(to_text(&value), spec.precision);
+                (String::new(), text.replace(quote, &quote.repeat(2)))
+            }
+            'Q' => match next_value() {
+                Column::Null() => (String::new(), "NULL".to_string()),
+                value => {
+                    let text = truncate(to_text(&value), spec.precision);
+                    (String::new(), format!("'{}'", text.replace('\'', "''")))
+                }
+            },
+            // like SQLite, the output stops at a conversion it doesn't know
+            _ => break,
+        };
+        let numeric = !matches!(conversion, 's' | 'z' | 'c' | 'q' | 'Q' | 'w');
+        output.push_str(&pad(prefix, body, numeric, &spec));
+    }
+    Ok(Column::Text(output))
+}
+
+// the flags, width and precision of a printf() conversion
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    thousands: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+fn read_number<I: Iterator<Item = char>>(chars: &mut std::iter::Peekable<I>) -> usize {
+    let mut number: usize = 0;
+    while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+        number = number.saturating_mul(10).saturating_add(digit as usize);
+        chars.next();
+    }
+    number.min(PRINTF_LIMIT)
+}
+
+fn sign(negative: bool, spec: &Spec) -> String {
+    let sign = if negative {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    };
+    sign.to_string()
+}
+
+fn group_thousands(digits: &str, spec: &Spec) -> String {
+    if !spec.thousands {
+        return digits.to_string();
+    }
+    let mut grouped = String::new();
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// the precision of an integer conversion is the least number of digits
+fn pad_digits(digits: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) if digits.len() < precision => {
+            "0".repeat(precision - digits.len()) + &digits
+        }
+        _ => digits,
+    }
+}
+
+fn truncate(text: String, precision: Option<usize>) -> String {
+    match precision {
+        Some(precision) => text.chars().take(precision).collect(),
+        None => text,
+    }
+}
+
+fn format_float(real: f64, conversion: char, spec: &Spec) -> String {
+    if real.is_nan() {
+        return "NaN".to_string();
+    }
+    if real.is_infinite() {
+        return "Inf".to_string();
+    }
+    let precision = spec.precision.unwrap_or(6);
+    let body = match conversion {
+        'f' | 'F' => format!("{:.*}", precision, real),
+        'e' | 'E' => exponent_form(real, precision),
+        _ => {
+            // %g uses the shorter form for the exponent, then drops trailing zeros
+            let precision = precision.max(1);
+            let exponent = exponent_of(&exponent_form(real, precision - 1));
+            let body = if exponent < -4 || exponent >= precision as i32 {
+                exponent_form(real, precision - 1)
+            } else {
+                format!("{:.*}", (precision as i32 - 1 - exponent) as usize, real)
+            };
+            if spec.alternate {
+                body
+            } else {
+                drop_trailing_zeros(body)
+            }
+        }
+    };
+    if conversion.is_ascii_uppercase() {
+        body.to_ascii_uppercase()
+    } else {
+        body
+    }
+}
+
+// C's exponent form, with a sign and at least two digits in the exponent
+fn exponent_form(real: f64, precision: usize) -> String {
+    let form = format!("{:.*e}", precision, real);
+    let (mantissa, exponent) = form.split_at(form.find('e').unwrap_or(form.len()));
+    let exponent: i32 = exponent[1..].parse().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{}e{}{:02}", mantissa, sign, exponent.abs())
+}
+
+fn exponent_of(form: &str) -> i32 {
+    form.find('e')
+        .and_then(|index| form[index + 1..].parse().ok())
+        .unwrap_or(0)
+}
+
+fn drop_trailing_zeros(body: String) -> String {
+    let (mantissa, exponent) = body.split_at(body.find('e').unwrap_or(body.len()));
+    if !mantissa.contains('.') {
+        return body;
+    }
+    let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", mantissa, exponent)
+}
+
+// pads a conversion to its width, numbers with zeros after their sign when asked to
+fn pad(prefix: String, body: String, numeric: bool, spec: &Spec) -> String {
+    let length = prefix.chars().count() + body.chars().count();
+    if length >= spec.width {
+        return prefix + &body;
+    }
+    let padding = spec.width - length;
+    if spec.left {
+        prefix + &body + &" ".repeat(padding)
+    } else if spec.zero && numeric {
+        prefix + &"0".repeat(padding) + &body
+    } else {
+        " ".repeat(padding) + &prefix + &body
+    }
+}
+
+fn text_function<F>(value: &Column, function: F) -> Result<Column, SqlError>
+where
+    F: Fn(&str) -> String,
+{
+    match value {
+        Column::Null() => Ok(Column::Null()),
+        value => Ok(Column::Text(function(&to_text(value)))),
+    }
+}
+
+fn any_null(arguments: &[Column]) -> bool {
+    arguments.contains(&Column::Null())
+}
+
+/// A value as an integer, the way SQLite reads the integer arguments of functions: text is
+/// read by its numeric prefix and the fractions of reals and decimals are dropped. NULL is 0.
+pub fn integer_of(value: &Column) -> i128 {
+    match to_numeric(value) {
+        Column::Integer(int) => int,
+        // `as` saturates, and gives 0 for NaN
+        Column::Real(real) => real as i128,
+        Column::Decimal(decimal) => decimal.value / 10i128.pow(decimal.scale as u32),
+        _ => 0,
+    }
+}
+
+/// A value as a real, read like `integer_of`.
+pub fn real_of(value: &Column) -> f64 {
+    match to_numeric(value) {
+        Column::Integer(int) => int as f64,
+        Column::Real(real) => real,
+        Column::Decimal(decimal) => decimal.to_real(),
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,394 @@
+use super::aggregate::{self, Group};
+use super::common_table;
+use super::compound;
+use super::executor::{matches, Context, Relation, ResultSet};
+use super::expression::{ordered, to_numeric, ColumnName, Expression, Row};
+use super::join;
+use super::statement::{OrderingTerm, ResultColumn, Select};
+use super::window;
+use super::SqlError;
+use backend::database::{Collation, Column};
+use std::cmp::Ordering;
+use std::error;
+
+// an ORDER BY term refers either to an output column or an expression over the source row
+pub enum SortKey<'a> {
+    Output(usize),
+    Expression(&'a Expression),
+}
+
+/// Runs a query, `outer` is the row of the enclosing query when running a subquery.
+pub fn execute_select(
+    context: &Context,
+    select: &Select,
+    outer: Option<&Row>,
+) -> Result<ResultSet, Box<dyn error::Error>> {
+    execute_collated(context, select, outer).map(|(result, _collations)| result)
+}
+
+/// Runs a query like `execute_select`, along with the collation each result column compares
+/// by. The columns of a compound query take the collations of its leftmost query.
+pub fn execute_collated(
+    context: &Context,
+    select: &Select,
+    outer: Option<&Row>,
+) -> Result<(ResultSet, Vec<Collation>), Box<dyn error::Error>> {
+    // common tables are only visible while the query that defines them runs
+    let count = context.common_table_count();
+    let result = match &select.with {
+        Some(with) => common_table::define(context, with, outer)
+            .and_then(|_| run_query(context, select, outer)),
+        None => run_query(context, select, outer),
+    };
+    context.forget_common_tables(count);
+    result
+}
+
+fn run_query(
+    context: &Context,
+    select: &Select,
+    outer: Option<&Row>,
+) -> Result<(ResultSet, Vec<Collation>), Box<dyn error::Error>> {
+    if select.compound.is_empty() {
+        run_select(context, select, outer)
+    } else {
+        compound::execute_compound(context, select, outer)
+    }
+}
+
+fn run_select(
+    context: &Context,
+    select: &Select,
+    outer: Option<&Row>,
+) -> Result<(ResultSet, Vec<Collation>), Box<dyn error::Error>> {
+    let scope = context.scope(outer);
+    let source = match &select.from {
+        Some(from) => join::scan_from(context, from, select.selection.as_ref(), scope)?,
+        None => Relation {
+            names: Vec::new(),
+            rows: vec![Vec::new()],
+        },
+    };
+
+    let (columns, projection) = expand_columns(&select.columns, &source.names)?;
+    let sort_keys = resolve_sort_keys(&select.order_by, &columns, projection.len())?;
+    let group_by = resolve_group_by(&select.group_by, &source.names, &columns, &projection)?;
+
+    let mut rows = Vec::new();
+    for values in source.rows {
+        if matches(&select.selection, &source.names, &values, scope)? {
+            rows.push(values);
+        }
+    }
+
+    let calls = aggregate::find_calls(
+        projection
+            .iter()
+            .chain(select.having.iter())
+            .chain(sort_expressions(&sort_keys)),
+    );
+
+    let groups = if group_by.is_empty() && calls.is_empty() {
+        rows.into_iter()
+            .map(|values| Group {
+                values,
+                aggregates: Vec::new(),
+            })
+            .collect()
+    } else {
+        aggregate::group_rows(&source.names, rows, &group_by, &calls, scope)?
+    };
+
+    let mut groups = match &select.having {
+        Some(having) => {
+            let mut kept = Vec::with_capacity(groups.len());
+            for group in groups {
+                let row = Row::with_aggregates(&source.names, &group.values, &group.aggregates)
+                    .with_scope(scope);
+                if having.is_true(&row)? {
+                    kept.push(group);
+                }
+            }
+            kept
+        }
+        None => groups,
+    };
+
+    // window functions see the rows left after grouping and HAVING
+    let window_calls = window::find_calls(projection.iter().chain(sort_expressions(&sort_keys)));
+    if !window_calls.is_empty() {
+        window::evaluate_windows(&source.names, &mut groups, &window_calls, scope)?;
+    }
+
+    // DISTINCT compares result columns by their collations
+    let names = Row::new(&source.names, &[]).with_scope(scope);
+    let collations = projection
+        .iter()
+        .map(|expression| names.collation(expression))
+        .collect::<Result<Vec<_>, _>>()?;
+    // ORDER BY terms are sorted in the form their affinity orders them in
+    let sort_affinities: Vec<_> = sort_keys
+        .iter()
+        .map(|key| match key {
+            SortKey::Output(index) => names.affinity(&projection[*index]),
+            SortKey::Expression(expression) => names.affinity(expression),
+        })
+        .collect();
+
+    let mut output: Vec<(Vec<Column>, Vec<Column>)> = Vec::new();
+    for group in &groups {
+        let row =
+            Row::with_aggregates(&source.names, &group.values, &group.aggregates).with_scope(scope);
+        let mut record = Vec::with_capacity(projection.len());
+        for expression in &projection {
+            record.push(expression.evaluate(&row)?);
+        }
+
+        if select.distinct
+            && output
+                .iter()
+                .any(|(_, seen)| same_row(seen, &record, &collations))
+        {
+            continue;
+        }
+
+        let mut keys = Vec::with_capacity(sort_keys.len());
+        for (key, affinity) in sort_keys.iter().zip(&sort_affinities) {
+            let value = match key {
+                SortKey::Output(index) => record[*index].clone(),
+                SortKey::Expression(expression) => expression.evaluate(&row)?,
+            };
+            keys.push(ordered(value, affinity));
+        }
+        output.push((keys, record));
+    }
+
+    if !select.order_by.is_empty() {
+        let order_collations = sort_collations(&sort_keys, &projection, &names)?;
+        output.sort_by(|(left, _), (right, _)| {
+            compare_keys(left, right, &select.order_by, &order_collations)
+        });
+    }
+
+    let offset = match &select.offset {
+        Some(offset) => evaluate_count(offset)?.unwrap_or(0),
+        None => 0,
+    };
+    let limit = match &select.limit {
+        Some(limit) => evaluate_count(limit)?,
+        None => None,
+    };
+
+    let rows = output
+        .into_iter()
+        .map(|(_keys, record)| record)
+        .skip(offset)
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok((ResultSet::new(columns, rows), collations))
+}
+
+// the collation each ORDER BY term sorts by, `names` holds the names of the source row
+fn sort_collations(
+    sort_keys: &[SortKey],
+    projection: &[Expression],
+    names: &Row,
+) -> Result<Vec<Collation>, SqlError> {
+    sort_keys
+        .iter()
+        .map(|key| match key {
+            SortKey::Output(index) => names.collation(&projection[*index]),
+            SortKey::Expression(expression) => names.collation(expression),
+        })
+        .collect()
+}
+
+// the ORDER BY terms evaluated against the source row rather than taken from the result
+fn sort_expressions<'a>(sort_keys: &'a [SortKey<'a>]) -> impl Iterator<Item = &'a Expression> {
+    sort_keys.iter().filter_map(|key| match key {
+        SortKey::Expression(expression) => Some(*expression),
+        SortKey::Output(_) => None,
+    })
+}
+
+// expands `*` into the source columns and names every result column
+pub fn expand_columns(
+    result_columns: &[ResultColumn],
+    names: &[ColumnName],
+) -> Result<(Vec<String>, Vec<Expression>), SqlError> {
+    let mut columns = Vec::new();
+    let mut projection = Vec::new();
+
+    for result_column in result_columns {
+        match result_column {
+            ResultColumn::All => {
+                if names.is_empty() {
+                    return Err(SqlError::new("no tables specified"));
+                }
+                // columns merged by USING or NATURAL appear once
+                for name in names.iter().filter(|name| !name.hidden) {
+                    columns.push(name.name.clone());
+                    projection.push(column_reference(name));
+                }
+            }
+            ResultColumn::TableAll(table) => {
+                let mut found = false;
+                for name in names {
+                    if name
+                        .table
+                        .as_ref()
+                        .is_some_and(|t| t.eq_ignore_ascii_case(table))
+                    {
+                        columns.push(name.name.clone());
+                        projection.push(column_reference(name));
+                        found = true;
+                    }
+                }
+                if !found {
+                    return Err(SqlError::new(format!("no such table: {}", table)));
+                }
+            }
+            ResultColumn::Expression { expression, alias } => {
+                let name = match (alias, expression) {
+                    (Some(alias), _) => alias.clone(),
+                    (None, Expression::Column { name, .. }) => name.clone(),
+                    (None, expression) => expression.to_string(),
+                };
+                columns.push(name);
+                projection.push(expression.clone());
+            }
+        }
+    }
+
+    Ok((columns, projection))
+}
+
+fn column_reference(name: &ColumnName) -> Expression {
+    Expression::Column {
+        table: name.table.clone(),
+        name: name.name.clone(),
+    }
+}
+
+// `ORDER BY 2` and `ORDER BY alias` refer to output columns, anything else is an expression
+pub fn resolve_sort_keys<'a>(
+    order_by: &'a [OrderingTerm],
+    columns: &[String],
+    column_count: usize,
+) -> Result<Vec<SortKey<'a>>, SqlError> {
+    let mut keys = Vec::with_capacity(order_by.len());
+
+    for term in order_by {
+        let key = match &term.expression {
+            Expression::Literal(Column::Integer(position)) => {
+                if *position < 1 || *position as usize > column_count {
+                    return Err(SqlError::new(format!(
+                        "ORDER BY term out of range - should be between 1 and {}",
+                        column_count
+                    )));
+                }
+                SortKey::Output(*position as usize - 1)
+            }
+            Expression::Column { table: None, name } => {
+                match columns.iter().position(|c| c.eq_ignore_ascii_case(name)) {
+                    Some(index) => SortKey::Output(index),
+                    None => SortKey::Expression(&term.expression),
+                }
+            }
+            expression => SortKey::Expression(expression),
+        };
+        keys.push(key);
+    }
+
+    Ok(keys)
+}
+
+// `GROUP BY 2` and `GROUP BY alias` group on a result column, source columns take precedence
+fn resolve_group_by(
+    group_by: &[Expression],
+    names: &[ColumnName],
+    columns: &[String],
+    projection: &[Expression],
+) -> Result<Vec<Expression>, SqlError> {
+    let mut terms = Vec::with_capacity(group_by.len());
+
+    for term in group_by {
+        let term = match term {
+            Expression::Literal(Column::Integer(position)) => {
+                if *position < 1 || *position as usize > projection.len() {
+                    return Err(SqlError::new(format!(
+                        "GROUP BY term out of range - should be between 1 and {}",
+                        projection.len()
+                    )));
+                }
+                projection[*position as usize - 1].clone()
+            }
+            Expression::Column { table: None, name }
+                if !names.iter().any(|n| n.name.eq_ignore_ascii_case(name)) =>
+            {
+                match columns.iter().position(|c| c.eq_ignore_ascii_case(name)) {
+                    Some(index) => projection[index].clone(),
+                    None => term.clone(),
+                }
+            }
+            _ => term.clone(),
+        };
+        if term.contains_aggregate() {
+            return Err(SqlError::new(
+                "aggregate functions are not allowed in the GROUP BY clause",
+            ));
+        }
+        terms.push(term);
+    }
+
+    Ok(terms)
+}
+
+/// Whether two rows hold the same values, text is compared by the collation of each column
+/// and with BINARY for the columns `collations` doesn't cover.
+pub fn same_row(left: &[Column], right: &[Column], collations: &[Collation]) -> bool {
+    left.iter()
+        .zip(right)
+        .enumerate()
+        .all(|(position, (left, right))| {
+            let collation = collations.get(position).unwrap_or(&Collation::Binary);
+            collation.compare(left, right) == Ordering::Equal
+        })
+}
+
+/// Compares the sort keys of two rows, text is compared by the collation of each term and
+/// with BINARY for the terms `collations` doesn't cover.
+pub fn compare_keys(
+    left: &[Column],
+    right: &[Column],
+    order_by: &[OrderingTerm],
+    collations: &[Collation],
+) -> Ordering {
+    for (position, ((left, right), term)) in left.iter().zip(right).zip(order_by).enumerate() {
+        let collation = collations.get(position).unwrap_or(&Collation::Binary);
+        let nulls_first = term.nulls_first.unwrap_or(!term.descending);
+        let ordering = match (left, right) {
+            (Column::Null(), Column::Null()) => Ordering::Equal,
+            (Column::Null(), _) if nulls_first => Ordering::Less,
+            (Column::Null(), _) => Ordering::Greater,
+            (_, Column::Null()) if nulls_first => Ordering::Greater,
+            (_, Column::Null()) => Ordering::Less,
+            _ if term.descending => collation.compare(right, left),
+            _ => collation.compare(left, right),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+// LIMIT and OFFSET values, a negative value means no limit
+pub fn evaluate_count(expression: &Expression) -> Result<Option<usize>, SqlError> {
+    match to_numeric(&expression.evaluate(&Row::empty())?) {
+        Column::Integer(count) if count < 0 => Ok(None),
+        Column::Integer(count) => Ok(Some(count as usize)),
+        _ => Err(SqlError::new("datatype mismatch")),
+    }
+}
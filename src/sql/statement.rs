@@ -0,0 +1,601 @@
+use super::expression::Expression;
+use backend::database::ColumnType;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Select(Box<Select>),
+    Insert(Insert),
+    Update(Update),
+    Delete(Delete),
+    CreateIndex(CreateIndex),
+    CreateView(CreateView),
+    /// `DROP VIEW [IF EXISTS] view`
+    DropView {
+        view: String,
+        if_exists: bool,
+    },
+    CreateTrigger(CreateTrigger),
+    /// `DROP TRIGGER [IF EXISTS] trigger`
+    DropTrigger {
+        trigger: String,
+        if_exists: bool,
+    },
+    /// `DROP TABLE [IF EXISTS] table`
+    DropTable {
+        table: String,
+        if_exists: bool,
+    },
+    AlterTable(AlterTable),
+    /// `ANALYZE [table]`, gathers the statistics the planner uses
+    Analyze(Option<String>),
+    /// `EXPLAIN [QUERY PLAN] statement`
+    Explain {
+        statement: Box<Statement>,
+        query_plan: bool,
+    },
+}
+
+impl Statement {
+    /// Calls `visit` on every expression of the statement, those of nested queries included.
+    /// The queries of views and triggers being created are kept as written and not visited.
+    pub fn visit_expressions_mut<F>(&mut self, visit: &mut F)
+    where
+        F: FnMut(&mut Expression),
+    {
+        match self {
+            Statement::Select(select) => select.visit_expressions_mut(visit),
+            Statement::Insert(insert) => {
+                for expression in insert.values.iter_mut().flatten() {
+                    expression.visit_mut(visit);
+                }
+            }
+            Statement::Update(update) => {
+                let assigned = update.assignments.iter_mut().map(|(_, value)| value);
+                for expression in assigned.chain(update.selection.iter_mut()) {
+                    expression.visit_mut(visit);
+                }
+            }
+            Statement::Delete(delete) => {
+                for expression in delete.selection.iter_mut() {
+                    expression.visit_mut(visit);
+                }
+            }
+            Statement::AlterTable(alter_table) => {
+                if let AlterAction::AddColumn {
+                    default, generated, ..
+                } = &mut alter_table.action
+                {
+                    let generated = generated.iter_mut().map(|(expression, _)| expression);
+                    for expression in default.iter_mut().chain(generated) {
+                        expression.visit_mut(visit);
+                    }
+                }
+            }
+            Statement::Explain { statement, .. } => statement.visit_expressions_mut(visit),
+            Statement::CreateIndex(_)
+            | Statement::CreateView(_)
+            | Statement::DropView { .. }
+            | Statement::CreateTrigger(_)
+            | Statement::DropTrigger { .. }
+            | Statement::DropTable { .. }
+            | Statement::Analyze(_) => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Select {
+    pub with: Option<With>,
+    pub distinct: bool,
+    pub columns: Vec<ResultColumn>,
+    pub from: Option<From>,
+    pub selection: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<Expression>,
+    /// the queries combined with this one by `UNION`, `INTERSECT` or `EXCEPT`, from left to
+    /// right, the ORDER BY and LIMIT below then apply to the combined rows
+    pub compound: Vec<(CompoundOperator, Select)>,
+    pub order_by: Vec<OrderingTerm>,
+    pub limit: Option<Expression>,
+    pub offset: Option<Expression>,
+}
+
+impl Select {
+    /// Calls `visit` on every expression of the query, those of nested queries included.
+    pub fn visit_expressions_mut<F>(&mut self, visit: &mut F)
+    where
+        F: FnMut(&mut Expression),
+    {
+        if let Some(with) = &mut self.with {
+            for table in with.tables.iter_mut() {
+                table.select.visit_expressions_mut(visit);
+            }
+        }
+
+        for column in self.columns.iter_mut() {
+            if let ResultColumn::Expression { expression, .. } = column {
+                expression.visit_mut(visit);
+            }
+        }
+
+        if let Some(from) = &mut self.from {
+            let joined = from.joins.iter_mut().map(|join| &mut join.table);
+            for table in std::iter::once(&mut from.table).chain(joined) {
+                match &mut table.source {
+                    TableSource::Subquery(subquery) => subquery.visit_expressions_mut(visit),
+                    TableSource::Function { arguments, .. } => {
+                        for argument in arguments.iter_mut() {
+                            argument.visit_mut(visit);
+                        }
+                    }
+                    TableSource::Table(_) => {}
+                }
+            }
+            for join in from.joins.iter_mut() {
+                if let JoinConstraint::On(condition) = &mut join.constraint {
+                    condition.visit_mut(visit);
+                }
+            }
+        }
+
+        let expressions = self
+            .selection
+            .iter_mut()
+            .chain(self.group_by.iter_mut())
+            .chain(self.having.iter_mut())
+            .chain(self.order_by.iter_mut().map(|term| &mut term.expression))
+            .chain(self.limit.iter_mut())
+            .chain(self.offset.iter_mut());
+        for expression in expressions {
+            expression.visit_mut(visit);
+        }
+
+        for (_, term) in self.compound.iter_mut() {
+            term.visit_expressions_mut(visit);
+        }
+    }
+}
+
+/// `WITH [RECURSIVE]`, the common tables a query can refer to by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct With {
+    pub recursive: bool,
+    pub tables: Vec<CommonTable>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommonTable {
+    pub name: String,
+    /// names for the result columns, when empty they keep the names the query gives them
+    pub columns: Vec<String>,
+    pub select: Box<Select>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompoundOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResultColumn {
+    /// `*`
+    All,
+    /// `table.*`
+    TableAll(String),
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
+}
+
+/// The `FROM` clause, a table followed by the tables joined to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct From {
+    pub table: TableReference,
+    pub joins: Vec<Join>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableReference {
+    pub source: TableSource,
+    pub alias: Option<String>,
+}
+
+impl TableReference {
+    /// The name columns of the table are qualified by, a subquery without an alias has none.
+    pub fn reference_name(&self) -> Option<&str> {
+        match (&self.alias, &self.source) {
+            (Some(alias), _) => Some(alias),
+            (None, TableSource::Table(name)) => Some(name),
+            (None, TableSource::Function { name, .. }) => Some(name),
+            (None, TableSource::Subquery(_)) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableSource {
+    Table(String),
+    /// a derived table, `FROM (SELECT ...)`
+    Subquery(Box<Select>),
+    /// a table-valued function such as `FROM json_each(x)`, its arguments can read the
+    /// tables before it
+    Function {
+        name: String,
+        arguments: Vec<Expression>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub operator: JoinOperator,
+    pub table: TableReference,
+    pub constraint: JoinConstraint,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinOperator {
+    /// `JOIN`, `INNER JOIN` or a comma
+    Inner,
+    /// `LEFT [OUTER] JOIN`
+    Left,
+    /// `CROSS JOIN`
+    Cross,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinConstraint {
+    On(Expression),
+    Using(Vec<String>),
+    /// `NATURAL JOIN`, the same as `USING` every column the tables have in common
+    Natural,
+    None,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderingTerm {
+    pub expression: Expression,
+    pub descending: bool,
+    /// defaults to NULLs first when ascending and last when descending
+    pub nulls_first: Option<bool>,
+}
+
+/// `OVER (PARTITION BY ... ORDER BY ... frame)`, the rows a window function is computed over.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub partition_by: Vec<Expression>,
+    pub order_by: Vec<OrderingTerm>,
+    /// defaults to `RANGE BETWEEN UNBOUNDED PRECEDING AND CURRENT ROW`
+    pub frame: Option<Frame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameUnits {
+    /// bounds count rows
+    Rows,
+    /// bounds are distances from the ORDER BY value, rows with equal values are peers
+    Range,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Expression),
+    CurrentRow,
+    Following(Expression),
+    UnboundedFollowing,
+}
+
+/// `INSERT INTO table [(columns)] VALUES (...), ...`, columns left out are NULL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Insert {
+    pub table: String,
+    pub columns: Vec<String>,
+    pub values: Vec<Vec<Expression>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Update {
+    pub table: String,
+    pub assignments: Vec<(String, Expression)>,
+    pub selection: Option<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Delete {
+    pub table: String,
+    pub selection: Option<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndex {
+    pub name: String,
+    pub table: String,
+    pub columns: Vec<String>,
+}
+
+/// `CREATE VIEW [IF NOT EXISTS] name [(columns)] AS select`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateView {
+    pub name: String,
+    pub if_not_exists: bool,
+    /// names for the result columns, when empty they keep the names the query gives them
+    pub columns: Vec<String>,
+    pub select: Box<Select>,
+    /// the statement as it was written, which is what the master table keeps
+    pub sql: String,
+}
+
+/// `CREATE TRIGGER [IF NOT EXISTS] name [timing] event ON table [FOR EACH ROW] [WHEN
+/// condition] BEGIN statement; ... END`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateTrigger {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    pub table: String,
+    /// checked for each row, which can be referred to as `OLD` and `NEW`
+    pub when: Option<Expression>,
+    pub body: Vec<Statement>,
+    /// the statement as it was written, which is what the master table keeps
+    pub sql: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    /// runs in place of the change, for views
+    InsteadOf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TriggerEvent {
+    Insert,
+    /// `UPDATE [OF columns]`, with no columns any update fires the trigger
+    Update(Vec<String>),
+    Delete,
+}
+
+/// `ALTER TABLE table action`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTable {
+    pub table: String,
+    pub action: AlterAction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterAction {
+    /// `RENAME TO name`
+    RenameTable(String),
+    /// `RENAME [COLUMN] column TO name`
+    RenameColumn { column: String, new_name: String },
+    /// `ADD [COLUMN] name [type] [DEFAULT value] [COLLATE collation]
+    /// [[GENERATED ALWAYS] AS (expression) [VIRTUAL | STORED]]`
+    AddColumn {
+        name: String,
+        column_type: ColumnType,
+        default: Option<Expression>,
+        collation: Option<String>,
+        /// the expression of a generated column and whether it's STORED
+        generated: Option<(Expression, bool)>,
+    },
+    /// `DROP [COLUMN] column`
+    DropColumn(String),
+}
+
+/// Writes the query back out as SQL.
+impl fmt::Display for Select {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(with) = &self.with {
+            write!(f, "{} ", with)?;
+        }
+        write!(f, "SELECT ")?;
+        if self.distinct {
+            write!(f, "DISTINCT ")?;
+        }
+        for (index, column) in self.columns.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            match column {
+                ResultColumn::All => write!(f, "*")?,
+                ResultColumn::TableAll(table) => write!(f, "{}.*", table)?,
+                ResultColumn::Expression {
+                    expression,
+                    alias: Some(alias),
+                } => write!(f, "{} AS {}", expression, alias)?,
+                ResultColumn::Expression {
+                    expression,
+                    alias: None,
+                } => write!(f, "{}", expression)?,
+            }
+        }
+
+        if let Some(from) = &self.from {
+            write!(f, " FROM {}", from)?;
+        }
+        if let Some(selection) = &self.selection {
+            write!(f, " WHERE {}", selection)?;
+        }
+        if !self.group_by.is_empty() {
+            write!(f, " GROUP BY ")?;
+            write_list(f, &self.group_by)?;
+        }
+        if let Some(having) = &self.having {
+            write!(f, " HAVING {}", having)?;
+        }
+        for (operator, select) in &self.compound {
+            write!(f, " {} {}", operator, select)?;
+        }
+        if !self.order_by.is_empty() {
+            write!(f, " ORDER BY ")?;
+            for (index, term) in self.order_by.iter().enumerate() {
+                if index > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", term)?;
+            }
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, " LIMIT {}", limit)?;
+        }
+        if let Some(offset) = &self.offset {
+            write!(f, " OFFSET {}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for With {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WITH ")?;
+        if self.recursive {
+            write!(f, "RECURSIVE ")?;
+        }
+        for (index, table) in self.tables.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", table.name)?;
+            if !table.columns.is_empty() {
+                write!(f, "({})", table.columns.join(", "))?;
+            }
+            write!(f, " AS ({})", table.select)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CompoundOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operator = match self {
+            CompoundOperator::Union => "UNION",
+            CompoundOperator::UnionAll => "UNION ALL",
+            CompoundOperator::Intersect => "INTERSECT",
+            CompoundOperator::Except => "EXCEPT",
+        };
+        write!(f, "{}", operator)
+    }
+}
+
+impl fmt::Display for From {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.table)?;
+        for join in &self.joins {
+            let operator = match join.operator {
+                JoinOperator::Inner => "JOIN",
+                JoinOperator::Left => "LEFT JOIN",
+                JoinOperator::Cross => "CROSS JOIN",
+            };
+            match &join.constraint {
+                JoinConstraint::Natural => write!(f, " NATURAL {} {}", operator, join.table)?,
+                JoinConstraint::On(condition) => {
+                    write!(f, " {} {} ON {}", operator, join.table, condition)?
+                }
+                JoinConstraint::Using(columns) => write!(
+                    f,
+                    " {} {} USING ({})",
+                    operator,
+                    join.table,
+                    columns.join(", ")
+                )?,
+                JoinConstraint::None => write!(f, " {} {}", operator, join.table)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TableReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.source {
+            TableSource::Table(name) => write!(f, "{}", name)?,
+            TableSource::Subquery(select) => write!(f, "({})", select)?,
+            TableSource::Function { name, arguments } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .map(|argument| argument.to_string())
+                    .collect();
+                write!(f, "{}({})", name, arguments.join(", "))?
+            }
+        }
+        match &self.alias {
+            Some(alias) => write!(f, " AS {}", alias),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for OrderingTerm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)?;
+        if self.descending {
+            write!(f, " DESC")?;
+        }
+        match self.nulls_first {
+            Some(true) => write!(f, " NULLS FIRST"),
+            Some(false) => write!(f, " NULLS LAST"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut clauses = Vec::new();
+        if !self.partition_by.is_empty() {
+            let terms: Vec<String> = self.partition_by.iter().map(|e| e.to_string()).collect();
+            clauses.push(format!("PARTITION BY {}", terms.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            let terms: Vec<String> = self.order_by.iter().map(|t| t.to_string()).collect();
+            clauses.push(format!("ORDER BY {}", terms.join(", ")));
+        }
+        if let Some(frame) = &self.frame {
+            let units = match frame.units {
+                FrameUnits::Rows => "ROWS",
+                FrameUnits::Range => "RANGE",
+            };
+            clauses.push(format!(
+                "{} BETWEEN {} AND {}",
+                units, frame.start, frame.end
+            ));
+        }
+        write!(f, "{}", clauses.join(" "))
+    }
+}
+
+impl fmt::Display for FrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameBound::UnboundedPreceding => write!(f, "UNBOUNDED PRECEDING"),
+            FrameBound::Preceding(offset) => write!(f, "{} PRECEDING", offset),
+            FrameBound::CurrentRow => write!(f, "CURRENT ROW"),
+            FrameBound::Following(offset) => write!(f, "{} FOLLOWING", offset),
+            FrameBound::UnboundedFollowing => write!(f, "UNBOUNDED FOLLOWING"),
+        }
+    }
+}
+
+fn write_list(f: &mut fmt::Formatter, list: &[Expression]) -> fmt::Result {
+    for (index, item) in list.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use super::SqlError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Identifier(String),
+    QuotedIdentifier(String),
+    Integer(i128),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+    /// a placeholder for a value bound later, as written: `?`, `?NNN`, `:name`, `@name` or
+    /// `$name`
+    Parameter(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Dot,
+    Semicolon,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Concat,
+    /// `->`
+    Arrow,
+    /// `->>`
+    DoubleArrow,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Token {
+    // keywords are not reserved, the parser checks identifiers against them
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        match self {
+            Token::Identifier(word) => word.eq_ignore_ascii_case(keyword),
+            _ => false,
+        }
+    }
+}
+
+pub fn tokenize(sql: &str) -> Result<Vec<Token>, SqlError> {
+    let tokens = tokenize_with_offsets(sql)?;
+    Ok(tokens.into_iter().map(|(token, _offset)| token).collect())
+}
+
+/// Tokenizes the SQL along with the byte offset each token starts at.
+pub fn tokenize_with_offsets(sql: &str) -> Result<Vec<(Token, usize)>, SqlError> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut starts = Vec::new();
+    let mut start = 0;
+    let mut index = 0;
+
+    while index < chars.len() {
+        // each pass reads at most one token, the one read by the pass before started where
+        // that pass did
+        starts.resize(tokens.len(), start);
+        start = index;
+        let c = chars[index];
+
+        if c.is_whitespace() {
+            index += 1;
+            continue;
+        }
+
+        // comments
+        if c == '-' && chars.get(index + 1) == Some(&'-') {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+            continue;
+        }
+        if c == '/' && chars.get(index + 1) == Some(&'*') {
+            index += 2;
+            while index < chars.len()
+                && !(chars[index] == '*' && chars.get(index + 1) == Some(&'/'))
+            {
+                index += 1;
+            }
+            index += 2;
+            continue;
+        }
+
+        if (c == 'x' || c == 'X') && chars.get(index + 1) == Some(&'\'') {
+            index += 1;
+            let hex = read_quoted(&chars, &mut index, '\'')?;
+            tokens.push(Token::Blob(parse_hex(&hex)?));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = index;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            let word: String = chars[start..index].iter().collect();
+            tokens.push(Token::Identifier(word));
+            continue;
+        }
+
+        if c.is_ascii_digit()
+            || (c == '.' && chars.get(index + 1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            tokens.push(read_number(&chars, &mut index)?);
+            continue;
+        }
+
+        if c == '?' || c == ':' || c == '@' || c == '$' {
+            let start = index;
+            index += 1;
+            while index < chars.len()
+                && (chars[index].is_alphanumeric() || chars[index] == '_')
+                && (c != '?' || chars[index].is_ascii_digit())
+            {
+                index += 1;
+            }
+            if c != '?' && index == start + 1 {
+                return Err(SqlError::new(format!("unrecognised token `{}`", c)));
+            }
+            tokens.push(Token::Parameter(chars[start..index].iter().collect()));
+            continue;
+        }
+
+        let token = match c {
+            '\'' => Token::Text(read_quoted(&chars, &mut index, '\'')?),
+            '"' => Token::QuotedIdentifier(read_quoted(&chars, &mut index, '"')?),
+            '`' => Token::QuotedIdentifier(read_quoted(&chars, &mut index, '`')?),
+            '[' => {
+                let start = index + 1;
+                while index < chars.len() && chars[index] != ']' {
+                    index += 1;
+                }
+                if index == chars.len() {
+                    return Err(SqlError::new("unterminated identifier"));
+                }
+                index += 1;
+                Token::QuotedIdentifier(chars[start..index - 1].iter().collect())
+            }
+            _ => {
+                let next = chars.get(index + 1).cloned();
+                let (token, length) = match (c, next) {
+                    ('(', _) => (Token::LeftParen, 1),
+                    (')', _) => (Token::RightParen, 1),
+                    (',', _) => (Token::Comma, 1),
+                    ('.', _) => (Token::Dot, 1),
+                    (';', _) => (Token::Semicolon, 1),
+                    ('+', _) => (Token::Plus, 1),
+                    ('-', Some('>')) if chars.get(index + 2) == Some(&'>') => {
+                        (Token::DoubleArrow, 3)
+                    }
+                    ('-', Some('>')) => (Token::Arrow, 2),
+                    ('-', _) => (Token::Minus, 1),
+                    ('*', _) => (Token::Star, 1),
+                    ('/', _) => (Token::Slash, 1),
+                    ('%', _) => (Token::Percent, 1),
+                    ('|', Some('|')) => (Token::Concat, 2),
+                    ('=', Some('=')) => (Token::Equal, 2),
+                    ('=', _) => (Token::Equal, 1),
+                    ('!', Some('=')) => (Token::NotEqual, 2),
+                    ('<', Some('>')) => (Token::NotEqual, 2),
+                    ('<', Some('=')) => (Token::LessEqual, 2),
+                    ('<', _) => (Token::Less, 1),
+                    ('>', Some('=')) => (Token::GreaterEqual, 2),
+                    ('>', _) => (Token::Greater, 1),
+                    _ => return Err(SqlError::new(format!("unrecognised token `{}`", c))),
+                };
+                index += length;
+                token
+            }
+        };
+        tokens.push(token);
+    }
+    starts.resize(tokens.len(), start);
+
+    let offsets: Vec<usize> = sql.char_indices().map(|(offset, _)| offset).collect();
+    Ok(tokens
+        .into_iter()
+        .zip(starts)
+        .map(|(token, start)| (token, offsets[start]))
+        .collect())
+}
+
+// reads a quoted string starting at the opening quote, a doubled quote escapes itself
+fn read_quoted(chars: &[char], index: &mut usize, quote: char) -> Result<String, SqlError> {
+    let mut out = String::new();
+    *index += 1;
+
+    loop {
+        match chars.get(*index) {
+            None => return Err(SqlError::new("unterminated string")),
+            Some(&c) if c == quote => {
+                if chars.get(*index + 1) == Some(&quote) {
+                    out.push(quote);
+                    *index += 2;
+                } else {
+                    *index += 1;
+                    return Ok(out);
+                }
+            }
+            Some(&c) => {
+                out.push(c);
+                *index += 1;
+            }
+        }
+    }
+}
+
+fn read_number(chars: &[char], index: &mut usize) -> Result<Token, SqlError> {
+    let start = *index;
+    let mut is_real = false;
+
+    while *index < chars.len() && chars[*index].is_ascii_digit() {
+        *index += 1;
+    }
+    if *index < chars.len() && chars[*index] == '.' {
+        is_real = true;
+        *index += 1;
+        while *index < chars.len() && chars[*index].is_ascii_digit() {
+            *index += 1;
+        }
+    }
+    if *index < chars.len() && (chars[*index] == 'e' || chars[*index] == 'E') {
+        is_real = true;
+        *index += 1;
+        if *index < chars.len() && (chars[*index] == '+' || chars[*index] == '-') {
+            *index += 1;
+        }
+        while *index < chars.len() && chars[*index].is_ascii_digit() {
+            *index += 1;
+        }
+    }
+
+    let text: String = chars[start..*index].iter().collect();
+    if !is_real {
+        if let Ok(int) = text.parse::<i128>() {
+            return Ok(Token::Integer(int));
+        }
+        // 2^127 is only an integer once it's negated, the parser reads it as a real when
+        // there is no minus sign before it
+        if text.parse::<u128>() == Ok(i128::MIN.unsigned_abs()) {
+            return Ok(Token::Integer(i128::MIN));
+        }
+    }
+    match text.parse::<f64>() {
+        Ok(real) => Ok(Token::Real(real)),
+        Err(_) => Err(SqlError::new(format!("malformed number `{}`", text))),
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Vec<u8>, SqlError> {
+    if !hex.len().is_multiple_of(2) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(SqlError::new(format!("malformed blob literal `{}`", hex)));
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+    Ok(bytes)
+}
@@ -0,0 +1,153 @@
+use super::executor::{self, Context};
+use super::expression::{Expression, Row};
+use super::function;
+use super::parser;
+use super::statement::{CreateTrigger, Statement, TriggerEvent, TriggerTiming};
+use super::view;
+use super::SqlError;
+use backend::database::{Column, Database};
+use std::error;
+
+/// The triggers a change to a table or view fires, each one runs for every row changed.
+pub struct Triggers {
+    triggers: Vec<CreateTrigger>,
+    /// the names of the columns of the table, which `OLD` and `NEW` have
+    names: Vec<String>,
+}
+
+impl Triggers {
+    /// Reads the triggers on the table for the event, an update fires the triggers on any of
+    /// the columns it assigns.
+    pub fn load(
+        database: &mut Database,
+        table: &str,
+        event: &TriggerEvent,
+        names: Vec<String>,
+    ) -> Result<Triggers, Box<dyn error::Error>> {
+        let mut triggers = Vec::new();
+        for sql in database.table_triggers(table)? {
+            let trigger = match parser::parse_statement(&sql)? {
+                Statement::CreateTrigger(trigger) => trigger,
+                _ => {
+                    return Err(Box::new(SqlError::new(format!(
+                        "a trigger on {} is stored incorrectly",
+                        table
+                    ))))
+                }
+            };
+            if fired_by(&trigger.event, event) {
+                triggers.push(trigger);
+            }
+        }
+        Ok(Triggers { triggers, names })
+    }
+
+    pub fn any(&self, timing: TriggerTiming) -> bool {
+        self.triggers.iter().any(|trigger| trigger.timing == timing)
+    }
+
+    /// Runs the triggers with the timing for a row, `old` is the row before the change and
+    /// `new` the row after it. A trigger doesn't fire again while its own statements run.
+    pub fn fire(
+        &self,
+        context: &Context,
+        timing: TriggerTiming,
+        old: Option<&[Column]>,
+        new: Option<&[Column]>,
+    ) -> Result<(), Box<dyn error::Error>> {
+        for trigger in &self.triggers {
+            if trigger.timing != timing || context.trigger_running(&trigger.name) {
+                continue;
+            }
+            let mut bind = |expression: &mut Expression| {
+                if let Some(value) = self.row_value(expression, old, new) {
+                    *expression = Expression::Literal(value);
+                }
+            };
+
+            if let Some(when) = &trigger.when {
+                let mut when = when.clone();
+                when.visit_mut(&mut bind);
+                if !when.is_true(&Row::empty().with_scope(context.scope(None)))? {
+                    continue;
+                }
+            }
+
+            context.start_trigger(trigger.name.clone());
+            let mut result = Ok(());
+            for statement in &trigger.body {
+                let mut statement = statement.clone();
+                statement.visit_expressions_mut(&mut bind);
+                result = view::expand(&mut context.database(), &mut statement);
+                if result.is_ok() {
+                    function::resolve(&mut statement, &context.database().functions);
+                    result = executor::execute_statement(context, statement).map(|_| ());
+                }
+                if result.is_err() {
+                    break;
+                }
+            }
+            context.finish_trigger();
+            result?;
+        }
+        Ok(())
+    }
+
+    // the value of `OLD.column` or `NEW.column`
+    fn row_value(
+        &self,
+        expression: &Expression,
+        old: Option<&[Column]>,
+        new: Option<&[Column]>,
+    ) -> Option<Column> {
+        let (row, name) = match expression {
+            Expression::Column {
+                table: Some(table),
+                name,
+            } if table.eq_ignore_ascii_case("old") => (old?, name),
+            Expression::Column {
+                table: Some(table),
+                name,
+            } if table.eq_ignore_ascii_case("new") => (new?, name),
+            _ => return None,
+        };
+        let position = self
+            .names
+            .iter()
+            .position(|column| column.eq_ignore_ascii_case(name))?;
+        row.get(position).cloned()
+    }
+}
+
+// whether a trigger on the event fires for a change, `UPDATE OF` triggers only fire when one
+// of their columns is assigned
+fn fired_by(trigger_event: &TriggerEvent, event: &TriggerEvent) -> bool {
+    match (trigger_event, event) {
+        (TriggerEvent::Insert, TriggerEvent::Insert) => true,
+        (TriggerEvent::Delete, TriggerEvent::Delete) => true,
+        (TriggerEvent::Update(columns), TriggerEvent::Update(assigned)) => {
+            columns.is_empty()
+                || columns.iter().any(|column| {
+                    assigned
+                        .iter()
+                        .any(|assigned| assigned.eq_ignore_ascii_case(column))
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Whether a statement writes to a table or view with triggers, those statements are run by
+/// the executor rather than compiled.
+pub fn fires(
+    database: &mut Database,
+    statement: &Statement,
+) -> Result<bool, Box<dyn error::Error>> {
+    let table = match statement {
+        Statement::Insert(insert) => &insert.table,
+        Statement::Update(update) => &update.table,
+        Statement::Delete(delete) => &delete.table,
+        _ => return Ok(false),
+    };
+    Ok(!database.table_triggers(table)?.is_empty())
+}
@@ -0,0 +1,274 @@
+use super::executor::{Context, Relation};
+use super::expression::{ColumnName, Expression};
+use super::parser;
+use super::select;
+use super::statement::{
+    CommonTable, From, JoinConstraint, ResultColumn, Select, Statement, TableReference,
+    TableSource, With,
+};
+use super::SqlError;
+use backend::database::Database;
+use std::error;
+
+/// Replaces the views a statement reads with the queries they were created with, each one
+/// becomes a derived table named after its view. Only views with triggers can be written to.
+pub fn expand(
+    database: &mut Database,
+    statement: &mut Statement,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut expander = Expander {
+        database,
+        common_tables: Vec::new(),
+        expanding: Vec::new(),
+    };
+    expander.statement(statement)
+}
+
+/// Reads every row of a view, with the columns qualified by its name.
+pub fn scan(context: &Context, name: &str) -> Result<Relation, Box<dyn error::Error>> {
+    let mut statement = Statement::Select(Box::new(select_all(name)));
+    expand(&mut context.database(), &mut statement)?;
+    let result = match &statement {
+        Statement::Select(select) => select::execute_select(context, select, None)?,
+        _ => unreachable!(),
+    };
+    let names = result
+        .columns
+        .into_iter()
+        .map(|column| ColumnName::new(Some(name.to_string()), column))
+        .collect();
+    Ok(Relation {
+        names,
+        rows: result.rows,
+    })
+}
+
+// `SELECT * FROM name`
+fn select_all(name: &str) -> Select {
+    let from = From {
+        table: TableReference {
+            source: TableSource::Table(name.to_string()),
+            alias: None,
+        },
+        joins: Vec::new(),
+    };
+    Select {
+        with: None,
+        distinct: false,
+        columns: vec![ResultColumn::All],
+        from: Some(from),
+        selection: None,
+        group_by: Vec::new(),
+        having: None,
+        compound: Vec::new(),
+        order_by: Vec::new(),
+        limit: None,
+        offset: None,
+    }
+}
+
+struct Expander<'d> {
+    database: &'d mut Database,
+    /// the common tables in scope, which hide views with the same name
+    common_tables: Vec<String>,
+    /// the views being expanded, innermost last
+    expanding: Vec<String>,
+}
+
+impl<'d> Expander<'d> {
+    fn statement(&mut self, statement: &mut Statement) -> Result<(), Box<dyn error::Error>> {
+        match statement {
+            Statement::Select(select) => self.select(select),
+            Statement::Insert(insert) => {
+                self.check_writable(&insert.table)?;
+                for expression in insert.values.iter_mut().flatten() {
+                    self.expression(expression)?;
+                }
+                Ok(())
+            }
+            Statement::Update(update) => {
+                self.check_writable(&update.table)?;
+                let assigned = update.assignments.iter_mut().map(|(_, value)| value);
+                for expression in assigned.chain(update.selection.iter_mut()) {
+                    self.expression(expression)?;
+                }
+                Ok(())
+            }
+            Statement::Delete(delete) => {
+                self.check_writable(&delete.table)?;
+                for expression in delete.selection.iter_mut() {
+                    self.expression(expression)?;
+                }
+                Ok(())
+            }
+            Statement::Explain { statement, .. } => self.statement(statement),
+            // a view is kept as it was written and only expanded when it is read
+            Statement::CreateView(_)
+            | Statement::DropView { .. }
+            | Statement::CreateTrigger(_)
+            | Statement::DropTrigger { .. }
+            | Statement::CreateIndex(_)
+            | Statement::DropTable { .. }
+            | Statement::AlterTable(_)
+            | Statement::Analyze(_) => Ok(()),
+        }
+    }
+
+    fn select(&mut self, select: &mut Select) -> Result<(), Box<dyn error::Error>> {
+        let count = self.common_tables.len();
+        let result = self.scoped_select(select);
+        self.common_tables.truncate(count);
+        result
+    }
+
+    fn scoped_select(&mut self, select: &mut Select) -> Result<(), Box<dyn error::Error>> {
+        if let Some(with) = &mut select.with {
+            for table in with.tables.iter_mut() {
+                // a common table can read itself, so it hides a view while it is defined
+                self.common_tables.push(table.name.clone());
+                self.select(&mut table.select)?;
+            }
+        }
+
+        for column in select.columns.iter_mut() {
+            if let ResultColumn::Expression { expression, .. } = column {
+                self.expression(expression)?;
+            }
+        }
+
+        if let Some(from) = &mut select.from {
+            let joined = from.joins.iter_mut().map(|join| &mut join.table);
+            for table in std::iter::once(&mut from.table).chain(joined) {
+                self.table(table)?;
+            }
+            for join in from.joins.iter_mut() {
+                if let JoinConstraint::On(condition) = &mut join.constraint {
+                    self.expression(condition)?;
+                }
+            }
+        }
+
+        let expressions = select
+            .selection
+            .iter_mut()
+            .chain(select.group_by.iter_mut())
+            .chain(select.having.iter_mut())
+            .chain(select.order_by.iter_mut().map(|term| &mut term.expression))
+            .chain(select.limit.iter_mut())
+            .chain(select.offset.iter_mut());
+        for expression in expressions {
+            self.expression(expression)?;
+        }
+
+        for (_, term) in select.compound.iter_mut() {
+            self.select(term)?;
+        }
+        Ok(())
+    }
+
+    fn table(&mut self, table: &mut TableReference) -> Result<(), Box<dyn error::Error>> {
+        let name = match &mut table.source {
+            TableSource::Subquery(subquery) => return self.select(subquery),
+            TableSource::Function { arguments, .. } => {
+                for argument in arguments.iter_mut() {
+                    self.expression(argument)?;
+                }
+                return Ok(());
+            }
+            TableSource::Table(name) if !self.is_common_table(name) => name.clone(),
+            TableSource::Table(_) => return Ok(()),
+        };
+        let view = match self.view(&name)? {
+            Some(view) => view,
+            None => return Ok(()),
+        };
+
+        table.source = TableSource::Subquery(Box::new(view));
+        if table.alias.is_none() {
+            table.alias = Some(name);
+        }
+        Ok(())
+    }
+
+    fn expression(&mut self, expression: &mut Expression) -> Result<(), Box<dyn error::Error>> {
+        match expression {
+            Expression::Subquery(select)
+            | Expression::Exists(select)
+            | Expression::InSubquery { select, .. } => self.select(select)?,
+            _ => {}
+        }
+        for child in expression.children_mut() {
+            self.expression(child)?;
+        }
+        Ok(())
+    }
+
+    // the query a view stands for with the views it reads expanded in turn, None when there
+    // is no view with the name
+    fn view(&mut self, name: &str) -> Result<Option<Select>, Box<dyn error::Error>> {
+        let sql = match self.database.view_definition(name)? {
+            Some(sql) => sql,
+            None => return Ok(None),
+        };
+        if self.expanding.iter().any(|view| view == name) {
+            return Err(Box::new(SqlError::new(format!(
+                "view {} is circularly defined",
+                name
+            ))));
+        }
+        let create_view = match parser::parse_statement(&sql)? {
+            Statement::CreateView(create_view) => create_view,
+            _ => {
+                return Err(Box::new(SqlError::new(format!(
+                    "view {} is stored incorrectly",
+                    name
+                ))))
+            }
+        };
+
+        // the query is expanded with only its own common tables in scope
+        let common_tables = std::mem::take(&mut self.common_tables);
+        self.expanding.push(name.to_string());
+        let mut select = *create_view.select;
+        let result = self.select(&mut select);
+        self.expanding.pop();
+        self.common_tables = common_tables;
+        result?;
+
+        if create_view.columns.is_empty() {
+            return Ok(Some(select));
+        }
+        // the columns are renamed by reading the query as a common table
+        let with = With {
+            recursive: false,
+            tables: vec![CommonTable {
+                name: name.to_string(),
+                columns: create_view.columns,
+                select: Box::new(select),
+            }],
+        };
+        Ok(Some(Select {
+            with: Some(with),
+            ..select_all(name)
+        }))
+    }
+
+    // a view can only be written to by its triggers
+    fn check_writable(&mut self, table: &str) -> Result<(), Box<dyn error::Error>> {
+        if self.database.view_definition(table)?.is_some()
+            && self.database.table_triggers(table)?.is_empty()
+        {
+            return Err(Box::new(SqlError::new(format!(
+                "cannot modify {} because it is a view",
+                table
+            ))));
+        }
+        Ok(())
+    }
+
+    fn is_common_table(&self, name: &str) -> bool {
+        self.common_tables
+            .iter()
+            .any(|table| table.eq_ignore_ascii_case(name))
+    }
+}
@@ -0,0 +1,458 @@
+use super::executor::ResultSet;
+use super::expression::{apply_binary, evaluate_unary, to_bool, BinaryOperator, UnaryOperator};
+use super::select::compare_keys;
+use super::statement::OrderingTerm;
+use super::SqlError;
+use backend::database::{Column, ColumnType, Database, TableCursor};
+use std::error;
+
+/// A statement compiled into instructions for the virtual machine. Instructions read and
+/// write numbered registers, walk tables and sorters through numbered cursors and jump to
+/// other instructions by their address.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    pub register_count: usize,
+    pub cursor_count: usize,
+    /// the names of the columns of the rows the program returns
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Goto {
+        address: usize,
+    },
+    /// ends the program, any cursors still open are closed
+    Halt,
+    OpenRead {
+        cursor: usize,
+        table: String,
+    },
+    OpenWrite {
+        cursor: usize,
+        table: String,
+    },
+    /// opens a sorter whose rows are ordered by their leading values, one for each term
+    SorterOpen {
+        cursor: usize,
+        order: Vec<OrderingTerm>,
+    },
+    Close {
+        cursor: usize,
+    },
+    /// moves to the first row, jumping to `address` when there are none
+    Rewind {
+        cursor: usize,
+        address: usize,
+    },
+    /// moves to the next row, jumping to `address` when there is one
+    Next {
+        cursor: usize,
+        address: usize,
+    },
+    /// sorts the rows then moves to the first, jumping to `address` when there are none
+    SorterSort {
+        cursor: usize,
+        address: usize,
+    },
+    SorterNext {
+        cursor: usize,
+        address: usize,
+    },
+    SorterInsert {
+        cursor: usize,
+        start: usize,
+        count: usize,
+    },
+    /// reads a column of the current row, NULL when the row is too short to have it
+    Column {
+        cursor: usize,
+        column: usize,
+        target: usize,
+    },
+    Value {
+        value: Column,
+        target: usize,
+    },
+    Copy {
+        source: usize,
+        target: usize,
+    },
+    Unary {
+        operator: UnaryOperator,
+        source: usize,
+        target: usize,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: usize,
+        right: usize,
+        target: usize,
+    },
+    IsNull {
+        source: usize,
+        target: usize,
+        negated: bool,
+    },
+    /// converts the register by a column affinity before it is compared
+    Affinity {
+        register: usize,
+        affinity: ColumnType,
+    },
+    /// jumps when the register is true
+    If {
+        register: usize,
+        address: usize,
+    },
+    /// jumps when the register is false or NULL
+    IfNot {
+        register: usize,
+        address: usize,
+    },
+    /// jumps and decrements the register when it is positive, used for OFFSET
+    IfPos {
+        register: usize,
+        address: usize,
+    },
+    /// decrements the register and jumps once it reaches zero, used for LIMIT
+    DecrJumpZero {
+        register: usize,
+        address: usize,
+    },
+    ResultRow {
+        start: usize,
+        count: usize,
+    },
+    /// appends the registers to the cursor's table as a new row
+    Insert {
+        cursor: usize,
+        start: usize,
+        count: usize,
+    },
+    /// overwrites the cursor's current row with the registers
+    Replace {
+        cursor: usize,
+        start: usize,
+        count: usize,
+    },
+    Delete {
+        cursor: usize,
+    },
+}
+
+impl Instruction {
+    /// The address the instruction can jump to.
+    pub fn address_mut(&mut self) -> Option<&mut usize> {
+        match self {
+            Instruction::Goto { address }
+            | Instruction::Rewind { address, .. }
+            | Instruction::Next { address, .. }
+            | Instruction::SorterSort { address, .. }
+            | Instruction::SorterNext { address, .. }
+            | Instruction::If { address, .. }
+            | Instruction::IfNot { address, .. }
+            | Instruction::IfPos { address, .. }
+            | Instruction::DecrJumpZero { address, .. } => Some(address),
+            _ => None,
+        }
+    }
+}
+
+/// Runs a program, returning the rows it produced.
+pub fn run(database: &mut Database, program: &Program) -> Result<ResultSet, Box<dyn error::Error>> {
+    let mut machine = Machine {
+        registers: vec![Column::Null(); program.register_count],
+        cursors: (0..program.cursor_count).map(|_| None).collect(),
+        rows: Vec::new(),
+    };
+    let result = machine.run(database, &program.instructions);
+    // cursors are closed even when the program fails, before its statement is rolled back
+    let closed = machine.close_all(database);
+    result?;
+    closed?;
+
+    Ok(ResultSet::new(program.columns.clone(), machine.rows))
+}
+
+enum Cursor {
+    Table { cursor: TableCursor, writable: bool },
+    Sorter(Sorter),
+}
+
+// rows held in memory until they have all been added, then read back in order
+struct Sorter {
+    order: Vec<OrderingTerm>,
+    rows: Vec<Vec<Column>>,
+    position: usize,
+}
+
+struct Machine {
+    registers: Vec<Column>,
+    cursors: Vec<Option<Cursor>>,
+    rows: Vec<Vec<Column>>,
+}
+
+impl Machine {
+    fn run(
+        &mut self,
+        database: &mut Database,
+        instructions: &[Instruction],
+    ) -> Result<(), Box<dyn error::Error>> {
+        let mut address = 0;
+        while let Some(instruction) = instructions.get(address) {
+            address += 1;
+            match instruction {
+                Instruction::Goto { address: target } => address = *target,
+                Instruction::Halt => break,
+                Instruction::OpenRead { cursor, table } => {
+                    let table_cursor = database.open_cursor(table.clone())?;
+                    self.open(
+                        *cursor,
+                        Cursor::Table {
+                            cursor: table_cursor,
+                            writable: false,
+                        },
+                    )?;
+                }
+                Instruction::OpenWrite { cursor, table } => {
+                    let table_cursor = database.open_cursor(table.clone())?;
+                    self.open(
+                        *cursor,
+                        Cursor::Table {
+                            cursor: table_cursor,
+                            writable: true,
+                        },
+                    )?;
+                }
+                Instruction::SorterOpen { cursor, order } => {
+                    let sorter = Sorter {
+                        order: order.clone(),
+                        rows: Vec::new(),
+                        position: 0,
+                    };
+                    self.open(*cursor, Cursor::Sorter(sorter))?;
+                }
+                Instruction::Close { cursor } => {
+                    if let Some(Cursor::Table { cursor, .. }) = self.cursors[*cursor].take() {
+                        cursor.close(database)?;
+                    }
+                }
+                Instruction::Rewind {
+                    cursor,
+                    address: target,
+                } => {
+                    if !self.table(*cursor)?.rewind() {
+                        address = *target;
+                    }
+                }
+                Instruction::Next {
+                    cursor,
+                    address: target,
+                } => {
+                    if self.table(*cursor)?.advance() {
+                        address = *target;
+                    }
+                }
+                Instruction::SorterSort {
+                    cursor,
+                    address: target,
+                } => {
+                    let sorter = self.sorter(*cursor)?;
+                    let order = &sorter.order;
+                    sorter
+                        .rows
+                        .sort_by(|left, right| compare_keys(left, right, order, &[]));
+                    sorter.position = 0;
+                    if sorter.rows.is_empty() {
+                        address = *target;
+                    }
+                }
+                Instruction::SorterNext {
+                    cursor,
+                    address: target,
+                } => {
+                    let sorter = self.sorter(*cursor)?;
+                    sorter.position += 1;
+                    if sorter.position < sorter.rows.len() {
+                        address = *target;
+                    }
+                }
+                Instruction::SorterInsert {
+                    cursor,
+                    start,
+                    count,
+                } => {
+                    let row = self.registers[*start..*start + *count].to_vec();
+                    self.sorter(*cursor)?.rows.push(row);
+                }
+                Instruction::Column {
+                    cursor,
+                    column,
+                    target,
+                } => {
+                    let value = self.column(database, *cursor, *column)?;
+                    self.registers[*target] = value;
+                }
+                Instruction::Value { value, target } => self.registers[*target] = value.clone(),
+                Instruction::Copy { source, target } => {
+                    self.registers[*target] = self.registers[*source].clone();
+                }
+                Instruction::Unary {
+                    operator,
+                    source,
+                    target,
+                } => {
+                    let value = self.registers[*source].clone();
+                    self.registers[*target] = evaluate_unary(operator, value);
+                }
+                Instruction::Binary {
+                    operator,
+                    left,
+                    right,
+                    target,
+                } => {
+                    let value =
+                        apply_binary(operator, &self.registers[*left], &self.registers[*right]);
+                    self.registers[*target] = value;
+                }
+                Instruction::IsNull {
+                    source,
+                    target,
+                    negated,
+                } => {
+                    let is_null = self.registers[*source] == Column::Null();
+                    self.registers[*target] = Column::Integer((is_null != *negated) as i128);
+                }
+                Instruction::Affinity { register, affinity } => {
+                    let value = std::mem::replace(&mut self.registers[*register], Column::Null());
+                    self.registers[*register] = affinity.apply(value);
+                }
+                Instruction::If {
+                    register,
+                    address: target,
+                } => {
+                    if to_bool(&self.registers[*register]) == Some(true) {
+                        address = *target;
+                    }
+                }
+                Instruction::IfNot {
+                    register,
+                    address: target,
+                } => {
+                    if to_bool(&self.registers[*register]) != Some(true) {
+                        address = *target;
+                    }
+                }
+                Instruction::IfPos {
+                    register,
+                    address: target,
+                } => {
+                    if let Column::Integer(count) = &mut self.registers[*register] {
+                        if *count > 0 {
+                            *count -= 1;
+                            address = *target;
+                        }
+                    }
+                }
+                Instruction::DecrJumpZero {
+                    register,
+                    address: target,
+                } => {
+                    if let Column::Integer(count) = &mut self.registers[*register] {
+                        *count -= 1;
+                        if *count == 0 {
+                            address = *target;
+                        }
+                    }
+                }
+                Instruction::ResultRow { start, count } => {
+                    self.rows
+                        .push(self.registers[*start..*start + *count].to_vec());
+                }
+                Instruction::Insert {
+                    cursor,
+                    start,
+                    count,
+                } => {
+                    let row = self.registers[*start..*start + *count].to_vec();
+                    self.writable(*cursor)?.insert(database, row)?;
+                }
+                Instruction::Replace {
+                    cursor,
+                    start,
+                    count,
+                } => {
+                    let row = self.registers[*start..*start + *count].to_vec();
+                    self.writable(*cursor)?.replace(database, row)?;
+                }
+                Instruction::Delete { cursor } => self.writable(*cursor)?.delete(database)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, number: usize, cursor: Cursor) -> Result<(), SqlError> {
+        match self.cursors.get_mut(number) {
+            Some(slot) => {
+                *slot = Some(cursor);
+                Ok(())
+            }
+            None => Err(SqlError::new(format!("no cursor {}", number))),
+        }
+    }
+
+    fn table(&mut self, number: usize) -> Result<&mut TableCursor, SqlError> {
+        match self.cursors.get_mut(number) {
+            Some(Some(Cursor::Table { cursor, .. })) => Ok(cursor),
+            _ => Err(SqlError::new(format!("cursor {} is not a table", number))),
+        }
+    }
+
+    fn writable(&mut self, number: usize) -> Result<&mut TableCursor, SqlError> {
+        match self.cursors.get_mut(number) {
+            Some(Some(Cursor::Table {
+                cursor,
+                writable: true,
+            })) => Ok(cursor),
+            _ => Err(SqlError::new(format!("cursor {} is not writable", number))),
+        }
+    }
+
+    fn sorter(&mut self, number: usize) -> Result<&mut Sorter, SqlError> {
+        match self.cursors.get_mut(number) {
+            Some(Some(Cursor::Sorter(sorter))) => Ok(sorter),
+            _ => Err(SqlError::new(format!("cursor {} is not a sorter", number))),
+        }
+    }
+
+    fn column(
+        &mut self,
+        database: &mut Database,
+        number: usize,
+        column: usize,
+    ) -> Result<Column, Box<dyn error::Error>> {
+        let row = match self.cursors.get_mut(number) {
+            Some(Some(Cursor::Table { cursor, .. })) => cursor.record(database)?,
+            Some(Some(Cursor::Sorter(sorter))) => match sorter.rows.get(sorter.position) {
+                Some(row) => row.as_slice(),
+                None => return Err(Box::new(SqlError::new("sorter is not at a row"))),
+            },
+            _ => return Err(Box::new(SqlError::new(format!("no cursor {}", number)))),
+        };
+        Ok(row.get(column).cloned().unwrap_or(Column::Null()))
+    }
+
+    fn close_all(&mut self, database: &mut Database) -> Result<(), Box<dyn error::Error>> {
+        let mut result = Ok(());
+        for slot in self.cursors.iter_mut() {
+            if let Some(Cursor::Table { cursor, .. }) = slot.take() {
+                let closed = cursor.close(database);
+                if result.is_ok() {
+                    result = closed;
+                }
+            }
+        }
+        result
+    }
+}
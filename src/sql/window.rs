@@ -0,0 +1,532 @@
+use super::aggregate::{self, compare_group_keys, Group};
+use super::expression::{ordered, to_numeric, ColumnName, Expression, Row, Scope};
+use super::select::{compare_keys, same_row};
+use super::statement::{Frame, FrameBound, FrameUnits, Window};
+use super::SqlError;
+use backend::database::{Collation, Column, ColumnType};
+
+// functions that can only be called with OVER, aggregates can be called either way
+pub fn is_window_function(name: &str) -> bool {
+    matches!(
+        name,
+        "row_number"
+            | "rank"
+            | "dense_rank"
+            | "lag"
+            | "lead"
+            | "first_value"
+            | "last_value"
+            | "nth_value"
+    )
+}
+
+/// Finds the window function calls in the expressions, each distinct call is listed once.
+pub fn find_calls<'a, I>(expressions: I) -> Vec<Expression>
+where
+    I: IntoIterator<Item = &'a Expression>,
+{
+    let mut calls = Vec::new();
+    for expression in expressions {
+        collect_calls(expression, &mut calls);
+    }
+    calls
+}
+
+fn collect_calls(expression: &Expression, calls: &mut Vec<Expression>) {
+    match expression {
+        Expression::WindowFunction { .. } => {
+            if !calls.contains(expression) {
+                calls.push(expression.clone());
+            }
+        }
+        _ => {
+            for child in expression.children() {
+                collect_calls(child, calls);
+            }
+        }
+    }
+}
+
+/// Computes each window function call for every row. The rows are sorted by their partition
+/// and ORDER BY keys, then each partition is walked in order. The results are added to the
+/// rows' aggregates, where evaluating the call finds them.
+pub fn evaluate_windows(
+    names: &[ColumnName],
+    groups: &mut [Group],
+    calls: &[Expression],
+    scope: Scope,
+) -> Result<(), SqlError> {
+    for call in calls {
+        let values = evaluate_call(names, groups, call, scope)?;
+        for (group, value) in groups.iter_mut().zip(values) {
+            group.aggregates.push((call.clone(), value));
+        }
+    }
+    Ok(())
+}
+
+// the values of a row a window function needs
+struct WindowRow {
+    partition: Vec<Column>,
+    order: Vec<Column>,
+    arguments: Vec<Column>,
+}
+
+fn evaluate_call(
+    names: &[ColumnName],
+    groups: &[Group],
+    call: &Expression,
+    scope: Scope,
+) -> Result<Vec<Column>, SqlError> {
+    let (function, window) = match call {
+        Expression::WindowFunction { call, window } => (call.as_ref(), window.as_ref()),
+        _ => return Err(SqlError::new(format!("{} is not a window function", call))),
+    };
+    let (name, arguments, aggregate) = match function {
+        Expression::Function {
+            distinct: true,
+            name,
+            ..
+        } => {
+            return Err(SqlError::new(format!(
+                "DISTINCT is not supported for window functions: {}()",
+                name
+            )))
+        }
+        Expression::Function {
+            name,
+            arguments,
+            aggregate,
+            ..
+        } => (name, arguments, *aggregate),
+        _ => return Err(SqlError::new(format!("{} is not a window function", call))),
+    };
+    check_arguments(name, arguments.len(), aggregate)?;
+    let orders = aggregate::argument_orders(function, names, scope)?;
+    // partitions and peers are told apart by the collations of their terms
+    let names_row = Row::new(names, &[]).with_scope(scope);
+    let partition_collations = window
+        .partition_by
+        .iter()
+        .map(|expression| names_row.collation(expression))
+        .collect::<Result<Vec<_>, _>>()?;
+    let order_collations = window
+        .order_by
+        .iter()
+        .map(|term| names_row.collation(&term.expression))
+        .collect::<Result<Vec<_>, _>>()?;
+    let order_affinities: Vec<_> = window
+        .order_by
+        .iter()
+        .map(|term| names_row.affinity(&term.expression))
+        .collect();
+
+    let mut rows = Vec::with_capacity(groups.len());
+    for group in groups {
+        let row = Row::with_aggregates(names, &group.values, &group.aggregates).with_scope(scope);
+        rows.push(WindowRow {
+            partition: evaluate_all(window.partition_by.iter(), &row)?,
+            order: evaluate_all(window.order_by.iter().map(|term| &term.expression), &row)?
+                .into_iter()
+                .zip(&order_affinities)
+                .map(|(value, affinity)| ordered(value, affinity))
+                .collect(),
+            arguments: evaluate_all(arguments.iter(), &row)?,
+        });
+    }
+
+    // the sort is stable so rows that tie keep the order they were found in
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by(|&left, &right| {
+        compare_group_keys(
+            &rows[left].partition,
+            &rows[right].partition,
+            &partition_collations,
+        )
+        .then_with(|| {
+            compare_keys(
+                &rows[left].order,
+                &rows[right].order,
+                &window.order_by,
+                &order_collations,
+            )
+        })
+    });
+
+    let mut values = vec![Column::Null(); rows.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len()
+            && same_row(
+                &rows[order[start]].partition,
+                &rows[order[end]].partition,
+                &partition_collations,
+            )
+        {
+            end += 1;
+        }
+
+        let partition: Vec<&WindowRow> = order[start..end].iter().map(|&i| &rows[i]).collect();
+        let peers = peer_ranges(&partition, &order_collations);
+        let results = evaluate_partition(function, &partition, &peers, window, &orders, scope)?;
+        for (&index, value) in order[start..end].iter().zip(results) {
+            values[index] = value;
+        }
+        start = end;
+    }
+    Ok(values)
+}
+
+fn check_arguments(name: &str, count: usize, aggregate: bool) -> Result<(), SqlError> {
+    let expected = match name {
+        _ if aggregate => return Ok(()),
+        "row_number" | "rank" | "dense_rank" => 0..=0,
+        "lag" | "lead" => 1..=3,
+        "first_value" | "last_value" => 1..=1,
+        "nth_value" => 2..=2,
+        _ => return Err(SqlError::new(format!("no such window function: {}", name))),
+    };
+    if expected.contains(&count) {
+        Ok(())
+    } else {
+        Err(SqlError::new(format!(
+            "wrong number of arguments to function {}()",
+            name
+        )))
+    }
+}
+
+// the results for the rows of one partition, in window order
+fn evaluate_partition(
+    function: &Expression,
+    partition: &[&WindowRow],
+    peers: &[(usize, usize)],
+    window: &Window,
+    orders: &[(Collation, Option<ColumnType>)],
+    scope: Scope,
+) -> Result<Vec<Column>, SqlError> {
+    let mut values = Vec::with_capacity(partition.len());
+
+    let name = match function {
+        Expression::Function {
+            aggregate: true, ..
+        } => return evaluate_aggregate(function, partition, peers, window, orders, scope),
+        Expression::Function { name, .. } => name.as_str(),
+        _ => {
+            return Err(SqlError::new(format!(
+                "{} is not a window function",
+                function
+            )))
+        }
+    };
+    match name {
+        "row_number" => {
+            for index in 0..partition.len() {
+                values.push(Column::Integer(index as i128 + 1));
+            }
+        }
+        "rank" => {
+            for (peer_start, _) in peers {
+                values.push(Column::Integer(*peer_start as i128 + 1));
+            }
+        }
+        "dense_rank" => {
+            let mut rank = 0;
+            for (index, (peer_start, _)) in peers.iter().enumerate() {
+                if *peer_start == index {
+                    rank += 1;
+                }
+                values.push(Column::Integer(rank));
+            }
+        }
+        "lag" | "lead" => {
+            for (index, row) in partition.iter().enumerate() {
+                let offset = match row.arguments.get(1) {
+                    Some(offset) => integer_argument(name, offset)?,
+                    None => 1,
+                };
+                let target = if name == "lag" {
+                    index as i128 - offset
+                } else {
+                    index as i128 + offset
+                };
+                let value = if target >= 0 && (target as usize) < partition.len() {
+                    partition[target as usize].arguments[0].clone()
+                } else {
+                    row.arguments.get(2).cloned().unwrap_or(Column::Null())
+                };
+                values.push(value);
+            }
+        }
+        _ => {
+            for (index, row) in partition.iter().enumerate() {
+                let (start, end) = frame_range(partition, peers, index, window)?;
+                let value = match name {
+                    "first_value" if start < end => partition[start].arguments[0].clone(),
+                    "last_value" if start < end => partition[end - 1].arguments[0].clone(),
+                    "nth_value" => {
+                        let n = integer_argument(name, &row.arguments[1])?;
+                        if n < 1 {
+                            return Err(SqlError::new(
+                                "second argument to nth_value must be a positive integer",
+                            ));
+                        }
+                        let target = start as i128 + n - 1;
+                        if target < end as i128 {
+                            partition[target as usize].arguments[0].clone()
+                        } else {
+                            Column::Null()
+                        }
+                    }
+                    _ => Column::Null(),
+                };
+                values.push(value);
+            }
+        }
+    }
+
+    Ok(values)
+}
+
+// an aggregate over the frame of each row. When a frame only moves forward from the last one
+// the rows it gained are stepped and those it lost are taken away, if the aggregate can take
+// rows away, otherwise the frame is stepped through from its start.
+// `orders` are the collations and affinities of the aggregate's arguments
+fn evaluate_aggregate(
+    function: &Expression,
+    partition: &[&WindowRow],
+    peers: &[(usize, usize)],
+    window: &Window,
+    orders: &[(Collation, Option<ColumnType>)],
+    scope: Scope,
+) -> Result<Vec<Column>, SqlError> {
+    let mut values = Vec::with_capacity(partition.len());
+    let mut running: Option<(Box<dyn aggregate::Aggregate>, usize, usize)> = None;
+
+    for index in 0..partition.len() {
+        let (start, end) = frame_range(partition, peers, index, window)?;
+        let slid = match running.take() {
+            Some((mut aggregate, last_start, last_end))
+                if last_start <= start && last_end <= end =>
+            {
+                let mut slid = true;
+                for removed in &partition[last_start..start.min(last_end)] {
+                    if !aggregate.inverse(&removed.arguments)? {
+                        slid = false;
+                        break;
+                    }
+                }
+                if slid {
+                    for added in &partition[last_end.max(start)..end] {
+                        aggregate.step(&added.arguments)?;
+                    }
+                    Some(aggregate)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        let aggregate = match slid {
+            Some(aggregate) => aggregate,
+            None => {
+                let mut aggregate = aggregate::create_call(function, orders, scope)?;
+                for framed in &partition[start..end] {
+                    aggregate.step(&framed.arguments)?;
+                }
+                aggregate
+            }
+        };
+        values.push(aggregate.finalize()?);
+        running = Some((aggregate, start, end));
+    }
+
+    Ok(values)
+}
+
+// for each row, the range of rows with the same ORDER BY values, its peers
+fn peer_ranges(partition: &[&WindowRow], collations: &[Collation]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::with_capacity(partition.len());
+    let mut start = 0;
+    while start < partition.len() {
+        let mut end = start + 1;
+        while end < partition.len()
+            && same_row(&partition[start].order, &partition[end].order, collations)
+        {
+            end += 1;
+        }
+        for _ in start..end {
+            ranges.push((start, end));
+        }
+        start = end;
+    }
+    ranges
+}
+
+// the rows in the frame of the row at `index`, as a range of positions in the partition
+fn frame_range(
+    partition: &[&WindowRow],
+    peers: &[(usize, usize)],
+    index: usize,
+    window: &Window,
+) -> Result<(usize, usize), SqlError> {
+    let (peer_start, peer_end) = peers[index];
+    let frame = match &window.frame {
+        Some(frame) => frame,
+        None if window.order_by.is_empty() => return Ok((0, partition.len())),
+        None => return Ok((0, peer_end)),
+    };
+
+    let (start, end) = match frame.units {
+        FrameUnits::Rows => {
+            let start = row_offset(&frame.start, index, partition.len())?;
+            let end = row_offset(&frame.end, index, partition.len())? + 1;
+            (
+                start.max(0) as usize,
+                end.clamp(0, partition.len() as i128) as usize,
+            )
+        }
+        FrameUnits::Range => range_bounds(partition, frame, index, window, peer_start, peer_end)?,
+    };
+
+    // a frame that starts after it ends is empty
+    Ok((start.min(end), end))
+}
+
+// the position a ROWS bound refers to, which may be outside the partition
+fn row_offset(bound: &FrameBound, index: usize, length: usize) -> Result<i128, SqlError> {
+    let index = index as i128;
+    Ok(match bound {
+        FrameBound::UnboundedPreceding => 0,
+        FrameBound::Preceding(offset) => index - frame_offset(offset)?,
+        FrameBound::CurrentRow => index,
+        FrameBound::Following(offset) => index + frame_offset(offset)?,
+        FrameBound::UnboundedFollowing => length as i128 - 1,
+    })
+}
+
+fn frame_offset(offset: &Expression) -> Result<i128, SqlError> {
+    match to_numeric(&offset.evaluate(&Row::empty())?) {
+        Column::Integer(offset) if offset >= 0 => Ok(offset),
+        _ => Err(SqlError::new("frame offset must be a non-negative integer")),
+    }
+}
+
+// RANGE bounds are distances from the current row's ORDER BY value, in the direction the
+// rows are sorted, every peer of a row is in or out of the frame together
+fn range_bounds(
+    partition: &[&WindowRow],
+    frame: &Frame,
+    index: usize,
+    window: &Window,
+    peer_start: usize,
+    peer_end: usize,
+) -> Result<(usize, usize), SqlError> {
+    let needs_value =
+        |bound: &FrameBound| matches!(bound, FrameBound::Preceding(_) | FrameBound::Following(_));
+    if (needs_value(&frame.start) || needs_value(&frame.end)) && window.order_by.len() != 1 {
+        return Err(SqlError::new(
+            "RANGE with offset PRECEDING/FOLLOWING requires one ORDER BY term",
+        ));
+    }
+
+    let direction = match window.order_by.first() {
+        Some(term) if term.descending => -1.0,
+        _ => 1.0,
+    };
+    let position = |row: &WindowRow| match row.order.first() {
+        None | Some(Column::Null()) => None,
+        Some(value) => Some(real(value) * direction),
+    };
+    let current = position(partition[index]);
+
+    let start = match (&frame.start, current) {
+        (FrameBound::UnboundedPreceding, _) => 0,
+        (FrameBound::UnboundedFollowing, _) => partition.len(),
+        (FrameBound::CurrentRow, _) | (_, None) => peer_start,
+        (FrameBound::Preceding(offset), Some(current)) => {
+            let low = current - range_offset(offset)?;
+            first_at_or_after(partition, &position, low)
+        }
+        (FrameBound::Following(offset), Some(current)) => {
+            let low = current + range_offset(offset)?;
+            first_at_or_after(partition, &position, low)
+        }
+    };
+    let end = match (&frame.end, current) {
+        (FrameBound::UnboundedPreceding, _) => 0,
+        (FrameBound::UnboundedFollowing, _) => partition.len(),
+        (FrameBound::CurrentRow, _) | (_, None) => peer_end,
+        (FrameBound::Preceding(offset), Some(current)) => {
+            let high = current - range_offset(offset)?;
+            last_at_or_before(partition, &position, high)
+        }
+        (FrameBound::Following(offset), Some(current)) => {
+            let high = current + range_offset(offset)?;
+            last_at_or_before(partition, &position, high)
+        }
+    };
+    Ok((start, end))
+}
+
+fn range_offset(offset: &Expression) -> Result<f64, SqlError> {
+    match to_numeric(&offset.evaluate(&Row::empty())?) {
+        Column::Integer(offset) if offset >= 0 => Ok(offset as f64),
+        Column::Real(offset) if offset >= 0.0 => Ok(offset),
+        Column::Decimal(offset) if offset.value >= 0 => Ok(offset.to_real()),
+        _ => Err(SqlError::new("frame offset must be a non-negative number")),
+    }
+}
+
+// the first row whose value is at least `low`, rows with NULL values are never in range
+fn first_at_or_after<F>(partition: &[&WindowRow], position: &F, low: f64) -> usize
+where
+    F: Fn(&WindowRow) -> Option<f64>,
+{
+    partition
+        .iter()
+        .position(|row| position(row).is_some_and(|value| value >= low))
+        .unwrap_or(partition.len())
+}
+
+// the position after the last row whose value is at most `high`
+fn last_at_or_before<F>(partition: &[&WindowRow], position: &F, high: f64) -> usize
+where
+    F: Fn(&WindowRow) -> Option<f64>,
+{
+    partition
+        .iter()
+        .rposition(|row| position(row).is_some_and(|value| value <= high))
+        .map_or(0, |index| index + 1)
+}
+
+fn real(value: &Column) -> f64 {
+    match to_numeric(value) {
+        Column::Integer(int) => int as f64,
+        Column::Real(real) => real,
+        Column::Decimal(decimal) => decimal.to_real(),
+        _ => 0.0,
+    }
+}
+
+fn integer_argument(name: &str, value: &Column) -> Result<i128, SqlError> {
+    match to_numeric(value) {
+        Column::Integer(int) => Ok(int),
+        _ => Err(SqlError::new(format!(
+            "argument to {}() must be an integer",
+            name
+        ))),
+    }
+}
+
+fn evaluate_all<'a, I>(expressions: I, row: &Row) -> Result<Vec<Column>, SqlError>
+where
+    I: Iterator<Item = &'a Expression>,
+{
+    let mut values = Vec::new();
+    for expression in expressions {
+        values.push(expression.evaluate(row)?);
+    }
+    Ok(values)
+}
@@ -0,0 +1,196 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn coercion_on_insert() {
+    let filename = create_db_file("coercion_on_insert");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute(
+            "insert into mixed values (' 12 ', '3', 45, 'raw', '1e2'), \
+             (3.0, 4, 1.5, 7, '2.5'), ('abc', '1x', null, 1.0, '0x10')",
+        )
+        .unwrap();
+    let output = database.execute("select * from mixed").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(12), real(3.0), text("45"), text("raw"), int(100)],
+            vec![int(3), real(4.0), text("1.5"), int(7), real(2.5)],
+            vec![
+                text("abc"),
+                text("1x"),
+                Column::Null(),
+                real(1.0),
+                text("0x10")
+            ],
+        ]
+    );
+
+    // updates are converted the same way
+    database
+        .execute("update mixed set i = '42', r = 2 where t = '45'")
+        .unwrap();
+    let output = database
+        .execute("select i, r from mixed where t = '45'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(42), real(2.0)]]);
+
+    // values given as text no longer have to parse as the column's type
+    let mut row = HashMap::new();
+    row.insert("i".to_string(), "seven".to_string());
+    row.insert("r".to_string(), "-8".to_string());
+    row.insert("n".to_string(), "9.0".to_string());
+    database.insert_record("mixed".to_string(), row).unwrap();
+    let output = database
+        .execute("select i, r, n from mixed where t is null and b is null")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("seven"), real(-8.0), int(9)]]);
+
+    // columns of any other declared type have NUMERIC affinity
+    database
+        .execute("alter table mixed add column born year default '2000'")
+        .unwrap();
+    let (_rootpage, columns) = database.describe_table("mixed".to_string()).unwrap();
+    assert_eq!(columns[5], ("born".to_string(), ColumnType::Numeric));
+    let output = database.execute("select distinct born from mixed").unwrap();
+    assert_eq!(output.rows, vec![vec![int(2000)]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn reals_stored_as_integers() {
+    let filename = create_db_file("reals_stored_as_integers");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute("insert into mixed (r) values (5.0), (-3.0), (0.0), (2.5), (1e300)")
+        .unwrap();
+    let output = database.execute("select r from mixed").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![real(5.0)],
+            vec![real(-3.0)],
+            vec![real(0.0)],
+            vec![real(2.5)],
+            vec![real(1e300)],
+        ]
+    );
+    let output = database
+        .execute("select sum(r) from mixed where r < 3")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![real(-0.5)]]);
+
+    // the table can be read back after reopening and through the record API
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let rows = database.select_all_records("mixed".to_string()).unwrap();
+    assert_eq!(rows[1][1], real(-3.0));
+
+    cleanup(&filename);
+}
+
+#[test]
+fn comparisons_with_affinity() {
+    let filename = create_db_file("comparisons_with_affinity");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .execute(
+            "insert into mixed values (12, 1.5, '45', 'a', 7), (9, 10, '100', 'b', '8'), \
+             (3, 2, '9', 'c', 'x')",
+        )
+        .unwrap();
+
+    // a number column compared with text compares numbers, text columns compare text
+    let queries = vec![
+        ("select b from mixed where i = '12'", vec!["a"]),
+        ("select b from mixed where i > '9' order by b", vec!["a"]),
+        ("select b from mixed where '10' <= r order by b", vec!["b"]),
+        ("select b from mixed where t = 45", vec!["a"]),
+        ("select b from mixed where t > 50 order by b", vec!["c"]),
+        (
+            "select b from mixed where i in ('3', '9') order by b",
+            vec!["b", "c"],
+        ),
+        (
+            "select b from mixed where i between '4' and '10'",
+            vec!["b"],
+        ),
+        ("select b from mixed where n = i - 1", vec!["b"]),
+        ("select b from mixed where t = i", vec![]),
+    ];
+    for (query, expected) in queries {
+        let expected: Vec<Vec<Column>> = expected.into_iter().map(|b| vec![text(b)]).collect();
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, expected, "{}", query);
+        // the executor gives the same answers as the compiled program
+        let output = database
+            .execute(&query.replace("select b", "select distinct b"))
+            .unwrap();
+        assert_eq!(output.rows, expected, "{}", query);
+    }
+
+    let output = database
+        .execute("explain select b from mixed where t = i")
+        .unwrap();
+    assert!(output.rows.iter().any(|row| row[1] == text("Affinity")));
+
+    // values without an affinity are compared as they are
+    let output = database
+        .execute("select 1 = '1', 2 < 'a', 9007199254740993 > 9007199254740992.0, 0.5 < 1")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(0), int(1), int(1), int(1)]]);
+    let output = database
+        .execute("select b from mixed where i in (select t from mixed) order by b")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("b")]]);
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("i".to_string(), ColumnType::Integer),
+        ("r".to_string(), ColumnType::Real),
+        ("t".to_string(), ColumnType::Text),
+        ("b".to_string(), ColumnType::Blob),
+        ("n".to_string(), ColumnType::Numeric),
+    ];
+    database.create_table("mixed".to_string(), columns).unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn real(value: f64) -> Column {
+    Column::Real(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
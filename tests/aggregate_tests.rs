@@ -0,0 +1,149 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn aggregates_over_table() {
+    let filename = create_db_file("aggregates_over_table");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_sales_table(&mut database);
+
+    let output = database
+        .execute(
+            "select count(*), count(amount), sum(amount), total(amount), avg(amount), \
+             min(amount), max(region), group_concat(region, '|') from sales",
+        )
+        .unwrap();
+    let expected = vec![vec![
+        Column::Integer(6),
+        Column::Integer(5),
+        Column::Integer(100),
+        Column::Real(100.0),
+        Column::Real(20.0),
+        Column::Integer(5),
+        text("west"),
+        text("east|east|west|west|north|east"),
+    ]];
+    assert_eq!(output.rows, expected);
+
+    // aggregates over no rows
+    let output = database
+        .execute("select count(*), sum(amount), total(amount), max(amount) from sales where 0")
+        .unwrap();
+    let expected = vec![vec![
+        Column::Integer(0),
+        Column::Null(),
+        Column::Real(0.0),
+        Column::Null(),
+    ]];
+    assert_eq!(output.rows, expected);
+
+    assert!(database
+        .execute("select * from sales where sum(amount) > 1")
+        .is_err());
+    assert!(database
+        .execute("select region from sales group by count(*)")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn group_by_and_having() {
+    let filename = create_db_file("group_by_and_having");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_sales_table(&mut database);
+
+    let output = database
+        .execute(
+            "select region, count(*) as sales, sum(amount) from sales \
+             group by region having count(*) > 1 order by sales desc, region",
+        )
+        .unwrap();
+    assert_eq!(output.columns, vec!["region", "sales", "sum(amount)"]);
+    let expected = vec![
+        vec![text("east"), Column::Integer(3), Column::Integer(25)],
+        vec![text("west"), Column::Integer(2), Column::Integer(35)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // grouping on an expression and by position
+    let output = database
+        .execute("select amount > 10 as big, count(*) from sales group by 1 order by 1")
+        .unwrap();
+    let expected = vec![
+        vec![Column::Null(), Column::Integer(1)],
+        vec![Column::Integer(0), Column::Integer(2)],
+        vec![Column::Integer(1), Column::Integer(3)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn distinct() {
+    let filename = create_db_file("distinct");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_sales_table(&mut database);
+
+    let output = database
+        .execute("select distinct region from sales order by region")
+        .unwrap();
+    let expected = vec![vec![text("east")], vec![text("north")], vec![text("west")]];
+    assert_eq!(output.rows, expected);
+
+    let output = database
+        .execute("select count(distinct region), group_concat(distinct region) from sales")
+        .unwrap();
+    let expected = vec![vec![Column::Integer(3), text("east,west,north")]];
+    assert_eq!(output.rows, expected);
+
+    cleanup(&filename);
+}
+
+fn create_sales_table(database: &mut Database) {
+    let columns = vec![
+        ("region".to_string(), ColumnType::Text),
+        ("amount".to_string(), ColumnType::Integer),
+    ];
+    database.create_table("sales".to_string(), columns).unwrap();
+
+    let sales = vec![
+        ("east", Some(10)),
+        ("east", Some(15)),
+        ("west", Some(5)),
+        ("west", Some(30)),
+        ("north", Some(40)),
+        ("east", None),
+    ];
+    for (region, amount) in sales {
+        let mut row = HashMap::new();
+        row.insert("region".to_string(), region.to_string());
+        if let Some(amount) = amount {
+            row.insert("amount".to_string(), amount.to_string());
+        }
+        database.insert_record("sales".to_string(), row).unwrap();
+    }
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
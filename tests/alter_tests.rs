@@ -0,0 +1,206 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn drop_table() {
+    let filename = create_db_file("drop_table");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database, "users");
+    create_table(&mut database, "admins");
+    database
+        .execute("create index users_name on users (name)")
+        .unwrap();
+    assert_eq!(database.free_page_count().unwrap(), 0);
+
+    database.execute("drop table users").unwrap();
+    assert!(database.execute("select * from users").is_err());
+    assert!(database.execute("drop table users").is_err());
+    database.execute("drop table if exists users").unwrap();
+
+    // the pages of the table and its index are reused by the next tables
+    assert_eq!(database.free_page_count().unwrap(), 2);
+    create_table(&mut database, "guests");
+    assert_eq!(database.free_page_count().unwrap(), 1);
+    database.execute("analyze").unwrap();
+    assert_eq!(database.free_page_count().unwrap(), 0);
+
+    let output = database.execute("select name from guests").unwrap();
+    assert_eq!(output.rows, vec![vec![text("alice")], vec![text("bob")]]);
+    let output = database.execute("select name from admins").unwrap();
+    assert_eq!(output.rows, vec![vec![text("alice")], vec![text("bob")]]);
+
+    // dropping a table forgets its statistics
+    database.execute("drop table guests").unwrap();
+    let output = database.execute("select tbl from sqlite_stat1").unwrap();
+    assert_eq!(output.rows, vec![vec![text("admins")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn rename_table_and_column() {
+    let filename = create_db_file("rename_table_and_column");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database, "users");
+    create_table(&mut database, "admins");
+    database
+        .execute("create index users_name on users (name)")
+        .unwrap();
+
+    database
+        .execute("alter table users rename to members")
+        .unwrap();
+    assert!(database.execute("select * from users").is_err());
+    assert!(database
+        .execute("alter table members rename to admins")
+        .is_err());
+    assert!(database
+        .execute("alter table members rename to users_name")
+        .is_err());
+
+    database
+        .execute("alter table members rename column name to username")
+        .unwrap();
+    database
+        .execute("alter table members rename email to address")
+        .unwrap();
+    assert!(database
+        .execute("alter table members rename column id to username")
+        .is_err());
+    assert!(database
+        .execute("alter table members rename column age to years")
+        .is_err());
+
+    // the index follows both the table and the column
+    let query = "select m.id, m.address from admins a join members m on m.username = a.name \
+                 where a.id = 2";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        output.rows[1][2],
+        text("SEARCH members AS m USING INDEX users_name (username=?)")
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.columns, vec!["id", "address"]);
+    assert_eq!(output.rows, vec![vec![int(2), text("bob@example.com")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn add_and_drop_columns() {
+    let filename = create_db_file("add_and_drop_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database, "users");
+
+    database
+        .execute("alter table users add column age integer default 30")
+        .unwrap();
+    database
+        .execute("alter table users add note varchar(20)")
+        .unwrap();
+    database
+        .execute("alter table users add column score double precision default (-1.5 * 2)")
+        .unwrap();
+    assert!(database.execute("alter table users add name text").is_err());
+    assert_eq!(
+        database.describe_table("users".to_string()).unwrap().1[3..],
+        [
+            ("age".to_string(), ColumnType::Integer),
+            ("note".to_string(), ColumnType::Text),
+            ("score".to_string(), ColumnType::Real),
+        ]
+    );
+
+    // rows written before the columns were added read their defaults, new rows that leave
+    // them out are given them
+    database
+        .execute("insert into users (id, name, email, note) values (3, 'carol', 'c', 'new')")
+        .unwrap();
+    let output = database
+        .execute("select id, age, note, score from users")
+        .unwrap();
+    let expected = vec![
+        vec![int(1), int(30), Column::Null(), Column::Real(-3.0)],
+        vec![int(2), int(30), Column::Null(), Column::Real(-3.0)],
+        vec![int(3), int(30), text("new"), Column::Real(-3.0)],
+    ];
+    assert_eq!(output.rows, expected);
+    let output = database
+        .execute("select name from users where age = 30 and note is null")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("alice")], vec![text("bob")]]);
+
+    database
+        .execute("create index users_age on users (age)")
+        .unwrap();
+    let output = database
+        .execute("select id, score from users where age = 30 order by id")
+        .unwrap();
+    assert_eq!(output.rows.len(), 3);
+    assert_eq!(output.rows[0], vec![int(1), Column::Real(-3.0)]);
+
+    // dropping a column rewrites every row without it
+    assert!(database
+        .execute("alter table users drop column age")
+        .is_err());
+    database
+        .execute("alter table users drop column email")
+        .unwrap();
+    database.execute("alter table users drop note").unwrap();
+    let output = database.execute("select * from users").unwrap();
+    assert_eq!(output.columns, vec!["id", "name", "age", "score"]);
+    assert_eq!(
+        output.rows[2],
+        vec![int(3), text("carol"), int(30), Column::Real(-3.0)]
+    );
+    assert!(database.execute("alter table users drop missing").is_err());
+
+    let columns = vec![("id".to_string(), ColumnType::Integer)];
+    database.create_table("ids".to_string(), columns).unwrap();
+    assert!(database.execute("alter table ids drop column id").is_err());
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database, name: &str) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("email".to_string(), ColumnType::Text),
+    ];
+    database.create_table(name.to_string(), columns).unwrap();
+    database
+        .execute(&format!(
+            "insert into {} values (1, 'alice', 'alice@example.com'), \
+             (2, 'bob', 'bob@example.com')",
+            name
+        ))
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
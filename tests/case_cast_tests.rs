@@ -0,0 +1,244 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn case_expressions() {
+    let filename = create_db_file("case_expressions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    // the searched form takes the first condition that is true
+    let output = database
+        .execute(
+            "select id, case when amount >= 100 then 'large' when amount >= 20 then 'medium' \
+             else 'small' end from orders order by id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), text("small")],
+            vec![int(2), text("large")],
+            vec![int(3), text("medium")],
+            vec![int(4), text("small")],
+        ]
+    );
+
+    // the simple form compares the operand with each value, NULL matches nothing
+    let output = database
+        .execute(
+            "select id, case status when 'open' then 1 when 'closed' then 2 end \
+             from orders order by id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), int(1)],
+            vec![int(2), int(2)],
+            vec![int(3), int(1)],
+            vec![int(4), Column::Null()],
+        ]
+    );
+
+    let queries = vec![
+        ("select case when null then 1 else 2 end", int(2)),
+        ("select case null when null then 1 else 2 end", int(2)),
+        ("select case 1 when 1.0 then 'same' end", text("same")),
+        ("select case when 0 then 1 end", Column::Null()),
+        (
+            "select case 'A' collate nocase when 'a' then 1 else 0 end",
+            int(1),
+        ),
+        (
+            "select case 3 when 1 then 'a' when 3 then 'c' else 'z' end",
+            text("c"),
+        ),
+    ];
+    check(&mut database, queries);
+
+    // a column's affinity applies to the values it's compared with
+    let output = database
+        .execute(
+            "select case amount when '150' then 'yes' else 'no' end \
+             from orders where id = 2",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("yes")]]);
+
+    // CASE can be used with aggregates, in conditions and in updates
+    let output = database
+        .execute(
+            "select sum(case when status = 'open' then amount else 0 end), \
+             count(case status when 'closed' then 1 end) from orders",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(45), int(1)]]);
+    let output = database
+        .execute(
+            "select id from orders where case when status is null then 1 end \
+             order by id",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(4)]]);
+    database
+        .execute(
+            "update orders set status = case when amount > 10 then 'open' else 'new' end \
+             where status is null",
+        )
+        .unwrap();
+    let output = database
+        .execute("select status from orders where id = 4")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("new")]]);
+
+    let output = database
+        .execute("select case when id = 1 then 'one' end from orders where id = 1")
+        .unwrap();
+    assert_eq!(
+        output.columns,
+        vec!["CASE WHEN id = 1 THEN 'one' END".to_string()]
+    );
+
+    assert!(database.execute("select case end").is_err());
+    assert!(database.execute("select case when 1 then 2").is_err());
+    assert!(database.execute("select case when 1 2 end").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn cast_expressions() {
+    let filename = create_db_file("cast_expressions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select cast('42' as integer)", int(42)),
+        ("select cast('12.7abc' as integer)", int(12)),
+        ("select cast('abc' as integer)", int(0)),
+        ("select cast(-3.9 as int)", int(-3)),
+        ("select cast('1e3' as integer)", int(1000)),
+        ("select cast(x'3132' as integer)", int(12)),
+        ("select cast(3 as real)", Column::Real(3.0)),
+        ("select cast('2.5 apples' as real)", Column::Real(2.5)),
+        ("select cast('x' as double precision)", Column::Real(0.0)),
+        ("select cast(12 as text)", text("12")),
+        ("select cast(1.5 as varchar(10))", text("1.5")),
+        ("select cast(x'6869' as text)", text("hi")),
+        ("select cast('hi' as blob)", Column::Blob(vec![b'h', b'i'])),
+        ("select cast(12 as blob)", Column::Blob(vec![b'1', b'2'])),
+        ("select cast('3.0' as numeric)", int(3)),
+        ("select cast('3.5' as numeric)", Column::Real(3.5)),
+        ("select cast('abc' as numeric)", int(0)),
+        ("select cast(null as integer)", Column::Null()),
+        ("select cast(null as text)", Column::Null()),
+        ("select typeof(cast(5 as text))", text("text")),
+        (
+            "select cast('2024-02-03T04:05:06Z' as timestamp)",
+            text("2024-02-03 04:05:06"),
+        ),
+        (
+            "select cast(cast('1.239' as decimal(5, 2)) as text)",
+            text("1.24"),
+        ),
+    ];
+    check(&mut database, queries);
+
+    // a cast has the affinity of its type when it is compared
+    let queries = vec![
+        ("select cast(5 as text) = '5'", int(1)),
+        ("select cast('5' as integer) = '5'", int(1)),
+        ("select '5' = 5", int(0)),
+    ];
+    check(&mut database, queries);
+
+    let output = database
+        .execute("select cast(1 as decimal(10, 2))")
+        .unwrap();
+    assert_eq!(
+        output.columns,
+        vec!["CAST(1 AS decimal(10, 2))".to_string()]
+    );
+
+    assert!(database.execute("select cast(1 as)").is_err());
+    assert!(database.execute("select cast(1 integer)").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn cast_columns() {
+    let filename = create_db_file("cast_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    let output = database
+        .execute(
+            "select cast(amount as text) || ' each', cast(amount / 3 as real) \
+             from orders where id = 3",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("30 each"), Column::Real(10.0)]]);
+    let output = database
+        .execute("select id from orders where cast(amount as text) like '1%' order by id")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)], vec![int(2)], vec![int(4)]]);
+    let output = database
+        .execute(
+            "select case cast(amount as text) when '150' then 'big' else 'other' end \
+             from orders where id = 2",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("big")]]);
+
+    cleanup(&filename);
+}
+
+fn check(database: &mut Database, queries: Vec<(&str, Column)>) {
+    for (query, expected) in queries {
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, vec![vec![expected]], "{}", query);
+    }
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("status".to_string(), ColumnType::Text),
+        ("amount".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("orders".to_string(), columns)
+        .unwrap();
+    database
+        .execute(
+            "insert into orders values (1, 'open', 15), (2, 'closed', 150), (3, 'open', 30), \
+             (4, null, 10)",
+        )
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
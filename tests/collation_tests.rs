@@ -0,0 +1,374 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn builtin_collations() {
+    let filename = create_db_file("builtin_collations");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select 'abc' = 'ABC'", int(0)),
+        ("select 'abc' = 'ABC' collate nocase", int(1)),
+        ("select 'abc' collate NOCASE = 'ABC'", int(1)),
+        ("select 'a' < 'B' collate nocase", int(1)),
+        ("select 'a' < 'B' collate binary", int(0)),
+        ("select 'abc  ' = 'abc' collate rtrim", int(1)),
+        ("select ' abc' = 'abc' collate rtrim", int(0)),
+        ("select 'X' in ('a', 'x') ", int(0)),
+        ("select 'X' collate nocase in ('a', 'x')", int(1)),
+        ("select 'B' collate nocase between 'a' and 'c'", int(1)),
+        ("select 'é' = 'É' collate nocase", int(0)),
+        ("select 1 = '1' collate nocase", int(0)),
+        ("select null = 'a' collate nocase", Column::Null()),
+        ("select 'Abc' collate nocase", text("Abc")),
+    ];
+    for (query, expected) in queries {
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, vec![vec![expected]], "{}", query);
+    }
+
+    assert!(database.execute("select 'a' collate nothing").is_err());
+    assert!(database
+        .execute("select 'a' = 'b' collate nothing")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn column_collations() {
+    let filename = create_db_file("column_collations");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    // comparisons with the column use its collation, unless the other side names one
+    let output = database
+        .execute("select id from people where name = 'ALICE'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(2)], vec![int(3)]]);
+    let output = database
+        .execute("select id from people where name = 'ALICE' collate binary")
+        .unwrap();
+    assert_eq!(output.rows, Vec::<Vec<Column>>::new());
+    let output = database
+        .execute("select id from people where code = 'x1'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)], vec![int(2)]]);
+
+    let output = database
+        .execute("select name from people order by name, id")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("alice")],
+            vec![text("Alice")],
+            vec![text("bob")],
+            vec![text("Carol")],
+        ]
+    );
+    let output = database
+        .execute("select name from people order by name collate binary")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("Alice")],
+            vec![text("Carol")],
+            vec![text("alice")],
+            vec![text("bob")],
+        ]
+    );
+
+    let output = database
+        .execute("select count(*) from people group by name order by name")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(2)], vec![int(1)], vec![int(1)]]);
+    let output = database
+        .execute("select count(*) from people group by name collate binary")
+        .unwrap();
+    assert_eq!(output.rows.len(), 4);
+
+    // the collation is kept with the table
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database
+        .execute("select id from people where name > 'BOB' order by id")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(4)]]);
+
+    // dropping a column leaves the collations of the others in place
+    database
+        .execute("alter table people drop column code")
+        .unwrap();
+    let output = database
+        .execute("select count(*) from people where name = 'CAROL'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)]]);
+
+    assert!(database
+        .execute("alter table people add column other text collate nothing")
+        .is_err());
+
+    // columns can be given their collations when the table is created
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer, None),
+        (
+            "tag".to_string(),
+            ColumnType::Text,
+            Some("nocase".to_string()),
+        ),
+    ];
+    database
+        .create_collated_table("tags".to_string(), columns)
+        .unwrap();
+    database
+        .execute("insert into tags values (1, 'Red'), (2, 'blue'), (3, 'RED')")
+        .unwrap();
+    let output = database
+        .execute("select id from tags where tag = 'red' order by id")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)], vec![int(3)]]);
+    let output = database
+        .execute("select tag from tags order by tag, id")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![text("blue")], vec![text("Red")], vec![text("RED")]]
+    );
+    let columns = vec![(
+        "tag".to_string(),
+        ColumnType::Text,
+        Some("nothing".to_string()),
+    )];
+    assert!(database
+        .create_collated_table("others".to_string(), columns)
+        .is_err());
+    assert!(database.execute("select * from others").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn distinct_collations() {
+    let filename = create_db_file("distinct_collations");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    // rows are told apart by the collations of their columns wherever duplicates are dropped
+    let queries = vec![
+        (
+            "select distinct name from people order by name",
+            vec![text("alice"), text("bob"), text("Carol")],
+        ),
+        (
+            "select distinct name collate binary from people order by 1",
+            vec![text("Alice"), text("Carol"), text("alice"), text("bob")],
+        ),
+        ("select count(distinct name) from people", vec![int(3)]),
+        (
+            "select count(distinct name collate binary) from people",
+            vec![int(4)],
+        ),
+        (
+            "select name from people where id < 3 \
+             union select name from people where id >= 3 order by 1",
+            vec![text("alice"), text("bob"), text("Carol")],
+        ),
+        (
+            "select name from people where id = 2 intersect select 'ALICE'",
+            vec![text("alice")],
+        ),
+        (
+            "select name from people except select 'BOB' order by 1",
+            vec![text("alice"), text("Carol")],
+        ),
+    ];
+    for (query, expected) in queries {
+        let expected: Vec<Vec<Column>> = expected.into_iter().map(|value| vec![value]).collect();
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, expected, "{}", query);
+    }
+
+    // window partitions and peers compare the same way
+    let output = database
+        .execute(
+            "select id, count(*) over (partition by name), rank() over (order by name) \
+             from people order by id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), int(1), int(3)],
+            vec![int(2), int(2), int(1)],
+            vec![int(3), int(2), int(1)],
+            vec![int(4), int(1), int(4)],
+        ]
+    );
+
+    cleanup(&filename);
+}
+
+#[test]
+fn collated_indexes() {
+    let filename = create_db_file("collated_indexes");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    let columns = vec![("login".to_string(), ColumnType::Text)];
+    database
+        .create_table("logins".to_string(), columns)
+        .unwrap();
+    database
+        .execute("insert into logins values ('ALICE'), ('carol')")
+        .unwrap();
+    database
+        .execute("create index people_name on people (name)")
+        .unwrap();
+
+    // the index is ordered by the column's collation, so it can answer comparisons by it
+    let join = "select logins.login, people.id from logins \
+                join people on people.name = logins.login order by people.id";
+    let output = database
+        .execute(&format!("explain query plan {}", join))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SCAN logins",
+            "SEARCH people USING INDEX people_name (name=?)",
+            "USE TEMP B-TREE FOR ORDER BY",
+        ]
+    );
+    let output = database.execute(join).unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("ALICE"), int(2)],
+            vec![text("ALICE"), int(3)],
+            vec![text("carol"), int(4)],
+        ]
+    );
+
+    // a comparison by another collation can't use it
+    let join = "select logins.login, people.id from logins \
+                join people on people.name = logins.login collate binary";
+    let output = database
+        .execute(&format!("explain query plan {}", join))
+        .unwrap();
+    assert_eq!(plan(&output.rows), vec!["SCAN logins", "SCAN people"]);
+    let output = database.execute(join).unwrap();
+    assert_eq!(output.rows, Vec::<Vec<Column>>::new());
+
+    // ANALYZE counts the values the index tells apart by its collation
+    database.execute("analyze people").unwrap();
+    let output = database
+        .execute("select stat from sqlite_stat1 where idx = 'people_name'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("4 2")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn custom_collations() {
+    let filename = create_db_file("custom_collations");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    // orders text by its length, then as BINARY does
+    database.create_collation("by_length", |left, right| {
+        left.len().cmp(&right.len()).then(left.cmp(right))
+    });
+    let output = database
+        .execute("select name from people order by name collate BY_LENGTH")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("bob")],
+            vec![text("Alice")],
+            vec![text("Carol")],
+            vec![text("alice")],
+        ]
+    );
+    let output = database
+        .execute("select 'zz' < 'aaa' collate by_length")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)]]);
+
+    database
+        .execute("alter table people add column nickname text collate by_length")
+        .unwrap();
+    database
+        .execute("update people set nickname = name || '!'")
+        .unwrap();
+    let output = database
+        .execute("select nickname from people where nickname < 'zzzzz'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob!")]]);
+
+    // the registration isn't kept in the file
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    assert!(database
+        .execute("select nickname from people where nickname < 'zzzzz'")
+        .is_err());
+    assert!(database.collation("by_length").is_err());
+    assert!(database.collation("nocase").is_ok());
+
+    cleanup(&filename);
+}
+
+fn plan(rows: &[Vec<Column>]) -> Vec<String> {
+    rows.iter()
+        .map(|row| match &row[2] {
+            Column::Text(detail) => detail.clone(),
+            detail => panic!("unexpected detail {:?}", detail),
+        })
+        .collect()
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![("id".to_string(), ColumnType::Integer)];
+    database
+        .create_table("people".to_string(), columns)
+        .unwrap();
+    database
+        .execute("alter table people add column name text collate nocase")
+        .unwrap();
+    database
+        .execute("alter table people add column code text default 'x1' collate rtrim")
+        .unwrap();
+    database
+        .execute(
+            "insert into people values (1, 'bob', 'x1  '), (2, 'alice', 'x1'), \
+             (3, 'Alice', 'x2'), (4, 'Carol', ' x1')",
+        )
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
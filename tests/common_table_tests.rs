@@ -0,0 +1,169 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn common_tables() {
+    let filename = create_db_file("common_tables");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_staff_table(&mut database);
+
+    let output = database
+        .execute(
+            "with managers as (select distinct manager as id from staff where manager is not null), \
+             named(id, name) as (select s.id, s.name from staff s join managers m on m.id = s.id) \
+             select name from named order by name",
+        )
+        .unwrap();
+    assert_eq!(output.columns, vec!["name"]);
+    let expected = vec![vec![text("ada")], vec![text("brian")], vec![text("cleo")]];
+    assert_eq!(output.rows, expected);
+
+    // common tables are visible to subqueries
+    let output = database
+        .execute(
+            "with big as (select id from staff where id > 4) \
+             select count(*) from staff where id in (select id from big)",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Integer(2)]]);
+
+    // and only to the query that defines them
+    assert!(database
+        .execute("select * from (with t as (select 1) select * from t), t")
+        .is_err());
+
+    assert!(database
+        .execute("with t(a, b) as (select 1) select * from t")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn recursive_common_tables() {
+    let filename = create_db_file("recursive_common_tables");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_staff_table(&mut database);
+
+    let output = database
+        .execute(
+            "with recursive counter(x) as (select 1 union all select x + 1 from counter where x < 5) \
+             select sum(x), count(*) from counter",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![Column::Integer(15), Column::Integer(5)]]
+    );
+
+    // LIMIT stops a query that would otherwise never finish
+    let output = database
+        .execute(
+            "with recursive counter(x) as (select 1 union all select x + 1 from counter limit 3) \
+             select x from counter",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![Column::Integer(1)],
+        vec![Column::Integer(2)],
+        vec![Column::Integer(3)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // a LIMIT on the query reading the table applies once the table is complete
+    let output = database
+        .execute(
+            "with recursive counter(x) as (select 1 union all select x + 1 from counter limit 5) \
+             select x from counter limit 3",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![Column::Integer(1)],
+        vec![Column::Integer(2)],
+        vec![Column::Integer(3)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // walking an org tree, the depth of everyone under brian
+    let output = database
+        .execute(
+            "with recursive reports(id, name, depth) as ( \
+                 select id, name, 0 from staff where name = 'brian' \
+                 union all \
+                 select s.id, s.name, r.depth + 1 from staff s join reports r on s.manager = r.id) \
+             select name, depth from reports order by depth, name",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("brian"), Column::Integer(0)],
+        vec![text("cleo"), Column::Integer(1)],
+        vec![text("dan"), Column::Integer(1)],
+        vec![text("eve"), Column::Integer(2)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // UNION drops rows that were already found, so cycles end
+    let output = database
+        .execute(
+            "with recursive cycle(x) as (select 0 union select (x + 1) % 3 from cycle) \
+             select x from cycle order by x",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![Column::Integer(0)],
+        vec![Column::Integer(1)],
+        vec![Column::Integer(2)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    cleanup(&filename);
+}
+
+fn create_staff_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("manager".to_string(), ColumnType::Integer),
+    ];
+    database.create_table("staff".to_string(), columns).unwrap();
+
+    let staff = vec![
+        ("1", "ada", None),
+        ("2", "brian", Some("1")),
+        ("3", "cleo", Some("2")),
+        ("4", "dan", Some("2")),
+        ("5", "eve", Some("3")),
+        ("6", "fay", Some("1")),
+    ];
+    for (id, name, manager) in staff {
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), id.to_string());
+        row.insert("name".to_string(), name.to_string());
+        if let Some(manager) = manager {
+            row.insert("manager".to_string(), manager.to_string());
+        }
+        database.insert_record("staff".to_string(), row).unwrap();
+    }
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,146 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn union_and_union_all() {
+    let filename = create_db_file("union_and_union_all");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute("select city from customers union select city from suppliers order by city")
+        .unwrap();
+    assert_eq!(output.columns, vec!["city"]);
+    let expected = vec![
+        vec![text("berlin")],
+        vec![text("london")],
+        vec![text("oslo")],
+        vec![text("paris")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    let output = database
+        .execute("select city from customers union all select city from suppliers")
+        .unwrap();
+    assert_eq!(output.rows.len(), 6);
+
+    // ORDER BY and LIMIT apply to the whole compound, by name or position
+    let output = database
+        .execute(
+            "select name, city from customers union all select name, city from suppliers \
+             order by 2 desc, name limit 2 offset 1",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("acme"), text("oslo")],
+        vec![text("ann"), text("london")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // 1 and 1.0 are the same value
+    let output = database.execute("select 1 union select 1.0").unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Integer(1)]]);
+
+    assert!(database
+        .execute("select name, city from customers union select city from suppliers")
+        .is_err());
+    assert!(database
+        .execute("select city from customers union select city from suppliers order by name")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn intersect_and_except() {
+    let filename = create_db_file("intersect_and_except");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute("select city from customers intersect select city from suppliers")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("london")]]);
+
+    let output = database
+        .execute("select city from customers except select city from suppliers order by city")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("berlin")], vec![text("paris")]]);
+
+    // operators are applied from left to right
+    let output = database
+        .execute(
+            "select city from customers union select city from suppliers \
+             except select 'oslo' order by 1",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("berlin")],
+        vec![text("london")],
+        vec![text("paris")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // compounds can be used as subqueries
+    let output = database
+        .execute(
+            "select count(*) from (select city from customers intersect select city from suppliers)",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Integer(1)]]);
+
+    cleanup(&filename);
+}
+
+fn create_tables(database: &mut Database) {
+    for table in &["customers", "suppliers"] {
+        let columns = vec![
+            ("name".to_string(), ColumnType::Text),
+            ("city".to_string(), ColumnType::Text),
+        ];
+        database.create_table(table.to_string(), columns).unwrap();
+    }
+
+    let customers = vec![
+        ("ann", "london"),
+        ("bea", "london"),
+        ("cat", "paris"),
+        ("dot", "berlin"),
+    ];
+    for (name, city) in customers {
+        insert(database, "customers", name, city);
+    }
+    let suppliers = vec![("acme", "oslo"), ("bolt", "london")];
+    for (name, city) in suppliers {
+        insert(database, "suppliers", name, city);
+    }
+}
+
+fn insert(database: &mut Database, table: &str, name: &str, city: &str) {
+    let mut row = HashMap::new();
+    row.insert("name".to_string(), name.to_string());
+    row.insert("city".to_string(), city.to_string());
+    database.insert_record(table.to_string(), row).unwrap();
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,343 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn date_columns() {
+    let filename = create_db_file("date_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute(
+            "insert into events values \
+             (1, '2024-03-10T15:04:05Z', '2024-03-10', '15:04', '2 days'), \
+             (2, '2024-01-31 23:30:00.250+01:00', '2024-01-31 08:00', '08:00:00.5', \
+              '1 year 3 months'), \
+             (3, 'soon', 2460000, 'noon', '-01:30'), \
+             (4, null, '2023-12-25', '23:59:59', '+0000-00-01 12:00:00.000')",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![
+            int(1),
+            text("2024-03-10 15:04:05"),
+            text("2024-03-10"),
+            text("15:04:00"),
+            text("+0000-00-02 00:00:00.000"),
+        ],
+        vec![
+            int(2),
+            text("2024-01-31 22:30:00.250"),
+            text("2024-01-31"),
+            text("08:00:00.500"),
+            text("+0001-03-00 00:00:00.000"),
+        ],
+        vec![
+            int(3),
+            text("soon"),
+            text("2023-02-24"),
+            text("noon"),
+            text("-0000-00-00 01:30:00.000"),
+        ],
+        vec![
+            int(4),
+            Column::Null(),
+            text("2023-12-25"),
+            text("23:59:59"),
+            text("+0000-00-01 12:00:00.000"),
+        ],
+    ];
+    let output = database.execute("select * from events").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // the values are read back the same after reopening
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database.execute("select * from events").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // values compared with a date column are read as dates first
+    let queries = vec![
+        (
+            "select id from events where day = '2024-03-10T00:00'",
+            vec![1],
+        ),
+        (
+            "select id from events where day < '2024-01-31' order by id",
+            vec![3, 4],
+        ),
+        (
+            "select id from events where at between '2024-01-31T22:00Z' and '2024-03-10 16:00'",
+            vec![1, 2],
+        ),
+        (
+            "select id from events where day >= 2460310 order by day",
+            vec![2, 1],
+        ),
+        ("select id from events order by day desc", vec![1, 2, 4, 3]),
+        (
+            "select id from events where time > '12:00' order by time",
+            vec![1, 4, 3],
+        ),
+    ];
+    for (query, expected) in queries {
+        let expected: Vec<Vec<Column>> = expected.into_iter().map(|id| vec![int(id)]).collect();
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, expected, "{}", query);
+    }
+
+    // updates are converted the same way
+    database
+        .execute("update events set day = '2024-02-29T10:00', at = at where id = 3")
+        .unwrap();
+    let output = database
+        .execute("select day, at from events where id = 3")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("2024-02-29"), text("soon")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn date_functions() {
+    let filename = create_db_file("date_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select date('2024-03-10 15:04:05')", text("2024-03-10")),
+        ("select time('2024-03-10T15:04:05.678')", text("15:04:05")),
+        (
+            "select datetime('2024-03-10T15:04:05+02:00')",
+            text("2024-03-10 13:04:05"),
+        ),
+        ("select julianday('2000-01-01 12:00')", real(2451545.0)),
+        ("select unixepoch('1970-01-02')", int(86400)),
+        (
+            "select datetime(86400, 'unixepoch')",
+            text("1970-01-02 00:00:00"),
+        ),
+        (
+            "select datetime(1700000000, 'auto')",
+            text("2023-11-14 22:13:20"),
+        ),
+        ("select date(2460000)", text("2023-02-24")),
+        ("select date('2460000.5')", text("2023-02-25")),
+        ("select date('2024-01-31', '+1 month')", text("2024-03-02")),
+        (
+            "select date('2024-03-31', '-1 months', 'start of month')",
+            text("2024-03-01"),
+        ),
+        (
+            "select datetime('2024-01-01 10:00', '+90 minutes', '-1.5 hours')",
+            text("2024-01-01 10:00:00"),
+        ),
+        (
+            "select date('2024-06-15', 'start of year', '+1 years')",
+            text("2025-01-01"),
+        ),
+        ("select date('2024-03-10', 'weekday 1')", text("2024-03-11")),
+        ("select date('2024-03-11', 'weekday 1')", text("2024-03-11")),
+        (
+            "select datetime('2024-03-10 15:04:05', 'start of day', '+0000-01-01 06:00')",
+            text("2024-04-11 06:00:00"),
+        ),
+        ("select time('10:00', '-02:30')", text("07:30:00")),
+        (
+            "select strftime('%Y/%m/%d %H:%M:%f %j %w %u %W %s %%', '2024-03-10 15:04:05.250')",
+            text("2024/03/10 15:04:05.250 070 0 7 10 1710083045 %"),
+        ),
+        (
+            "select strftime('%I %p %e %T', '2024-03-01 00:30')",
+            text("12 AM  1 00:30:00"),
+        ),
+        (
+            "select timediff('2024-03-01', '2024-01-15 12:00')",
+            text("+0000-01-14 12:00:00.000"),
+        ),
+        (
+            "select timediff('2023-01-01', '2024-01-01')",
+            text("-0001-00-00 00:00:00.000"),
+        ),
+        (
+            "select datetime('2024-01-15 12:00', timediff('2024-03-01', '2024-01-15 12:00'))",
+            text("2024-03-01 00:00:00"),
+        ),
+        ("select date('2024-13-01')", Column::Null()),
+        ("select date('2024-01-01', 'next tuesday')", Column::Null()),
+        ("select date('2024-01-01', '2024-01-01')", Column::Null()),
+        ("select date(null)", Column::Null()),
+        ("select date('9999-12-31', '+1 day')", Column::Null()),
+        ("select strftime('%Q', '2024-01-01')", Column::Null()),
+        ("select date('now') = date(julianday('now'))", int(1)),
+        ("select unixepoch() >= 1700000000", int(1)),
+    ];
+    for (query, expected) in queries {
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, vec![vec![expected]], "{}", query);
+    }
+
+    assert!(database.execute("select timediff('2024-01-01')").is_err());
+    assert!(database.execute("select strftime()").is_err());
+    assert!(database.execute("select nothing(1)").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn interval_arithmetic() {
+    let filename = create_db_file("interval_arithmetic");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .execute(
+            "insert into events (id, at, length) values \
+             (1, '2024-01-31 09:00', '1 month'), (2, '2024-02-01 09:00', '36 hours'), \
+             (3, '2024-02-01 10:00', '-2 days'), (4, '2024-01-01', '1 day -2 hours')",
+        )
+        .unwrap();
+
+    let output = database
+        .execute("select id, datetime(at, length) as finish from events order by finish")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(4), text("2024-01-01 22:00:00")],
+            vec![int(3), text("2024-01-30 10:00:00")],
+            vec![int(2), text("2024-02-02 21:00:00")],
+            vec![int(1), text("2024-03-02 09:00:00")],
+        ]
+    );
+    let output = database
+        .execute(
+            "select id from events \
+             where datetime(at, length) > '2024-02-01' and date(at, '+1 day') < '2024-02-02'",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)]]);
+
+    // months and the rest of an interval can't have opposite signs
+    database
+        .execute("insert into events (id, length) values (5, '1 month -1 day')")
+        .unwrap();
+    let output = database
+        .execute("select length from events where id = 5")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("1 month -1 day")]]);
+    database.execute("delete from events where id = 5").unwrap();
+
+    // intervals are ordered and compared by their length rather than their text
+    let queries = vec![
+        ("select id from events order by length", vec![3, 4, 2, 1]),
+        ("select id from events where length < '0 days'", vec![3]),
+        (
+            "select id from events where length between '1 day' and '2 days' order by id",
+            vec![2],
+        ),
+        (
+            "select id from events order by row_number() over (order by length desc)",
+            vec![1, 2, 4, 3],
+        ),
+    ];
+    for (query, expected) in queries {
+        let expected: Vec<Vec<Column>> = expected.into_iter().map(|id| vec![int(id)]).collect();
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, expected, "{}", query);
+    }
+    let output = database
+        .execute("select min(length), max(length) from events")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![
+            text("-0000-00-02 00:00:00.000"),
+            text("+0000-01-00 00:00:00.000")
+        ]]
+    );
+    // that's the order of their column rather than a collation that can be named
+    assert!(database
+        .execute("select id from events order by length collate interval")
+        .is_err());
+
+    // intervals move dates and times, and dates and times taken from each other give one
+    let output = database
+        .execute(
+            "select at + length, length + day, at - '+01:30', at - '2024-01-01', \
+             length - '1 day' from events where id = 2",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![
+            text("2024-02-02 21:00:00"),
+            Column::Null(),
+            text("2024-02-01 07:30:00"),
+            text("+0000-01-00 09:00:00.000"),
+            text("+0000-00-00 12:00:00.000"),
+        ]]
+    );
+    database
+        .execute("update events set day = '2024-01-31' where id = 1")
+        .unwrap();
+    let output = database
+        .execute("select day + length, day - '2024-03-01' from events where id = 1")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![text("2024-03-02"), text("-0000-00-30 00:00:00.000")]]
+    );
+    for query in vec![
+        "select at * 2 from events",
+        "select at + 1 from events",
+        "select at + at from events",
+    ] {
+        assert!(database.execute(query).is_err(), "{}", query);
+    }
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("at".to_string(), ColumnType::Timestamp),
+        ("day".to_string(), ColumnType::Date),
+        ("time".to_string(), ColumnType::Time),
+    ];
+    database
+        .create_table("events".to_string(), columns)
+        .unwrap();
+    // the type names are read by ALTER TABLE as they are by CREATE TABLE
+    database
+        .execute("alter table events add column length interval")
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn real(value: f64) -> Column {
+    Column::Real(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
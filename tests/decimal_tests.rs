@@ -0,0 +1,250 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+use basic_db::backend::database::Decimal;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn rounding_on_insert() {
+    let filename = create_db_file("rounding_on_insert");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute(
+            "insert into ledger (id, amount) values (1, 19.99), (2, '0.125'), (3, -0.125), \
+             (4, 3), (5, ' 1e2 '), (6, 'abc'), (7, 12345678.999), (8, null), (9, 0.1)",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![int(1), decimal("19.99")],
+        vec![int(2), decimal("0.13")],
+        vec![int(3), decimal("-0.13")],
+        vec![int(4), decimal("3.00")],
+        vec![int(5), decimal("100.00")],
+        vec![int(6), text("abc")],
+        vec![int(7), decimal("12345679.00")],
+        vec![int(8), Column::Null()],
+        vec![int(9), decimal("0.10")],
+    ];
+    let output = database.execute("select id, amount from ledger").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // numbers with more digits than the precision can't be kept exactly, and a statement
+    // writing one writes none of its rows
+    for (query, message) in vec![
+        (
+            "insert into ledger (id, amount) values (10, 123456789.12)",
+            "123456789.12 does not fit DECIMAL(10, 2) in column amount",
+        ),
+        (
+            "insert into ledger (id, amount) values (10, 1e30)",
+            "1000000000000000000000000000000 does not fit DECIMAL(10, 2) in column amount",
+        ),
+        (
+            "insert into ledger (id, amount) values (10, '-99999999.995')",
+            "-99999999.995 does not fit DECIMAL(10, 2) in column amount",
+        ),
+        (
+            "update ledger set amount = amount * 100000 where id = 7",
+            "1234567900000.00 does not fit DECIMAL(10, 2) in column amount",
+        ),
+        (
+            "insert into ledger (id, amount) values (10, 1.5), (11, 2), (12, 123456789.12)",
+            "123456789.12 does not fit DECIMAL(10, 2) in column amount",
+        ),
+        (
+            "update ledger set amount = amount * 100000",
+            "1234567900000.00 does not fit DECIMAL(10, 2) in column amount",
+        ),
+    ] {
+        let error = database.execute(query).unwrap_err();
+        assert_eq!(error.to_string(), message, "{}", query);
+    }
+    let output = database.execute("select id, amount from ledger").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // decimals are stored exactly and read back the same after reopening
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database.execute("select id, amount from ledger").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // the precision and scale are read from the declared type
+    database
+        .execute("alter table ledger add column rate numeric(5, 3) default 0.0425")
+        .unwrap();
+    database
+        .execute("alter table ledger add column plain numeric")
+        .unwrap();
+    let (_rootpage, columns) = database.describe_table("ledger".to_string()).unwrap();
+    assert_eq!(
+        columns[2].1,
+        ColumnType::Decimal {
+            precision: 5,
+            scale: 3
+        }
+    );
+    assert_eq!(columns[3].1, ColumnType::Numeric);
+    let output = database
+        .execute("select rate from ledger where id = 1")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![decimal("0.043")]]);
+    assert!(database
+        .execute("alter table ledger add column wide decimal(40, 2)")
+        .is_err());
+    assert!(database
+        .execute("alter table ledger add column odd decimal(4, 5)")
+        .is_err());
+    assert!(database
+        .execute("alter table ledger add column cap decimal(3, 1) default 1000")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn exact_arithmetic() {
+    let filename = create_db_file("exact_arithmetic");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .execute(
+            "insert into ledger (id, amount) values (1, 0.10), (2, 0.20), (3, 10.00), \
+             (4, 20.00), (5, 20.05)",
+        )
+        .unwrap();
+
+    let queries = vec![
+        (
+            "select amount + 0.2 from ledger where id = 1",
+            decimal("0.30"),
+        ),
+        (
+            "select amount * 3 from ledger where id = 1",
+            decimal("0.30"),
+        ),
+        (
+            "select amount - 1 from ledger where id = 2",
+            decimal("-0.80"),
+        ),
+        (
+            "select amount * amount from ledger where id = 5",
+            decimal("402.0025"),
+        ),
+        (
+            "select amount / 4 from ledger where id = 3",
+            decimal("2.50"),
+        ),
+        (
+            "select 1 / (amount * 3) from ledger where id = 1",
+            decimal("3.33333333"),
+        ),
+        (
+            "select amount % 3 from ledger where id = 5",
+            decimal("2.05"),
+        ),
+        ("select -amount from ledger where id = 5", decimal("-20.05")),
+        ("select amount / 0 from ledger where id = 5", Column::Null()),
+        (
+            "select amount || '!' from ledger where id = 5",
+            text("20.05!"),
+        ),
+        (
+            "select sum(amount) from ledger where id < 3",
+            decimal("0.30"),
+        ),
+        ("select sum(amount) from ledger", decimal("50.35")),
+        (
+            "select avg(amount) from ledger where id > 2",
+            decimal("16.68333333"),
+        ),
+        (
+            "select avg(amount) from ledger where id in (3, 4)",
+            decimal("15.00"),
+        ),
+        (
+            "select total(amount) from ledger where id < 3",
+            Column::Real(0.30000000000000004),
+        ),
+        ("select max(amount) from ledger", decimal("20.05")),
+    ];
+    for (query, expected) in queries {
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, vec![vec![expected]], "{}", query);
+    }
+
+    // comparisons are exact too, and values compared with the column are read as numbers
+    let queries = vec![
+        ("select id from ledger where amount + 0.2 = 0.3", vec![1]),
+        ("select id from ledger where amount = '20.050'", vec![5]),
+        (
+            "select id from ledger where amount between 10 and 20 order by id",
+            vec![3, 4],
+        ),
+        ("select id from ledger where amount > 20", vec![5]),
+        (
+            "select id from ledger order by amount desc limit 2",
+            vec![5, 4],
+        ),
+    ];
+    for (query, expected) in queries {
+        let expected: Vec<Vec<Column>> = expected.into_iter().map(|id| vec![int(id)]).collect();
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows, expected, "{}", query);
+    }
+
+    // updates round the same way
+    database
+        .execute("update ledger set amount = amount / 3 where id = 3")
+        .unwrap();
+    let output = database
+        .execute("select amount from ledger where id = 3")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![decimal("3.33")]]);
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        (
+            "amount".to_string(),
+            ColumnType::Decimal {
+                precision: 10,
+                scale: 2,
+            },
+        ),
+    ];
+    database
+        .create_table("ledger".to_string(), columns)
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn decimal(value: &str) -> Column {
+    Column::Decimal(Decimal::parse(value).unwrap())
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
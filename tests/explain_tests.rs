@@ -0,0 +1,187 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn explain_program() {
+    let filename = create_db_file("explain_program");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute("explain select name from customers where id > 1")
+        .unwrap();
+    assert_eq!(
+        output.columns,
+        vec!["addr", "opcode", "p1", "p2", "p3", "p4"]
+    );
+    let opcodes: Vec<Column> = output.rows.iter().map(|row| row[1].clone()).collect();
+    let expected = [
+        "OpenRead",
+        "Rewind",
+        "Column",
+        "Integer",
+        "Gt",
+        "IfNot",
+        "Column",
+        "ResultRow",
+        "Next",
+        "Halt",
+    ];
+    assert_eq!(
+        opcodes,
+        expected
+            .iter()
+            .map(|opcode| text(opcode))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(output.rows[0][5], text("customers"));
+    // Rewind jumps past the loop when the table is empty, Next jumps back to its start
+    assert_eq!(output.rows[1][3], int(9));
+    assert_eq!(output.rows[8][3], int(2));
+
+    let output = database
+        .execute("explain insert into customers values (4, 'dan')")
+        .unwrap();
+    let opcodes: Vec<Column> = output.rows.iter().map(|row| row[1].clone()).collect();
+    let expected = ["OpenWrite", "Integer", "String", "Insert", "Close", "Halt"];
+    assert_eq!(
+        opcodes,
+        expected
+            .iter()
+            .map(|opcode| text(opcode))
+            .collect::<Vec<_>>()
+    );
+
+    // explaining a statement doesn't run it
+    let output = database.execute("select count(*) from customers").unwrap();
+    assert_eq!(output.rows, vec![vec![int(3)]]);
+
+    // statements the executor runs are explained by their query plan
+    let output = database
+        .execute("explain select count(*) from orders group by customer")
+        .unwrap();
+    assert_eq!(output.columns, vec!["id", "parent", "detail"]);
+
+    assert!(database.execute("explain explain select 1").is_err());
+    assert!(database.execute("explain select * from shelves").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn explain_query_plan() {
+    let filename = create_db_file("explain_query_plan");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let query = "explain query plan select c.name, o.item from customers c \
+                 left join orders o on o.customer = c.id order by c.name";
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.columns, vec!["id", "parent", "detail"]);
+    assert!(output.query_plan);
+    let expected = vec![
+        vec![int(1), int(0), text("SCAN customers AS c")],
+        vec![int(2), int(0), text("SCAN orders AS o LEFT-JOIN")],
+        vec![int(3), int(0), text("USE TEMP B-TREE FOR ORDER BY")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    database
+        .execute("create index orders_customer on orders (customer)")
+        .unwrap();
+    let output = database.execute(query).unwrap();
+    assert_eq!(
+        output.rows[1][2],
+        text("SEARCH orders AS o USING INDEX orders_customer (customer=?) LEFT-JOIN")
+    );
+
+    // nested queries are listed under the step that runs them
+    let output = database
+        .execute(
+            "explain query plan with big(id) as (select customer from orders where id > 10) \
+             select name from customers where id in (select id from big) \
+             union select item from (select * from orders) as o",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![int(1), int(0), text("MATERIALIZE big")],
+        vec![int(2), int(1), text("SCAN orders")],
+        vec![int(3), int(0), text("COMPOUND QUERY")],
+        vec![int(4), int(3), text("LEFT-MOST SUBQUERY")],
+        vec![int(5), int(4), text("SCAN customers")],
+        vec![int(6), int(4), text("LIST SUBQUERY")],
+        vec![int(7), int(6), text("SCAN big")],
+        vec![int(8), int(3), text("UNION")],
+        vec![int(9), int(8), text("SCAN SUBQUERY AS o")],
+        vec![int(10), int(9), text("SCAN orders")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // a query with the same columns is still just rows
+    let output = database
+        .execute("select 1 as id, 0 as parent, 'x' as detail")
+        .unwrap();
+    assert!(!output.query_plan);
+
+    let output = database.execute("explain query plan select 1").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![int(1), int(0), text("SCAN CONSTANT ROW")]]
+    );
+    let output = database
+        .execute("explain query plan delete from orders where item = 'pen'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1), int(0), text("SCAN orders")]]);
+
+    cleanup(&filename);
+}
+
+fn create_tables(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("customers".to_string(), columns)
+        .unwrap();
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("customer".to_string(), ColumnType::Integer),
+        ("item".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("orders".to_string(), columns)
+        .unwrap();
+
+    database
+        .execute("insert into customers values (1, 'alice'), (2, 'bob'), (3, 'carol')")
+        .unwrap();
+    database
+        .execute("insert into orders values (10, 1, 'book'), (11, 2, 'lamp'), (12, 1, 'pen')")
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
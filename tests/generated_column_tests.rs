@@ -0,0 +1,393 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+use basic_db::sql;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn virtual_columns() {
+    let filename = create_db_file("virtual_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute("alter table items add column total integer as (price * quantity)")
+        .unwrap();
+    database
+        .execute(
+            "alter table items add column label text generated always as \
+             (upper(name) || ' x' || quantity) virtual",
+        )
+        .unwrap();
+
+    // rows already in the table read their virtual columns too
+    let output = database
+        .execute("select id, total, label from items order by id")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), int(20), text("PEN x4")],
+            vec![int(2), int(30), text("BOOK x2")],
+            vec![int(3), Column::Null(), Column::Null()],
+        ]
+    );
+
+    // an insert without a list of columns gives values for the other columns
+    database
+        .execute("insert into items values (4, 'ink', 3, '7')")
+        .unwrap();
+    database
+        .execute("insert into items (id, name, price) values (5, 'cap', 1)")
+        .unwrap();
+    database
+        .execute("update items set quantity = 10 where id in (1, 3)")
+        .unwrap();
+    let output = database
+        .execute("select id, total, label from items where total > 20 order by total")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(4), int(21), text("INK x7")],
+            vec![int(2), int(30), text("BOOK x2")],
+            vec![int(1), int(50), text("PEN x10")],
+        ]
+    );
+
+    // rows read from the backend have their virtual columns worked out too
+    let rows = database.select_all_records("items".to_string()).unwrap();
+    assert_eq!(
+        rows[0],
+        vec![
+            int(1),
+            text("pen"),
+            int(5),
+            int(10),
+            int(50),
+            text("PEN x10")
+        ]
+    );
+
+    // and so do those of a database just loaded by the SQL layer, before it has run any SQL,
+    // while the backend alone can't work them out
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let error = database
+        .select_all_records("items".to_string())
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "generated column total needs a database loaded by the SQL layer"
+    );
+    drop(database);
+    let mut database = sql::load(&filename).unwrap();
+    let rows = database.select_all_records("items".to_string()).unwrap();
+    assert_eq!(rows[1][4..], [int(30), text("BOOK x2")]);
+    let mut row = HashMap::new();
+    row.insert("id".to_string(), "6".to_string());
+    row.insert("name".to_string(), "cup".to_string());
+    row.insert("price".to_string(), "2".to_string());
+    row.insert("quantity".to_string(), "3".to_string());
+    database.insert_record("items".to_string(), row).unwrap();
+    let rows = database.select_all_records("items".to_string()).unwrap();
+    assert_eq!(rows[5][4..], [int(6), text("CUP x3")]);
+
+    // real literals are kept as reals in the stored expression
+    database
+        .execute("alter table items add column share real as (price / 1e20 + 1e400)")
+        .unwrap();
+    database
+        .execute("alter table items add column tiny real as (price / 1e20)")
+        .unwrap();
+    let output = database
+        .execute("select share, tiny from items where id = 2")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![Column::Real(f64::INFINITY), Column::Real(15.0 / 1e20)]]
+    );
+
+    cleanup(&filename);
+}
+
+#[test]
+fn stored_columns() {
+    let filename = create_db_file("stored_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    // adding a stored column works it out for the rows already in the table
+    database
+        .execute(
+            "alter table items add column total real generated always as (price * quantity) stored",
+        )
+        .unwrap();
+    database
+        .execute("alter table items add column doubled as (total * 2) virtual")
+        .unwrap();
+    database
+        .execute("insert into items (name, id, quantity, price) values ('cup', 4, 3, 2)")
+        .unwrap();
+    database
+        .execute("update items set price = price + 1")
+        .unwrap();
+
+    let output = database
+        .execute("select id, total, doubled, typeof(total) from items order by id")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), Column::Real(24.0), Column::Real(48.0), text("real")],
+            vec![int(2), Column::Real(32.0), Column::Real(64.0), text("real")],
+            vec![int(3), Column::Null(), Column::Null(), text("null")],
+            vec![int(4), Column::Real(9.0), Column::Real(18.0), text("real")],
+        ]
+    );
+
+    database.execute("delete from items where id = 2").unwrap();
+    let output = database
+        .execute("select sum(total), count(doubled) from items")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Real(33.0), int(2)]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn indexed_generated_columns() {
+    let filename = create_db_file("indexed_generated_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute("alter table items add column total integer as (price * quantity)")
+        .unwrap();
+    database
+        .execute("alter table items add column initial text as (substr(name, 1, 1)) stored")
+        .unwrap();
+    database
+        .execute("create index items_total on items (total)")
+        .unwrap();
+    database
+        .execute("create index items_initial on items (initial)")
+        .unwrap();
+    database
+        .execute("insert into items values (4, 'bag', 10, 3), (5, 'pad', 15, 2)")
+        .unwrap();
+
+    let indexes = database.list_indexes("items".to_string()).unwrap();
+    let total = indexes
+        .iter()
+        .find(|index| index.name == "items_total")
+        .unwrap();
+    let rows = database.search_index(total, &[int(30)]).unwrap();
+    let ids: Vec<Column> = rows.iter().map(|row| row[0].clone()).collect();
+    assert_eq!(ids, vec![int(2), int(4), int(5)]);
+
+    // the index follows changes to the columns its generated column is worked out from
+    database
+        .execute("update items set quantity = 1 where id = 4")
+        .unwrap();
+    let output = database
+        .execute("select id from items where total = 30 order by id")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(2)], vec![int(5)]]);
+
+    let output = database
+        .execute(
+            "select a.id, b.id from items a join items b on b.initial = a.initial \
+             where a.id < b.id order by a.id, b.id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), int(3)],
+            vec![int(1), int(5)],
+            vec![int(2), int(4)],
+            vec![int(3), int(5)],
+        ]
+    );
+
+    cleanup(&filename);
+}
+
+#[test]
+fn generated_column_errors() {
+    let filename = create_db_file("generated_column_errors");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .create_scalar_function("shuffled", 1, false, |arguments| Ok(arguments[0].clone()))
+        .unwrap();
+    database
+        .create_scalar_function("same", 1, true, |arguments| Ok(arguments[0].clone()))
+        .unwrap();
+
+    let errors = vec![
+        (
+            "alter table items add column a as (random())",
+            "non-deterministic functions prohibited in generated columns",
+        ),
+        (
+            "alter table items add column a as (shuffled(price))",
+            "non-deterministic functions prohibited in generated columns",
+        ),
+        (
+            "alter table items add column a as (date('now'))",
+            "non-deterministic functions prohibited in generated columns",
+        ),
+        (
+            "alter table items add column a as ((select 1))",
+            "subqueries prohibited in generated columns",
+        ),
+        (
+            "alter table items add column a as (sum(price))",
+            "aggregate functions prohibited in generated columns",
+        ),
+        (
+            "alter table items add column a as (price + nothing)",
+            "no such column: nothing",
+        ),
+        (
+            "alter table items add column a as (a + 1)",
+            "no such column: a",
+        ),
+        (
+            "alter table items add column a as (nosuch(price))",
+            "no such function: nosuch",
+        ),
+        (
+            "alter table items add column a integer default 1 as (price)",
+            "cannot use DEFAULT on a generated column",
+        ),
+    ];
+    for (sql, message) in errors {
+        let error = database.execute(sql).unwrap_err();
+        assert_eq!(error.to_string(), message, "{}", sql);
+    }
+
+    database
+        .execute("alter table items add column total as (same(price) * items.quantity)")
+        .unwrap();
+    let errors = vec![
+        (
+            "insert into items (id, total) values (9, 1)",
+            "cannot INSERT into generated column total",
+        ),
+        (
+            "insert into items values (9, 'x', 1, 1, 1)",
+            "table items has 4 columns but 5 values were supplied",
+        ),
+        (
+            "update items set total = 1",
+            "cannot UPDATE generated column total",
+        ),
+        (
+            "alter table items drop column quantity",
+            "cannot drop column quantity: it is used by generated column total",
+        ),
+        (
+            "alter table items rename column price to cost",
+            "cannot rename column price: it is used by generated column total",
+        ),
+    ];
+    for (sql, message) in errors {
+        let error = database.execute(sql).unwrap_err();
+        assert_eq!(error.to_string(), message, "{}", sql);
+    }
+
+    // the generated column follows its table, and can itself be dropped
+    database
+        .execute("alter table items rename to things")
+        .unwrap();
+    let output = database
+        .execute("select total from things where id = 1")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(20)]]);
+    database
+        .execute("alter table things drop column total")
+        .unwrap();
+    database
+        .execute("alter table things drop column quantity")
+        .unwrap();
+    let output = database
+        .execute("select * from things where id = 1")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1), text("pen"), int(5)]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn generated_columns_in_triggers() {
+    let filename = create_db_file("generated_columns_in_triggers");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("total".to_string(), ColumnType::Integer),
+    ];
+    database.create_table("log".to_string(), columns).unwrap();
+
+    database
+        .execute("alter table items add column total integer as (price * quantity)")
+        .unwrap();
+    database
+        .execute(
+            "create trigger logged after update on items begin \
+             insert into log values (new.id, new.total - old.total); end",
+        )
+        .unwrap();
+    database
+        .execute("update items set quantity = quantity + 1 where id < 3")
+        .unwrap();
+    let output = database.execute("select * from log order by id").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![int(1), int(5)], vec![int(2), int(15)]]
+    );
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("price".to_string(), ColumnType::Integer),
+        ("quantity".to_string(), ColumnType::Integer),
+    ];
+    database.create_table("items".to_string(), columns).unwrap();
+    database
+        .execute(
+            "insert into items values (1, 'pen', 5, 4), (2, 'book', 15, 2), (3, 'pad', 2, null)",
+        )
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,160 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn integers_round_trip() {
+    let filename = create_db_file("integers_round_trip");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    // the values either side of each serial type's limits
+    let mut values = vec![0, 1, -1, 2, -5, i128::MAX, i128::MIN];
+    for bytes in &[1, 2, 3, 4, 6, 8] {
+        let limit = 1i128 << (bytes * 8 - 1);
+        values.extend_from_slice(&[limit - 1, limit, -limit, -limit - 1]);
+    }
+    let rows = values
+        .iter()
+        .map(|value| vec![int(*value), text(&value.to_string())])
+        .collect();
+    database
+        .replace_records("numbers".to_string(), rows)
+        .unwrap();
+
+    // the values are read back the same after the file is opened again
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database
+        .execute("select value, label from numbers")
+        .unwrap();
+    for (row, value) in output.rows.iter().zip(&values) {
+        assert_eq!(*row, vec![int(*value), text(&value.to_string())]);
+    }
+    assert_eq!(output.rows.len(), values.len());
+
+    let output = database
+        .execute("select count(*) from numbers where value < 0")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(15)]]);
+    let output = database
+        .execute("select label from numbers order by value limit 2")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text(&i128::MIN.to_string())],
+            vec![text(&(-(1i128 << 63) - 1).to_string())],
+        ]
+    );
+
+    cleanup(&filename);
+}
+
+#[test]
+fn negative_integers_in_sql() {
+    let filename = create_db_file("negative_integers_in_sql");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .execute(
+            "insert into numbers values (-5, 'a'), (200, 'b'), (-129, 'c'), \
+             (9223372036854775807 * 4, 'd')",
+        )
+        .unwrap();
+    database
+        .execute("create index numbers_value on numbers (value)")
+        .unwrap();
+
+    let output = database
+        .execute("select value from numbers order by value")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(-129)],
+            vec![int(-5)],
+            vec![int(200)],
+            vec![int(36893488147419103228)],
+        ]
+    );
+
+    let output = database
+        .execute("select n.label from numbers n join numbers m on m.value = n.value * -1 - 134")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("a")], vec![text("c")]]);
+
+    database
+        .execute("update numbers set value = value - 1000 where label = 'b'")
+        .unwrap();
+    let output = database
+        .execute("select value from numbers where label = 'b'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(-800)]]);
+
+    // the smallest integer can be written, though its magnitude alone is a real
+    let smallest = i128::MIN.to_string();
+    database
+        .execute(&format!("insert into numbers values ({}, 'e')", smallest))
+        .unwrap();
+    let output = database
+        .execute(&format!(
+            "select label, -value from numbers where value = {}",
+            smallest
+        ))
+        .unwrap();
+    let magnitude = -(i128::MIN as f64);
+    assert_eq!(output.rows, vec![vec![text("e"), Column::Real(magnitude)]]);
+    let output = database
+        .execute(&format!("select {}, 1 {}", &smallest[1..], smallest))
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![Column::Real(magnitude), Column::Real(1.0 - magnitude)]]
+    );
+
+    // the remainder of the smallest integer by -1 doesn't overflow
+    let output = database
+        .execute(&format!(
+            "select ({}) % -1, -1e300 % -1, value % -1 from numbers where label = 'e'",
+            smallest
+        ))
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(0), Column::Real(0.0), int(0)]]);
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("value".to_string(), ColumnType::Integer),
+        ("label".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("numbers".to_string(), columns)
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,229 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn inner_and_cross_joins() {
+    let filename = create_db_file("inner_and_cross_joins");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute(
+            "select c.name, o.item from customers as c join orders o on c.id = o.customer \
+             order by o.item",
+        )
+        .unwrap();
+    assert_eq!(output.columns, vec!["name", "item"]);
+    let expected = vec![
+        vec![text("alice"), text("book")],
+        vec![text("bob"), text("lamp")],
+        vec![text("alice"), text("pen")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // the comma form with the condition in WHERE gives the same rows
+    let output = database
+        .execute(
+            "select customers.name, orders.item from customers, orders \
+             where customers.id = orders.customer order by orders.item",
+        )
+        .unwrap();
+    assert_eq!(output.rows, expected);
+
+    let output = database
+        .execute("select count(*) from customers cross join orders")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Integer(9)]]);
+
+    // unqualified names that exist in both tables are ambiguous
+    assert!(database
+        .execute("select id from customers join orders on customer = id")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn left_join() {
+    let filename = create_db_file("left_join");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute(
+            "select c.name, o.item from customers c left join orders o \
+             on c.id = o.customer order by c.name, o.item",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("alice"), text("book")],
+        vec![text("alice"), text("pen")],
+        vec![text("bob"), text("lamp")],
+        vec![text("carol"), Column::Null()],
+    ];
+    assert_eq!(output.rows, expected);
+
+    let output = database
+        .execute(
+            "select c.name from customers c left outer join orders o \
+             on c.id = o.customer where o.item is null",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("carol")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn using_and_natural_joins() {
+    let filename = create_db_file("using_and_natural_joins");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute("select * from orders join shipping using (item) order by item")
+        .unwrap();
+    assert_eq!(output.columns, vec!["id", "customer", "item", "days"]);
+    let expected = vec![
+        vec![
+            Column::Integer(10),
+            Column::Integer(1),
+            text("book"),
+            Column::Integer(3),
+        ],
+        vec![
+            Column::Integer(12),
+            Column::Integer(2),
+            text("lamp"),
+            Column::Integer(7),
+        ],
+    ];
+    assert_eq!(output.rows, expected);
+
+    let output = database
+        .execute("select * from orders natural join shipping order by item")
+        .unwrap();
+    assert_eq!(output.rows, expected);
+
+    assert!(database
+        .execute("select * from orders join shipping using (days)")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn index_nested_loop_join() {
+    let filename = create_db_file("index_nested_loop_join");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let query = "select c.name, o.item from customers c left join orders o \
+                 on o.customer = c.id order by c.name, o.item";
+    let scanned = database.execute(query).unwrap();
+
+    database
+        .execute("create index orders_customer on orders (customer)")
+        .unwrap();
+    let indexes = database.list_indexes("orders".to_string()).unwrap();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(indexes[0].columns, vec!["customer"]);
+
+    let searched = database.execute(query).unwrap();
+    assert_eq!(searched, scanned);
+
+    // the index is kept up to date when the table changes
+    let mut row = HashMap::new();
+    row.insert("id".to_string(), "13".to_string());
+    row.insert("customer".to_string(), "3".to_string());
+    row.insert("item".to_string(), "cup".to_string());
+    database.insert_record("orders".to_string(), row).unwrap();
+    database
+        .execute("update orders set customer = 3 where item = 'pen'")
+        .unwrap();
+
+    let output = database
+        .execute(
+            "select o.item from customers c join orders o on o.customer = c.id \
+             where c.name = 'carol' order by o.item",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("cup")], vec![text("pen")]]);
+
+    assert!(database
+        .execute("create index orders_customer on orders (item)")
+        .is_err());
+    assert!(database
+        .execute("create index orders_missing on orders (missing)")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+fn create_tables(database: &mut Database) {
+    let customers = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("customers".to_string(), customers)
+        .unwrap();
+    for (id, name) in vec![("1", "alice"), ("2", "bob"), ("3", "carol")] {
+        insert(database, "customers", &[("id", id), ("name", name)]);
+    }
+
+    let orders = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("customer".to_string(), ColumnType::Integer),
+        ("item".to_string(), ColumnType::Text),
+    ];
+    database.create_table("orders".to_string(), orders).unwrap();
+    for (id, customer, item) in vec![("10", "1", "book"), ("11", "1", "pen"), ("12", "2", "lamp")] {
+        insert(
+            database,
+            "orders",
+            &[("id", id), ("customer", customer), ("item", item)],
+        );
+    }
+
+    let shipping = vec![
+        ("item".to_string(), ColumnType::Text),
+        ("days".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("shipping".to_string(), shipping)
+        .unwrap();
+    for (item, days) in vec![("book", "3"), ("lamp", "7"), ("desk", "14")] {
+        insert(database, "shipping", &[("item", item), ("days", days)]);
+    }
+}
+
+fn insert(database: &mut Database, table: &str, values: &[(&str, &str)]) {
+    let mut row = HashMap::new();
+    for (column, value) in values {
+        row.insert(column.to_string(), value.to_string());
+    }
+    database.insert_record(table.to_string(), row).unwrap();
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
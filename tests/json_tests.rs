@@ -0,0 +1,284 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn json_columns() {
+    let filename = create_db_file("json_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .execute(
+            "insert into documents values \
+             (1, '{ \"name\": \"ada\", \"tags\": [\"math\", \"code\"], \"age\": 36 }'), \
+             (2, ' [1, 2.5, null, true] '), (3, 42), (4, null)",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![
+            int(1),
+            text("{\"name\":\"ada\",\"tags\":[\"math\",\"code\"],\"age\":36}"),
+        ],
+        vec![int(2), text("[1,2.5,null,true]")],
+        vec![int(3), int(42)],
+        vec![int(4), Column::Null()],
+    ];
+    let output = database.execute("select * from documents").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // text that isn't JSON is refused, and the table is left as it was
+    assert!(database
+        .execute("insert into documents values (5, '{\"name\": ')")
+        .is_err());
+    assert!(database
+        .execute("insert into documents values (5, 'plain text')")
+        .is_err());
+    assert!(database
+        .execute("insert into documents values (5, '[]'), (6, '{}'), (7, '[1,]')")
+        .is_err());
+    assert!(database
+        .execute("update documents set body = '[1, 2' where id = 2")
+        .is_err());
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database.execute("select * from documents").unwrap();
+    assert_eq!(output.rows, expected);
+
+    // the type name is read by ALTER TABLE as it is by CREATE TABLE
+    let (_rootpage, columns) = database.describe_table("documents".to_string()).unwrap();
+    assert_eq!(columns[1].1, ColumnType::Json);
+
+    database
+        .execute("update documents set body = json_set(body, '$.age', 37) where id = 1")
+        .unwrap();
+    let output = database
+        .execute("select body ->> 'age', body -> 'tags', body -> '$.tags[#-1]' from documents where id = 1")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![int(37), text("[\"math\",\"code\"]"), text("\"code\"")]]
+    );
+
+    cleanup(&filename);
+}
+
+#[test]
+fn json_functions() {
+    let filename = create_db_file("json_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let document = "'{\"a\": {\"b\": [10, 20, {\"c\": \"x\"}]}, \"d\": 1.5, \"e\": false}'";
+    let queries = vec![
+        ("select json(' [ 1 , \"two\" ] ')", text("[1,\"two\"]")),
+        ("select json_valid('{\"a\": 1}')", int(1)),
+        ("select json_valid('{a: 1}')", int(0)),
+        ("select json_valid(null)", Column::Null()),
+        ("select json_extract(DOC, '$.a.b[1]')", int(20)),
+        ("select json_extract(DOC, '$.a.b[#-1].c')", text("x")),
+        ("select json_extract(DOC, '$.d')", real(1.5)),
+        ("select json_extract(DOC, '$.e')", int(0)),
+        (
+            "select json_extract(DOC, '$.a')",
+            text("{\"b\":[10,20,{\"c\":\"x\"}]}"),
+        ),
+        ("select json_extract(DOC, '$.missing')", Column::Null()),
+        (
+            "select json_extract(DOC, '$.d', '$.x', '$.a.b[0]')",
+            text("[1.5,null,10]"),
+        ),
+        ("select json_extract('{\"a.b\": 1}', '$.\"a.b\"')", int(1)),
+        ("select json_type(DOC, '$.a.b')", text("array")),
+        ("select json_type(DOC, '$.d')", text("real")),
+        ("select json_array_length(DOC, '$.a.b')", int(3)),
+        ("select json_array_length(DOC)", int(0)),
+        ("select json_array_length('[]')", int(0)),
+        ("select json_array_length(DOC, '$.none')", Column::Null()),
+        (
+            "select json_set(DOC, '$.d', 2, '$.f', 'new', '$.a.b[#]', 30)",
+            text("{\"a\":{\"b\":[10,20,{\"c\":\"x\"},30]},\"d\":2,\"e\":false,\"f\":\"new\"}"),
+        ),
+        (
+            "select json_set('{}', '$.a.b', null, '$.c[0]', 1)",
+            text("{\"a\":{\"b\":null},\"c\":[1]}"),
+        ),
+        ("select json_set('[1]', '$', 'x')", text("\"x\"")),
+        (
+            "select json_insert('{\"a\": 1}', '$.a', 2, '$.b', 3)",
+            text("{\"a\":1,\"b\":3}"),
+        ),
+        (
+            "select json_replace('{\"a\": 1}', '$.a', 2, '$.b', 3)",
+            text("{\"a\":2}"),
+        ),
+        ("select json_remove(DOC, '$.a', '$.e')", text("{\"d\":1.5}")),
+        (
+            "select json_remove('[1, 2, 3]', '$[0]', '$[#-1]')",
+            text("[2]"),
+        ),
+        ("select json_remove('[1]', '$')", Column::Null()),
+        ("select DOC -> 'a' -> 'b' -> 2", text("{\"c\":\"x\"}")),
+        ("select DOC -> '$.a.b[2].c'", text("\"x\"")),
+        ("select DOC ->> '$.a.b[2].c'", text("x")),
+        ("select '[1, 2, 3]' ->> -1", int(3)),
+        ("select DOC ->> 'nothing'", Column::Null()),
+        ("select null -> 'a'", Column::Null()),
+        ("select '\"\\u00e9\\n\"' ->> '$'", text("é\n")),
+        ("select '[1, 2]' ->> 0 + 1", int(2)),
+    ];
+    for (query, expected) in queries {
+        let query = query.replace("DOC", document);
+        let output = database.execute(&query).unwrap();
+        assert_eq!(output.rows, vec![vec![expected]], "{}", query);
+    }
+
+    assert!(database.execute("select json('[1,]')").is_err());
+    assert!(database.execute("select json_extract('{}', 'a')").is_err());
+    assert!(database.execute("select json_extract('{}', '$.')").is_err());
+    assert!(database.execute("select json_set('{}', '$.a')").is_err());
+    assert!(database.execute("select 'not json' -> 'a'").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn json_table_functions() {
+    let filename = create_db_file("json_table_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .execute(
+            "insert into documents values (1, '{\"tags\": [\"a\", \"b\"]}'), \
+             (2, '{\"tags\": [\"c\"]}'), (3, '{\"tags\": []}')",
+        )
+        .unwrap();
+
+    let output = database
+        .execute("select key, value, type, atom, fullkey, path from json_each('[5, {\"x\": 1}]')")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![
+                int(0),
+                int(5),
+                text("integer"),
+                int(5),
+                text("$[0]"),
+                text("$")
+            ],
+            vec![
+                int(1),
+                text("{\"x\":1}"),
+                text("object"),
+                Column::Null(),
+                text("$[1]"),
+                text("$")
+            ],
+        ]
+    );
+
+    let output = database
+        .execute("select key, value from json_each('{\"a\": {\"b\": 1, \"c\": 2}}', '$.a')")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![text("b"), int(1)], vec![text("c"), int(2)]]
+    );
+
+    let output = database
+        .execute("select id, parent, key, fullkey from json_tree('{\"a\": [1, 2], \"b\": 3}')")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(0), Column::Null(), Column::Null(), text("$")],
+            vec![int(1), int(0), text("a"), text("$.a")],
+            vec![int(2), int(1), int(0), text("$.a[0]")],
+            vec![int(3), int(1), int(1), text("$.a[1]")],
+            vec![int(4), int(0), text("b"), text("$.b")],
+        ]
+    );
+
+    // joined onto a table the function reads each row's document
+    let output = database
+        .execute(
+            "select documents.id, tag.value from documents, json_each(documents.body, '$.tags') as tag \
+             order by tag.value desc",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(2), text("c")],
+            vec![int(1), text("b")],
+            vec![int(1), text("a")],
+        ]
+    );
+    let output = database
+        .execute(
+            "select documents.id, count(tag.value) from documents \
+             left join json_each(body -> 'tags') as tag group by documents.id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), int(2)],
+            vec![int(2), int(1)],
+            vec![int(3), int(0)],
+        ]
+    );
+    let output = database
+        .execute(
+            "select documents.id from documents \
+             where exists (select 1 from json_each(body, '$.tags') where value = 'c')",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(2)]]);
+
+    assert!(database
+        .execute("select * from json_nothing('[]')")
+        .is_err());
+    assert!(database.execute("select * from json_each('[1')").is_err());
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![("id".to_string(), ColumnType::Integer)];
+    database
+        .create_table("documents".to_string(), columns)
+        .unwrap();
+    database
+        .execute("alter table documents add column body json")
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn real(value: f64) -> Column {
+    Column::Real(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,327 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn analyze_statistics() {
+    let filename = create_db_file("analyze_statistics");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    database.execute("analyze").unwrap();
+    let output = database
+        .execute("select tbl, idx, stat from sqlite_stat1 order by tbl, idx")
+        .unwrap();
+    let expected = vec![
+        vec![text("customers"), Column::Null(), text("3")],
+        vec![text("customers"), text("customers_id"), text("3 1")],
+        vec![text("orders"), Column::Null(), text("6")],
+        vec![text("orders"), text("orders_customer"), text("6 2 1")],
+        vec![text("orders"), text("orders_status"), text("6 6")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // analyzing one table only replaces its own statistics
+    database
+        .execute("insert into customers values (4, 'dan')")
+        .unwrap();
+    database.execute("analyze customers").unwrap();
+    let output = database
+        .execute("select idx, stat from sqlite_stat1 order by tbl, idx")
+        .unwrap();
+    let stats: Vec<Column> = output.rows.iter().map(|row| row[1].clone()).collect();
+    assert_eq!(
+        stats,
+        vec![
+            text("4"),
+            text("4 1"),
+            text("6"),
+            text("6 2 1"),
+            text("6 6")
+        ]
+    );
+
+    let stats = database.table_stats("orders").unwrap().unwrap();
+    assert_eq!(stats.row_count, 6);
+    assert_eq!(stats.rows_per_key("orders_customer"), Some(&[2, 1][..]));
+    assert_eq!(database.table_stats("shelves").unwrap(), None);
+
+    assert!(database.execute("analyze shelves").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn planned_access_paths() {
+    let filename = create_db_file("planned_access_paths");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let join = "select o.item, c.name from orders o join customers c on c.id = o.customer \
+                order by o.id";
+    let unselective = "select c.name, o.item from customers c \
+                       left join orders o on o.status = c.name";
+    let before = database.execute(join).unwrap();
+
+    // without statistics the tables are joined in the order they are written and any
+    // usable index is searched
+    let output = database
+        .execute(&format!("explain query plan {}", join))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SCAN orders AS o",
+            "SEARCH customers AS c USING INDEX customers_id (id=?)",
+            "USE TEMP B-TREE FOR ORDER BY",
+        ]
+    );
+    let output = database
+        .execute(&format!("explain query plan {}", unselective))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SCAN customers AS c",
+            "SEARCH orders AS o USING INDEX orders_status (status=?) LEFT-JOIN",
+        ]
+    );
+
+    // terms of the WHERE clause are used to join the tables they read
+    let output = database
+        .execute(
+            "explain query plan select c.name, o.item from customers c, orders o \
+             where o.customer = c.id and o.item <> 'pen'",
+        )
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SCAN customers AS c",
+            "SEARCH orders AS o USING INDEX orders_customer (customer=?)",
+        ]
+    );
+
+    // with statistics the smaller table is read first, and an index whose keys are all
+    // the same is no better than a scan
+    database.execute("analyze").unwrap();
+    let output = database
+        .execute(&format!("explain query plan {}", join))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SCAN customers AS c",
+            "SEARCH orders AS o USING INDEX orders_customer (customer=?)",
+            "USE TEMP B-TREE FOR ORDER BY",
+        ]
+    );
+    let output = database
+        .execute(&format!("explain query plan {}", unselective))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec!["SCAN customers AS c", "SCAN orders AS o LEFT-JOIN"]
+    );
+
+    // the rows and the order of their columns don't depend on the join order
+    assert_eq!(database.execute(join).unwrap(), before);
+    let output = database
+        .execute(
+            "select * from orders o join customers c on c.id = o.customer \
+             where o.item = 'lamp'",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![
+            int(11),
+            int(2),
+            text("lamp"),
+            text("open"),
+            int(2),
+            text("bob")
+        ]]
+    );
+
+    cleanup(&filename);
+}
+
+#[test]
+fn first_table_access_paths() {
+    let filename = create_db_file("first_table_access_paths");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    // a query of one table searches an index the WHERE clause gives the key of
+    let query = "select id from orders where customer = 1 and item <> 'pen' order by id";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SEARCH orders USING INDEX orders_customer (customer=?)",
+            "USE TEMP B-TREE FOR ORDER BY",
+        ]
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.rows, vec![vec![int(10)], vec![int(14)]]);
+
+    // a key has to be known before the table is read
+    let output = database
+        .execute("explain query plan select * from orders where customer = id - 9")
+        .unwrap();
+    assert_eq!(plan(&output.rows), vec!["SCAN orders"]);
+
+    // the first table of a join is searched the same way
+    let query = "select c.name, o.item from customers c join orders o on o.customer = c.id \
+                 where c.id = 3";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SEARCH customers AS c USING INDEX customers_id (id=?)",
+            "SEARCH orders AS o USING INDEX orders_customer (customer=?)",
+        ]
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.rows, vec![vec![text("carol"), text("desk")]]);
+
+    let query = "select c.name, o.item from customers c \
+                 left join orders o on o.status = c.name where c.id = 2";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SEARCH customers AS c USING INDEX customers_id (id=?)",
+            "SEARCH orders AS o USING INDEX orders_status (status=?) LEFT-JOIN",
+        ]
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob"), Column::Null()]]);
+
+    // a key is converted by the comparison's affinity, so an index finds what a scan would
+    let query = "select id from orders where customer = '1' order by id";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SEARCH orders USING INDEX orders_customer (customer=?)",
+            "USE TEMP B-TREE FOR ORDER BY",
+        ]
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![int(10)], vec![int(12)], vec![int(14)]]
+    );
+
+    // but a comparison that converts the indexed column's values can't search its index
+    database
+        .execute("insert into orders values (16, 3, 'cup', '02')")
+        .unwrap();
+    let query = "select c.name, o.id from customers c join orders o on o.status = c.id";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec!["SCAN customers AS c", "SCAN orders AS o"]
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob"), int(16)]]);
+    let query = "select c.name, o.id from orders o join customers c on c.id = o.status";
+    let output = database
+        .execute(&format!("explain query plan {}", query))
+        .unwrap();
+    assert_eq!(
+        plan(&output.rows),
+        vec![
+            "SCAN orders AS o",
+            "SEARCH customers AS c USING INDEX customers_id (id=?)",
+        ]
+    );
+    let output = database.execute(query).unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob"), int(16)]]);
+
+    cleanup(&filename);
+}
+
+fn plan(rows: &[Vec<Column>]) -> Vec<String> {
+    rows.iter()
+        .map(|row| match &row[2] {
+            Column::Text(detail) => detail.clone(),
+            detail => panic!("unexpected detail {:?}", detail),
+        })
+        .collect()
+}
+
+fn create_tables(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("customers".to_string(), columns)
+        .unwrap();
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("customer".to_string(), ColumnType::Integer),
+        ("item".to_string(), ColumnType::Text),
+        ("status".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("orders".to_string(), columns)
+        .unwrap();
+
+    database
+        .execute("insert into customers values (1, 'alice'), (2, 'bob'), (3, 'carol')")
+        .unwrap();
+    database
+        .execute(
+            "insert into orders values (10, 1, 'book', 'open'), (11, 2, 'lamp', 'open'), \
+             (12, 1, 'pen', 'open'), (13, 3, 'desk', 'open'), (14, 1, 'ink', 'open'), \
+             (15, 2, 'mug', 'open')",
+        )
+        .unwrap();
+    database
+        .execute("create index customers_id on customers (id)")
+        .unwrap();
+    database
+        .execute("create index orders_customer on orders (customer, item)")
+        .unwrap();
+    database
+        .execute("create index orders_status on orders (status)")
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
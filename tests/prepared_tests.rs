@@ -0,0 +1,166 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn positional_parameters() {
+    let filename = create_db_file("positional_parameters");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    let mut insert = database
+        .prepare("insert into users values (?, ?, ?)")
+        .unwrap();
+    assert_eq!(insert.parameter_count(), 3);
+    assert_eq!(insert.parameter_name(1), None);
+    let users = [
+        (1, "alice", "alice@example.com"),
+        (2, "bob'); delete from users; --", "bob@example.com"),
+        (3, "carol", "carol@example.com"),
+    ];
+    for (id, name, email) in users.iter() {
+        insert.bind(1, int(*id)).unwrap();
+        insert.bind(2, text(name)).unwrap();
+        insert.bind(3, text(email)).unwrap();
+        assert_eq!(insert.step(&mut database).unwrap(), None);
+    }
+
+    // values are never read as SQL
+    let mut select = database
+        .prepare("select id, name from users where id >= ? order by id")
+        .unwrap();
+    select.bind(1, int(2)).unwrap();
+    let output = select.query(&mut database).unwrap();
+    assert_eq!(output.columns, vec!["id", "name"]);
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(2), text("bob'); delete from users; --")],
+            vec![int(3), text("carol")],
+        ]
+    );
+
+    // a statement is stepped a row at a time and run again after a reset
+    assert_eq!(
+        select.step(&mut database).unwrap(),
+        Some(vec![int(2), text("bob'); delete from users; --")])
+    );
+    assert!(select.bind(1, int(3)).is_err());
+    select.reset();
+    select.bind(1, int(3)).unwrap();
+    assert_eq!(
+        select.step(&mut database).unwrap(),
+        Some(vec![int(3), text("carol")])
+    );
+    assert_eq!(select.step(&mut database).unwrap(), None);
+    assert_eq!(
+        select.step(&mut database).unwrap(),
+        Some(vec![int(3), text("carol")])
+    );
+    assert_eq!(select.columns(), ["id", "name"]);
+
+    // parameters that aren't bound are NULL
+    select.reset();
+    select.clear_bindings();
+    assert_eq!(select.query(&mut database).unwrap().rows.len(), 0);
+
+    assert!(select.bind(0, int(1)).is_err());
+    assert!(select.bind(2, int(1)).is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn named_parameters() {
+    let filename = create_db_file("named_parameters");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    database
+        .execute(
+            "insert into users values (1, 'alice', 'a@example.com'), (2, 'bob', 'b@example.com')",
+        )
+        .unwrap();
+
+    let mut update = database
+        .prepare("update users set email = $email where id = :id or name = :id")
+        .unwrap();
+    assert_eq!(update.parameter_count(), 2);
+    assert_eq!(update.parameter_number(":id"), Some(2));
+    assert_eq!(update.parameter_name(1), Some("$email"));
+    update.bind_named(":id", int(2)).unwrap();
+    update
+        .bind_named("$email", text("bob@example.com"))
+        .unwrap();
+    update.query(&mut database).unwrap();
+    assert!(update.bind_named(":name", int(1)).is_err());
+
+    let output = database
+        .execute("select email from users order by id")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![text("a@example.com")], vec![text("bob@example.com")]]
+    );
+
+    // `?NNN` has its own number and `?` follows the largest number so far
+    let mut select = database.prepare("select ?2, ?, @x, ?1 + 1").unwrap();
+    assert_eq!(select.parameter_count(), 4);
+    assert_eq!(select.parameter_name(2), Some("?2"));
+    assert_eq!(select.parameter_name(3), None);
+    for number in 1..=4 {
+        select.bind(number, int(number as i128 * 10)).unwrap();
+    }
+    let output = select.query(&mut database).unwrap();
+    assert_eq!(output.columns, vec!["?2", "?", "@x", "?1 + 1"]);
+    assert_eq!(output.rows, vec![vec![int(20), int(30), int(40), int(11)]]);
+
+    // parameters can be used in nested queries
+    let mut select = database
+        .prepare(
+            "select name from users where id in (select id from users where name = :name) \
+             and exists (select 1 from users where id = :id)",
+        )
+        .unwrap();
+    select.bind_named(":name", text("bob")).unwrap();
+    select.bind_named(":id", int(1)).unwrap();
+    let output = select.query(&mut database).unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob")]]);
+
+    assert!(database.prepare("select ?0").is_err());
+    assert!(database.prepare("select :").is_err());
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("email".to_string(), ColumnType::Text),
+    ];
+    database.create_table("users".to_string(), columns).unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
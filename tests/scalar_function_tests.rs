@@ -0,0 +1,285 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn text_functions() {
+    let filename = create_db_file("text_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select length('héllo')", int(5)),
+        ("select length(x'0102')", int(2)),
+        ("select length(12.5)", int(4)),
+        ("select lower('ÀBc'), upper('àbC')", text("Àbc")),
+        ("select upper('àbC')", text("àBC")),
+        ("select substr('abcdef', 2, 3)", text("bcd")),
+        ("select substr('abcdef', 4)", text("def")),
+        ("select substr('abcdef', -2)", text("ef")),
+        ("select substr('abcdef', 0, 2)", text("a")),
+        ("select substr('abcdef', 4, -2)", text("bc")),
+        (
+            "select substr('abc', 3, -170141183460469231731687303715884105728)",
+            text("ab"),
+        ),
+        ("select substring('abcdef', 10)", text("")),
+        ("select substr(x'01020304', 2, 2)", Column::Blob(vec![2, 3])),
+        ("select trim('  a b  ')", text("a b")),
+        ("select ltrim('xxaxx', 'x')", text("axx")),
+        ("select rtrim('xxaxx', 'x')", text("xxa")),
+        ("select replace('a-b-c', '-', '+')", text("a+b+c")),
+        ("select replace('abc', '', 'x')", text("abc")),
+        ("select instr('héllo', 'l')", int(3)),
+        ("select instr('hello', 'z')", int(0)),
+        ("select hex('abc')", text("616263")),
+        ("select hex(x'00ff')", text("00FF")),
+        ("select quote('it''s')", text("'it''s'")),
+        ("select quote(x'0a')", text("X'0A'")),
+        ("select typeof(1), typeof(1.5)", text("integer")),
+        ("select typeof(1.5)", text("real")),
+        ("select typeof('a')", text("text")),
+        ("select typeof(x'00')", text("blob")),
+        ("select typeof(null)", text("null")),
+    ];
+    check(&mut database, queries);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn null_handling() {
+    let filename = create_db_file("null_handling");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select coalesce(null, null, 3, 4)", int(3)),
+        ("select coalesce(null, null)", Column::Null()),
+        ("select ifnull(null, 'x')", text("x")),
+        ("select ifnull(1, 'x')", int(1)),
+        ("select nullif(1, 1.0)", Column::Null()),
+        ("select nullif('a', 'b')", text("a")),
+        ("select hex(null)", text("")),
+        ("select quote(null)", text("NULL")),
+    ];
+    check(&mut database, queries);
+
+    // everything else gives NULL for a NULL argument
+    let calls = vec![
+        "abs(null)",
+        "round(null)",
+        "round(1.5, null)",
+        "length(null)",
+        "lower(null)",
+        "upper(null)",
+        "substr(null, 1)",
+        "substr('abc', null)",
+        "trim(null)",
+        "trim('abc', null)",
+        "replace('abc', null, 'x')",
+        "instr(null, 'a')",
+        "printf(null)",
+        "min(1, null, 3)",
+        "max(null, 2)",
+        "sqrt(null)",
+        "pow(2, null)",
+        "log(null, 8)",
+        "ceil(null)",
+        "sign(null)",
+        "mod(null, 2)",
+    ];
+    for call in calls {
+        let output = database.execute(&format!("select {}", call)).unwrap();
+        assert_eq!(output.rows, vec![vec![Column::Null()]], "{}", call);
+    }
+
+    cleanup(&filename);
+}
+
+#[test]
+fn numeric_functions() {
+    let filename = create_db_file("numeric_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select abs(-3)", int(3)),
+        ("select abs(-2.5)", Column::Real(2.5)),
+        ("select abs('-4')", Column::Real(4.0)),
+        ("select round(2.5)", Column::Real(3.0)),
+        ("select round(-2.5)", Column::Real(-3.0)),
+        ("select round(3.14159, 2)", Column::Real(3.14)),
+        ("select round(7)", Column::Real(7.0)),
+        ("select round(1.5, 30)", Column::Real(1.5)),
+        ("select round(0.1 + 0.2, 20)", Column::Real(0.1 + 0.2)),
+        ("select min(3, 1, 2)", int(1)),
+        ("select max(3, 'a', 2)", text("a")),
+        ("select max(1, 2.5)", Column::Real(2.5)),
+        ("select sqrt(16)", Column::Real(4.0)),
+        ("select sqrt(-1)", Column::Null()),
+        ("select pow(2, 10)", Column::Real(1024.0)),
+        ("select power('3', 2)", Column::Real(9.0)),
+        ("select exp(0)", Column::Real(1.0)),
+        ("select ln(1)", Column::Real(0.0)),
+        ("select ln(0)", Column::Null()),
+        ("select log(100)", Column::Real(2.0)),
+        ("select log(2, 8)", Column::Real(3.0)),
+        ("select log2(8)", Column::Real(3.0)),
+        ("select log10(1000)", Column::Real(3.0)),
+        ("select ceil(1.2)", Column::Real(2.0)),
+        ("select ceiling(-1.2)", Column::Real(-1.0)),
+        ("select floor(-1.2)", Column::Real(-2.0)),
+        ("select trunc(-1.7)", Column::Real(-1.0)),
+        ("select floor(5)", int(5)),
+        ("select sign(-7)", int(-1)),
+        ("select sign(0.0)", int(0)),
+        ("select sign('abc')", Column::Null()),
+        ("select mod(7, 3)", Column::Real(1.0)),
+        ("select mod(7, 0)", Column::Null()),
+        ("select degrees(pi())", Column::Real(180.0)),
+        ("select radians(180) = pi()", int(1)),
+        ("select cos(0)", Column::Real(1.0)),
+        ("select atan2(0, 1)", Column::Real(0.0)),
+        ("select sqrt('abc')", Column::Null()),
+    ];
+    check(&mut database, queries);
+
+    // random() gives a different integer each time
+    let output = database
+        .execute("select random(), random(), typeof(random())")
+        .unwrap();
+    let row = &output.rows[0];
+    assert_ne!(row[0], row[1]);
+    assert_eq!(row[2], text("integer"));
+
+    cleanup(&filename);
+}
+
+#[test]
+fn printf_function() {
+    let filename = create_db_file("printf_function");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+
+    let queries = vec![
+        ("select printf('%d items', 3)", text("3 items")),
+        (
+            "select printf('%5d|%-5d|%05d', 42, 42, -42)",
+            text("   42|42   |-0042"),
+        ),
+        ("select printf('%+d % d', 5, 5)", text("+5  5")),
+        ("select printf('%,d', 1234567)", text("1,234,567")),
+        ("select printf('%.2f', 3.14159)", text("3.14")),
+        ("select printf('%8.3f', -2.5)", text("  -2.500")),
+        ("select printf('%f', 1)", text("1.000000")),
+        ("select printf('%e', 12345.678)", text("1.234568e+04")),
+        (
+            "select printf('%g %g %g', 0.0001, 123456789, 100)",
+            text("0.0001 1.23457e+08 100"),
+        ),
+        ("select printf('%x %X %#o', 255, 255, 8)", text("ff FF 010")),
+        (
+            "select printf('%s and %s', 'this', 'that')",
+            text("this and that"),
+        ),
+        (
+            "select printf('%.3s|%-6s|%6s', 'abcdef', 'ab', 'ab')",
+            text("abc|ab    |    ab"),
+        ),
+        ("select printf('%c', 'xyz')", text("x")),
+        ("select printf('%q', 'it''s')", text("it''s")),
+        (
+            "select printf('%Q %Q', 'it''s', null)",
+            text("'it''s' NULL"),
+        ),
+        ("select printf('100%%')", text("100%")),
+        ("select printf('%*d', 4, 7)", text("   7")),
+        ("select printf('%d %s', 1)", text("1 ")),
+        ("select format('%s-%d', 'a', '12abc')", text("a-12")),
+    ];
+    check(&mut database, queries);
+
+    // widths and precisions are capped rather than passed on whole
+    let output = database
+        .execute(
+            "select length(printf('%.1000000000f', 1)), length(printf('%.*g', 1000000000, 0.5)), \
+             length(printf('%1000000000d', 1))",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(65002), int(3), int(65000)]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn functions_on_columns() {
+    let filename = create_db_file("functions_on_columns");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("score".to_string(), ColumnType::Real),
+    ];
+    database
+        .create_table("players".to_string(), columns)
+        .unwrap();
+    database
+        .execute("insert into players values (1, ' Ann ', 7.25), (2, 'bob', null), (3, 'Cy', 9.5)")
+        .unwrap();
+
+    let output = database
+        .execute(
+            "select upper(trim(name)), coalesce(round(score, 1), 0), length(trim(name)) \
+             from players where instr(lower(name), 'b') = 0 order by id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("ANN"), Column::Real(7.3), int(3)],
+            vec![text("CY"), Column::Real(9.5), int(2)],
+        ]
+    );
+    // one argument is the aggregate, more are the scalar function
+    let output = database
+        .execute("select max(score), min(max(id, 2)), max(id, score) from players where id = 3")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![Column::Real(9.5), int(3), Column::Real(9.5)]]
+    );
+
+    assert!(database.execute("select substr('abc')").is_err());
+    assert!(database.execute("select coalesce(1)").is_err());
+    assert!(database.execute("select nosuch(1)").is_err());
+
+    cleanup(&filename);
+}
+
+fn check(database: &mut Database, queries: Vec<(&str, Column)>) {
+    for (query, expected) in queries {
+        let output = database.execute(query).unwrap();
+        assert_eq!(output.rows[0][0], expected, "{}", query);
+    }
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
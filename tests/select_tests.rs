@@ -0,0 +1,137 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn projections_and_aliases() {
+    let filename = create_db_file("projections_and_aliases");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_people_table(&mut database);
+
+    let output = database
+        .execute("select name, age * 2 as double_age, age + 1 from people where name = 'bob'")
+        .unwrap();
+    assert_eq!(output.columns, vec!["name", "double_age", "age + 1"]);
+    assert_eq!(
+        output.rows,
+        vec![vec![text("bob"), Column::Integer(84), Column::Integer(43)]]
+    );
+
+    let output = database
+        .execute("select people.*, 'x' label from people limit 1")
+        .unwrap();
+    assert_eq!(output.columns, vec!["name", "age", "label"]);
+    assert_eq!(
+        output.rows,
+        vec![vec![text("alice"), Column::Integer(25), text("x")]]
+    );
+
+    let output = database.execute("select 1 + 2 as three").unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Integer(3)]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn order_by_limit_offset() {
+    let filename = create_db_file("order_by_limit_offset");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_people_table(&mut database);
+
+    let names = |database: &mut Database, sql: &str| -> Vec<Column> {
+        let output = database.execute(sql).unwrap();
+        output
+            .rows
+            .into_iter()
+            .map(|mut row| row.remove(0))
+            .collect()
+    };
+
+    let sorted = names(&mut database, "select name from people order by age");
+    assert_eq!(
+        sorted,
+        vec![text("dave"), text("alice"), text("erin"), text("bob")]
+    );
+
+    let sorted = names(
+        &mut database,
+        "select name from people order by age desc nulls first",
+    );
+    assert_eq!(
+        sorted,
+        vec![text("dave"), text("bob"), text("alice"), text("erin")]
+    );
+
+    let sorted = names(
+        &mut database,
+        "select name, age a from people order by a asc nulls last, 1 desc",
+    );
+    assert_eq!(
+        sorted,
+        vec![text("erin"), text("alice"), text("bob"), text("dave")]
+    );
+
+    let sorted = names(
+        &mut database,
+        "select name from people order by age limit 2 offset 1",
+    );
+    assert_eq!(sorted, vec![text("alice"), text("erin")]);
+
+    let sorted = names(
+        &mut database,
+        "select name from people order by name desc limit 1, 2",
+    );
+    assert_eq!(sorted, vec![text("dave"), text("bob")]);
+
+    assert!(database
+        .execute("select name from people order by 3")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+fn create_people_table(database: &mut Database) {
+    let columns = vec![
+        ("name".to_string(), ColumnType::Text),
+        ("age".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("people".to_string(), columns)
+        .unwrap();
+
+    let people = vec![
+        ("alice", Some(25)),
+        ("bob", Some(42)),
+        ("dave", None),
+        ("erin", Some(25)),
+    ];
+    for (name, age) in people {
+        let mut row = HashMap::new();
+        row.insert("name".to_string(), name.to_string());
+        if let Some(age) = age {
+            row.insert("age".to_string(), age.to_string());
+        }
+        database.insert_record("people".to_string(), row).unwrap();
+    }
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,220 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn scalar_subqueries() {
+    let filename = create_db_file("scalar_subqueries");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute("select name from employees where salary = (select max(salary) from employees)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob")]]);
+
+    // no rows gives NULL
+    let output = database
+        .execute("select (select name from employees where salary > 1000)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Null()]]);
+
+    assert!(database
+        .execute("select (select name, salary from employees)")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn in_and_exists() {
+    let filename = create_db_file("in_and_exists");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute(
+            "select name from employees where department in \
+             (select id from departments where city = 'london') order by name",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("alice")], vec![text("bob")]]);
+
+    let output = database
+        .execute(
+            "select name from departments d where not exists \
+             (select 1 from employees e where e.department = d.id)",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("legal")]]);
+
+    let output = database
+        .execute("select name from departments where id not in (select department from employees)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("legal")]]);
+
+    // NOT IN with a NULL in the list is never true
+    let output = database
+        .execute("select 3 not in (select null), 1 in (select 1 where 0)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Null(), Column::Integer(0)]]);
+
+    // subqueries in the WHERE clause of a delete
+    database
+        .execute("delete from departments where id not in (select id from departments where city = 'paris')")
+        .unwrap();
+    let output = database.execute("select name from departments").unwrap();
+    assert_eq!(output.rows, vec![vec![text("sales")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn correlated_subqueries() {
+    let filename = create_db_file("correlated_subqueries");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute(
+            "select d.name, (select count(*) from employees e where e.department = d.id) as staff \
+             from departments d order by staff desc, d.name",
+        )
+        .unwrap();
+    assert_eq!(output.columns, vec!["name", "staff"]);
+    let expected = vec![
+        vec![text("engineering"), Column::Integer(2)],
+        vec![text("sales"), Column::Integer(2)],
+        vec![text("legal"), Column::Integer(0)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // employees paid more than the average of their department
+    let output = database
+        .execute(
+            "select name from employees e where salary > \
+             (select avg(salary) from employees where department = e.department) order by name",
+        )
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("bob")], vec![text("dave")]]);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn derived_tables() {
+    let filename = create_db_file("derived_tables");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let output = database
+        .execute(
+            "select t.department, t.total from \
+             (select department, sum(salary) as total from employees group by department) as t \
+             where t.total > 250 order by t.department",
+        )
+        .unwrap();
+    assert_eq!(output.columns, vec!["department", "total"]);
+    assert_eq!(
+        output.rows,
+        vec![vec![Column::Integer(1), Column::Integer(300)]]
+    );
+
+    let output = database
+        .execute(
+            "select d.name, t.top from departments d join \
+             (select department, max(salary) as top from employees group by department) t \
+             on t.department = d.id order by d.name",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("engineering"), Column::Integer(180)],
+        vec![text("sales"), Column::Integer(120)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    let output = database
+        .execute("select count(*) from (select distinct department from employees)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Integer(2)]]);
+
+    cleanup(&filename);
+}
+
+fn create_tables(database: &mut Database) {
+    let departments = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("city".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("departments".to_string(), departments)
+        .unwrap();
+    let rows = vec![
+        ("1", "engineering", "london"),
+        ("2", "sales", "paris"),
+        ("3", "legal", "berlin"),
+    ];
+    for (id, name, city) in rows {
+        insert(
+            database,
+            "departments",
+            &[("id", id), ("name", name), ("city", city)],
+        );
+    }
+
+    let employees = vec![
+        ("name".to_string(), ColumnType::Text),
+        ("department".to_string(), ColumnType::Integer),
+        ("salary".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("employees".to_string(), employees)
+        .unwrap();
+    let rows = vec![
+        ("alice", "1", "120"),
+        ("bob", "1", "180"),
+        ("carol", "2", "90"),
+        ("dave", "2", "120"),
+    ];
+    for (name, department, salary) in rows {
+        insert(
+            database,
+            "employees",
+            &[
+                ("name", name),
+                ("department", department),
+                ("salary", salary),
+            ],
+        );
+    }
+}
+
+fn insert(database: &mut Database, table: &str, values: &[(&str, &str)]) {
+    let mut row = HashMap::new();
+    for (column, value) in values {
+        row.insert(column.to_string(), value.to_string());
+    }
+    database.insert_record(table.to_string(), row).unwrap();
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
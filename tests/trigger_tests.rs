@@ -0,0 +1,328 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn audit_triggers() {
+    let filename = create_db_file("audit_triggers");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    database
+        .execute(
+            "create trigger accounts_insert after insert on accounts begin \
+             insert into audit values ('insert', new.id, null, new.balance); end",
+        )
+        .unwrap();
+    database
+        .execute(
+            "create trigger accounts_balance after update of balance on accounts for each row \
+             when new.balance <> old.balance begin \
+             insert into audit values ('update', old.id, old.balance, new.balance); end",
+        )
+        .unwrap();
+    database
+        .execute(
+            "create trigger accounts_delete before delete on accounts begin \
+             insert into audit (action, account, old_balance) \
+             values ('delete', old.id, old.balance); end",
+        )
+        .unwrap();
+
+    database
+        .execute("insert into accounts values (1, 'alice', 100), (2, 'bob', 50)")
+        .unwrap();
+    database
+        .execute("update accounts set balance = balance + 25 where id = 1")
+        .unwrap();
+    // the WHEN condition is false and owner isn't watched by the trigger
+    database
+        .execute("update accounts set balance = 50 where id = 2")
+        .unwrap();
+    database
+        .execute("update accounts set owner = 'robert' where id = 2")
+        .unwrap();
+    database
+        .execute("delete from accounts where id = 2")
+        .unwrap();
+
+    let output = database.execute("select * from audit").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("insert"), int(1), Column::Null(), int(100)],
+            vec![text("insert"), int(2), Column::Null(), int(50)],
+            vec![text("update"), int(1), int(100), int(125)],
+            vec![text("delete"), int(2), int(50), Column::Null()],
+        ]
+    );
+
+    // triggers are kept in the master table and dropped with their table
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    database
+        .execute("insert into accounts values (3, 'carol', 10)")
+        .unwrap();
+    let output = database
+        .execute("select count(*) from audit where action = 'insert'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(3)]]);
+
+    database.execute("drop trigger accounts_insert").unwrap();
+    assert!(!database.trigger_exists("accounts_insert").unwrap());
+    assert!(database.execute("drop trigger accounts_insert").is_err());
+    database
+        .execute("drop trigger if exists accounts_insert")
+        .unwrap();
+    database.execute("drop table accounts").unwrap();
+    assert!(!database.trigger_exists("accounts_balance").unwrap());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn denormalising_triggers() {
+    let filename = create_db_file("denormalising_triggers");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    database
+        .execute("insert into accounts values (1, 'alice', 0), (2, 'bob', 0)")
+        .unwrap();
+    database
+        .execute(
+            "create trigger deposit after insert on audit when new.action = 'deposit' begin \
+             update accounts set balance = balance + new.new_balance where id = new.account; \
+             end",
+        )
+        .unwrap();
+    database
+        .execute(
+            "insert into audit (action, account, new_balance) values \
+             ('deposit', 1, 30), ('deposit', 2, 5), ('other', 1, 1000), ('deposit', 1, 12)",
+        )
+        .unwrap();
+    let output = database
+        .execute("select id, balance from accounts")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![int(1), int(42)], vec![int(2), int(5)]]
+    );
+
+    // a trigger doesn't fire itself
+    database
+        .execute(
+            "create trigger chain after insert on audit when new.action = 'chain' begin \
+             insert into audit (action, account) values ('chain', new.account + 1); end",
+        )
+        .unwrap();
+    database
+        .execute("insert into audit (action, account) values ('chain', 1)")
+        .unwrap();
+    let output = database
+        .execute("select account from audit where action = 'chain'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)], vec![int(2)]]);
+
+    // an error in a trigger fails the statement that fired it
+    database
+        .execute(
+            "create trigger broken before delete on accounts begin \
+             insert into missing values (old.id); end",
+        )
+        .unwrap();
+    assert!(database.execute("delete from accounts").is_err());
+    let output = database.execute("select count(*) from accounts").unwrap();
+    assert_eq!(output.rows, vec![vec![int(2)]]);
+
+    assert!(database
+        .execute("create trigger broken after insert on accounts begin select 1; end")
+        .is_err());
+    assert!(database
+        .execute("create trigger nowhere after insert on missing begin select 1; end")
+        .is_err());
+    assert!(database
+        .execute("create trigger empty after insert on accounts begin end")
+        .is_err());
+    assert!(database
+        .execute("create trigger params after insert on accounts begin select ?; end")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn instead_of_triggers() {
+    let filename = create_db_file("instead_of_triggers");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+    database
+        .execute("insert into accounts values (1, 'alice', 100), (2, 'bob', 50)")
+        .unwrap();
+    database
+        .execute("create view owners as select id, owner as name from accounts")
+        .unwrap();
+
+    assert!(database
+        .execute("insert into owners values (3, 'carol')")
+        .is_err());
+    assert!(database
+        .execute("create trigger early before insert on owners begin select 1; end")
+        .is_err());
+    assert!(database
+        .execute("create trigger instead instead of insert on accounts begin select 1; end")
+        .is_err());
+
+    database
+        .execute(
+            "create trigger owners_insert instead of insert on owners begin \
+             insert into accounts values (new.id, new.name, 0); end",
+        )
+        .unwrap();
+    database
+        .execute(
+            "create trigger owners_update instead of update on owners begin \
+             update accounts set owner = new.name where id = old.id; end",
+        )
+        .unwrap();
+    database
+        .execute(
+            "create trigger owners_delete instead of delete on owners begin \
+             delete from accounts where id = old.id; end",
+        )
+        .unwrap();
+
+    database
+        .execute("insert into owners values (3, 'carol')")
+        .unwrap();
+    database
+        .execute("update owners set name = 'ALICE' where id = 1")
+        .unwrap();
+    database
+        .execute("delete from owners where name = 'bob'")
+        .unwrap();
+
+    let output = database.execute("select * from accounts").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), text("ALICE"), int(100)],
+            vec![int(3), text("carol"), int(0)],
+        ]
+    );
+
+    // dropping the view drops its triggers
+    database.execute("drop view owners").unwrap();
+    assert!(!database.trigger_exists("owners_insert").unwrap());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn failed_statements() {
+    let filename = create_db_file("failed_statements");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("body".to_string(), ColumnType::Json),
+    ];
+    database.create_table("docs".to_string(), columns).unwrap();
+    database
+        .execute("alter table docs add column tag text collate nocase")
+        .unwrap();
+    database
+        .execute("create index docs_id on docs (id)")
+        .unwrap();
+
+    // the rows written before one fails are undone, and so are their index entries
+    assert!(database
+        .execute("insert into docs values (1, '{}', 'a'), (2, '{bad', 'b')")
+        .is_err());
+    let output = database
+        .execute("select d.tag from docs d join docs e on e.id = d.id")
+        .unwrap();
+    assert_eq!(output.rows, Vec::<Vec<Column>>::new());
+    database
+        .execute("insert into docs values (1, '{}', 'a')")
+        .unwrap();
+    let output = database
+        .execute("select d.tag from docs d join docs e on e.id = d.id")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("a")]]);
+
+    // a statement that fires triggers is undone along with them when any of them fails
+    let columns = vec![("id".to_string(), ColumnType::Integer)];
+    database.create_table("log".to_string(), columns).unwrap();
+    database
+        .execute(
+            "create trigger logged after insert on docs begin \
+             insert into log values (new.id); insert into nothing values (new.id); end",
+        )
+        .unwrap();
+    let error = database
+        .execute("insert into docs values (3, '[]', 'c'), (4, '[]', 'd')")
+        .unwrap_err();
+    assert_eq!(error.to_string(), "table nothing does not exist");
+    assert!(database.execute("update docs set id = 5").is_ok());
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database.execute("select id, tag from docs").unwrap();
+    assert_eq!(output.rows, vec![vec![int(5), text("a")]]);
+    let output = database.execute("select * from log").unwrap();
+    assert_eq!(output.rows, Vec::<Vec<Column>>::new());
+
+    // the table can still be written once the trigger is dropped
+    database.execute("drop trigger logged").unwrap();
+    database
+        .execute("insert into docs values (6, '[]', 'f')")
+        .unwrap();
+    let output = database
+        .execute("select d.tag from docs d join docs e on e.id = d.id order by d.id")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("a")], vec![text("f")]]);
+
+    cleanup(&filename);
+}
+
+fn create_tables(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("owner".to_string(), ColumnType::Text),
+        ("balance".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("accounts".to_string(), columns)
+        .unwrap();
+    let columns = vec![
+        ("action".to_string(), ColumnType::Text),
+        ("account".to_string(), ColumnType::Integer),
+        ("old_balance".to_string(), ColumnType::Integer),
+        ("new_balance".to_string(), ColumnType::Integer),
+    ];
+    database.create_table("audit".to_string(), columns).unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
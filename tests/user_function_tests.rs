@@ -0,0 +1,287 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::AggregateFunction;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::cell::Cell;
+use std::error;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+#[test]
+fn scalar_functions() {
+    let filename = create_db_file("scalar_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    database
+        .create_scalar_function("double", 1, true, |arguments| match &arguments[0] {
+            Column::Integer(int) => Ok(Column::Integer(int * 2)),
+            Column::Null() => Ok(Column::Null()),
+            _ => Err("double takes integers".into()),
+        })
+        .unwrap();
+    // a function taking any number of arguments
+    database
+        .create_scalar_function("joined", -1, true, |arguments| {
+            let parts: Vec<String> = arguments
+                .iter()
+                .map(|argument| match argument {
+                    Column::Text(text) => text.clone(),
+                    other => format!("{:?}", other),
+                })
+                .collect();
+            Ok(Column::Text(parts.join("-")))
+        })
+        .unwrap();
+
+    let output = database
+        .execute("select id, DOUBLE(amount) from sales where double(amount) > 25 order by id")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(2), int(40)],
+            vec![int(3), int(60)],
+            vec![int(4), int(30)]
+        ]
+    );
+    let output = database
+        .execute("select joined(), joined('a'), joined('a', 'b', 'c'), double(null)")
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![vec![text(""), text("a"), text("a-b-c"), Column::Null()]]
+    );
+
+    // errors from the function fail the statement
+    let error = database.execute("select double('x')").unwrap_err();
+    assert_eq!(error.to_string(), "double takes integers");
+    assert!(database.execute("select double(1, 2)").is_err());
+
+    // a function for an exact number of arguments is found first, and a new one replaces it
+    database
+        .create_scalar_function("joined", 2, true, |_| Ok(Column::Text("two".to_string())))
+        .unwrap();
+    database
+        .create_scalar_function("double", 1, false, |_| Ok(Column::Integer(0)))
+        .unwrap();
+    let output = database
+        .execute("select joined('a', 'b'), joined('a'), double(4)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("two"), text("a"), int(0)]]);
+
+    assert!(database
+        .create_scalar_function("bad", -2, true, |_| Ok(Column::Null()))
+        .is_err());
+
+    cleanup(&filename);
+}
+
+// the product of the values, which can take a value away again
+struct Product {
+    product: Option<i128>,
+    steps: Rc<Cell<usize>>,
+}
+
+impl AggregateFunction for Product {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), Box<dyn error::Error>> {
+        self.steps.set(self.steps.get() + 1);
+        match arguments[0] {
+            Column::Integer(int) => self.product = Some(self.product.unwrap_or(1) * int),
+            Column::Null() => {}
+            _ => return Err("product takes integers".into()),
+        }
+        Ok(())
+    }
+
+    fn inverse(&mut self, arguments: &[Column]) -> Result<bool, Box<dyn error::Error>> {
+        match arguments[0] {
+            Column::Integer(0) => Ok(false),
+            Column::Integer(int) => {
+                self.product = self.product.map(|product| product / int);
+                Ok(true)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn finalize(&self) -> Result<Column, Box<dyn error::Error>> {
+        Ok(match self.product {
+            Some(product) => Column::Integer(product),
+            None => Column::Null(),
+        })
+    }
+}
+
+// the values in the order they're stepped, which can't take any away
+struct Listed {
+    values: Vec<String>,
+}
+
+impl AggregateFunction for Listed {
+    fn step(&mut self, arguments: &[Column]) -> Result<(), Box<dyn error::Error>> {
+        if let Column::Integer(int) = arguments[0] {
+            self.values.push(int.to_string());
+        }
+        Ok(())
+    }
+
+    fn finalize(&self) -> Result<Column, Box<dyn error::Error>> {
+        Ok(Column::Text(self.values.join(",")))
+    }
+}
+
+#[test]
+fn aggregate_functions() {
+    let filename = create_db_file("aggregate_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+
+    let steps = Rc::new(Cell::new(0));
+    let counted = steps.clone();
+    database
+        .create_aggregate_function("product", 1, move || Product {
+            product: None,
+            steps: counted.clone(),
+        })
+        .unwrap();
+    database
+        .create_aggregate_function("listed", 1, || Listed { values: Vec::new() })
+        .unwrap();
+
+    let output = database
+        .execute(
+            "select region, product(amount), listed(amount), count(*) from sales \
+             group by region order by region",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("east"), int(300), text("10,30"), int(2)],
+            vec![text("west"), int(300), text("20,15"), int(2)],
+        ]
+    );
+    let output = database
+        .execute("select product(amount), product(distinct region = 'east') from sales")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(90000), int(0)]]);
+    let output = database
+        .execute("select product(amount) from sales where amount > 100")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![Column::Null()]]);
+    let output = database
+        .execute("select region from sales group by region having product(amount) > 299")
+        .unwrap();
+    assert_eq!(output.rows.len(), 2);
+
+    // a sliding frame steps each row once when the aggregate can take rows away
+    steps.set(0);
+    let output = database
+        .execute(
+            "select id, product(amount) over (order by id rows between 1 preceding and current row), \
+             listed(amount) over (order by id rows between 1 preceding and current row) \
+             from sales order by id",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(1), int(10), text("10")],
+            vec![int(2), int(200), text("10,20")],
+            vec![int(3), int(600), text("20,30")],
+            vec![int(4), int(450), text("30,15")],
+        ]
+    );
+    assert_eq!(steps.get(), 4);
+
+    assert!(database
+        .execute("select product(region) from sales")
+        .is_err());
+    assert!(database.execute("select product(1, 2)").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn functions_in_triggers() {
+    let filename = create_db_file("functions_in_triggers");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_table(&mut database);
+    let columns = vec![
+        ("region".to_string(), ColumnType::Text),
+        ("total".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("totals".to_string(), columns)
+        .unwrap();
+
+    database
+        .create_scalar_function("tax", 1, true, |arguments| match arguments[0] {
+            Column::Integer(int) => Ok(Column::Integer(int / 10)),
+            _ => Ok(Column::Null()),
+        })
+        .unwrap();
+    database
+        .create_aggregate_function("listed", 1, || Listed { values: Vec::new() })
+        .unwrap();
+    database
+        .execute(
+            "create trigger totalled after insert on sales begin \
+             insert into totals values \
+             (new.region, tax((select sum(amount) from sales where region = new.region))); \
+             insert into totals values ('all', (select listed(id) from sales)); end",
+        )
+        .unwrap();
+    database
+        .execute("insert into sales values (5, 'east', 60)")
+        .unwrap();
+    let output = database.execute("select * from totals").unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("east"), int(10)],
+            vec![text("all"), text("1,2,3,4,5")],
+        ]
+    );
+
+    cleanup(&filename);
+}
+
+fn create_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("region".to_string(), ColumnType::Text),
+        ("amount".to_string(), ColumnType::Integer),
+    ];
+    database.create_table("sales".to_string(), columns).unwrap();
+    database
+        .execute(
+            "insert into sales values (1, 'east', 10), (2, 'west', 20), (3, 'east', 30), \
+             (4, 'west', 15)",
+        )
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,187 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn create_and_query_views() {
+    let filename = create_db_file("create_and_query_views");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    let sql = "CREATE VIEW open_orders AS\n  SELECT id, customer, item FROM orders \
+               WHERE status = 'open'";
+    database.execute(&format!("{};", sql)).unwrap();
+    database
+        .execute(
+            "create view totals (customer, orders) as \
+             select customer, count(*) from open_orders group by customer",
+        )
+        .unwrap();
+    assert_eq!(
+        database.view_definition("open_orders").unwrap(),
+        Some(sql.to_string())
+    );
+    assert_eq!(
+        database.list_views().unwrap(),
+        vec!["open_orders", "totals"]
+    );
+
+    let output = database
+        .execute("select * from open_orders where customer = 1")
+        .unwrap();
+    assert_eq!(output.columns, vec!["id", "customer", "item"]);
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![int(10), int(1), text("book")],
+            vec![int(12), int(1), text("pen")],
+        ]
+    );
+
+    // views can be joined, read by other views and used in subqueries
+    let output = database
+        .execute(
+            "select c.name, totals.orders from customers c \
+             join totals on totals.customer = c.id order by c.name",
+        )
+        .unwrap();
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![text("alice"), int(2)],
+            vec![text("bob"), int(1)],
+            vec![text("carol"), int(1)],
+        ]
+    );
+    let output = database
+        .execute("select name from customers where id not in (select customer from open_orders)")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("dan")]]);
+
+    // the view reads the table as it is now
+    database
+        .execute("insert into orders values (16, 4, 'cup', 'open')")
+        .unwrap();
+    let output = database
+        .execute("select o.item from open_orders as o where o.customer = 4")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("cup")]]);
+
+    // a common table hides a view with the same name
+    let output = database
+        .execute("with totals as (select 1 as orders) select orders from totals")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)]]);
+
+    // views are kept in the master table
+    drop(database);
+    let mut database = database::load(&filename).unwrap();
+    let output = database
+        .execute("select orders from totals where customer = 4")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(1)]]);
+
+    assert!(database.execute("create view orders as select 1").is_err());
+    assert!(database.execute("create view totals as select 1").is_err());
+    database
+        .execute("create view if not exists totals as select 1")
+        .unwrap();
+    assert!(database
+        .execute("insert into open_orders values (17, 1, 'pad')")
+        .is_err());
+    assert!(database.execute("delete from totals").is_err());
+    assert!(database
+        .execute("create view params as select ? as value")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn drop_views() {
+    let filename = create_db_file("drop_views");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_tables(&mut database);
+
+    database
+        .execute("create view first_view as select id, name from customers")
+        .unwrap();
+    database
+        .execute("create view second_view as select name from first_view where id > 2")
+        .unwrap();
+    let output = database.execute("select * from second_view").unwrap();
+    assert_eq!(output.rows, vec![vec![text("carol")], vec![text("dan")]]);
+
+    database.execute("drop view first_view").unwrap();
+    assert!(database.execute("select * from first_view").is_err());
+    assert!(database.execute("select * from second_view").is_err());
+    assert!(database.execute("drop view first_view").is_err());
+    database.execute("drop view if exists first_view").unwrap();
+    assert!(database.execute("drop view customers").is_err());
+
+    // a view that ends up reading itself can't be expanded
+    database
+        .execute("create view first_view as select * from second_view")
+        .unwrap();
+    let error = database.execute("select * from first_view").unwrap_err();
+    assert_eq!(error.to_string(), "view first_view is circularly defined");
+
+    database.execute("drop view first_view").unwrap();
+    database.execute("drop view second_view").unwrap();
+    assert_eq!(database.list_views().unwrap(), Vec::<String>::new());
+
+    cleanup(&filename);
+}
+
+fn create_tables(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("customers".to_string(), columns)
+        .unwrap();
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("customer".to_string(), ColumnType::Integer),
+        ("item".to_string(), ColumnType::Text),
+        ("status".to_string(), ColumnType::Text),
+    ];
+    database
+        .create_table("orders".to_string(), columns)
+        .unwrap();
+
+    database
+        .execute("insert into customers values (1, 'alice'), (2, 'bob'), (3, 'carol'), (4, 'dan')")
+        .unwrap();
+    database
+        .execute(
+            "insert into orders values (10, 1, 'book', 'open'), (11, 2, 'lamp', 'open'), \
+             (12, 1, 'pen', 'open'), (13, 3, 'desk', 'open'), (14, 1, 'ink', 'closed')",
+        )
+        .unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,187 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn insert_statements() {
+    let filename = create_db_file("insert_statements");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_items_table(&mut database);
+
+    database
+        .execute("insert into items (name, price) values ('lamp', 12 * 2), ('cup', 3)")
+        .unwrap();
+    database
+        .execute("insert into items values (9, 'pen', 1.5)")
+        .unwrap();
+
+    let output = database.execute("select * from items").unwrap();
+    let expected = vec![
+        vec![Column::Null(), text("lamp"), Column::Real(24.0)],
+        vec![Column::Null(), text("cup"), Column::Real(3.0)],
+        vec![int(9), text("pen"), Column::Real(1.5)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // values can be anything the executor can evaluate
+    database
+        .execute("insert into items (id, name) values ((select max(id) from items) + 1, 'ink')")
+        .unwrap();
+    let output = database
+        .execute("select id from items where name = 'ink'")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![int(10)]]);
+
+    assert!(database
+        .execute("insert into items values (1, 'book')")
+        .is_err());
+    assert!(database
+        .execute("insert into items (id, name) values (1)")
+        .is_err());
+    assert!(database
+        .execute("insert into items (colour) values ('red')")
+        .is_err());
+    assert!(database
+        .execute("insert into items (id) values (price)")
+        .is_err());
+    assert!(database.execute("insert into shelves values (1)").is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn compiled_queries() {
+    let filename = create_db_file("compiled_queries");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_items_table(&mut database);
+    database
+        .execute(
+            "insert into items values (1, 'lamp', 24), (2, 'cup', 3), (3, 'pen', NULL), \
+             (4, 'book', 12), (5, 'ink', 3)",
+        )
+        .unwrap();
+
+    let output = database
+        .execute(
+            "select name, price * 2 as double from items \
+             where price between 3 and 20 and id not in (5, 6) order by double desc, name",
+        )
+        .unwrap();
+    assert_eq!(output.columns, vec!["name", "double"]);
+    let expected = vec![
+        vec![text("book"), Column::Real(24.0)],
+        vec![text("cup"), Column::Real(6.0)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // NULLs sort first, LIMIT and OFFSET apply to the sorted rows
+    let output = database
+        .execute("select name from items order by price, id limit 2 offset 1")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("cup")], vec![text("ink")]]);
+
+    let output = database
+        .execute("select name from items where price is null or id = 1 limit 5 offset 1")
+        .unwrap();
+    assert_eq!(output.rows, vec![vec![text("pen")]]);
+
+    let output = database.execute("select id from items limit 0").unwrap();
+    assert!(output.rows.is_empty());
+
+    let output = database.execute("select 1 + 2, 'a' || 'b'").unwrap();
+    assert_eq!(output.rows, vec![vec![int(3), text("ab")]]);
+    let output = database.execute("select 1 where 1 = 0").unwrap();
+    assert!(output.rows.is_empty());
+
+    assert!(database.execute("select colour from items").is_err());
+    assert!(database
+        .execute("select name from items order by 3")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn compiled_updates_and_deletes() {
+    let filename = create_db_file("compiled_updates_and_deletes");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_items_table(&mut database);
+    database
+        .execute(
+            "insert into items values (1, 'lamp', 24), (2, 'cup', 3), (3, 'pen', 1), \
+             (4, 'book', 12)",
+        )
+        .unwrap();
+    database
+        .execute("create index items_name on items (name)")
+        .unwrap();
+
+    // assignments see the row as it was before the update
+    database
+        .execute("update items set price = price + id, id = price where price < 20")
+        .unwrap();
+    let output = database
+        .execute("select id, name, price from items")
+        .unwrap();
+    let expected = vec![
+        vec![int(1), text("lamp"), Column::Real(24.0)],
+        vec![int(3), text("cup"), Column::Real(5.0)],
+        vec![int(1), text("pen"), Column::Real(4.0)],
+        vec![int(12), text("book"), Column::Real(16.0)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // deleting neighbouring rows doesn't skip any
+    database
+        .execute("delete from items where name in ('cup', 'pen')")
+        .unwrap();
+    let output = database.execute("select name from items").unwrap();
+    assert_eq!(output.rows, vec![vec![text("lamp")], vec![text("book")]]);
+
+    // the index is rebuilt once the statement is done
+    let index = database.list_indexes("items".to_string()).unwrap();
+    let rows = database.search_index(&index[0], &[text("book")]).unwrap();
+    assert_eq!(rows, vec![vec![int(12), text("book"), int(16)]]);
+
+    assert!(database.execute("update items set colour = 'red'").is_err());
+    assert!(database.execute("delete from shelves").is_err());
+
+    database.execute("delete from items").unwrap();
+    let output = database.execute("select * from items").unwrap();
+    assert!(output.rows.is_empty());
+
+    cleanup(&filename);
+}
+
+fn create_items_table(database: &mut Database) {
+    let columns = vec![
+        ("id".to_string(), ColumnType::Integer),
+        ("name".to_string(), ColumnType::Text),
+        ("price".to_string(), ColumnType::Real),
+    ];
+    database.create_table("items".to_string(), columns).unwrap();
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
@@ -0,0 +1,160 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+use basic_db::sql::expression::Row;
+use basic_db::sql::parser;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn where_clauses() {
+    let filename = create_db_file("where_clauses");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_people_table(&mut database);
+
+    let output = database
+        .execute("select * from people where age > 30 and name != 'carol'")
+        .unwrap()
+        .rows;
+    assert_eq!(output, vec![person("bob", Some(42))]);
+
+    let output = database
+        .execute("select * from people where age between 20 and 30 or age is null")
+        .unwrap()
+        .rows;
+    assert_eq!(
+        output,
+        vec![person("alice", Some(25)), person("dave", None)]
+    );
+
+    let output = database
+        .execute("select * from people where name like 'A%' or name glob '*ro?'")
+        .unwrap()
+        .rows;
+    assert_eq!(
+        output,
+        vec![person("alice", Some(25)), person("carol", Some(51))]
+    );
+
+    let output = database
+        .execute("select * from people where age not in (25, 42)")
+        .unwrap()
+        .rows;
+    assert_eq!(output, vec![person("carol", Some(51))]);
+
+    // NULL is neither equal nor unequal to anything
+    let output = database
+        .execute("select * from people where not (age = 25)")
+        .unwrap()
+        .rows;
+    assert_eq!(output.len(), 2);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn update_and_delete() {
+    let filename = create_db_file("update_and_delete");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_people_table(&mut database);
+
+    database
+        .execute("update people set age = age + 1, name = name || '!' where age < 50")
+        .unwrap();
+    database
+        .execute("delete from people where age is null")
+        .unwrap();
+
+    let output = database.select_all_records("people".to_string()).unwrap();
+    let expected = vec![
+        person("alice!", Some(26)),
+        person("bob!", Some(43)),
+        person("carol", Some(51)),
+    ];
+    assert_eq!(output, expected);
+
+    assert!(database
+        .execute("delete from people where height > 2")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn three_valued_logic() {
+    let cases = vec![
+        ("null and 0", Column::Integer(0)),
+        ("null and 1", Column::Null()),
+        ("null or 1", Column::Integer(1)),
+        ("null or 0", Column::Null()),
+        ("not null", Column::Null()),
+        ("1 in (2, null)", Column::Null()),
+        ("1 not in (1, null)", Column::Integer(0)),
+        ("null is null", Column::Integer(1)),
+        ("null is not 1", Column::Integer(1)),
+        ("7 / 2 + 7 % 2 * 1.5", Column::Real(4.5)),
+        ("1 / 0", Column::Null()),
+        ("'a' || 1 || 2.5", Column::Text("a12.5".to_string())),
+        ("'10%' like '10\\%' escape '\\'", Column::Integer(1)),
+        ("'abc' glob '[a-c]b[^d]'", Column::Integer(1)),
+        ("'ABC' glob 'a*'", Column::Integer(0)),
+    ];
+
+    for (sql, expected) in cases {
+        let expression = parser::parse_expression(sql).unwrap();
+        assert_eq!(
+            expression.evaluate(&Row::empty()).unwrap(),
+            expected,
+            "{}",
+            sql
+        );
+    }
+}
+
+fn create_people_table(database: &mut Database) {
+    let columns = vec![
+        ("name".to_string(), ColumnType::Text),
+        ("age".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("people".to_string(), columns)
+        .unwrap();
+
+    let people = vec![
+        ("alice", Some(25)),
+        ("bob", Some(42)),
+        ("carol", Some(51)),
+        ("dave", None),
+    ];
+    for (name, age) in people {
+        let mut row = HashMap::new();
+        row.insert("name".to_string(), name.to_string());
+        if let Some(age) = age {
+            row.insert("age".to_string(), age.to_string());
+        }
+        database.insert_record("people".to_string(), row).unwrap();
+    }
+}
+
+fn person(name: &str, age: Option<i128>) -> Vec<Column> {
+    let age = match age {
+        Some(age) => Column::Integer(age),
+        None => Column::Null(),
+    };
+    vec![Column::Text(name.to_string()), age]
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}
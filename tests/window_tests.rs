@@ -0,0 +1,175 @@
+extern crate basic_db;
+
+use basic_db::backend::database;
+use basic_db::backend::database::Column;
+use basic_db::backend::database::ColumnType;
+use basic_db::backend::database::Database;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn ranking_functions() {
+    let filename = create_db_file("ranking_functions");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_scores_table(&mut database);
+
+    let output = database
+        .execute(
+            "select player, \
+             row_number() over (partition by team order by points desc, player), \
+             rank() over (partition by team order by points desc), \
+             dense_rank() over (partition by team order by points desc) \
+             from scores order by team, player",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("dee"), int(1), int(1), int(1)],
+        vec![text("eli"), int(2), int(2), int(2)],
+        vec![text("amy"), int(1), int(1), int(1)],
+        vec![text("ben"), int(2), int(1), int(1)],
+        vec![text("cal"), int(3), int(3), int(2)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    assert!(database.execute("select row_number() from scores").is_err());
+    assert!(database
+        .execute("select player from scores where rank() over (order by points) = 1")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+#[test]
+fn lag_lead_and_values() {
+    let filename = create_db_file("lag_lead_and_values");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_scores_table(&mut database);
+
+    let output = database
+        .execute(
+            "select day, lag(points) over (order by day), lead(points, 2, -1) over (order by day), \
+             first_value(player) over (order by day), last_value(player) over (order by day) \
+             from scores order by day",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![int(1), Column::Null(), int(7), text("amy"), text("amy")],
+        vec![int(2), int(10), int(12), text("amy"), text("ben")],
+        vec![int(3), int(10), int(3), text("amy"), text("cal")],
+        vec![int(4), int(7), int(-1), text("amy"), text("dee")],
+        vec![int(5), int(12), int(-1), text("amy"), text("eli")],
+    ];
+    assert_eq!(output.rows, expected);
+
+    cleanup(&filename);
+}
+
+#[test]
+fn aggregate_windows_and_frames() {
+    let filename = create_db_file("aggregate_windows_and_frames");
+    let mut database = database::load(&filename).expect("Error creating a new database file");
+    create_scores_table(&mut database);
+
+    // running totals, the whole partition without ORDER BY, and moving averages
+    let output = database
+        .execute(
+            "select day, sum(points) over (order by day) as running, \
+             sum(points) over () as total, \
+             avg(points) over (order by day rows between 1 preceding and 1 following) as moving, \
+             count(*) over (order by day rows 2 preceding) as recent \
+             from scores order by day",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![int(1), int(10), int(42), Column::Real(10.0), int(1)],
+        vec![int(2), int(20), int(42), Column::Real(9.0), int(2)],
+        vec![int(3), int(27), int(42), Column::Real(29.0 / 3.0), int(3)],
+        vec![int(4), int(39), int(42), Column::Real(22.0 / 3.0), int(3)],
+        vec![int(5), int(42), int(42), Column::Real(7.5), int(3)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // peers share a running total, RANGE offsets are distances from the ORDER BY value
+    let output = database
+        .execute(
+            "select player, sum(points) over (order by points), \
+             count(*) over (order by points range between 2 preceding and 2 following) \
+             from scores order by player",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("amy"), int(30), int(3)],
+        vec![text("ben"), int(30), int(3)],
+        vec![text("cal"), int(10), int(1)],
+        vec![text("dee"), int(42), int(3)],
+        vec![text("eli"), int(3), int(1)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    // window functions over grouped rows
+    let output = database
+        .execute(
+            "select team, sum(points), rank() over (order by sum(points) desc) \
+             from scores group by team order by team",
+        )
+        .unwrap();
+    let expected = vec![
+        vec![text("blue"), int(15), int(2)],
+        vec![text("red"), int(27), int(1)],
+    ];
+    assert_eq!(output.rows, expected);
+
+    assert!(database
+        .execute("select sum(points) over (order by team, day range 1 preceding) from scores")
+        .is_err());
+
+    cleanup(&filename);
+}
+
+fn create_scores_table(database: &mut Database) {
+    let columns = vec![
+        ("player".to_string(), ColumnType::Text),
+        ("team".to_string(), ColumnType::Text),
+        ("day".to_string(), ColumnType::Integer),
+        ("points".to_string(), ColumnType::Integer),
+    ];
+    database
+        .create_table("scores".to_string(), columns)
+        .unwrap();
+
+    let scores = vec![
+        ("amy", "red", "1", "10"),
+        ("ben", "red", "2", "10"),
+        ("cal", "red", "3", "7"),
+        ("dee", "blue", "4", "12"),
+        ("eli", "blue", "5", "3"),
+    ];
+    for (player, team, day, points) in scores {
+        let mut row = HashMap::new();
+        row.insert("player".to_string(), player.to_string());
+        row.insert("team".to_string(), team.to_string());
+        row.insert("day".to_string(), day.to_string());
+        row.insert("points".to_string(), points.to_string());
+        database.insert_record("scores".to_string(), row).unwrap();
+    }
+}
+
+fn int(value: i128) -> Column {
+    Column::Integer(value)
+}
+
+fn text(value: &str) -> Column {
+    Column::Text(value.to_string())
+}
+
+fn create_db_file(version: &str) -> String {
+    let filename = format!("test_database_{}.db", version);
+    cleanup(&filename);
+    filename
+}
+
+fn cleanup(filename: &str) {
+    let _ = fs::remove_file(Path::new(filename));
+}